@@ -0,0 +1,85 @@
+use boinc_rpc::rpc::{BoincCodec, CodecMode};
+use bytes::BytesMut;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::fmt::Write;
+
+/// `get_state` reply of a host with `tasks` tasks, about 2 KB each as the daemon sends them.
+fn get_state_reply(tasks: usize) -> Vec<u8> {
+    let mut reply = String::from(
+        "<boinc_gui_rpc_reply>\n<client_state>\n<host_info>\n<domain_name>cruncher</domain_name>\n\
+         <p_ncpus>64</p_ncpus>\n</host_info>\n",
+    );
+    for i in 0..tasks {
+        write!(
+            reply,
+            "<result>\n    <name>wu_{0}_1700000000_{0}_0</name>\n    \
+             <wu_name>wu_{0}_1700000000_{0}</wu_name>\n    \
+             <platform>x86_64-pc-linux-gnu</platform>\n    <version_num>712</version_num>\n    \
+             <project_url>https://einsteinathome.org/</project_url>\n    \
+             <final_cpu_time>0.000000</final_cpu_time>\n    \
+             <final_elapsed_time>0.000000</final_elapsed_time>\n    <exit_status>0</exit_status>\n    \
+             <state>2</state>\n    <report_deadline>1700604800.000000</report_deadline>\n    \
+             <received_time>1700000000.000000</received_time>\n    \
+             <estimated_cpu_time_remaining>{1}.000000</estimated_cpu_time_remaining>\n    \
+             <active_task>\n        <active_task_state>1</active_task_state>\n        \
+             <app_version_num>712</app_version_num>\n        <slot>{2}</slot>\n        \
+             <pid>{3}</pid>\n        <scheduler_state>2</scheduler_state>\n        \
+             <checkpoint_cpu_time>1234.567890</checkpoint_cpu_time>\n        \
+             <fraction_done>0.{4:06}</fraction_done>\n        \
+             <current_cpu_time>1300.123456</current_cpu_time>\n        \
+             <elapsed_time>1320.654321</elapsed_time>\n        \
+             <swap_size>123456789.000000</swap_size>\n        \
+             <working_set_size>98765432.000000</working_set_size>\n        \
+             <working_set_size_smoothed>98765432.000000</working_set_size_smoothed>\n        \
+             <page_fault_rate>0.000000</page_fault_rate>\n        \
+             <bytes_sent>0.000000</bytes_sent>\n        <bytes_received>0.000000</bytes_received>\n    \
+             </active_task>\n</result>\n",
+            i,
+            3600 + i,
+            i % 64,
+            10000 + i,
+            i * 37 % 1_000_000,
+        )
+        .unwrap();
+    }
+    reply.push_str("</client_state>\n</boinc_gui_rpc_reply>\n\x03");
+    reply.into_bytes()
+}
+
+fn decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode_get_state");
+    group.sample_size(20);
+    for &tasks in &[100, 5000] {
+        let reply = get_state_reply(tasks);
+        group.throughput(Throughput::Bytes(reply.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("whole", tasks), &reply, |b, reply| {
+            b.iter(|| {
+                let mut buf = BytesMut::from(&reply[..]);
+                BoincCodec::new(CodecMode::Client)
+                    .decode_frame(&mut buf)
+                    .unwrap()
+                    .unwrap()
+            })
+        });
+
+        // As it arrives over the network, in TCP segment sized pieces
+        group.bench_with_input(BenchmarkId::new("segments", tasks), &reply, |b, reply| {
+            b.iter(|| {
+                let mut codec = BoincCodec::new(CodecMode::Client);
+                let mut buf = BytesMut::new();
+                let mut pieces = reply.chunks(1448);
+                loop {
+                    buf.extend_from_slice(pieces.next().unwrap());
+                    if let Some(reply) = codec.decode_frame(&mut buf).unwrap() {
+                        break reply;
+                    }
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, decode);
+criterion_main!(benches);
@@ -0,0 +1,568 @@
+//! Command line client taking the options of `boinccmd`, for hosts without it.
+//!
+//! ```text
+//! cargo run --example boinc-cli --features serde -- [--host host[:port]] [--passwd passwd] \
+//!     [--json] command
+//! ```
+//!
+//! Commands:
+//!
+//! ```text
+//! --get_state
+//! --get_tasks                                 also --get_results
+//! --get_messages [seqno]
+//! --project URL op                            op as for boinccmd, such as update or nomorework
+//! --task URL name suspend|resume|abort
+//! --set_run_mode always|auto|never|restore [duration]
+//! --set_gpu_mode always|auto|never|restore [duration]
+//! --set_network_mode always|auto|never|restore [duration]
+//! ```
+//!
+//! Without `--passwd`, the password is read from `gui_rpc_auth.cfg` in the current directory if
+//! there is one, as `boinccmd` does. `--json` prints replies as JSON rather than text.
+
+use boinc_rpc::{
+    models::{self, describe},
+    Client, Error,
+};
+use std::{fmt::Write, time::Duration};
+
+const USAGE: &str = "usage: boinc-cli [--host host[:port]] [--passwd passwd] [--json] command
+
+commands:
+ --get_state
+ --get_tasks
+ --get_messages [seqno]
+ --project URL op
+ --task URL name suspend|resume|abort
+ --set_run_mode always|auto|never|restore [duration]
+ --set_gpu_mode always|auto|never|restore [duration]
+ --set_network_mode always|auto|never|restore [duration]";
+
+#[derive(Debug, PartialEq)]
+enum TaskOp {
+    Suspend,
+    Resume,
+    Abort,
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    GetState,
+    GetTasks,
+    GetMessages {
+        seqno: i64,
+    },
+    Project {
+        url: String,
+        op: models::ProjectOp,
+    },
+    Task {
+        url: String,
+        name: String,
+        op: TaskOp,
+    },
+    SetMode {
+        component: models::Component,
+        mode: models::RunMode,
+        duration: Option<Duration>,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+struct Args {
+    host: String,
+    password: Option<String>,
+    json: bool,
+    command: Command,
+}
+
+/// Argument of `option`, described as `what` if missing.
+fn value<I: Iterator<Item = String>>(
+    args: &mut I,
+    option: &str,
+    what: &str,
+) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("{option} needs {what}"))
+}
+
+fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Args, String> {
+    let mut args = args.into_iter().peekable();
+    let mut host = "localhost".to_string();
+    let mut password = None;
+    let mut json = false;
+    let mut command = None;
+    while let Some(arg) = args.next() {
+        let parsed = match &*arg {
+            "--host" => {
+                host = value(&mut args, &arg, "a host")?;
+                continue;
+            }
+            "--passwd" => {
+                password = Some(value(&mut args, &arg, "a password")?);
+                continue;
+            }
+            "--json" => {
+                json = true;
+                continue;
+            }
+            "--get_state" => Command::GetState,
+            "--get_tasks" | "--get_results" => Command::GetTasks,
+            "--get_messages" => {
+                let seqno = match args.next_if(|arg| !arg.starts_with("--")) {
+                    Some(seqno) => seqno
+                        .parse()
+                        .map_err(|_| format!("Invalid sequence number: {seqno}"))?,
+                    None => 0,
+                };
+                Command::GetMessages { seqno }
+            }
+            "--project" => {
+                let url = value(&mut args, &arg, "a project URL")?;
+                let op = value(&mut args, &arg, "an operation")?;
+                Command::Project {
+                    url,
+                    op: op.parse().map_err(|e: Error| e.to_string())?,
+                }
+            }
+            "--task" => {
+                let url = value(&mut args, &arg, "a project URL")?;
+                let name = value(&mut args, &arg, "a task name")?;
+                let op = match &*value(&mut args, &arg, "an operation")? {
+                    "suspend" => TaskOp::Suspend,
+                    "resume" => TaskOp::Resume,
+                    "abort" => TaskOp::Abort,
+                    other => return Err(format!("Unknown task operation: {other}")),
+                };
+                Command::Task { url, name, op }
+            }
+            "--set_run_mode" | "--set_gpu_mode" | "--set_network_mode" => {
+                let component = arg["--set_".len()..arg.len() - "_mode".len()]
+                    .parse()
+                    .map_err(|e: Error| e.to_string())?;
+                let mode = value(&mut args, &arg, "a mode")?
+                    .parse()
+                    .map_err(|e: Error| e.to_string())?;
+                // Seconds, the mode being for good without them
+                let duration = match args.next_if(|arg| !arg.starts_with("--")) {
+                    Some(seconds) => Some(
+                        seconds
+                            .parse()
+                            .ok()
+                            .filter(|seconds: &f64| seconds.is_finite() && *seconds > 0.0)
+                            .map(Duration::from_secs_f64)
+                            .ok_or_else(|| format!("Invalid duration: {seconds}"))?,
+                    ),
+                    None => None,
+                };
+                Command::SetMode {
+                    component,
+                    mode,
+                    duration,
+                }
+            }
+            other => return Err(format!("Unknown option: {other}")),
+        };
+        if command.replace(parsed).is_some() {
+            return Err("Only one command may be given".into());
+        }
+    }
+    Ok(Args {
+        host,
+        password,
+        json,
+        command: command.ok_or("No command given")?,
+    })
+}
+
+/// Password `boinccmd` would use, from the data directory it is run in.
+fn password_from_current_dir() -> Option<String> {
+    let password = std::fs::read_to_string("gui_rpc_auth.cfg").ok()?;
+    Some(password.trim().to_string()).filter(|password| !password.is_empty())
+}
+
+/// Writes `label: value`, if there is a value.
+fn field<T: std::fmt::Display>(out: &mut String, label: &str, value: Option<T>) {
+    if let Some(value) = value {
+        writeln!(out, "   {label}: {value}").unwrap();
+    }
+}
+
+fn write_tasks(out: &mut String, tasks: &[models::TaskResult]) {
+    writeln!(out, "\n======== Tasks ========").unwrap();
+    for (i, task) in tasks.iter().enumerate() {
+        writeln!(out, "{}) -----------", i + 1).unwrap();
+        field(out, "name", task.name.as_ref());
+        field(out, "WU name", task.wu_name.as_ref());
+        field(out, "project URL", task.project_url.as_ref());
+        field(out, "report deadline", task.report_deadline);
+        field(out, "ready to report", task.ready_to_report);
+        writeln!(out, "   state: {}", describe::task_status_line(task)).unwrap();
+        field(
+            out,
+            "estimated CPU time remaining",
+            task.estimated_cpu_time_remaining,
+        );
+        if let Some(active) = &task.active_task {
+            field(out, "fraction done", active.fraction_done);
+            field(out, "current CPU time", active.current_cpu_time);
+            field(out, "elapsed time", active.elapsed_time);
+        }
+    }
+}
+
+fn write_state(out: &mut String, state: &models::ClientState) {
+    writeln!(out, "======== Client ========").unwrap();
+    writeln!(out, "   version: {}", state.core_client_version).unwrap();
+    field(out, "platform", state.platform_name.as_ref());
+    if let Some(host) = &state.host_info {
+        field(out, "host name", host.domain_name.as_ref());
+        field(out, "OS", host.os_name.as_ref());
+    }
+    writeln!(out, "\n======== Applications ========").unwrap();
+    for (i, app) in state.apps.iter().enumerate() {
+        writeln!(out, "{}) -----------", i + 1).unwrap();
+        field(out, "name", app.name.as_ref());
+        field(out, "user friendly name", app.user_friendly_name.as_ref());
+    }
+    write_tasks(out, &state.results);
+}
+
+fn write_messages(out: &mut String, messages: &[models::Message]) {
+    for message in messages {
+        writeln!(
+            out,
+            "{}: {} ({}) [{}] {}",
+            message.msg_number.unwrap_or_default(),
+            message.timestamp.unwrap_or_default(),
+            message.priority.unwrap_or_default(),
+            message.project_name.as_deref().unwrap_or("---"),
+            message.body.as_deref().unwrap_or_default().trim()
+        )
+        .unwrap();
+    }
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> String {
+    let mut json = serde_json::to_string_pretty(value).unwrap();
+    json.push('\n');
+    json
+}
+
+/// Runs `command`, returning what to print.
+async fn run<S>(client: &Client<S>, command: &Command, json: bool) -> Result<String, Error>
+where
+    S: tower::Service<Vec<treexml::Element>, Response = Vec<treexml::Element>> + Clone,
+    S::Error: Into<Error>,
+{
+    let mut out = String::new();
+    match command {
+        Command::GetState => {
+            let state = client.get_state().await?;
+            if json {
+                out = to_json(&state);
+            } else {
+                write_state(&mut out, &state);
+            }
+        }
+        Command::GetTasks => {
+            let tasks = client.get_results(false).await?;
+            if json {
+                out = to_json(&tasks);
+            } else {
+                write_tasks(&mut out, &tasks);
+            }
+        }
+        Command::GetMessages { seqno } => {
+            let messages = client.get_messages(*seqno).await?;
+            if json {
+                out = to_json(&messages);
+            } else {
+                write_messages(&mut out, &messages);
+            }
+        }
+        Command::Project { url, op } => client.project_op(url, *op).await?,
+        Command::Task { url, name, op } => match op {
+            TaskOp::Suspend => client.suspend_result(url, name).await?,
+            TaskOp::Resume => client.resume_result(url, name).await?,
+            TaskOp::Abort => client.abort_result(url, name).await?,
+        },
+        Command::SetMode {
+            component,
+            mode,
+            duration,
+        } => client.set_mode_for(*component, *mode, *duration).await?,
+    }
+    Ok(out)
+}
+
+#[tokio::main(basic_scheduler)]
+async fn main() {
+    let args = parse_args(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{e}\n\n{USAGE}");
+        std::process::exit(2);
+    });
+    let password = args.password.or_else(password_from_current_dir);
+    let outcome = match Client::connect(args.host, password).await {
+        Ok(client) => run(&client, &args.command, args.json).await,
+        Err(e) => Err(e),
+    };
+    match outcome {
+        Ok(out) => print!("{out}"),
+        Err(e) => {
+            eprintln!("Operation failed: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn args(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn node(name: &str, text: &str) -> treexml::Element {
+        let mut node = treexml::Element::new(name);
+        node.text = Some(text.into());
+        node
+    }
+
+    fn parent(name: &str, children: Vec<treexml::Element>) -> treexml::Element {
+        let mut node = treexml::Element::new(name);
+        node.children = children;
+        node
+    }
+
+    fn task() -> treexml::Element {
+        parent(
+            "result",
+            vec![
+                node("name", "wu_1_0"),
+                node("wu_name", "wu_1"),
+                node("project_url", "https://a.example/"),
+                node("state", "2"),
+                node("report_deadline", "1700000000"),
+                node("estimated_cpu_time_remaining", "3600"),
+                parent(
+                    "active_task",
+                    vec![
+                        node("active_task_state", "1"),
+                        node("scheduler_state", "2"),
+                        node("fraction_done", "0.25"),
+                    ],
+                ),
+            ],
+        )
+    }
+
+    fn message() -> treexml::Element {
+        let mut body = treexml::Element::new("body");
+        body.cdata = Some("\nRequesting new tasks\n".into());
+        parent(
+            "msg",
+            vec![
+                node("project", "Einstein@Home"),
+                node("pri", "1"),
+                node("seqno", "7"),
+                body,
+                node("time", "1700000000"),
+            ],
+        )
+    }
+
+    /// Requests sent, the first element of each.
+    type Sent = Arc<Mutex<Vec<treexml::Element>>>;
+
+    /// Client answering with canned replies, keeping the requests in `sent`.
+    fn client(
+        sent: &Sent,
+    ) -> Client<
+        impl tower::Service<Vec<treexml::Element>, Response = Vec<treexml::Element>, Error = Error>
+            + Clone,
+    > {
+        let requests = sent.clone();
+        let transport = tower::service_fn(move |request: Vec<treexml::Element>| {
+            let request = request.into_iter().next().unwrap();
+            let reply = match &*request.name {
+                "get_results" => vec![parent("results", vec![task()])],
+                "get_messages" => vec![parent("msgs", vec![message()])],
+                "get_state" => vec![parent(
+                    "client_state",
+                    vec![
+                        node("platform_name", "x86_64-pc-linux-gnu"),
+                        node("core_client_major_version", "7"),
+                        node("core_client_minor_version", "16"),
+                        node("core_client_release", "11"),
+                        parent(
+                            "app",
+                            vec![
+                                node("name", "einstein_O3"),
+                                node("user_friendly_name", "Gravitational Wave search"),
+                            ],
+                        ),
+                        task(),
+                    ],
+                )],
+                _ => vec![treexml::Element::new("success")],
+            };
+            requests.lock().unwrap().push(request);
+            async { Ok::<_, Error>(reply) }
+        });
+        Client::new(transport)
+    }
+
+    #[test]
+    fn boinccmd_options() {
+        assert_eq!(
+            args(&[
+                "--host",
+                "cruncher:31416",
+                "--passwd",
+                "secret",
+                "--get_state"
+            ]),
+            Ok(Args {
+                host: "cruncher:31416".into(),
+                password: Some("secret".into()),
+                json: false,
+                command: Command::GetState,
+            })
+        );
+        let parsed = args(&["--get_messages", "--json"]).unwrap();
+        assert_eq!(parsed.command, Command::GetMessages { seqno: 0 });
+        assert!(parsed.json);
+        assert_eq!(
+            args(&["--project", "https://a.example/", "nomorework"])
+                .unwrap()
+                .command,
+            Command::Project {
+                url: "https://a.example/".into(),
+                op: models::ProjectOp::NoMoreWork,
+            }
+        );
+        assert_eq!(
+            args(&["--set_gpu_mode", "never", "3600"]).unwrap().command,
+            Command::SetMode {
+                component: models::Component::GPU,
+                mode: models::RunMode::Never,
+                duration: Some(Duration::from_secs(3600)),
+            }
+        );
+        assert_eq!(
+            args(&["--set_run_mode", "auto"]).unwrap().command,
+            Command::SetMode {
+                component: models::Component::CPU,
+                mode: models::RunMode::Auto,
+                duration: None,
+            }
+        );
+
+        assert_eq!(args(&[]), Err("No command given".into()));
+        assert_eq!(
+            args(&["--task", "https://a.example/", "wu_1_0", "pause"]),
+            Err("Unknown task operation: pause".into())
+        );
+        assert_eq!(
+            args(&["--set_run_mode", "never", "-5"]),
+            Err("Invalid duration: -5".into())
+        );
+        assert_eq!(
+            args(&["--get_state", "--get_tasks"]),
+            Err("Only one command may be given".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn tasks() {
+        let client = client(&Sent::default());
+        assert_eq!(
+            run(&client, &Command::GetTasks, false).await.unwrap(),
+            "
+======== Tasks ========
+1) -----------
+   name: wu_1_0
+   WU name: wu_1
+   project URL: https://a.example/
+   report deadline: 1700000000
+   state: Running
+   estimated CPU time remaining: 3600
+   fraction done: 0.25
+"
+        );
+
+        let json = run(&client, &Command::GetTasks, true).await.unwrap();
+        let tasks: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(tasks[0]["name"], "wu_1_0");
+        assert_eq!(tasks[0]["active_task"]["fraction_done"], 0.25);
+    }
+
+    #[tokio::test]
+    async fn state_and_messages() {
+        let client = client(&Sent::default());
+        assert_eq!(
+            run(&client, &Command::GetState, false).await.unwrap(),
+            "======== Client ========
+   version: 7.16.11
+   platform: x86_64-pc-linux-gnu
+
+======== Applications ========
+1) -----------
+   name: einstein_O3
+   user friendly name: Gravitational Wave search
+
+======== Tasks ========
+1) -----------
+   name: wu_1_0
+   WU name: wu_1
+   project URL: https://a.example/
+   report deadline: 1700000000
+   state: Running
+   estimated CPU time remaining: 3600
+   fraction done: 0.25
+"
+        );
+        assert_eq!(
+            run(&client, &Command::GetMessages { seqno: 0 }, false)
+                .await
+                .unwrap(),
+            "7: 1700000000 (1) [Einstein@Home] Requesting new tasks\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn operations() {
+        let sent = Sent::default();
+        let client = client(&sent);
+        let commands = [
+            Command::Task {
+                url: "https://a.example/".into(),
+                name: "wu_1_0".into(),
+                op: TaskOp::Suspend,
+            },
+            Command::Project {
+                url: "https://a.example/".into(),
+                op: models::ProjectOp::Update,
+            },
+            Command::SetMode {
+                component: models::Component::Network,
+                mode: models::RunMode::Never,
+                duration: Some(Duration::from_secs(600)),
+            },
+        ];
+        for command in &commands {
+            assert_eq!(run(&client, command, false).await, Ok(String::new()));
+        }
+
+        let sent = sent.lock().unwrap();
+        let names: Vec<_> = sent.iter().map(|request| &*request.name).collect();
+        assert_eq!(
+            names,
+            ["suspend_result", "project_update", "set_network_mode"]
+        );
+        assert_eq!(sent[2].children[0].text.as_deref(), Some("600"));
+    }
+}
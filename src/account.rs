@@ -0,0 +1,368 @@
+//! Setting up accounts with projects, several RPCs at a time.
+
+use crate::{errors::Error, secret::Secret};
+use std::{fmt, time::Duration};
+
+/// How `Client::add_project_by_account` goes about its RPCs.
+#[derive(Clone, Debug)]
+pub struct AddProjectOptions {
+    pub(crate) poll_interval: Duration,
+    pub(crate) timeout: Duration,
+}
+
+impl Default for AddProjectOptions {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+impl AddProjectOptions {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How often to ask the daemon whether a stage is done. One second by default.
+    #[must_use]
+    pub const fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// How long each stage may take. One minute by default, as project servers can be slow.
+    #[must_use]
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// Stage of `Client::add_project_by_account`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AddProjectStage {
+    /// Fetching the project's configuration.
+    ProjectConfig,
+    /// Looking up the account, where a wrong password fails.
+    LookupAccount,
+    Attach,
+}
+
+impl AddProjectStage {
+    /// Runs `work` as this stage, for up to `timeout`.
+    pub(crate) async fn run<T, F>(self, timeout: Duration, work: F) -> Result<T, AddProjectError>
+    where
+        F: std::future::Future<Output = Result<T, Error>>,
+    {
+        let result = crate::time::timeout(timeout, work)
+            .await
+            .and_then(|result| result);
+        result.map_err(|error| AddProjectError { stage: self, error })
+    }
+}
+
+impl fmt::Display for AddProjectStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ProjectConfig => "fetching the project configuration",
+            Self::LookupAccount => "looking up the account",
+            Self::Attach => "attaching",
+        })
+    }
+}
+
+/// Failure of `Client::add_project_by_account` at `stage`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AddProjectError {
+    pub stage: AddProjectStage,
+    pub error: Error,
+}
+
+impl fmt::Display for AddProjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Adding the project failed {}: {}",
+            self.stage, self.error
+        )
+    }
+}
+
+impl std::error::Error for AddProjectError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// How to log in to a project of a `ProjectPlan`.
+#[derive(Clone, Debug)]
+pub enum Credentials {
+    /// Account key, as shown on the project's website.
+    Authenticator(Secret),
+    /// Account to look up, as `Client::add_project_by_account` does.
+    Account {
+        email_or_user: String,
+        password: Secret,
+    },
+}
+
+/// Project a `ProjectPlan` wants attached.
+#[derive(Clone, Debug)]
+// Only applying a plan, which needs the runtime, looks at what to attach
+#[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+pub struct PlannedProject {
+    pub(crate) url: String,
+    pub(crate) credentials: Credentials,
+    pub(crate) name: Option<String>,
+    pub(crate) resource_share: Option<f64>,
+}
+
+impl PlannedProject {
+    pub fn new<U: Into<String>>(url: U, credentials: Credentials) -> Self {
+        Self {
+            url: url.into(),
+            credentials,
+            name: None,
+            resource_share: None,
+        }
+    }
+
+    /// Name to show until the daemon learns the project's own, when attaching with an
+    /// authenticator. The URL by default.
+    #[must_use]
+    pub fn name<N: Into<String>>(mut self, name: N) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Resource share the project should have. Shares are set on the project's website rather
+    /// than over RPC, so a different one is reported but not changed.
+    #[must_use]
+    pub const fn resource_share(mut self, resource_share: f64) -> Self {
+        self.resource_share = Some(resource_share);
+        self
+    }
+}
+
+/// What to do with attached projects that a `ProjectPlan` does not list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExtraProjects {
+    /// Detach at once, abandoning their tasks.
+    Detach,
+    /// Detach once their tasks are done.
+    DetachWhenDone,
+    /// Leave them attached.
+    Ignore,
+}
+
+/// Projects a host should be attached to, for `Client::apply_project_plan`.
+#[derive(Clone, Debug)]
+pub struct ProjectPlan {
+    pub(crate) projects: Vec<PlannedProject>,
+    pub(crate) extras: ExtraProjects,
+    pub(crate) options: AddProjectOptions,
+}
+
+impl Default for ProjectPlan {
+    fn default() -> Self {
+        Self {
+            projects: Vec::new(),
+            extras: ExtraProjects::Ignore,
+            options: AddProjectOptions::default(),
+        }
+    }
+}
+
+impl ProjectPlan {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn project(mut self, project: PlannedProject) -> Self {
+        self.projects.push(project);
+        self
+    }
+
+    /// What to do with the projects not planned. `ExtraProjects::Ignore` by default.
+    #[must_use]
+    pub const fn extras(mut self, extras: ExtraProjects) -> Self {
+        self.extras = extras;
+        self
+    }
+
+    /// How to go about attaching. Every attachment may take up to the timeout.
+    #[must_use]
+    pub const fn options(mut self, options: AddProjectOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub(crate) fn planned(&self, url: &str) -> Option<&PlannedProject> {
+        self.projects.iter().find(|project| project.url == url)
+    }
+
+    /// Changes that bring `attached` in line with the plan, those to planned projects first so
+    /// that a host is not left without projects when attaching fails, and the planned resource
+    /// shares that differ.
+    pub(crate) fn diff(
+        &self,
+        attached: &[crate::models::Project],
+    ) -> (Vec<ProjectChange>, Vec<ShareMismatch>) {
+        let mut changes = Vec::new();
+        let mut mismatches = Vec::new();
+        for planned in &self.projects {
+            let found = attached.iter().find(|project| {
+                matches!(&project.master_url, Some(master_url) if crate::util::same_master_url(master_url, &planned.url))
+            });
+            if let Some(project) = found {
+                let master_url = project.master_url.clone().unwrap_or_default();
+                if project.detach_when_done == Some(true) {
+                    changes.push(ProjectChange::KeepAttached(master_url.clone()));
+                }
+                if let Some(resource_share) = planned.resource_share {
+                    if !matches!(project.resource_share, Some(actual) if (actual - resource_share).abs() < 1e-6)
+                    {
+                        mismatches.push(ShareMismatch {
+                            master_url,
+                            planned: resource_share,
+                            actual: project.resource_share,
+                        });
+                    }
+                }
+            } else {
+                changes.push(ProjectChange::Attach(planned.url.clone()));
+            }
+        }
+
+        let urls = attached
+            .iter()
+            .filter_map(|project| Some((project, project.master_url.as_ref()?)));
+        for (project, master_url) in urls {
+            if self
+                .projects
+                .iter()
+                .any(|planned| crate::util::same_master_url(master_url, &planned.url))
+            {
+                continue;
+            }
+            match self.extras {
+                ExtraProjects::Detach => changes.push(ProjectChange::Detach(master_url.clone())),
+                ExtraProjects::DetachWhenDone if project.detach_when_done != Some(true) => {
+                    changes.push(ProjectChange::DetachWhenDone(master_url.clone()));
+                }
+                ExtraProjects::DetachWhenDone | ExtraProjects::Ignore => {}
+            }
+        }
+        (changes, mismatches)
+    }
+}
+
+/// Change `Client::apply_project_plan` makes, by project URL.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProjectChange {
+    Attach(String),
+    Detach(String),
+    DetachWhenDone(String),
+    /// A planned project was set to detach when done, which is called off.
+    KeepAttached(String),
+}
+
+/// Planned project attached with another resource share than planned.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShareMismatch {
+    pub master_url: String,
+    pub planned: f64,
+    pub actual: Option<f64>,
+}
+
+/// Outcome of `Client::apply_project_plan`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlanReport {
+    /// Nothing was changed, `changes` lists what would have been.
+    pub dry_run: bool,
+    pub changes: Vec<ProjectChange>,
+    /// Changes that failed, and why.
+    pub failed: Vec<(ProjectChange, Error)>,
+    pub share_mismatches: Vec<ShareMismatch>,
+}
+
+impl PlanReport {
+    /// Whether the host is attached as planned, resource shares aside.
+    #[must_use]
+    pub const fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use super::*;
+    use crate::models;
+
+    fn project(master_url: &str, resource_share: f64, detach_when_done: bool) -> models::Project {
+        models::Project {
+            master_url: Some(master_url.into()),
+            resource_share: Some(resource_share),
+            detach_when_done: Some(detach_when_done),
+            ..models::Project::default()
+        }
+    }
+
+    fn planned(url: &str) -> PlannedProject {
+        PlannedProject::new(url, Credentials::Authenticator("0123abcd".into()))
+    }
+
+    #[test]
+    fn diff() {
+        let attached = [
+            project("https://a.example/", 100.0, false),
+            project("https://b.example/", 50.0, true),
+            project("https://c.example/", 100.0, false),
+            project("https://d.example/", 100.0, true),
+            models::Project::default(),
+        ];
+        let plan = ProjectPlan::new()
+            .project(planned("http://A.example").resource_share(100.0))
+            .project(planned("https://b.example/").resource_share(100.0))
+            .project(planned("https://e.example/").resource_share(100.0));
+
+        let (changes, mismatches) = plan.diff(&attached);
+        assert_eq!(
+            changes,
+            [
+                ProjectChange::KeepAttached("https://b.example/".into()),
+                ProjectChange::Attach("https://e.example/".into()),
+            ]
+        );
+        assert_eq!(
+            mismatches,
+            [ShareMismatch {
+                master_url: "https://b.example/".into(),
+                planned: 100.0,
+                actual: Some(50.0),
+            }]
+        );
+
+        let (changes, _) = plan
+            .clone()
+            .extras(ExtraProjects::DetachWhenDone)
+            .diff(&attached);
+        assert_eq!(
+            changes[2..],
+            [ProjectChange::DetachWhenDone("https://c.example/".into())]
+        );
+
+        let (changes, _) = plan.extras(ExtraProjects::Detach).diff(&attached);
+        assert_eq!(
+            changes[2..],
+            [
+                ProjectChange::Detach("https://c.example/".into()),
+                ProjectChange::Detach("https://d.example/".into()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,726 @@
+//! Object-safe view of `Client`, for code that is to be tested without a daemon.
+
+use crate::{
+    account, connection::ConnectionSource, deadline, errors::Error, models, rpc::PollResult, tasks,
+    Client, Secret,
+};
+use std::{future::Future, pin::Pin, time::Duration};
+
+/// Future returned by the methods of `BoincRpc`.
+pub type RpcFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Predicate on tasks, as taken by `BoincRpc::wait_for_task` and `BoincRpc::abort_tasks_matching`.
+pub type TaskPredicate<'a> = &'a (dyn Fn(&models::TaskResult) -> bool + Sync);
+
+/// Change to preferences, as taken by `BoincRpc::update_global_prefs`.
+pub type PrefsUpdate<'a> = &'a (dyn Fn(&mut models::GlobalPreferences) + Sync);
+
+pub(crate) mod sealed {
+    pub trait Sealed {}
+}
+
+/// Requests to the daemon, as `Client` makes them, for code that takes `&dyn BoincRpc` or a
+/// generic `R: BoincRpc` rather than a `Client` with a particular transport.
+///
+/// Each method is the `Client` method of the same name, boxed. Arguments the `Client` methods
+/// take generically are concrete here: passwords and authenticators are `Secret`s, predicates
+/// `TaskPredicate`s and changes to preferences `PrefsUpdate`s. Streams borrow the `Client` and
+/// are only offered there.
+///
+/// The trait is sealed, so that RPCs can be added without breaking anyone: it is implemented by
+/// `Client` over any `Send` transport and, behind the `test-util` feature, by
+/// `test_util::StubRpc`. Code under test is best handed a `Client<test_util::MockTransport>`
+/// to script replies, or a `StubRpc` to fail everything.
+///
+/// ```rust
+/// use boinc_rpc::{BoincRpc, Error};
+///
+/// async fn running(rpc: &dyn BoincRpc) -> Result<usize, Error> {
+///     Ok(rpc
+///         .get_results(true)
+///         .await?
+///         .iter()
+///         .filter(|task| task.active_task.is_some())
+///         .count())
+/// }
+/// ```
+pub trait BoincRpc: sealed::Sealed + Send + Sync {
+    /// See `Client::get_messages`.
+    fn get_messages(&self, seqno: i64) -> RpcFuture<'_, Result<Vec<models::Message>, Error>>;
+
+    /// See `Client::get_message_count`.
+    fn get_message_count(&self) -> RpcFuture<'_, Result<i64, Error>>;
+
+    /// See `Client::ping`.
+    fn ping(&self) -> RpcFuture<'_, Result<Duration, Error>>;
+
+    /// See `Client::get_notices`.
+    fn get_notices(&self, seqno: i64) -> RpcFuture<'_, Result<Vec<models::Notice>, Error>>;
+
+    /// See `Client::wait_for_task`.
+    fn wait_for_task<'a>(
+        &'a self,
+        name: &'a str,
+        predicate: TaskPredicate<'a>,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> RpcFuture<'a, Result<models::TaskResult, Error>>;
+
+    /// See `Client::wait_until_idle`.
+    fn wait_until_idle(
+        &self,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> RpcFuture<'_, Result<tasks::IdleReport, Error>>;
+
+    /// See `Client::wait_until_idle_with`.
+    fn wait_until_idle_with(
+        &self,
+        options: tasks::IdleOptions,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> RpcFuture<'_, Result<tasks::IdleReport, Error>>;
+
+    /// See `Client::get_projects`.
+    fn get_projects(&self) -> RpcFuture<'_, Result<Vec<models::ProjectInfo>, Error>>;
+
+    /// See `Client::get_project_status`.
+    fn get_project_status(&self) -> RpcFuture<'_, Result<Vec<models::Project>, Error>>;
+
+    /// See `Client::get_attached_project`.
+    fn get_attached_project<'a>(
+        &'a self,
+        url: &'a str,
+    ) -> RpcFuture<'a, Result<Option<models::Project>, Error>>;
+
+    /// See `Client::get_account_manager_info`.
+    fn get_account_manager_info(&self) -> RpcFuture<'_, Result<models::AccountManagerInfo, Error>>;
+
+    /// See `Client::get_account_manager_rpc_status`.
+    fn get_account_manager_rpc_status(&self) -> RpcFuture<'_, Result<i32, Error>>;
+
+    /// See `Client::poll_account_manager_rpc`.
+    fn poll_account_manager_rpc(&self) -> RpcFuture<'_, Result<PollResult<Vec<String>>, Error>>;
+
+    /// See `Client::poll_project_attach`.
+    fn poll_project_attach(&self) -> RpcFuture<'_, Result<PollResult<Vec<String>>, Error>>;
+
+    /// See `Client::start_project_attach`.
+    fn start_project_attach<'a>(
+        &'a self,
+        url: &'a str,
+        authenticator: Secret,
+        name: &'a str,
+    ) -> RpcFuture<'a, Result<(), Error>>;
+
+    /// See `Client::attach_project`.
+    fn attach_project<'a>(
+        &'a self,
+        url: &'a str,
+        authenticator: Secret,
+        name: &'a str,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> RpcFuture<'a, Result<(), Error>>;
+
+    /// See `Client::project_op`.
+    fn project_op<'a>(
+        &'a self,
+        url: &'a str,
+        op: models::ProjectOp,
+    ) -> RpcFuture<'a, Result<(), Error>>;
+
+    /// See `Client::wait_for_project_update`.
+    fn wait_for_project_update<'a>(
+        &'a self,
+        url: &'a str,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> RpcFuture<'a, Result<models::Project, Error>>;
+
+    /// See `Client::apply_project_plan`.
+    fn apply_project_plan<'a>(
+        &'a self,
+        plan: &'a account::ProjectPlan,
+        dry_run: bool,
+    ) -> RpcFuture<'a, Result<account::PlanReport, Error>>;
+
+    /// See `Client::add_project_by_account`.
+    fn add_project_by_account<'a>(
+        &'a self,
+        url: &'a str,
+        email_or_user: &'a str,
+        password: Secret,
+        options: &'a account::AddProjectOptions,
+    ) -> RpcFuture<'a, Result<(), account::AddProjectError>>;
+
+    /// See `Client::start_lookup_account`.
+    fn start_lookup_account<'a>(
+        &'a self,
+        url: &'a str,
+        email_or_user: &'a str,
+        password: Secret,
+        uses_username: bool,
+    ) -> RpcFuture<'a, Result<(), Error>>;
+
+    /// See `Client::poll_lookup_account`.
+    fn poll_lookup_account(&self) -> RpcFuture<'_, Result<PollResult<Secret>, Error>>;
+
+    /// See `Client::start_project_config`.
+    fn start_project_config<'a>(&'a self, url: &'a str) -> RpcFuture<'a, Result<(), Error>>;
+
+    /// See `Client::poll_project_config`.
+    fn poll_project_config(
+        &self,
+    ) -> RpcFuture<'_, Result<PollResult<models::ProjectConfig>, Error>>;
+
+    /// See `Client::connect_to_account_manager`.
+    fn connect_to_account_manager<'a>(
+        &'a self,
+        url: &'a str,
+        name: &'a str,
+        password: &'a str,
+    ) -> RpcFuture<'a, Result<bool, Error>>;
+
+    /// See `Client::attach_account_manager`.
+    fn attach_account_manager<'a>(
+        &'a self,
+        url: &'a str,
+        name: &'a str,
+        password: &'a str,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> RpcFuture<'a, Result<(), Error>>;
+
+    /// See `Client::exchange_versions`.
+    fn exchange_versions<'a>(
+        &'a self,
+        info: Option<&'a models::VersionInfo>,
+    ) -> RpcFuture<'a, Result<models::VersionInfo, Error>>;
+
+    /// See `Client::get_results`.
+    fn get_results(
+        &self,
+        active_only: bool,
+    ) -> RpcFuture<'_, Result<Vec<models::TaskResult>, Error>>;
+
+    /// See `Client::abort_result`.
+    fn abort_result<'a>(
+        &'a self,
+        project_url: &'a str,
+        name: &'a str,
+    ) -> RpcFuture<'a, Result<(), Error>>;
+
+    /// See `Client::suspend_result`.
+    fn suspend_result<'a>(
+        &'a self,
+        project_url: &'a str,
+        name: &'a str,
+    ) -> RpcFuture<'a, Result<(), Error>>;
+
+    /// See `Client::resume_result`.
+    fn resume_result<'a>(
+        &'a self,
+        project_url: &'a str,
+        name: &'a str,
+    ) -> RpcFuture<'a, Result<(), Error>>;
+
+    /// See `Client::abort_tasks_matching`.
+    fn abort_tasks_matching<'a>(
+        &'a self,
+        filter: TaskPredicate<'a>,
+        dry_run: bool,
+    ) -> RpcFuture<'a, Result<tasks::AbortReport, Error>>;
+
+    /// See `Client::get_tasks`.
+    fn get_tasks<'a>(
+        &'a self,
+        filter: &'a models::TaskFilter,
+    ) -> RpcFuture<'a, Result<Vec<models::TaskResult>, Error>>;
+
+    /// See `Client::deadline_report`.
+    fn deadline_report(&self) -> RpcFuture<'_, Result<Vec<deadline::TaskDeadline>, Error>>;
+
+    /// See `Client::deadline_report_with`.
+    fn deadline_report_with<'a>(
+        &'a self,
+        options: &'a deadline::DeadlineOptions,
+    ) -> RpcFuture<'a, Result<Vec<deadline::TaskDeadline>, Error>>;
+
+    /// See `Client::get_task`.
+    fn get_task<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> RpcFuture<'a, Result<Option<models::TaskResult>, Error>>;
+
+    /// See `Client::get_file_transfers`.
+    fn get_file_transfers(&self) -> RpcFuture<'_, Result<Vec<models::FileTransfer>, Error>>;
+
+    /// See `Client::get_statistics`.
+    fn get_statistics(&self) -> RpcFuture<'_, Result<Vec<models::ProjectStatistics>, Error>>;
+
+    /// See `Client::set_mode_for`.
+    fn set_mode_for(
+        &self,
+        c: models::Component,
+        m: models::RunMode,
+        duration: Option<Duration>,
+    ) -> RpcFuture<'_, Result<(), Error>>;
+
+    /// See `Client::set_mode`.
+    #[deprecated(note = "use `set_mode_for`, which takes the duration as `Option<Duration>`")]
+    fn set_mode(
+        &self,
+        c: models::Component,
+        m: models::RunMode,
+        duration: f64,
+    ) -> RpcFuture<'_, Result<(), Error>>;
+
+    /// See `Client::snooze`.
+    fn snooze(&self, duration: Duration) -> RpcFuture<'_, Result<(), Error>>;
+
+    /// See `Client::unsnooze`.
+    fn unsnooze(&self) -> RpcFuture<'_, Result<(), Error>>;
+
+    /// See `Client::get_host_info`.
+    fn get_host_info(&self) -> RpcFuture<'_, Result<models::HostInfo, Error>>;
+
+    /// See `Client::summary`.
+    fn summary(&self) -> RpcFuture<'_, models::Summary>;
+
+    /// See `Client::get_cc_status`.
+    fn get_cc_status(&self) -> RpcFuture<'_, Result<models::CcStatus, Error>>;
+
+    /// See `Client::run_benchmarks`.
+    fn run_benchmarks(&self) -> RpcFuture<'_, Result<(), Error>>;
+
+    /// See `Client::run_benchmarks_and_wait`.
+    fn run_benchmarks_and_wait(
+        &self,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> RpcFuture<'_, Result<models::HostInfo, Error>>;
+
+    /// See `Client::get_state`.
+    fn get_state(&self) -> RpcFuture<'_, Result<models::ClientState, Error>>;
+
+    /// See `Client::set_language`.
+    fn set_language<'a>(&'a self, v: &'a str) -> RpcFuture<'a, Result<(), Error>>;
+
+    /// See `Client::get_global_prefs_working`.
+    fn get_global_prefs_working(&self) -> RpcFuture<'_, Result<models::GlobalPreferences, Error>>;
+
+    /// See `Client::get_global_prefs_override`.
+    fn get_global_prefs_override(
+        &self,
+    ) -> RpcFuture<'_, Result<Option<models::GlobalPreferences>, Error>>;
+
+    /// See `Client::set_global_prefs_override`.
+    fn set_global_prefs_override<'a>(
+        &'a self,
+        prefs: Option<&'a models::GlobalPreferences>,
+    ) -> RpcFuture<'a, Result<(), Error>>;
+
+    /// See `Client::read_global_prefs_override`.
+    fn read_global_prefs_override(&self) -> RpcFuture<'_, Result<(), Error>>;
+
+    /// See `Client::update_global_prefs`.
+    fn update_global_prefs<'a>(
+        &'a self,
+        update: PrefsUpdate<'a>,
+    ) -> RpcFuture<'a, Result<models::GlobalPreferences, Error>>;
+}
+
+impl<S> sealed::Sealed for Client<S> {}
+
+impl<S> BoincRpc for Client<S>
+where
+    S: tower_service::Service<Vec<treexml::Element>, Response = Vec<treexml::Element>>
+        + ConnectionSource
+        + Clone
+        + Send
+        + Sync,
+    S::Future: Send,
+    S::Error: Into<Error> + Send,
+{
+    fn get_messages(&self, seqno: i64) -> RpcFuture<'_, Result<Vec<models::Message>, Error>> {
+        Box::pin(Self::get_messages(self, seqno))
+    }
+
+    fn get_message_count(&self) -> RpcFuture<'_, Result<i64, Error>> {
+        Box::pin(Self::get_message_count(self))
+    }
+
+    fn ping(&self) -> RpcFuture<'_, Result<Duration, Error>> {
+        Box::pin(Self::ping(self))
+    }
+
+    fn get_notices(&self, seqno: i64) -> RpcFuture<'_, Result<Vec<models::Notice>, Error>> {
+        Box::pin(Self::get_notices(self, seqno))
+    }
+
+    fn wait_for_task<'a>(
+        &'a self,
+        name: &'a str,
+        predicate: TaskPredicate<'a>,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> RpcFuture<'a, Result<models::TaskResult, Error>> {
+        Box::pin(Self::wait_for_task(
+            self,
+            name,
+            predicate,
+            poll_interval,
+            timeout,
+        ))
+    }
+
+    fn wait_until_idle(
+        &self,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> RpcFuture<'_, Result<tasks::IdleReport, Error>> {
+        Box::pin(Self::wait_until_idle(self, poll_interval, timeout))
+    }
+
+    fn wait_until_idle_with(
+        &self,
+        options: tasks::IdleOptions,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> RpcFuture<'_, Result<tasks::IdleReport, Error>> {
+        Box::pin(Self::wait_until_idle_with(
+            self,
+            options,
+            poll_interval,
+            timeout,
+        ))
+    }
+
+    fn get_projects(&self) -> RpcFuture<'_, Result<Vec<models::ProjectInfo>, Error>> {
+        Box::pin(Self::get_projects(self))
+    }
+
+    fn get_project_status(&self) -> RpcFuture<'_, Result<Vec<models::Project>, Error>> {
+        Box::pin(Self::get_project_status(self))
+    }
+
+    fn get_attached_project<'a>(
+        &'a self,
+        url: &'a str,
+    ) -> RpcFuture<'a, Result<Option<models::Project>, Error>> {
+        Box::pin(Self::get_attached_project(self, url))
+    }
+
+    fn get_account_manager_info(&self) -> RpcFuture<'_, Result<models::AccountManagerInfo, Error>> {
+        Box::pin(Self::get_account_manager_info(self))
+    }
+
+    fn get_account_manager_rpc_status(&self) -> RpcFuture<'_, Result<i32, Error>> {
+        Box::pin(Self::get_account_manager_rpc_status(self))
+    }
+
+    fn poll_account_manager_rpc(&self) -> RpcFuture<'_, Result<PollResult<Vec<String>>, Error>> {
+        Box::pin(Self::poll_account_manager_rpc(self))
+    }
+
+    fn poll_project_attach(&self) -> RpcFuture<'_, Result<PollResult<Vec<String>>, Error>> {
+        Box::pin(Self::poll_project_attach(self))
+    }
+
+    fn start_project_attach<'a>(
+        &'a self,
+        url: &'a str,
+        authenticator: Secret,
+        name: &'a str,
+    ) -> RpcFuture<'a, Result<(), Error>> {
+        Box::pin(Self::start_project_attach(self, url, authenticator, name))
+    }
+
+    fn attach_project<'a>(
+        &'a self,
+        url: &'a str,
+        authenticator: Secret,
+        name: &'a str,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> RpcFuture<'a, Result<(), Error>> {
+        Box::pin(Self::attach_project(
+            self,
+            url,
+            authenticator,
+            name,
+            poll_interval,
+            timeout,
+        ))
+    }
+
+    fn project_op<'a>(
+        &'a self,
+        url: &'a str,
+        op: models::ProjectOp,
+    ) -> RpcFuture<'a, Result<(), Error>> {
+        Box::pin(Self::project_op(self, url, op))
+    }
+
+    fn wait_for_project_update<'a>(
+        &'a self,
+        url: &'a str,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> RpcFuture<'a, Result<models::Project, Error>> {
+        Box::pin(Self::wait_for_project_update(
+            self,
+            url,
+            poll_interval,
+            timeout,
+        ))
+    }
+
+    fn apply_project_plan<'a>(
+        &'a self,
+        plan: &'a account::ProjectPlan,
+        dry_run: bool,
+    ) -> RpcFuture<'a, Result<account::PlanReport, Error>> {
+        Box::pin(Self::apply_project_plan(self, plan, dry_run))
+    }
+
+    fn add_project_by_account<'a>(
+        &'a self,
+        url: &'a str,
+        email_or_user: &'a str,
+        password: Secret,
+        options: &'a account::AddProjectOptions,
+    ) -> RpcFuture<'a, Result<(), account::AddProjectError>> {
+        Box::pin(Self::add_project_by_account(
+            self,
+            url,
+            email_or_user,
+            password,
+            options,
+        ))
+    }
+
+    fn start_lookup_account<'a>(
+        &'a self,
+        url: &'a str,
+        email_or_user: &'a str,
+        password: Secret,
+        uses_username: bool,
+    ) -> RpcFuture<'a, Result<(), Error>> {
+        Box::pin(Self::start_lookup_account(
+            self,
+            url,
+            email_or_user,
+            password,
+            uses_username,
+        ))
+    }
+
+    fn poll_lookup_account(&self) -> RpcFuture<'_, Result<PollResult<Secret>, Error>> {
+        Box::pin(Self::poll_lookup_account(self))
+    }
+
+    fn start_project_config<'a>(&'a self, url: &'a str) -> RpcFuture<'a, Result<(), Error>> {
+        Box::pin(Self::start_project_config(self, url))
+    }
+
+    fn poll_project_config(
+        &self,
+    ) -> RpcFuture<'_, Result<PollResult<models::ProjectConfig>, Error>> {
+        Box::pin(Self::poll_project_config(self))
+    }
+
+    fn connect_to_account_manager<'a>(
+        &'a self,
+        url: &'a str,
+        name: &'a str,
+        password: &'a str,
+    ) -> RpcFuture<'a, Result<bool, Error>> {
+        Box::pin(Self::connect_to_account_manager(self, url, name, password))
+    }
+
+    fn attach_account_manager<'a>(
+        &'a self,
+        url: &'a str,
+        name: &'a str,
+        password: &'a str,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> RpcFuture<'a, Result<(), Error>> {
+        Box::pin(Self::attach_account_manager(
+            self,
+            url,
+            name,
+            password,
+            poll_interval,
+            timeout,
+        ))
+    }
+
+    fn exchange_versions<'a>(
+        &'a self,
+        info: Option<&'a models::VersionInfo>,
+    ) -> RpcFuture<'a, Result<models::VersionInfo, Error>> {
+        Box::pin(Self::exchange_versions(self, info))
+    }
+
+    fn get_results(
+        &self,
+        active_only: bool,
+    ) -> RpcFuture<'_, Result<Vec<models::TaskResult>, Error>> {
+        Box::pin(Self::get_results(self, active_only))
+    }
+
+    fn abort_result<'a>(
+        &'a self,
+        project_url: &'a str,
+        name: &'a str,
+    ) -> RpcFuture<'a, Result<(), Error>> {
+        Box::pin(Self::abort_result(self, project_url, name))
+    }
+
+    fn suspend_result<'a>(
+        &'a self,
+        project_url: &'a str,
+        name: &'a str,
+    ) -> RpcFuture<'a, Result<(), Error>> {
+        Box::pin(Self::suspend_result(self, project_url, name))
+    }
+
+    fn resume_result<'a>(
+        &'a self,
+        project_url: &'a str,
+        name: &'a str,
+    ) -> RpcFuture<'a, Result<(), Error>> {
+        Box::pin(Self::resume_result(self, project_url, name))
+    }
+
+    fn abort_tasks_matching<'a>(
+        &'a self,
+        filter: TaskPredicate<'a>,
+        dry_run: bool,
+    ) -> RpcFuture<'a, Result<tasks::AbortReport, Error>> {
+        Box::pin(Self::abort_tasks_matching(self, filter, dry_run))
+    }
+
+    fn get_tasks<'a>(
+        &'a self,
+        filter: &'a models::TaskFilter,
+    ) -> RpcFuture<'a, Result<Vec<models::TaskResult>, Error>> {
+        Box::pin(Self::get_tasks(self, filter))
+    }
+
+    fn deadline_report(&self) -> RpcFuture<'_, Result<Vec<deadline::TaskDeadline>, Error>> {
+        Box::pin(Self::deadline_report(self))
+    }
+
+    fn deadline_report_with<'a>(
+        &'a self,
+        options: &'a deadline::DeadlineOptions,
+    ) -> RpcFuture<'a, Result<Vec<deadline::TaskDeadline>, Error>> {
+        Box::pin(Self::deadline_report_with(self, options))
+    }
+
+    fn get_task<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> RpcFuture<'a, Result<Option<models::TaskResult>, Error>> {
+        Box::pin(Self::get_task(self, name))
+    }
+
+    fn get_file_transfers(&self) -> RpcFuture<'_, Result<Vec<models::FileTransfer>, Error>> {
+        Box::pin(Self::get_file_transfers(self))
+    }
+
+    fn get_statistics(&self) -> RpcFuture<'_, Result<Vec<models::ProjectStatistics>, Error>> {
+        Box::pin(Self::get_statistics(self))
+    }
+
+    fn set_mode_for(
+        &self,
+        c: models::Component,
+        m: models::RunMode,
+        duration: Option<Duration>,
+    ) -> RpcFuture<'_, Result<(), Error>> {
+        Box::pin(Self::set_mode_for(self, c, m, duration))
+    }
+
+    #[allow(deprecated)]
+    fn set_mode(
+        &self,
+        c: models::Component,
+        m: models::RunMode,
+        duration: f64,
+    ) -> RpcFuture<'_, Result<(), Error>> {
+        Box::pin(Self::set_mode(self, c, m, duration))
+    }
+
+    fn snooze(&self, duration: Duration) -> RpcFuture<'_, Result<(), Error>> {
+        Box::pin(Self::snooze(self, duration))
+    }
+
+    fn unsnooze(&self) -> RpcFuture<'_, Result<(), Error>> {
+        Box::pin(Self::unsnooze(self))
+    }
+
+    fn get_host_info(&self) -> RpcFuture<'_, Result<models::HostInfo, Error>> {
+        Box::pin(Self::get_host_info(self))
+    }
+
+    fn summary(&self) -> RpcFuture<'_, models::Summary> {
+        Box::pin(Self::summary(self))
+    }
+
+    fn get_cc_status(&self) -> RpcFuture<'_, Result<models::CcStatus, Error>> {
+        Box::pin(Self::get_cc_status(self))
+    }
+
+    fn run_benchmarks(&self) -> RpcFuture<'_, Result<(), Error>> {
+        Box::pin(Self::run_benchmarks(self))
+    }
+
+    fn run_benchmarks_and_wait(
+        &self,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> RpcFuture<'_, Result<models::HostInfo, Error>> {
+        Box::pin(Self::run_benchmarks_and_wait(self, poll_interval, timeout))
+    }
+
+    fn get_state(&self) -> RpcFuture<'_, Result<models::ClientState, Error>> {
+        Box::pin(Self::get_state(self))
+    }
+
+    fn set_language<'a>(&'a self, v: &'a str) -> RpcFuture<'a, Result<(), Error>> {
+        Box::pin(Self::set_language(self, v))
+    }
+
+    fn get_global_prefs_working(&self) -> RpcFuture<'_, Result<models::GlobalPreferences, Error>> {
+        Box::pin(Self::get_global_prefs_working(self))
+    }
+
+    fn get_global_prefs_override(
+        &self,
+    ) -> RpcFuture<'_, Result<Option<models::GlobalPreferences>, Error>> {
+        Box::pin(Self::get_global_prefs_override(self))
+    }
+
+    fn set_global_prefs_override<'a>(
+        &'a self,
+        prefs: Option<&'a models::GlobalPreferences>,
+    ) -> RpcFuture<'a, Result<(), Error>> {
+        Box::pin(Self::set_global_prefs_override(self, prefs))
+    }
+
+    fn read_global_prefs_override(&self) -> RpcFuture<'_, Result<(), Error>> {
+        Box::pin(Self::read_global_prefs_override(self))
+    }
+
+    fn update_global_prefs<'a>(
+        &'a self,
+        update: PrefsUpdate<'a>,
+    ) -> RpcFuture<'a, Result<models::GlobalPreferences, Error>> {
+        Box::pin(Self::update_global_prefs(self, update))
+    }
+}
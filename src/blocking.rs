@@ -0,0 +1,720 @@
+//! Synchronous facade over `Client`, for scripts and GUI callbacks without a runtime of their
+//! own. Needs the `blocking` feature.
+//!
+//! ```rust,no_run
+//! let client = boinc_rpc::blocking::Client::connect("127.0.0.1:31416", Some("pass")).unwrap();
+//! for task in client.get_results(true).unwrap() {
+//!     println!("{:?}", task.name);
+//! }
+//! ```
+
+use crate::{
+    account, deadline,
+    errors::Error,
+    models,
+    rpc::PollResult,
+    tasks,
+    transport::{Address, Transport},
+    Secret,
+};
+use std::{
+    future::Future,
+    sync::{Mutex, PoisonError},
+    time::Duration,
+};
+use tokio::runtime::Runtime;
+
+/// `crate::Client` over TCP, with a single-threaded runtime of its own to run requests on.
+///
+/// Each method blocks the calling thread until the `Client` method of the same name is done,
+/// timeouts included: those of the transport, such as `TransportBuilder::request_timeout`, as
+/// well as those taken as arguments. The client is `Send` and `Sync`, so it may be kept in
+/// shared state; calls from several threads take turns.
+///
+/// # Panics
+///
+/// All methods panic when called from within an asynchronous runtime, use `crate::Client`
+/// there.
+pub struct Client {
+    inner: crate::Client<Transport>,
+    runtime: Mutex<Runtime>,
+}
+
+fn runtime() -> Result<Runtime, Error> {
+    Ok(tokio::runtime::Builder::new()
+        .basic_scheduler()
+        .enable_all()
+        .build()?)
+}
+
+impl Client {
+    /// Requests are made over `transport`, which connects when first needed.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NetworkError` if the runtime cannot be made.
+    pub fn new(transport: Transport) -> Result<Self, Error> {
+        Ok(Self {
+            inner: crate::Client::new(transport),
+            runtime: Mutex::new(runtime()?),
+        })
+    }
+
+    /// Connects and authenticates right away, as `crate::Client::connect` does.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NetworkError` if the runtime cannot be made, and as
+    /// `crate::Client::connect` otherwise.
+    pub fn connect<A, P>(addr: A, password: Option<P>) -> Result<Self, Error>
+    where
+        A: Into<Address>,
+        P: Into<Secret>,
+    {
+        let mut runtime = runtime()?;
+        let inner = runtime.block_on(crate::Client::connect(addr, password))?;
+        Ok(Self {
+            inner,
+            runtime: Mutex::new(runtime),
+        })
+    }
+
+    /// The asynchronous client the requests are made with.
+    #[must_use]
+    pub const fn inner(&self) -> &crate::Client<Transport> {
+        &self.inner
+    }
+
+    #[must_use]
+    pub const fn transport(&self) -> &Transport {
+        self.inner.transport()
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        // The runtime holds no state of ours that a panic could leave half updated
+        self.runtime
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .block_on(future)
+    }
+
+    /// See `crate::Client::server_version`.
+    #[must_use]
+    pub fn server_version(&self) -> Option<models::VersionInfo> {
+        self.inner.server_version()
+    }
+
+    /// See `crate::Client::close`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn close(&self) -> Result<(), Error> {
+        self.block_on(self.inner.close())
+    }
+
+    /// See `crate::Client::get_messages`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn get_messages(&self, seqno: i64) -> Result<Vec<models::Message>, Error> {
+        self.block_on(self.inner.get_messages(seqno))
+    }
+
+    /// See `crate::Client::get_message_count`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn get_message_count(&self) -> Result<i64, Error> {
+        self.block_on(self.inner.get_message_count())
+    }
+
+    /// See `crate::Client::ping`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn ping(&self) -> Result<Duration, Error> {
+        self.block_on(self.inner.ping())
+    }
+
+    /// See `crate::Client::get_notices`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn get_notices(&self, seqno: i64) -> Result<Vec<models::Notice>, Error> {
+        self.block_on(self.inner.get_notices(seqno))
+    }
+
+    /// See `crate::Client::wait_for_task`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn wait_for_task<P>(
+        &self,
+        name: &str,
+        predicate: P,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> Result<models::TaskResult, Error>
+    where
+        P: Fn(&models::TaskResult) -> bool,
+    {
+        self.block_on(
+            self.inner
+                .wait_for_task(name, predicate, poll_interval, timeout),
+        )
+    }
+
+    /// See `crate::Client::wait_until_idle`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn wait_until_idle(
+        &self,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> Result<tasks::IdleReport, Error> {
+        self.block_on(self.inner.wait_until_idle(poll_interval, timeout))
+    }
+
+    /// See `crate::Client::wait_until_idle_with`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn wait_until_idle_with(
+        &self,
+        options: tasks::IdleOptions,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> Result<tasks::IdleReport, Error> {
+        self.block_on(
+            self.inner
+                .wait_until_idle_with(options, poll_interval, timeout),
+        )
+    }
+
+    /// See `crate::Client::get_projects`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn get_projects(&self) -> Result<Vec<models::ProjectInfo>, Error> {
+        self.block_on(self.inner.get_projects())
+    }
+
+    /// See `crate::Client::get_project_status`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn get_project_status(&self) -> Result<Vec<models::Project>, Error> {
+        self.block_on(self.inner.get_project_status())
+    }
+
+    /// See `crate::Client::get_attached_project`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn get_attached_project(&self, url: &str) -> Result<Option<models::Project>, Error> {
+        self.block_on(self.inner.get_attached_project(url))
+    }
+
+    /// See `crate::Client::get_account_manager_info`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn get_account_manager_info(&self) -> Result<models::AccountManagerInfo, Error> {
+        self.block_on(self.inner.get_account_manager_info())
+    }
+
+    /// See `crate::Client::get_account_manager_rpc_status`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn get_account_manager_rpc_status(&self) -> Result<i32, Error> {
+        self.block_on(self.inner.get_account_manager_rpc_status())
+    }
+
+    /// See `crate::Client::poll_account_manager_rpc`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn poll_account_manager_rpc(&self) -> Result<PollResult<Vec<String>>, Error> {
+        self.block_on(self.inner.poll_account_manager_rpc())
+    }
+
+    /// See `crate::Client::poll_project_attach`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn poll_project_attach(&self) -> Result<PollResult<Vec<String>>, Error> {
+        self.block_on(self.inner.poll_project_attach())
+    }
+
+    /// See `crate::Client::start_project_attach`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn start_project_attach<A: Into<Secret>>(
+        &self,
+        url: &str,
+        authenticator: A,
+        name: &str,
+    ) -> Result<(), Error> {
+        self.block_on(self.inner.start_project_attach(url, authenticator, name))
+    }
+
+    /// See `crate::Client::attach_project`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn attach_project<A: Into<Secret>>(
+        &self,
+        url: &str,
+        authenticator: A,
+        name: &str,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        self.block_on(
+            self.inner
+                .attach_project(url, authenticator, name, poll_interval, timeout),
+        )
+    }
+
+    /// See `crate::Client::project_op`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn project_op(&self, url: &str, op: models::ProjectOp) -> Result<(), Error> {
+        self.block_on(self.inner.project_op(url, op))
+    }
+
+    /// See `crate::Client::wait_for_project_update`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn wait_for_project_update(
+        &self,
+        url: &str,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> Result<models::Project, Error> {
+        self.block_on(
+            self.inner
+                .wait_for_project_update(url, poll_interval, timeout),
+        )
+    }
+
+    /// See `crate::Client::apply_project_plan`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn apply_project_plan(
+        &self,
+        plan: &account::ProjectPlan,
+        dry_run: bool,
+    ) -> Result<account::PlanReport, Error> {
+        self.block_on(self.inner.apply_project_plan(plan, dry_run))
+    }
+
+    /// See `crate::Client::add_project_by_account`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn add_project_by_account<P: Into<Secret>>(
+        &self,
+        url: &str,
+        email_or_user: &str,
+        password: P,
+        options: &account::AddProjectOptions,
+    ) -> Result<(), account::AddProjectError> {
+        self.block_on(
+            self.inner
+                .add_project_by_account(url, email_or_user, password, options),
+        )
+    }
+
+    /// See `crate::Client::start_lookup_account`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn start_lookup_account<P: Into<Secret>>(
+        &self,
+        url: &str,
+        email_or_user: &str,
+        password: P,
+        uses_username: bool,
+    ) -> Result<(), Error> {
+        self.block_on(
+            self.inner
+                .start_lookup_account(url, email_or_user, password, uses_username),
+        )
+    }
+
+    /// See `crate::Client::poll_lookup_account`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn poll_lookup_account(&self) -> Result<PollResult<Secret>, Error> {
+        self.block_on(self.inner.poll_lookup_account())
+    }
+
+    /// See `crate::Client::start_project_config`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn start_project_config(&self, url: &str) -> Result<(), Error> {
+        self.block_on(self.inner.start_project_config(url))
+    }
+
+    /// See `crate::Client::poll_project_config`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn poll_project_config(&self) -> Result<PollResult<models::ProjectConfig>, Error> {
+        self.block_on(self.inner.poll_project_config())
+    }
+
+    /// See `crate::Client::connect_to_account_manager`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn connect_to_account_manager(
+        &self,
+        url: &str,
+        name: &str,
+        password: &str,
+    ) -> Result<bool, Error> {
+        self.block_on(self.inner.connect_to_account_manager(url, name, password))
+    }
+
+    /// See `crate::Client::attach_account_manager`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn attach_account_manager(
+        &self,
+        url: &str,
+        name: &str,
+        password: &str,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        self.block_on(self.inner.attach_account_manager(
+            url,
+            name,
+            password,
+            poll_interval,
+            timeout,
+        ))
+    }
+
+    /// See `crate::Client::exchange_versions`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn exchange_versions(
+        &self,
+        info: Option<&models::VersionInfo>,
+    ) -> Result<models::VersionInfo, Error> {
+        self.block_on(self.inner.exchange_versions(info))
+    }
+
+    /// See `crate::Client::get_results`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn get_results(&self, active_only: bool) -> Result<Vec<models::TaskResult>, Error> {
+        self.block_on(self.inner.get_results(active_only))
+    }
+
+    /// See `crate::Client::abort_result`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn abort_result(&self, project_url: &str, name: &str) -> Result<(), Error> {
+        self.block_on(self.inner.abort_result(project_url, name))
+    }
+
+    /// See `crate::Client::suspend_result`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn suspend_result(&self, project_url: &str, name: &str) -> Result<(), Error> {
+        self.block_on(self.inner.suspend_result(project_url, name))
+    }
+
+    /// See `crate::Client::resume_result`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn resume_result(&self, project_url: &str, name: &str) -> Result<(), Error> {
+        self.block_on(self.inner.resume_result(project_url, name))
+    }
+
+    /// See `crate::Client::abort_tasks_matching`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn abort_tasks_matching<F>(
+        &self,
+        filter: F,
+        dry_run: bool,
+    ) -> Result<tasks::AbortReport, Error>
+    where
+        F: Fn(&models::TaskResult) -> bool,
+    {
+        self.block_on(self.inner.abort_tasks_matching(filter, dry_run))
+    }
+
+    /// See `crate::Client::get_tasks`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn get_tasks(&self, filter: &models::TaskFilter) -> Result<Vec<models::TaskResult>, Error> {
+        self.block_on(self.inner.get_tasks(filter))
+    }
+
+    /// See `crate::Client::deadline_report`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn deadline_report(&self) -> Result<Vec<deadline::TaskDeadline>, Error> {
+        self.block_on(self.inner.deadline_report())
+    }
+
+    /// See `crate::Client::deadline_report_with`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn deadline_report_with(
+        &self,
+        options: &deadline::DeadlineOptions,
+    ) -> Result<Vec<deadline::TaskDeadline>, Error> {
+        self.block_on(self.inner.deadline_report_with(options))
+    }
+
+    /// See `crate::Client::get_task`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn get_task(&self, name: &str) -> Result<Option<models::TaskResult>, Error> {
+        self.block_on(self.inner.get_task(name))
+    }
+
+    /// See `crate::Client::get_file_transfers`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn get_file_transfers(&self) -> Result<Vec<models::FileTransfer>, Error> {
+        self.block_on(self.inner.get_file_transfers())
+    }
+
+    /// See `crate::Client::get_statistics`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn get_statistics(&self) -> Result<Vec<models::ProjectStatistics>, Error> {
+        self.block_on(self.inner.get_statistics())
+    }
+
+    /// See `crate::Client::set_mode_for`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn set_mode_for(
+        &self,
+        c: models::Component,
+        m: models::RunMode,
+        duration: Option<Duration>,
+    ) -> Result<(), Error> {
+        self.block_on(self.inner.set_mode_for(c, m, duration))
+    }
+
+    /// See `crate::Client::set_mode`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    #[deprecated(note = "use `set_mode_for`, which takes the duration as `Option<Duration>`")]
+    #[allow(deprecated)]
+    pub fn set_mode(
+        &self,
+        c: models::Component,
+        m: models::RunMode,
+        duration: f64,
+    ) -> Result<(), Error> {
+        self.block_on(self.inner.set_mode(c, m, duration))
+    }
+
+    /// See `crate::Client::snooze`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn snooze(&self, duration: Duration) -> Result<(), Error> {
+        self.block_on(self.inner.snooze(duration))
+    }
+
+    /// See `crate::Client::unsnooze`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn unsnooze(&self) -> Result<(), Error> {
+        self.block_on(self.inner.unsnooze())
+    }
+
+    /// See `crate::Client::get_host_info`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn get_host_info(&self) -> Result<models::HostInfo, Error> {
+        self.block_on(self.inner.get_host_info())
+    }
+
+    /// See `crate::Client::summary`.
+    #[must_use]
+    pub fn summary(&self) -> models::Summary {
+        self.block_on(self.inner.summary())
+    }
+
+    /// See `crate::Client::get_cc_status`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn get_cc_status(&self) -> Result<models::CcStatus, Error> {
+        self.block_on(self.inner.get_cc_status())
+    }
+
+    /// See `crate::Client::run_benchmarks`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn run_benchmarks(&self) -> Result<(), Error> {
+        self.block_on(self.inner.run_benchmarks())
+    }
+
+    /// See `crate::Client::run_benchmarks_and_wait`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn run_benchmarks_and_wait(
+        &self,
+        poll_interval: Duration,
+        timeout: Duration,
+    ) -> Result<models::HostInfo, Error> {
+        self.block_on(self.inner.run_benchmarks_and_wait(poll_interval, timeout))
+    }
+
+    /// See `crate::Client::get_state`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn get_state(&self) -> Result<models::ClientState, Error> {
+        self.block_on(self.inner.get_state())
+    }
+
+    /// See `crate::Client::set_language`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn set_language(&self, v: &str) -> Result<(), Error> {
+        self.block_on(self.inner.set_language(v))
+    }
+
+    /// See `crate::Client::get_global_prefs_working`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn get_global_prefs_working(&self) -> Result<models::GlobalPreferences, Error> {
+        self.block_on(self.inner.get_global_prefs_working())
+    }
+
+    /// See `crate::Client::get_global_prefs_override`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn get_global_prefs_override(&self) -> Result<Option<models::GlobalPreferences>, Error> {
+        self.block_on(self.inner.get_global_prefs_override())
+    }
+
+    /// See `crate::Client::set_global_prefs_override`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn set_global_prefs_override(
+        &self,
+        prefs: Option<&models::GlobalPreferences>,
+    ) -> Result<(), Error> {
+        self.block_on(self.inner.set_global_prefs_override(prefs))
+    }
+
+    /// See `crate::Client::read_global_prefs_override`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn read_global_prefs_override(&self) -> Result<(), Error> {
+        self.block_on(self.inner.read_global_prefs_override())
+    }
+
+    /// See `crate::Client::update_global_prefs`.
+    ///
+    /// # Errors
+    ///
+    /// As that method.
+    pub fn update_global_prefs<F>(&self, update: F) -> Result<models::GlobalPreferences, Error>
+    where
+        F: FnOnce(&mut models::GlobalPreferences),
+    {
+        self.block_on(self.inner.update_global_prefs(update))
+    }
+}
@@ -0,0 +1,24 @@
+//! What a transport tells the `Client` of its connection to the daemon, so that the client can
+//! forget what it learned of a daemon that may have restarted since.
+
+use crate::models::VersionInfo;
+
+/// Connection a transport makes its requests over.
+#[derive(Clone, Debug)]
+pub struct ConnectionInfo {
+    /// Connections made by the transport so far, which changes whenever the daemon may be a
+    /// different one.
+    pub number: u64,
+    /// Daemon version from the version handshake, if made.
+    pub version: Option<VersionInfo>,
+}
+
+/// Transport that can tell which connection it is on, asked by `Client::summary` before
+/// relying on the replies it kept.
+///
+/// Transports that never reconnect, or do not know, can report the same number all along, upon
+/// which the replies are kept for as long as the client.
+pub trait ConnectionSource {
+    /// The connection the last request was made over, or the one to be made next.
+    fn connection_info(&self) -> ConnectionInfo;
+}
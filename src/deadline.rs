@@ -0,0 +1,281 @@
+//! Telling which tasks risk missing their report deadline, as `Client::deadline_report` does.
+
+use crate::models::TaskResult;
+use std::{
+    cmp::Ordering,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// How a task stands against its deadline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Risk {
+    Safe,
+    /// Done in time, but with less slack than `DeadlineOptions::tight_slack`.
+    Tight,
+    /// Not done in time at the expected throughput, or suspended.
+    WillMiss,
+}
+
+/// How `deadline::report` judges the risk of tasks.
+#[derive(Clone, Debug)]
+pub struct DeadlineOptions {
+    pub(crate) throughput: f64,
+    pub(crate) tight_slack: Duration,
+}
+
+impl Default for DeadlineOptions {
+    fn default() -> Self {
+        Self {
+            throughput: 1.0,
+            tight_slack: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+}
+
+impl DeadlineOptions {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// CPU time a task gets per second of wall-clock time, such as 0.5 on a host computing half
+    /// of the time or running two tasks per core. Tasks never finish at a throughput of 0. 1 by
+    /// default.
+    #[must_use]
+    pub const fn throughput(mut self, throughput: f64) -> Self {
+        self.throughput = throughput;
+        self
+    }
+
+    /// Slack below which a task is `Risk::Tight`. One day by default.
+    #[must_use]
+    pub const fn tight_slack(mut self, tight_slack: Duration) -> Self {
+        self.tight_slack = tight_slack;
+        self
+    }
+}
+
+/// Standing of a task against its deadline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaskDeadline {
+    pub name: String,
+    pub project_url: Option<String>,
+    pub deadline: SystemTime,
+    /// Wall-clock time the task needs still at the expected throughput, `None` while it is
+    /// suspended and makes no progress. Tasks without an estimate are taken to need none.
+    pub remaining: Option<Duration>,
+    /// Seconds to spare between the task being done and its deadline: negative if it will be
+    /// late, and negative infinity while it is suspended.
+    pub slack: f64,
+    pub risk: Risk,
+}
+
+/// Standing of each task against its deadline as of `now`, the tasks with the least slack first.
+///
+/// A task will miss its deadline when the time left until the deadline is less than
+/// `estimated_cpu_time_remaining` divided by the throughput. Tasks done computing need no more
+/// time, even when suspended, but still miss their deadline once it has passed without them
+/// being reported. Tasks without a name or deadline are left out.
+#[must_use]
+pub fn report(
+    tasks: &[TaskResult],
+    now: SystemTime,
+    options: &DeadlineOptions,
+) -> Vec<TaskDeadline> {
+    let now = now
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    let mut report: Vec<_> = tasks
+        .iter()
+        .filter_map(|task| {
+            let deadline = task.report_deadline_at()?;
+            let suspended = task.suspended_via_gui == Some(true)
+                || task.project_suspended_via_gui == Some(true);
+            let remaining = match task
+                .estimated_cpu_time_remaining
+                .filter(|&seconds| seconds > 0.0)
+            {
+                None => Some(0.0),
+                Some(_) if suspended => None,
+                Some(cpu_time) => {
+                    Some(cpu_time / options.throughput).filter(|seconds| seconds.is_finite())
+                }
+            };
+
+            let slack = match remaining {
+                Some(remaining) => task.report_deadline? - now - remaining,
+                None => f64::NEG_INFINITY,
+            };
+            let risk = if slack < 0.0 {
+                Risk::WillMiss
+            } else if slack < options.tight_slack.as_secs_f64() {
+                Risk::Tight
+            } else {
+                Risk::Safe
+            };
+            Some(TaskDeadline {
+                name: task.name.clone()?,
+                project_url: task.project_url.clone(),
+                deadline,
+                remaining: remaining.map(Duration::from_secs_f64),
+                slack,
+                risk,
+            })
+        })
+        .collect();
+    report.sort_by(|a, b| a.slack.partial_cmp(&b.slack).unwrap_or(Ordering::Equal));
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: f64 = 3600.0;
+    const NOW: f64 = 1_700_000_000.0;
+
+    fn now() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs_f64(NOW)
+    }
+
+    /// Task due in `due` hours, needing `remaining` hours of CPU time.
+    fn task(name: &str, due: f64, remaining: f64) -> TaskResult {
+        TaskResult {
+            name: Some(name.into()),
+            project_url: Some("https://einsteinathome.org/".into()),
+            report_deadline: Some(due.mul_add(HOUR, NOW)),
+            estimated_cpu_time_remaining: Some(remaining * HOUR),
+            ..TaskResult::default()
+        }
+    }
+
+    /// Slack in hours and risk of each task, by name.
+    fn assess(tasks: &[TaskResult], options: &DeadlineOptions) -> Vec<(String, f64, Risk)> {
+        report(tasks, now(), options)
+            .into_iter()
+            .map(|task| (task.name, task.slack / HOUR, task.risk))
+            .collect()
+    }
+
+    fn row(name: &str, slack: f64, risk: Risk) -> (String, f64, Risk) {
+        (name.into(), slack, risk)
+    }
+
+    #[test]
+    fn risks() {
+        let tasks = [
+            task("safe", 72.0, 1.0),
+            task("tight", 10.0, 2.0),
+            task("late", 1.0, 2.0),
+            task("overdue", -1.0, 0.0),
+            // Right at the thresholds
+            task("exactly_tight", 24.0, 0.0),
+            task("just_in_time", 2.0, 2.0),
+        ];
+        assert_eq!(
+            assess(&tasks, &DeadlineOptions::new()),
+            [
+                row("late", -1.0, Risk::WillMiss),
+                row("overdue", -1.0, Risk::WillMiss),
+                row("just_in_time", 0.0, Risk::Tight),
+                row("tight", 8.0, Risk::Tight),
+                row("exactly_tight", 24.0, Risk::Safe),
+                row("safe", 71.0, Risk::Safe),
+            ]
+        );
+    }
+
+    #[test]
+    fn throughput() {
+        let tasks = [task("a", 3.0, 2.0), task("b", 10.0, 2.0)];
+        assert_eq!(
+            assess(&tasks, &DeadlineOptions::new()),
+            [row("a", 1.0, Risk::Tight), row("b", 8.0, Risk::Tight)]
+        );
+        // Twice the wall-clock time at half the throughput
+        assert_eq!(
+            assess(&tasks, &DeadlineOptions::new().throughput(0.5)),
+            [row("a", -1.0, Risk::WillMiss), row("b", 6.0, Risk::Tight)]
+        );
+        let stalled = report(&tasks, now(), &DeadlineOptions::new().throughput(0.0));
+        assert!(stalled
+            .iter()
+            .all(|task| task.remaining.is_none() && task.risk == Risk::WillMiss));
+    }
+
+    #[test]
+    fn tight_slack() {
+        let tasks = [task("a", 10.0, 2.0), task("b", 3.0, 2.0)];
+        let options = DeadlineOptions::new().tight_slack(Duration::from_secs(2 * 3600));
+        assert_eq!(
+            assess(&tasks, &options),
+            [row("b", 1.0, Risk::Tight), row("a", 8.0, Risk::Safe)]
+        );
+    }
+
+    #[test]
+    fn suspended() {
+        let tasks = [
+            TaskResult {
+                suspended_via_gui: Some(true),
+                ..task("suspended", 72.0, 1.0)
+            },
+            TaskResult {
+                project_suspended_via_gui: Some(true),
+                ..task("project_suspended", 72.0, 1.0)
+            },
+            // Done computing, so waiting on nothing but the report
+            TaskResult {
+                suspended_via_gui: Some(true),
+                ..task("done", 72.0, 0.0)
+            },
+        ];
+        let report = report(&tasks, now(), &DeadlineOptions::new());
+        assert_eq!(report.len(), 3);
+        for task in &report[..2] {
+            assert_eq!(task.remaining, None);
+            assert!(task.slack.is_infinite() && task.slack < 0.0);
+            assert_eq!(task.risk, Risk::WillMiss);
+        }
+        assert_eq!(report[2].name, "done");
+        assert_eq!(report[2].remaining, Some(Duration::from_secs(0)));
+        assert_eq!(report[2].risk, Risk::Safe);
+    }
+
+    #[test]
+    fn times() {
+        let report = report(&[task("a", 2.0, 0.5)], now(), &DeadlineOptions::new());
+        assert_eq!(report[0].deadline, now() + Duration::from_secs(2 * 3600));
+        assert_eq!(report[0].remaining, Some(Duration::from_secs(1800)));
+    }
+
+    #[test]
+    fn incomplete_tasks() {
+        let tasks = [
+            TaskResult {
+                report_deadline: None,
+                ..task("no_deadline", 1.0, 2.0)
+            },
+            TaskResult {
+                name: None,
+                ..task("", 1.0, 2.0)
+            },
+            TaskResult {
+                estimated_cpu_time_remaining: None,
+                ..task("no_estimate", 1.0, 0.0)
+            },
+            TaskResult {
+                estimated_cpu_time_remaining: Some(-5.0),
+                ..task("negative_estimate", 1.0, 0.0)
+            },
+        ];
+        assert_eq!(
+            assess(&tasks, &DeadlineOptions::new()),
+            [
+                row("no_estimate", 1.0, Risk::Tight),
+                row("negative_estimate", 1.0, Risk::Tight),
+            ]
+        );
+    }
+}
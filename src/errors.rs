@@ -1,20 +1,292 @@
+use std::fmt;
+
+/// Reason an authentication attempt failed.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum AuthFailure {
+    /// Daemon asked for a nonce hash but no password was supplied.
+    PasswordRequired,
+    /// Daemon rejected the supplied password.
+    WrongPassword,
+    /// Daemon sent an empty or missing nonce.
+    MalformedNonce,
+    /// Daemon refused access without asking for a password, or mid-session.
+    Unauthorized,
+    /// Daemon rejected a request with the given message, e.g. "Missing authenticator".
+    Rejected(String),
+}
+
+impl fmt::Display for AuthFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PasswordRequired => write!(f, "password required"),
+            Self::WrongPassword => write!(f, "wrong password"),
+            Self::MalformedNonce => write!(f, "malformed nonce"),
+            Self::Unauthorized => write!(f, "unauthorized"),
+            Self::Rejected(msg) => write!(f, "rejected: {msg}"),
+        }
+    }
+}
+
+/// Numeric error code reported by the daemon, as listed in BOINC's `lib/error_numbers.h`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BoincErrorCode {
+    AlreadyAttached,
+    DbNotFound,
+    Authenticator,
+    NotFound,
+    ProjectDown,
+    BadUserName,
+    InvalidUrl,
+    InProgress,
+    BadEmailAddr,
+    BadPasswd,
+    NonuniqueEmail,
+    AcctCreationDisabled,
+    Unknown(i32),
+}
+
+impl BoincErrorCode {
+    #[must_use]
+    pub const fn code(self) -> i32 {
+        match self {
+            Self::AlreadyAttached => -130,
+            Self::DbNotFound => -136,
+            Self::Authenticator => -155,
+            Self::NotFound => -161,
+            Self::ProjectDown => -183,
+            Self::BadUserName => -188,
+            Self::InvalidUrl => -189,
+            Self::InProgress => -204,
+            Self::BadEmailAddr => -205,
+            Self::BadPasswd => -206,
+            Self::NonuniqueEmail => -207,
+            Self::AcctCreationDisabled => -208,
+            Self::Unknown(v) => v,
+        }
+    }
+}
+
+impl From<i32> for BoincErrorCode {
+    fn from(v: i32) -> Self {
+        match v {
+            -130 => Self::AlreadyAttached,
+            -136 => Self::DbNotFound,
+            -155 => Self::Authenticator,
+            -161 => Self::NotFound,
+            -183 => Self::ProjectDown,
+            -188 => Self::BadUserName,
+            -189 => Self::InvalidUrl,
+            -204 => Self::InProgress,
+            -205 => Self::BadEmailAddr,
+            -206 => Self::BadPasswd,
+            -207 => Self::NonuniqueEmail,
+            -208 => Self::AcctCreationDisabled,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub enum Error {
     ConnectError(String),
+    /// A request could not be encoded, or a reply could not be parsed. Either way the whole
+    /// message was dealt with, so the connection can carry on.
     DataParseError(String),
+    /// Reply to `request` lacked the expected `node`.
+    NodeNotFound {
+        request: String,
+        node: String,
+    },
+    /// Reply to `request` carried a `node` this crate does not know. Only raised with the
+    /// `strict-unknown` feature.
+    UnexpectedNode {
+        request: String,
+        node: String,
+    },
+    /// Contents of `node` could not be parsed.
+    InvalidNodeValue {
+        node: String,
+        value: String,
+    },
     InvalidPasswordError(String),
     DaemonError(String),
     NullError(String),
     NetworkError(String),
-    StatusError(i32),
-    AuthError(String),
+    /// Daemon closed the connection.
+    ConnectionClosed,
+    /// Daemon did not respond in time.
+    Timeout,
+    /// Frame exceeded the codec's maximum length, in bytes. The connection cannot be used
+    /// any further.
+    FrameTooLong {
+        limit: usize,
+    },
+    StatusError(BoincErrorCode),
+    AuthError(AuthFailure),
     InvalidURLError(String),
     AlreadyAttachedError(String),
+    /// Account manager RPC failed with `code`, the account manager explaining with `messages`.
+    AccountManagerFailed {
+        code: BoincErrorCode,
+        messages: Vec<String>,
+    },
+    /// Task of the given name is not on the task list (any more), as when reported or aborted.
+    TaskGone(String),
+    /// The client backs off from contacting the scheduler of the project at `master_url` until
+    /// `min_rpc_time`, in seconds since the Unix epoch.
+    SchedulerRpcDeferred {
+        master_url: String,
+        min_rpc_time: f64,
+    },
+    /// Request was not sent, as its arguments make no sense.
+    InvalidArgument(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ConnectError(msg) => write!(f, "Connection failed: {msg}"),
+            Self::DataParseError(msg) => write!(f, "Invalid data: {msg}"),
+            Self::NodeNotFound { request, node } => {
+                write!(f, "Reply to {request} lacks {node}")
+            }
+            Self::UnexpectedNode { request, node } => {
+                write!(f, "Reply to {request} has unexpected {node}")
+            }
+            Self::InvalidNodeValue { node, value } => {
+                write!(f, "Invalid value for {node}: {value:?}")
+            }
+            Self::InvalidPasswordError(msg) => write!(f, "Invalid password: {msg}"),
+            Self::DaemonError(msg) => write!(f, "Daemon error: {msg}"),
+            Self::NullError(msg) => write!(f, "Missing data: {msg}"),
+            Self::NetworkError(msg) => write!(f, "Network error: {msg}"),
+            Self::ConnectionClosed => write!(f, "Connection closed"),
+            Self::Timeout => write!(f, "Timed out"),
+            Self::FrameTooLong { limit } => {
+                write!(f, "Frame exceeds the maximum length of {limit} bytes")
+            }
+            Self::StatusError(code) => {
+                write!(f, "Daemon returned error {:?} ({})", code, code.code())
+            }
+            Self::AuthError(reason) => write!(f, "Authentication failed: {reason}"),
+            Self::InvalidURLError(msg) => write!(f, "Invalid URL: {msg}"),
+            Self::AlreadyAttachedError(msg) => write!(f, "Already attached: {msg}"),
+            Self::AccountManagerFailed { code, messages } => {
+                write!(
+                    f,
+                    "Account manager returned error {:?} ({})",
+                    code,
+                    code.code()
+                )?;
+                if !messages.is_empty() {
+                    write!(f, ": {}", messages.join("; "))?;
+                }
+                Ok(())
+            }
+            Self::TaskGone(name) => write!(f, "Task {name} is gone"),
+            Self::SchedulerRpcDeferred {
+                master_url,
+                min_rpc_time,
+            } => write!(
+                f,
+                "Scheduler of {master_url} is not to be contacted until {min_rpc_time} (Unix time)"
+            ),
+            Self::InvalidArgument(msg) => write!(f, "Invalid argument: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Error type of generic `tower` middleware.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+impl From<BoxError> for Error {
+    /// Recovers the `Error` from a service wrapped in middleware. Timeouts of the `tower` timeout
+    /// layer become `Error::Timeout`, anything else a `NetworkError`.
+    fn from(e: BoxError) -> Self {
+        let mut source: Option<&(dyn std::error::Error + 'static)> = Some(&*e);
+        // Middleware such as `Buffer` wraps the inner error
+        while let Some(err) = source {
+            if let Some(err) = err.downcast_ref::<Self>() {
+                return err.clone();
+            }
+            #[cfg(feature = "tokio")]
+            if err.is::<tower::timeout::error::Elapsed>() {
+                return Self::Timeout;
+            }
+            source = err.source();
+        }
+        Self::NetworkError(format!("{e}"))
+    }
+}
+
+/// Coarse classification of `Error` variants.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// Connection could not be established or was lost.
+    Network,
+    /// Daemon sent something this crate could not make sense of.
+    Protocol,
+    Auth,
+    /// Daemon understood the request but refused or failed it.
+    Daemon,
+    /// Requested data does not exist.
+    NotFound,
+    /// Request was refused before being sent.
+    InvalidInput,
+}
+
+impl Error {
+    #[must_use]
+    pub const fn kind(&self) -> ErrorKind {
+        match self {
+            Self::ConnectError(_)
+            | Self::NetworkError(_)
+            | Self::ConnectionClosed
+            | Self::Timeout => ErrorKind::Network,
+            Self::DataParseError(_)
+            | Self::FrameTooLong { .. }
+            | Self::UnexpectedNode { .. }
+            | Self::InvalidNodeValue { .. }
+            | Self::NullError(_) => ErrorKind::Protocol,
+            Self::InvalidPasswordError(_) | Self::AuthError(_) => ErrorKind::Auth,
+            Self::NodeNotFound { .. }
+            | Self::StatusError(BoincErrorCode::NotFound | BoincErrorCode::DbNotFound)
+            | Self::TaskGone(_) => ErrorKind::NotFound,
+            Self::StatusError(_)
+            | Self::DaemonError(_)
+            | Self::InvalidURLError(_)
+            | Self::AlreadyAttachedError(_)
+            | Self::AccountManagerFailed { .. }
+            | Self::SchedulerRpcDeferred { .. } => ErrorKind::Daemon,
+            Self::InvalidArgument(_) => ErrorKind::InvalidInput,
+        }
+    }
+
+    /// Whether repeating the request later may succeed.
+    #[must_use]
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::StatusError(BoincErrorCode::InProgress | BoincErrorCode::ProjectDown)
+            | Self::AccountManagerFailed {
+                code: BoincErrorCode::ProjectDown,
+                ..
+            } => true,
+            _ => self.kind() == ErrorKind::Network,
+        }
+    }
+
+    /// Whether the connection that a request failed on is still in step with the daemon, and
+    /// so fit for the next request.
+    pub(crate) const fn keeps_connection(&self) -> bool {
+        matches!(self, Self::DataParseError(_))
+    }
 }
 
 impl From<std::io::Error> for Error {
     fn from(e: std::io::Error) -> Self {
-        Self::NetworkError(format!("{}", e))
+        Self::NetworkError(format!("{e}"))
     }
 }
 
@@ -26,6 +298,155 @@ impl From<std::string::FromUtf8Error> for Error {
 
 impl From<treexml::Error> for Error {
     fn from(e: treexml::Error) -> Self {
-        Self::DataParseError(format!("XML error: {}", e))
+        Self::DataParseError(format!("XML error: {e}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuthFailure, BoincErrorCode, BoxError, Error, ErrorKind};
+
+    #[test]
+    fn boinc_error_code_round_trip() {
+        for code in &[
+            -130, -136, -155, -161, -183, -188, -189, -204, -205, -206, -207, -208,
+        ] {
+            let v = BoincErrorCode::from(*code);
+            assert_ne!(v, BoincErrorCode::Unknown(*code));
+            assert_eq!(v.code(), *code);
+        }
+        assert_eq!(BoincErrorCode::from(-1), BoincErrorCode::Unknown(-1));
+        assert_eq!(BoincErrorCode::Unknown(-1).code(), -1);
+    }
+
+    fn deferred() -> Error {
+        Error::SchedulerRpcDeferred {
+            master_url: "https://project.example/".into(),
+            min_rpc_time: 0.0,
+        }
+    }
+
+    #[test]
+    fn error_classification() {
+        let s = String::new;
+        let fixtures = vec![
+            (Error::ConnectError(s()), ErrorKind::Network, true),
+            (Error::NetworkError(s()), ErrorKind::Network, true),
+            (Error::ConnectionClosed, ErrorKind::Network, true),
+            (Error::Timeout, ErrorKind::Network, true),
+            (Error::DataParseError(s()), ErrorKind::Protocol, false),
+            (Error::FrameTooLong { limit: 0 }, ErrorKind::Protocol, false),
+            (
+                Error::UnexpectedNode {
+                    request: s(),
+                    node: s(),
+                },
+                ErrorKind::Protocol,
+                false,
+            ),
+            (
+                Error::InvalidNodeValue {
+                    node: s(),
+                    value: s(),
+                },
+                ErrorKind::Protocol,
+                false,
+            ),
+            (Error::NullError(s()), ErrorKind::Protocol, false),
+            (Error::InvalidPasswordError(s()), ErrorKind::Auth, false),
+            (
+                Error::AuthError(AuthFailure::WrongPassword),
+                ErrorKind::Auth,
+                false,
+            ),
+            (
+                Error::NodeNotFound {
+                    request: s(),
+                    node: s(),
+                },
+                ErrorKind::NotFound,
+                false,
+            ),
+            (
+                Error::StatusError(BoincErrorCode::NotFound),
+                ErrorKind::NotFound,
+                false,
+            ),
+            (
+                Error::StatusError(BoincErrorCode::InProgress),
+                ErrorKind::Daemon,
+                true,
+            ),
+            (
+                Error::StatusError(BoincErrorCode::ProjectDown),
+                ErrorKind::Daemon,
+                true,
+            ),
+            (
+                Error::StatusError(BoincErrorCode::BadPasswd),
+                ErrorKind::Daemon,
+                false,
+            ),
+            (Error::DaemonError(s()), ErrorKind::Daemon, false),
+            (Error::InvalidURLError(s()), ErrorKind::Daemon, false),
+            (Error::AlreadyAttachedError(s()), ErrorKind::Daemon, false),
+            (
+                Error::AccountManagerFailed {
+                    code: BoincErrorCode::ProjectDown,
+                    messages: vec![],
+                },
+                ErrorKind::Daemon,
+                true,
+            ),
+            (Error::TaskGone(s()), ErrorKind::NotFound, false),
+            (deferred(), ErrorKind::Daemon, false),
+            (Error::InvalidArgument(s()), ErrorKind::InvalidInput, false),
+        ];
+
+        for (e, kind, transient) in fixtures {
+            covered(&e);
+            assert_eq!(e.kind(), kind, "{e:?}");
+            assert_eq!(e.is_transient(), transient, "{e:?}");
+        }
+    }
+
+    /// New variants have to be added to the fixtures of `error_classification`.
+    fn covered(e: &Error) {
+        match e {
+            Error::ConnectError(_)
+            | Error::DataParseError(_)
+            | Error::NodeNotFound { .. }
+            | Error::UnexpectedNode { .. }
+            | Error::InvalidNodeValue { .. }
+            | Error::InvalidPasswordError(_)
+            | Error::DaemonError(_)
+            | Error::NullError(_)
+            | Error::NetworkError(_)
+            | Error::ConnectionClosed
+            | Error::Timeout
+            | Error::FrameTooLong { .. }
+            | Error::StatusError(_)
+            | Error::AuthError(_)
+            | Error::InvalidURLError(_)
+            | Error::AlreadyAttachedError(_)
+            | Error::AccountManagerFailed { .. }
+            | Error::TaskGone(_)
+            | Error::SchedulerRpcDeferred { .. }
+            | Error::InvalidArgument(_) => {}
+        }
+    }
+
+    #[test]
+    fn from_box_error() {
+        let e: BoxError = Box::new(Error::AuthError(AuthFailure::WrongPassword));
+        assert_eq!(Error::from(e), Error::AuthError(AuthFailure::WrongPassword));
+
+        let e: BoxError = "overloaded".into();
+        assert_eq!(Error::from(e), Error::NetworkError("overloaded".into()));
+
+        assert_eq!(
+            format!("{}", Error::AuthError(AuthFailure::WrongPassword)),
+            "Authentication failed: wrong password"
+        );
     }
 }
@@ -0,0 +1,262 @@
+//! Running the same requests against many daemons at once.
+
+use crate::{errors::Error, models, Client, Transport};
+use futures::{Future, StreamExt};
+use std::collections::BTreeMap;
+
+/// Outcome of a bulk operation, by host name.
+pub type FleetResults<T> = BTreeMap<String, Result<T, Error>>;
+
+/// Named clients, one per host, queried together.
+///
+/// Bulk operations run on all hosts concurrently, up to the concurrency limit, and report
+/// every host's outcome separately: a host that is down or fails the request does not affect
+/// the others.
+pub struct MultiClient<S = Transport> {
+    clients: BTreeMap<String, Client<S>>,
+    concurrency: usize,
+}
+
+impl<S> Default for MultiClient<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> MultiClient<S> {
+    /// Queries up to 16 hosts at a time.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            clients: BTreeMap::new(),
+            concurrency: 16,
+        }
+    }
+
+    /// Limits how many hosts are queried at a time. A limit of 0 is treated as 1.
+    #[must_use]
+    pub fn concurrency(mut self, limit: usize) -> Self {
+        self.concurrency = limit.max(1);
+        self
+    }
+
+    /// Adds a host, returning the client it replaces.
+    pub fn insert<N: Into<String>>(&mut self, name: N, client: Client<S>) -> Option<Client<S>> {
+        self.clients.insert(name.into(), client)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Client<S>> {
+        self.clients.remove(name)
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&Client<S>> {
+        self.clients.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Client<S>> {
+        self.clients.get_mut(name)
+    }
+
+    /// Host names, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.clients.keys().map(String::as_str)
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// Runs `f` for every host, collecting the results.
+    ///
+    /// ```rust,no_run
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// use boinc_rpc::{fleet::MultiClient, Client, Transport};
+    ///
+    /// let mut fleet = MultiClient::new();
+    /// fleet.insert("alpha", Client::new(Transport::new("alpha:31416", Some("pass"))));
+    /// fleet.insert("beta", Client::new(Transport::new("beta:31416", Some("pass"))));
+    ///
+    /// let counts = fleet
+    ///     .for_each(|_, client| async move { Ok(client.get_results(true).await?.len()) })
+    ///     .await;
+    /// for (host, count) in counts {
+    ///     println!("{}: {:?}", host, count);
+    /// }
+    /// # })
+    /// ```
+    // `Send` if `Fut` and the clients are
+    #[allow(clippy::future_not_send)]
+    pub async fn for_each<'a, F, Fut, T>(&'a self, mut f: F) -> FleetResults<T>
+    where
+        F: FnMut(&'a str, &'a Client<S>) -> Fut,
+        Fut: Future<Output = Result<T, Error>> + 'a,
+    {
+        // A loop rather than `Iterator::map`, whose closure would keep the future from being
+        // `Send`
+        let mut futures = Vec::with_capacity(self.clients.len());
+        for (name, client) in &self.clients {
+            let future = f(name, client);
+            futures.push(async move { (name.clone(), future.await) });
+        }
+        futures::stream::iter(futures)
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await
+    }
+}
+
+// As with `Client`, the futures are `Send` if the transport is
+#[allow(clippy::future_not_send)]
+impl<S> MultiClient<S>
+where
+    S: tower::Service<Vec<treexml::Element>, Response = Vec<treexml::Element>> + Clone,
+    S::Error: Into<Error>,
+{
+    pub async fn get_results_all(
+        &self,
+        active_only: bool,
+    ) -> FleetResults<Vec<models::TaskResult>> {
+        self.for_each(|_, client| client.get_results(active_only))
+            .await
+    }
+
+    pub async fn get_host_info_all(&self) -> FleetResults<models::HostInfo> {
+        self.for_each(|_, client| client.get_host_info()).await
+    }
+
+    pub async fn set_mode_for_all(
+        &self,
+        c: models::Component,
+        m: models::RunMode,
+        duration: Option<std::time::Duration>,
+    ) -> FleetResults<()> {
+        self.for_each(|_, client| client.set_mode_for(c, m, duration))
+            .await
+    }
+
+    #[deprecated(note = "use `set_mode_for_all`, which takes the duration as `Option<Duration>`")]
+    #[allow(deprecated)]
+    pub async fn set_mode_all(
+        &self,
+        c: models::Component,
+        m: models::RunMode,
+        duration: f64,
+    ) -> FleetResults<()> {
+        self.for_each(|_, client| client.set_mode(c, m, duration))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::{BoincCodec, CodecMode};
+    use futures::{SinkExt, TryStreamExt};
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+    use tokio::net::TcpListener;
+    use tokio_util::codec::Decoder;
+
+    /// Lets anyone in, answers `get_results` with an empty list and acknowledges everything else.
+    async fn daemon() -> String {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let mut conn = BoincCodec::new(CodecMode::Server).framed(stream);
+                    while let Ok(Some(req)) = conn.try_next().await {
+                        let reply = match &*req[0].name {
+                            "auth1" => treexml::Element::new("authorized"),
+                            "get_results" => treexml::Element::new("results"),
+                            _ => treexml::Element::new("success"),
+                        };
+                        conn.send(vec![reply]).await.unwrap();
+                    }
+                });
+            }
+        });
+        addr
+    }
+
+    async fn refusing_addr() -> String {
+        TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string()
+    }
+
+    async fn fleet() -> MultiClient {
+        let mut fleet = MultiClient::new();
+        for name in &["alpha", "beta"] {
+            fleet.insert(
+                *name,
+                Client::new(Transport::new(daemon().await, None::<&str>)),
+            );
+        }
+        fleet.insert(
+            "down",
+            Client::new(Transport::new(refusing_addr().await, None::<&str>)),
+        );
+        fleet
+    }
+
+    #[tokio::test]
+    async fn partial_success() {
+        let fleet = fleet().await;
+        assert_eq!(fleet.names().collect::<Vec<_>>(), ["alpha", "beta", "down"]);
+
+        let results = fleet.get_results_all(false).await;
+        assert_eq!(results.len(), 3);
+        assert!(results["alpha"].as_ref().unwrap().is_empty());
+        assert!(results["beta"].as_ref().unwrap().is_empty());
+        assert!(matches!(results["down"], Err(Error::ConnectError(_))));
+
+        let results = fleet
+            .set_mode_for_all(models::Component::CPU, models::RunMode::Never, None)
+            .await;
+        assert_eq!(results["alpha"], Ok(()));
+        assert_eq!(results["beta"], Ok(()));
+        assert!(results["down"].is_err());
+    }
+
+    #[tokio::test]
+    async fn bounded_concurrency() {
+        let fleet = fleet().await.concurrency(2);
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let results = fleet
+            .for_each(|name, client| {
+                let running = running.clone();
+                let peak = peak.clone();
+                async move {
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    let res = client.get_results(false).await.map(|_| name.len());
+                    tokio::time::delay_for(Duration::from_millis(20)).await;
+                    running.fetch_sub(1, Ordering::SeqCst);
+                    res
+                }
+            })
+            .await;
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+        assert_eq!(results["alpha"], Ok(5));
+        assert!(matches!(results["down"], Err(Error::ConnectError(_))));
+    }
+}
@@ -3,36 +3,70 @@
 //! # Example
 //!
 //! ```rust,no_run
+//! # #[cfg(feature = "tokio")]
 //! # tokio::runtime::Runtime::new().unwrap().block_on(async {
 //! let transport = boinc_rpc::Transport::new("127.0.0.1:31416", Some("my-pass-in-gui_rpc_auth.cfg"));
-//! let mut client = boinc_rpc::Client::new(transport);
+//! let client = boinc_rpc::Client::new(transport);
 //!
 //! println!("{:?}\n", client.get_messages(0).await.unwrap());
 //! println!("{:?}\n", client.get_projects().await.unwrap());
 //! println!("{:?}\n", client.get_account_manager_info().await.unwrap());
-//! println!("{:?}\n", client.exchange_versions(&boinc_rpc::models::VersionInfo::default()).await.unwrap());
+//! println!("{:?}\n", client.exchange_versions(None).await.unwrap());
 //! println!("{:?}\n", client.get_results(false).await.unwrap());
-//! # })
+//! # });
 //! ```
+//!
+//! Synchronous code without a runtime of its own can use `blocking::Client` instead, behind the
+//! `blocking` feature.
+//!
+//! # Testing
+//!
+//! Code taking a `Client` is best tested with `test_util::MockTransport`, which answers the
+//! requests the code is expected to make as scripted, behind the `test-util` feature. Code that
+//! takes a `BoincRpc` instead can also be handed a `test_util::StubRpc`. Replies captured off the
+//! wire make for fixtures too, parsed with `parse_reply` and `parse_reply_vec` as `Client` would.
 
 #![warn(clippy::all, clippy::pedantic, clippy::nursery)]
 #![allow(clippy::pub_enum_variant_names, clippy::type_complexity)]
 
+pub mod account;
+pub mod api;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod connection;
+pub mod deadline;
 mod errors;
+#[cfg(feature = "tokio")]
+pub mod fleet;
 pub mod models;
+pub mod offline;
+pub mod prefs;
 pub mod rpc;
+mod secret;
+pub mod shared;
+pub mod tasks;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+mod time;
+#[cfg(feature = "tokio")]
+pub mod transport;
 mod util;
+pub mod watch;
+mod xml;
 
-use crate::{errors::*, rpc::*};
-use std::{
-    fmt::Display,
-    future::Future,
-    pin::Pin,
-    sync::Arc,
-    task::{Context, Poll},
+#[cfg(feature = "tokio")]
+pub use crate::transport::{Transport, DEFAULT_PORT};
+pub use crate::{
+    api::BoincRpc,
+    errors::{AuthFailure, BoincErrorCode, BoxError, Error, ErrorKind},
+    rpc::DaemonStream,
+    secret::Secret,
+    shared::SharedStream,
 };
-use tokio::{net::TcpStream, sync::Mutex};
-use tower::ServiceExt;
+
+use crate::rpc::request::Request;
+use crate::rpc::{compute_passwd_hash, PollResult, TERMCHAR};
+use tracing::Instrument;
 
 fn verify_rpc_reply_contents(data: &[treexml::Element]) -> Result<bool, Error> {
     let mut success = false;
@@ -41,23 +75,33 @@ fn verify_rpc_reply_contents(data: &[treexml::Element]) -> Result<bool, Error> {
             "success" => success = true,
             "status" => {
                 return Err(Error::StatusError(
-                    util::eval_node_contents(node).unwrap_or(9999),
+                    util::eval_node_contents::<i32>(node).unwrap_or(9999).into(),
                 ));
             }
             "unauthorized" => {
-                return Err(Error::AuthError(String::new()));
+                return Err(Error::AuthError(AuthFailure::Unauthorized));
             }
             "error" => {
-                let error_msg = node
-                    .text
-                    .clone()
+                if let Some(code) = node
+                    .children
+                    .iter()
+                    .find(|n| n.name == "error_num")
+                    .and_then(util::eval_node_contents::<i32>)
+                    .or_else(|| util::eval_node_contents(node))
+                {
+                    return Err(Error::StatusError(code.into()));
+                }
+
+                let error_msg = util::trimmed_optional(&node.text)
                     .ok_or_else(|| Error::DaemonError("Unknown error".into()))?;
 
                 return match &*error_msg {
-                    "unauthorized" | "Missing authenticator" => Err(Error::AuthError(error_msg)),
+                    "unauthorized" | "Missing authenticator" => {
+                        Err(Error::AuthError(AuthFailure::Rejected(error_msg)))
+                    }
                     "Missing URL" => Err(Error::InvalidURLError(error_msg)),
                     "Already attached to project" => Err(Error::AlreadyAttachedError(error_msg)),
-                    _ => Err(Error::DataParseError(error_msg)),
+                    _ => Err(Error::DaemonError(error_msg)),
                 };
             }
             _ => {}
@@ -66,7 +110,134 @@ fn verify_rpc_reply_contents(data: &[treexml::Element]) -> Result<bool, Error> {
     Ok(success)
 }
 
-impl<'a> From<&'a treexml::Element> for models::Message {
+/// Checks that the reply holds nothing besides `payload` and the status markers.
+/// A reply lacking the payload is left for the caller to report.
+fn check_reply_nodes(request: &str, data: &[treexml::Element], payload: &str) -> Result<(), Error> {
+    if !data.iter().any(|node| node.name == payload) {
+        return Ok(());
+    }
+    for node in data {
+        match &*node.name {
+            "success" | "status" | "unauthorized" | "error" => {}
+            name if name == payload => {}
+            _ => util::unknown_reply_node(request, node)?,
+        }
+    }
+    Ok(())
+}
+
+/// Extracts the `object_tag` node from the reply to `request`.
+fn parse_object<T: for<'a> From<&'a treexml::Element>>(
+    request: &str,
+    data: &[treexml::Element],
+    object_tag: &str,
+) -> Result<T, Error> {
+    verify_rpc_reply_contents(data)?;
+    check_reply_nodes(request, data, object_tag)?;
+    for child in data {
+        if child.name == object_tag {
+            return Ok(T::from(child));
+        }
+    }
+    Err(Error::NodeNotFound {
+        request: request.into(),
+        node: object_tag.into(),
+    })
+}
+
+/// Extracts the `object_tag` nodes within the `vec_tag` node from the reply to `request`. A reply
+/// lacking `vec_tag` makes for an empty list if `missing_ok`.
+fn parse_vec<T: for<'a> From<&'a treexml::Element>>(
+    request: &str,
+    data: &[treexml::Element],
+    vec_tag: &str,
+    object_tag: &str,
+    missing_ok: bool,
+) -> Result<Vec<T>, Error> {
+    verify_rpc_reply_contents(data)?;
+    check_reply_nodes(request, data, vec_tag)?;
+    let mut v = Vec::new();
+    let mut success = false;
+    for child in data {
+        if child.name == vec_tag {
+            success = true;
+            for vec_child in &child.children {
+                if vec_child.name == object_tag {
+                    v.push(T::from(vec_child));
+                }
+            }
+        }
+    }
+    if !success && !missing_ok {
+        return Err(Error::NodeNotFound {
+            request: request.into(),
+            node: vec_tag.into(),
+        });
+    }
+    Ok(v)
+}
+
+/// Name of the request in errors of `parse_reply`, which has no way of telling.
+const UNKNOWN_REQUEST: &str = "unknown request";
+
+/// Top-level nodes of `xml`, a reply as the daemon sends it.
+fn reply_nodes(xml: &str) -> Result<Vec<treexml::Element>, Error> {
+    let xml = xml.trim_end_matches(|c: char| c == char::from(TERMCHAR) || c.is_whitespace());
+    rpc::parse_frame(xml, "boinc_gui_rpc_reply", true, util::Limits::DEFAULT)
+}
+
+/// Parses the `object_tag` object out of `xml`, a reply of the daemon such as captured off the
+/// wire.
+///
+/// This is what `Client` does with replies, without a connection. The terminator of the
+/// reply may be left in.
+///
+/// # Errors
+///
+/// The root must be `boinc_gui_rpc_reply`, and errors reported by the daemon fail with the same
+/// `Error` as with `Client`. A reply without the object fails with `Error::NodeNotFound`.
+///
+/// ```
+/// let reply = "<boinc_gui_rpc_reply>\n<host_info>\n<domain_name>worker01</domain_name>\n\
+///              </host_info>\n</boinc_gui_rpc_reply>\n\u{3}";
+/// let host: boinc_rpc::models::HostInfo = boinc_rpc::parse_reply(reply, "host_info")?;
+/// assert_eq!(host.domain_name.as_deref(), Some("worker01"));
+/// # Ok::<(), boinc_rpc::Error>(())
+/// ```
+pub fn parse_reply<T: for<'a> From<&'a treexml::Element>>(
+    xml: &str,
+    object_tag: &str,
+) -> Result<T, Error> {
+    parse_object(UNKNOWN_REQUEST, &reply_nodes(xml)?, object_tag)
+}
+
+/// Like `parse_reply`, for the `object_tag` objects in the `vec_tag` list of the reply.
+///
+/// These are the `result`s in the `results` of `get_results`, for one.
+///
+/// # Errors
+///
+/// As `parse_reply`. Unlike `Client::get_results`, a reply without the list fails with
+/// `Error::NodeNotFound`.
+pub fn parse_reply_vec<T: for<'a> From<&'a treexml::Element>>(
+    xml: &str,
+    vec_tag: &str,
+    object_tag: &str,
+) -> Result<Vec<T>, Error> {
+    parse_vec(
+        UNKNOWN_REQUEST,
+        &reply_nodes(xml)?,
+        vec_tag,
+        object_tag,
+        false,
+    )
+}
+
+fn request_name(req_data: &[treexml::Element]) -> String {
+    req_data.first().map(|n| n.name.clone()).unwrap_or_default()
+}
+
+impl From<&treexml::Element> for models::Message {
     fn from(node: &treexml::Element) -> Self {
         let mut e = Self::default();
         for n in &node.children {
@@ -86,7 +257,7 @@ impl<'a> From<&'a treexml::Element> for models::Message {
                 "time" => {
                     e.timestamp = util::eval_node_contents(n);
                 }
-                _ => {}
+                _ => util::unknown_tag("Message", n),
             }
         }
 
@@ -94,7 +265,64 @@ impl<'a> From<&'a treexml::Element> for models::Message {
     }
 }
 
-impl<'a> From<&'a treexml::Element> for models::ProjectInfo {
+impl From<&models::Message> for treexml::Element {
+    fn from(v: &models::Message) -> Self {
+        let mut node = Self::new("msg");
+        util::push_node(&mut node, "project", v.project_name.as_ref());
+        util::push_node(&mut node, "pri", v.priority);
+        util::push_node(&mut node, "seqno", v.msg_number);
+        if let Some(body) = &v.body {
+            let mut child = Self::new("body");
+            child.cdata = Some(body.clone());
+            node.children.push(child);
+        }
+        util::push_node(&mut node, "time", v.timestamp);
+        node
+    }
+}
+
+impl From<&treexml::Element> for models::Notice {
+    fn from(node: &treexml::Element) -> Self {
+        let mut e = Self::default();
+        for n in &node.children {
+            match &*n.name {
+                "seqno" => e.seqno = util::eval_node_contents(n),
+                "title" => e.title = util::trimmed_optional(&util::any_text(n)),
+                "description" => e.description = util::trimmed_optional(&util::any_text(n)),
+                "create_time" => e.create_time = util::eval_node_contents(n),
+                "arrival_time" => e.arrival_time = util::eval_node_contents(n),
+                "is_private" => e.is_private = util::eval_bool(n),
+                "project_name" => e.project_name = util::trimmed_optional(&util::any_text(n)),
+                "category" => e.category = util::trimmed_optional(&n.text),
+                "link" => e.link = util::trimmed_optional(&util::any_text(n)),
+                _ => util::unknown_tag("Notice", n),
+            }
+        }
+        e
+    }
+}
+
+impl From<&models::Notice> for treexml::Element {
+    fn from(v: &models::Notice) -> Self {
+        let mut node = Self::new("notice");
+        util::push_node(&mut node, "seqno", v.seqno);
+        util::push_node(&mut node, "title", v.title.as_ref());
+        if let Some(description) = &v.description {
+            let mut child = Self::new("description");
+            child.cdata = Some(description.clone());
+            node.children.push(child);
+        }
+        util::push_node(&mut node, "create_time", v.create_time);
+        util::push_node(&mut node, "arrival_time", v.arrival_time);
+        util::push_node(&mut node, "is_private", v.is_private.map(u8::from));
+        util::push_node(&mut node, "project_name", v.project_name.as_ref());
+        util::push_node(&mut node, "category", v.category.as_ref());
+        util::push_node(&mut node, "link", v.link.as_ref());
+        node
+    }
+}
+
+impl From<&treexml::Element> for models::ProjectInfo {
     fn from(node: &treexml::Element) -> Self {
         let mut e = Self::default();
         for n in &node.children {
@@ -120,13 +348,25 @@ impl<'a> From<&'a treexml::Element> for models::ProjectInfo {
                 "home" => {
                     e.home = util::trimmed_optional(&util::any_text(n));
                 }
-                "platfroms" => {
-                    let mut platforms = Vec::new();
+                "platforms" => {
+                    let mut platforms: Vec<models::PlatformInfo> = Vec::new();
                     for platform_node in &n.children {
-                        if platform_node.name == "platform" {
-                            if let Some(v) = &platform_node.text {
-                                platforms.push(v.clone());
+                        // Catalog entries list bare <name> tags, project configs use <platform> blocks
+                        let platform = match &*platform_node.name {
+                            "name" | "platform" => models::PlatformInfo::from(platform_node),
+                            _ => continue,
+                        };
+                        if platform.name.is_none() {
+                            continue;
+                        }
+                        if let Some(existing) =
+                            platforms.iter_mut().find(|p| p.name == platform.name)
+                        {
+                            if existing.user_friendly_name.is_none() {
+                                existing.user_friendly_name = platform.user_friendly_name;
                             }
+                        } else {
+                            platforms.push(platform);
                         }
                     }
                     e.platforms = Some(platforms);
@@ -134,7 +374,7 @@ impl<'a> From<&'a treexml::Element> for models::ProjectInfo {
                 "image" => {
                     e.image = util::trimmed_optional(&util::any_text(n));
                 }
-                _ => {}
+                _ => util::unknown_tag("ProjectInfo", n),
             }
         }
 
@@ -142,7 +382,7 @@ impl<'a> From<&'a treexml::Element> for models::ProjectInfo {
     }
 }
 
-impl<'a> From<&'a treexml::Element> for models::AccountManagerInfo {
+impl From<&treexml::Element> for models::AccountManagerInfo {
     fn from(node: &treexml::Element) -> Self {
         let mut e = Self::default();
         for n in &node.children {
@@ -156,16 +396,23 @@ impl<'a> From<&'a treexml::Element> for models::AccountManagerInfo {
                     e.cookie_required = Some(true);
                 }
                 "cookie_failure_url" => {
-                    e.cookie_failure_url = util::trimmed_optional(&util::any_text(n))
+                    e.cookie_failure_url = util::trimmed_optional(&util::any_text(n));
                 }
-                _ => {}
+                "user_name" => e.user_name = util::trimmed_optional(&util::any_text(n)),
+                "team_name" => e.team_name = util::trimmed_optional(&util::any_text(n)),
+                "previous_host_cpid" => {
+                    e.previous_host_cpid = util::trimmed_optional(&n.text);
+                }
+                "host_velocity" => e.host_velocity = util::eval_node_contents(n),
+                "dynamic" => e.dynamic = util::eval_bool(n),
+                _ => util::unknown_tag("AccountManagerInfo", n),
             }
         }
         e
     }
 }
 
-impl<'a> From<&'a treexml::Element> for models::VersionInfo {
+impl From<&treexml::Element> for models::VersionInfo {
     fn from(node: &treexml::Element) -> Self {
         let mut e = Self::default();
         for n in &node.children {
@@ -173,14 +420,24 @@ impl<'a> From<&'a treexml::Element> for models::VersionInfo {
                 "major" => e.major = util::eval_node_contents(n),
                 "minor" => e.minor = util::eval_node_contents(n),
                 "release" => e.release = util::eval_node_contents(n),
-                _ => {}
+                _ => util::unknown_tag("VersionInfo", n),
             }
         }
         e
     }
 }
 
-impl<'a> From<&'a treexml::Element> for models::TaskResult {
+impl From<&models::VersionInfo> for treexml::Element {
+    fn from(v: &models::VersionInfo) -> Self {
+        let mut node = Self::new("server_version");
+        util::push_node(&mut node, "major", v.major);
+        util::push_node(&mut node, "minor", v.minor);
+        util::push_node(&mut node, "release", v.release);
+        node
+    }
+}
+
+impl From<&treexml::Element> for models::TaskResult {
     fn from(node: &treexml::Element) -> Self {
         let mut e = Self::default();
         for n in &node.children {
@@ -227,17 +484,73 @@ impl<'a> From<&'a treexml::Element> for models::TaskResult {
                 "completed_time" => {
                     e.completed_time = util::eval_node_contents(n);
                 }
+                "suspended_via_gui" => {
+                    e.suspended_via_gui = util::eval_bool(n);
+                }
+                "project_suspended_via_gui" => {
+                    e.project_suspended_via_gui = util::eval_bool(n);
+                }
+                "ready_to_report" => {
+                    e.ready_to_report = util::eval_bool(n);
+                }
+                "got_server_ack" => {
+                    e.got_server_ack = util::eval_bool(n);
+                }
                 "active_task" => {
                     e.active_task = Some(models::ActiveTask::from(n));
                 }
-                _ => {}
+                _ => util::unknown_tag("TaskResult", n),
             }
         }
         e
     }
 }
 
-impl<'a> From<&'a treexml::Element> for models::HostInfo {
+impl From<&models::TaskResult> for treexml::Element {
+    fn from(v: &models::TaskResult) -> Self {
+        let mut node = Self::new("result");
+        util::push_node(&mut node, "name", v.name.as_ref());
+        util::push_node(&mut node, "wu_name", v.wu_name.as_ref());
+        util::push_node(&mut node, "platform", v.platform.as_ref());
+        util::push_node(&mut node, "version_num", v.version_num);
+        util::push_node(&mut node, "plan_class", v.plan_class.as_ref());
+        util::push_node(&mut node, "project_url", v.project_url.as_ref());
+        util::push_node(&mut node, "final_cpu_time", v.final_cpu_time);
+        util::push_node(&mut node, "final_elapsed_time", v.final_elapsed_time);
+        util::push_node(&mut node, "exit_status", v.exit_status);
+        util::push_node(&mut node, "state", v.state);
+        util::push_node(&mut node, "report_deadline", v.report_deadline);
+        util::push_node(&mut node, "received_time", v.received_time);
+        util::push_node(
+            &mut node,
+            "estimated_cpu_time_remaining",
+            v.estimated_cpu_time_remaining,
+        );
+        util::push_node(&mut node, "completed_time", v.completed_time);
+        util::push_node(
+            &mut node,
+            "suspended_via_gui",
+            v.suspended_via_gui.map(u8::from),
+        );
+        util::push_node(
+            &mut node,
+            "project_suspended_via_gui",
+            v.project_suspended_via_gui.map(u8::from),
+        );
+        util::push_node(
+            &mut node,
+            "ready_to_report",
+            v.ready_to_report.map(u8::from),
+        );
+        util::push_node(&mut node, "got_server_ack", v.got_server_ack.map(u8::from));
+        if let Some(active_task) = &v.active_task {
+            node.children.push(active_task.into());
+        }
+        node
+    }
+}
+
+impl From<&treexml::Element> for models::HostInfo {
     fn from(node: &treexml::Element) -> Self {
         let mut e = Self::default();
         for n in &node.children {
@@ -246,20 +559,19 @@ impl<'a> From<&'a treexml::Element> for models::HostInfo {
                 "p_iops" => e.p_iops = util::eval_node_contents(n),
                 "p_membw" => e.p_membw = util::eval_node_contents(n),
                 "p_calculated" => e.p_calculated = util::eval_node_contents(n),
-                "p_vm_extensions_disabled" => {
-                    e.p_vm_extensions_disabled = util::eval_node_contents(n)
-                }
-                "host_cpid" => e.host_cpid = n.text.clone(),
-                "product_name" => e.product_name = n.text.clone(),
-                "mac_address" => e.mac_address = n.text.clone(),
-                "domain_name" => e.domain_name = n.text.clone(),
-                "ip_addr" => e.ip_addr = n.text.clone(),
-                "p_vendor" => e.p_vendor = n.text.clone(),
-                "p_model" => e.p_model = n.text.clone(),
-                "os_name" => e.os_name = n.text.clone(),
-                "os_version" => e.os_version = n.text.clone(),
-                "virtualbox_version" => e.virtualbox_version = n.text.clone(),
-                "p_features" => e.p_features = n.text.clone(),
+                "p_vm_extensions_disabled" => e.p_vm_extensions_disabled = util::eval_bool(n),
+                "host_cpid" => e.host_cpid = util::trimmed_optional(&n.text),
+                "serialnum" => e.serialnum = util::trimmed_optional(&n.text),
+                "product_name" => e.product_name = util::trimmed_optional(&n.text),
+                "mac_address" => e.mac_address = util::trimmed_optional(&n.text),
+                "domain_name" => e.domain_name = util::trimmed_optional(&n.text),
+                "ip_addr" => e.ip_addr = util::trimmed_optional(&n.text),
+                "p_vendor" => e.p_vendor = util::trimmed_optional(&n.text),
+                "p_model" => e.p_model = util::trimmed_optional(&n.text),
+                "os_name" => e.os_name = util::trimmed_optional(&n.text),
+                "os_version" => e.os_version = util::trimmed_optional(&n.text),
+                "virtualbox_version" => e.virtualbox_version = util::trimmed_optional(&n.text),
+                "p_features" => e.p_features = util::trimmed_optional(&n.text),
                 "timezone" => e.tz_shift = util::eval_node_contents(n),
                 "p_ncpus" => e.p_ncpus = util::eval_node_contents(n),
                 "m_nbytes" => e.m_nbytes = util::eval_node_contents(n),
@@ -267,353 +579,2800 @@ impl<'a> From<&'a treexml::Element> for models::HostInfo {
                 "m_swap" => e.m_swap = util::eval_node_contents(n),
                 "d_total" => e.d_total = util::eval_node_contents(n),
                 "d_free" => e.d_free = util::eval_node_contents(n),
-                _ => {}
+                _ => util::unknown_tag("HostInfo", n),
             }
         }
         e
     }
 }
 
-type DaemonStreamFuture =
-    Pin<Box<dyn Future<Output = Result<DaemonStream<TcpStream>, Error>> + Send + Sync + 'static>>;
+impl From<&models::HostInfo> for treexml::Element {
+    fn from(v: &models::HostInfo) -> Self {
+        let mut node = Self::new("host_info");
+        util::push_node(&mut node, "timezone", v.tz_shift);
+        util::push_node(&mut node, "domain_name", v.domain_name.as_ref());
+        util::push_node(&mut node, "serialnum", v.serialnum.as_ref());
+        util::push_node(&mut node, "ip_addr", v.ip_addr.as_ref());
+        util::push_node(&mut node, "host_cpid", v.host_cpid.as_ref());
+        util::push_node(&mut node, "p_ncpus", v.p_ncpus);
+        util::push_node(&mut node, "p_vendor", v.p_vendor.as_ref());
+        util::push_node(&mut node, "p_model", v.p_model.as_ref());
+        util::push_node(&mut node, "p_features", v.p_features.as_ref());
+        util::push_node(&mut node, "p_fpops", v.p_fpops);
+        util::push_node(&mut node, "p_iops", v.p_iops);
+        util::push_node(&mut node, "p_membw", v.p_membw);
+        util::push_node(&mut node, "p_calculated", v.p_calculated);
+        util::push_node(
+            &mut node,
+            "p_vm_extensions_disabled",
+            v.p_vm_extensions_disabled.map(u8::from),
+        );
+        util::push_node(&mut node, "m_nbytes", v.m_nbytes);
+        util::push_node(&mut node, "m_cache", v.m_cache);
+        util::push_node(&mut node, "m_swap", v.m_swap);
+        util::push_node(&mut node, "d_total", v.d_total);
+        util::push_node(&mut node, "d_free", v.d_free);
+        util::push_node(&mut node, "os_name", v.os_name.as_ref());
+        util::push_node(&mut node, "os_version", v.os_version.as_ref());
+        util::push_node(&mut node, "product_name", v.product_name.as_ref());
+        util::push_node(&mut node, "mac_address", v.mac_address.as_ref());
+        util::push_node(
+            &mut node,
+            "virtualbox_version",
+            v.virtualbox_version.as_ref(),
+        );
+        node
+    }
+}
 
-enum ConnState {
-    Connecting(DaemonStreamFuture),
-    Ready(DaemonStream<TcpStream>),
-    Error(Error),
+/// Typed requests to the daemon over a transport.
+///
+/// Methods take `&self`, so a client can be shared between tasks, for example in an `Arc`,
+/// without a lock of its own. Each request is made on a clone of the transport, which is why
+/// it has to be `Clone`. Clones of `Transport` share one connection and send requests one at a
+/// time, in the order they get to it; other transports decide for themselves.
+///
+/// Clients are `Clone` if the transport is, so that each task can own one, as `tokio::spawn`
+/// wants. Clones make their requests on clones of the same transport, and so over the same
+/// connection, one request at a time, unless the transport pools connections. They share
+/// the replies kept for `summary` too.
+///
+/// # Errors
+///
+/// Requests fail with the errors of the transport, such as `Error::ConnectionClosed` or
+/// `Error::Timeout`, with `Error::AuthError` when the daemon refuses them, and with the errors
+/// the daemon reports, such as `Error::StatusError`. A reply lacking what was asked for fails
+/// with `Error::NodeNotFound`, and one with a malformed required value with
+/// `Error::InvalidNodeValue`. The `# Errors` sections of the methods tell what comes on top.
+#[derive(Clone)]
+pub struct Client<S> {
+    transport: S,
+    cache: std::sync::Arc<std::sync::Mutex<Cache>>,
 }
 
-pub struct Transport {
-    state: Arc<Mutex<Option<ConnState>>>,
+/// Replies that rarely change, kept for `Client::summary`.
+#[derive(Default)]
+struct Cache {
+    host_info: Option<models::HostInfo>,
+    server_version: Option<models::VersionInfo>,
+    /// Connection the replies came over, as reported by the transport.
+    connection: Option<u64>,
 }
 
-impl Transport {
-    pub fn new<A: Display, P: Display>(addr: A, password: Option<P>) -> Self {
-        let addr = addr.to_string();
-        let password = password.map(|p| p.to_string());
-        Self {
-            state: Arc::new(Mutex::new(Some(ConnState::Connecting(Box::pin(
-                DaemonStream::connect(addr, password),
-            ))))),
+impl Cache {
+    /// Forgets the replies once the transport has connected anew, as the daemon may have
+    /// restarted or been replaced meanwhile, and takes the version from the handshake if made.
+    fn connected(&mut self, info: connection::ConnectionInfo) {
+        if self.connection != Some(info.number) {
+            *self = Self {
+                connection: Some(info.number),
+                ..Self::default()
+            };
+        }
+        if info.version.is_some() {
+            self.server_version = info.version;
         }
     }
 }
 
-impl tower::Service<Vec<treexml::Element>> for Transport {
-    type Response = Vec<treexml::Element>;
-    type Error = Error;
-    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
-
-    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
-        let mut g = match self.state.try_lock() {
-            Ok(g) => g,
-            Err(_) => return Poll::Pending,
-        };
-
-        let (state, out) = match g.take().unwrap() {
-            ConnState::Connecting(mut future) => {
-                let res = future.as_mut().poll(cx);
-                match res {
-                    Poll::Pending => (Some(ConnState::Connecting(future)), Poll::Pending),
-                    Poll::Ready(Ok(conn)) => (Some(ConnState::Ready(conn)), Poll::Ready(Ok(()))),
-                    Poll::Ready(Err(e)) => (None, Poll::Ready(Err(e))),
-                }
-            }
-            ConnState::Ready(conn) => (Some(ConnState::Ready(conn)), Poll::Ready(Ok(()))),
-            ConnState::Error(error) => (
-                Some(ConnState::Error(error.clone())),
-                Poll::Ready(Err(error)),
-            ),
-        };
-
-        *g = state;
-        out
+impl<S> Client<S> {
+    #[must_use]
+    pub const fn transport(&self) -> &S {
+        &self.transport
     }
 
-    fn call(&mut self, req: Vec<treexml::Element>) -> Self::Future {
-        let state = self.state.clone();
-        Box::pin(async move {
-            let mut state = state.lock().await;
-
-            let mut conn = match state.take() {
-                Some(ConnState::Ready(conn)) => conn,
-                _ => unreachable!(),
-            };
+    pub fn transport_mut(&mut self) -> &mut S {
+        &mut self.transport
+    }
 
-            let query_res = conn.query(req).await;
+    #[must_use]
+    pub fn into_inner(self) -> S {
+        self.transport
+    }
 
-            if let Err(e) = &query_res {
-                *state = Some(ConnState::Error(e.clone()));
-            }
+    fn cache(&self) -> std::sync::MutexGuard<'_, Cache> {
+        // Every update leaves the cache whole
+        self.cache
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
 
-            query_res
-        })
+#[cfg(feature = "tokio")]
+impl Client<Transport> {
+    /// Connects and authenticates right away, then exchanges versions with the daemon. See
+    /// `server_version`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if connecting, authenticating or the version handshake does, as `Transport::connect`.
+    pub async fn connect<A, P>(addr: A, password: Option<P>) -> Result<Self, Error>
+    where
+        A: Into<transport::Address>,
+        P: Into<Secret>,
+    {
+        let builder = Transport::builder(addr).version_handshake(true);
+        let transport = match password {
+            Some(password) => builder.password(password),
+            None => builder,
+        }
+        .build();
+        transport.connect().await?;
+        Ok(Self::new(transport))
     }
 }
 
-pub struct Client<S> {
-    transport: S,
+#[cfg(feature = "tokio")]
+impl<Io> Client<transport::Transport<Io>> {
+    /// Daemon version while connected, if the transport performs the version handshake. Kept up
+    /// to date across reconnections.
+    #[must_use]
+    pub fn server_version(&self) -> Option<models::VersionInfo> {
+        self.transport.server_version()
+    }
+
+    /// Closes the connection. See `Transport::close`.
+    ///
+    /// # Errors
+    ///
+    /// As `Transport::close`.
+    pub async fn close(&self) -> Result<(), Error>
+    where
+        Io: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+    {
+        self.transport.close().await
+    }
 }
 
+// The futures are `Send` if the transport and its futures are, which is up to the caller, so as
+// not to rule out transports for single-threaded runtimes
+#[allow(clippy::future_not_send)]
 impl<S> Client<S>
 where
-    S: tower::Service<Vec<treexml::Element>, Response = Vec<treexml::Element>, Error = Error>,
+    S: tower_service::Service<Vec<treexml::Element>, Response = Vec<treexml::Element>> + Clone,
+    S::Error: Into<Error>,
 {
+    /// The transport may be wrapped in `tower` middleware, as long as its errors convert into
+    /// `Error`, which includes the `BoxError` of generic middleware. Middleware without a
+    /// `Clone` impl of its own can be made shareable with `tower::buffer::Buffer`.
     pub fn new(transport: S) -> Self {
-        Self { transport }
+        Self {
+            transport,
+            cache: std::sync::Arc::default(),
+        }
     }
 
-    async fn get_object<T: for<'a> From<&'a treexml::Element>>(
-        &mut self,
+    /// Sends a request and makes sense of the reply with `parse`, which is given the name of
+    /// the request along with the reply.
+    ///
+    /// Each call is made in an `rpc` span, with the name of the request as `rpc`. Once done,
+    /// `outcome` is either `ok` or `error`, with the `ErrorKind` as `error_kind`, and
+    /// `elapsed_ms` the time taken. The transports of this crate add a
+    /// `transport` span within, with the daemon's address as `host`.
+    pub(crate) async fn call<T, F>(
+        &self,
         req_data: Vec<treexml::Element>,
-        object_tag: &str,
-    ) -> Result<T, Error> {
-        self.transport.ready().await?;
-        let data = self.transport.call(req_data).await?;
-        verify_rpc_reply_contents(&data)?;
-        for child in &data {
-            if child.name == object_tag {
-                return Ok(T::from(child));
-            }
+        parse: F,
+    ) -> Result<T, Error>
+    where
+        F: FnOnce(&str, Vec<treexml::Element>) -> Result<T, Error>,
+    {
+        let request = request_name(&req_data);
+        let span = tracing::info_span!(
+            "rpc",
+            rpc = %request,
+            outcome = tracing::field::Empty,
+            error_kind = tracing::field::Empty,
+            elapsed_ms = tracing::field::Empty,
+        );
+        let started = std::time::Instant::now();
+        let result = async {
+            let mut transport = self.transport.clone();
+            futures::future::poll_fn(|cx| transport.poll_ready(cx))
+                .await
+                .map_err(Into::into)?;
+            let data = transport.call(req_data).await.map_err(Into::into)?;
+            parse(&request, data)
         }
-        Err(Error::DataParseError("Object not found.".to_string()))
+        .instrument(span.clone())
+        .await;
+
+        span.record("elapsed_ms", started.elapsed().as_secs_f64() * 1000.0);
+        match &result {
+            Ok(_) => span.record("outcome", "ok"),
+            Err(e) => span
+                .record("outcome", "error")
+                .record("error_kind", tracing::field::debug(e.kind())),
+        };
+        result
+    }
+
+    /// Sends a state-changing request. Unlike reads, which are judged by the
+    /// presence of the requested data, the daemon must acknowledge these with `<success/>`.
+    async fn do_write(&self, req_data: Vec<treexml::Element>) -> Result<(), Error> {
+        self.call(req_data, |request, data| {
+            check_reply_nodes(request, &data, "success")?;
+            if verify_rpc_reply_contents(&data)? {
+                Ok(())
+            } else {
+                Err(Error::NodeNotFound {
+                    request: request.into(),
+                    node: "success".into(),
+                })
+            }
+        })
+        .await
     }
 
-    async fn get_object_by_req_tag<T: for<'a> From<&'a treexml::Element>>(
-        &mut self,
-        req_tag: &str,
+    async fn get_object<T: for<'a> From<&'a treexml::Element>>(
+        &self,
+        req_data: Vec<treexml::Element>,
         object_tag: &str,
     ) -> Result<T, Error> {
-        self.get_object(vec![treexml::Element::new(req_tag)], object_tag)
-            .await
+        self.call(req_data, |request, data| {
+            parse_object(request, &data, object_tag)
+        })
+        .await
     }
 
     async fn get_vec<T: for<'a> From<&'a treexml::Element>>(
-        &mut self,
+        &self,
         req_data: Vec<treexml::Element>,
         vec_tag: &str,
         object_tag: &str,
     ) -> Result<Vec<T>, Error> {
-        let mut v = Vec::new();
-        {
-            self.transport.ready().await?;
-            let data = self.transport.call(req_data).await?;
-            verify_rpc_reply_contents(&data)?;
-            let mut success = false;
-            for child in data {
-                if child.name == vec_tag {
-                    success = true;
-                    for vec_child in &child.children {
-                        if vec_child.name == object_tag {
-                            v.push(T::from(vec_child));
-                        }
-                    }
-                }
-            }
-            if !success {
-                return Err(Error::DataParseError("Objects not found.".to_string()));
-            }
-        }
-        Ok(v)
+        self.fetch_vec(req_data, vec_tag, object_tag, false).await
     }
 
-    async fn get_vec_by_req_tag<T: for<'a> From<&'a treexml::Element>>(
-        &mut self,
-        req_tag: &str,
+    /// Like `get_vec`, for requests where the daemon omits the wrapper when there is nothing to report.
+    async fn get_vec_or_empty<T: for<'a> From<&'a treexml::Element>>(
+        &self,
+        req_data: Vec<treexml::Element>,
+        vec_tag: &str,
+        object_tag: &str,
+    ) -> Result<Vec<T>, Error> {
+        self.fetch_vec(req_data, vec_tag, object_tag, true).await
+    }
+
+    async fn fetch_vec<T: for<'a> From<&'a treexml::Element>>(
+        &self,
+        req_data: Vec<treexml::Element>,
         vec_tag: &str,
         object_tag: &str,
+        missing_ok: bool,
     ) -> Result<Vec<T>, Error> {
-        self.get_vec(vec![treexml::Element::new(req_tag)], vec_tag, object_tag)
+        self.call(req_data, |request, data| {
+            parse_vec(request, &data, vec_tag, object_tag, missing_ok)
+        })
+        .await
+    }
+
+    /// Messages after sequence number `seqno`, or all of them for 0.
+    ///
+    /// # Errors
+    ///
+    /// Fails as any request does, see `Client`. A reply without `msgs` makes for an empty list.
+    pub async fn get_messages(&self, seqno: i64) -> Result<Vec<models::Message>, Error> {
+        self.get_vec_or_empty(Request::GetMessages { seqno }.into(), "msgs", "msg")
             .await
     }
 
-    pub async fn get_messages(&mut self, seqno: i64) -> Result<Vec<models::Message>, Error> {
-        self.get_vec(
-            vec![{
-                let mut node = treexml::Element::new("get_messages");
-                node.text = Some(format!("{}", seqno));
-                node
-            }],
-            "msgs",
-            "msg",
-        )
+    /// Sequence number of the latest message. Those after it are yet to come.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` for a reply without `seqno`, and as any request otherwise,
+    /// see `Client`.
+    pub async fn get_message_count(&self) -> Result<i64, Error> {
+        self.call(Request::GetMessageCount.into(), |request, data| {
+            verify_rpc_reply_contents(&data)?;
+            check_reply_nodes(request, &data, "seqno")?;
+            data.iter()
+                .find(|node| node.name == "seqno")
+                .ok_or_else(|| Error::NodeNotFound {
+                    request: request.into(),
+                    node: "seqno".into(),
+                })
+                .and_then(util::parse_node_contents)
+        })
         .await
     }
 
-    pub async fn get_projects(&mut self) -> Result<Vec<models::ProjectInfo>, Error> {
-        self.get_vec_by_req_tag("get_all_projects_list", "projects", "project")
+    /// Time taken by a round trip to the daemon, with the cheapest request there is,
+    /// `Request::PING`.
+    ///
+    /// # Errors
+    ///
+    /// Failures are those of any other request, so a health check can tell an unreachable
+    /// daemon from a refused password by `Error::kind`.
+    pub async fn ping(&self) -> Result<std::time::Duration, Error> {
+        let started = std::time::Instant::now();
+        self.call(Request::PING.into(), |_, data| {
+            verify_rpc_reply_contents(&data).map(drop)
+        })
+        .await?;
+        Ok(started.elapsed())
+    }
+
+    /// Messages that the daemon logs from now on, in order. See `stream_messages_since`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the latest sequence number cannot be had, see `get_message_count`.
+    pub async fn stream_messages(
+        &self,
+        poll_interval: std::time::Duration,
+    ) -> Result<impl futures::Stream<Item = Result<models::Message, Error>> + '_, Error> {
+        let seqno = self.get_message_count().await?;
+        Ok(self.stream_messages_since(seqno, poll_interval))
+    }
+
+    /// Messages after sequence number `seqno`, followed by those that the daemon logs from then
+    /// on, in order. Starting from 0 gives the whole history that the daemon keeps.
+    ///
+    /// The daemon is asked for new messages every `poll_interval`. Transient errors, such as a
+    /// lost connection, are retried with the next poll. Any other error is yielded and ends the
+    /// stream.
+    pub fn stream_messages_since(
+        &self,
+        seqno: i64,
+        poll_interval: std::time::Duration,
+    ) -> impl futures::Stream<Item = Result<models::Message, Error>> + '_ {
+        self.polling_stream(
+            watch::Seqno::new(seqno, false),
+            poll_interval,
+            |client, state| client.get_messages(state.seqno),
+            watch::Seqno::receive,
+        )
+    }
+
+    /// Notices after sequence number `seqno`, or all of them for 0.
+    ///
+    /// # Errors
+    ///
+    /// Fails as any request does, see `Client`. A reply without `notices` makes for an empty list.
+    pub async fn get_notices(&self, seqno: i64) -> Result<Vec<models::Notice>, Error> {
+        self.get_vec_or_empty(Request::GetNotices { seqno }.into(), "notices", "notice")
             .await
     }
 
-    pub async fn get_account_manager_info(&mut self) -> Result<models::AccountManagerInfo, Error> {
-        self.get_object_by_req_tag("acct_mgr_info", "acct_mgr_info")
+    /// Notices that appear from now on. See `stream_notices_since`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the current notices cannot be listed, see `get_notices`.
+    pub async fn stream_notices(
+        &self,
+        poll_interval: std::time::Duration,
+    ) -> Result<impl futures::Stream<Item = Result<models::Notice, Error>> + '_, Error> {
+        let seqno = self
+            .get_notices(0)
+            .await?
+            .iter()
+            .filter_map(|notice| notice.seqno)
+            .max()
+            .unwrap_or_default();
+        Ok(self.stream_notices_since(seqno, poll_interval))
+    }
+
+    /// Notices after sequence number `seqno`, followed by those that appear from then on, in
+    /// order. Starting from 0 gives all current notices first.
+    ///
+    /// When the daemon regenerates its notices, it numbers them from 1 again and sends all of
+    /// them. All of them are then yielded again, so that a consumer keeping a list of notices
+    /// should start over once it sees a lower sequence number than before. Errors are handled
+    /// as by `stream_messages_since`.
+    pub fn stream_notices_since(
+        &self,
+        seqno: i64,
+        poll_interval: std::time::Duration,
+    ) -> impl futures::Stream<Item = Result<models::Notice, Error>> + '_ {
+        self.polling_stream(
+            watch::Seqno::new(seqno, true),
+            poll_interval,
+            |client, state| client.get_notices(state.seqno),
+            watch::Seqno::receive,
+        )
+    }
+
+    /// Changes to the task list, as found by comparing snapshots taken every `poll_interval`.
+    /// See `watch_tasks_with`.
+    pub fn watch_tasks(
+        &self,
+        poll_interval: std::time::Duration,
+    ) -> impl futures::Stream<Item = Result<watch::TaskEvent, Error>> + '_ {
+        self.watch_tasks_with(watch::TaskDiff::default(), poll_interval)
+    }
+
+    /// Changes to the task list, as `diff` finds them in snapshots taken every `poll_interval`.
+    /// All tasks are reported as added at first. Errors are handled as by
+    /// `stream_messages_since`.
+    pub fn watch_tasks_with(
+        &self,
+        diff: watch::TaskDiff,
+        poll_interval: std::time::Duration,
+    ) -> impl futures::Stream<Item = Result<watch::TaskEvent, Error>> + '_ {
+        self.polling_stream(
+            diff,
+            poll_interval,
+            |client, _| client.get_results(false),
+            |diff, tasks| diff.update(&tasks),
+        )
+    }
+
+    /// Polls the task list every `poll_interval` until the task `name` satisfies `predicate`,
+    /// such as `watch::task_reached_state`, returning it as of then.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::TaskGone` once the task is not on the list, as when reported or
+    /// aborted before it got there, and with `Error::Timeout` after `timeout`. Errors of the
+    /// polls that are not transient fail it too.
+    pub async fn wait_for_task<P>(
+        &self,
+        name: &str,
+        predicate: P,
+        poll_interval: std::time::Duration,
+        timeout: std::time::Duration,
+    ) -> Result<models::TaskResult, Error>
+    where
+        P: Fn(&models::TaskResult) -> bool,
+    {
+        let wait = time::poll_until(
+            poll_interval,
+            || self.get_results(false),
+            |tasks| {
+                let task = tasks
+                    .into_iter()
+                    .find(|task| task.name.as_deref() == Some(name))
+                    .ok_or_else(|| Error::TaskGone(name.into()))?;
+                Ok(Some(task).filter(|task| predicate(task)))
+            },
+        );
+        time::timeout(timeout, wait).await?
+    }
+
+    /// Polls the active tasks every `poll_interval` until none of them is executing, as for a
+    /// maintenance window. See `wait_until_idle_with`.
+    ///
+    /// # Errors
+    ///
+    /// As `wait_until_idle_with`.
+    pub async fn wait_until_idle(
+        &self,
+        poll_interval: std::time::Duration,
+        timeout: std::time::Duration,
+    ) -> Result<tasks::IdleReport, Error> {
+        self.wait_until_idle_with(tasks::IdleOptions::default(), poll_interval, timeout)
             .await
     }
 
-    pub async fn get_account_manager_rpc_status(&mut self) -> Result<i32, Error> {
-        self.transport.ready().await?;
-        let data = self
-            .transport
-            .call(vec![treexml::Element::new("acct_mgr_rpc_poll")])
-            .await?;
-        verify_rpc_reply_contents(&data)?;
-
-        let mut v: Option<i32> = None;
-        for child in &data {
-            if &*child.name == "acct_mgr_rpc_reply" {
-                for c in &child.children {
-                    if &*c.name == "error_num" {
-                        v = util::eval_node_contents(c);
+    /// Polls the active tasks every `poll_interval` until none of them is executing, first
+    /// suspending computation if `options` say so. Returns the active tasks as of then.
+    ///
+    /// Tasks quitting or aborting count as executing for the grace period of `options`, and as
+    /// stuck after, to be listed in the report instead of waited for.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::Timeout` after `timeout`, and if computation cannot be suspended or a
+    /// poll fails with an error that is not transient.
+    pub async fn wait_until_idle_with(
+        &self,
+        options: tasks::IdleOptions,
+        poll_interval: std::time::Duration,
+        timeout: std::time::Duration,
+    ) -> Result<tasks::IdleReport, Error> {
+        let wait = async {
+            if options.suspend {
+                self.set_mode_for(models::Component::CPU, models::RunMode::Never, None)
+                    .await?;
+            }
+            // When each task was first seen quitting or aborting
+            let mut stopping = std::collections::HashMap::new();
+            time::poll_until(
+                poll_interval,
+                || self.get_results(true),
+                |tasks| {
+                    let now = time::Instant::now();
+                    let mut busy = false;
+                    let mut stuck = Vec::new();
+                    let mut still_stopping = std::collections::HashMap::new();
+                    for task in &tasks {
+                        let state = task
+                            .active_task
+                            .as_ref()
+                            .and_then(models::ActiveTask::process_state);
+                        match state {
+                            Some(models::Process::Executing) => busy = true,
+                            Some(models::Process::QuitPending | models::Process::AbortPending) => {
+                                let name = task.name.clone().unwrap_or_default();
+                                let since = stopping.get(&name).copied().unwrap_or(now);
+                                if now - since >= options.grace_period {
+                                    stuck.push(name.clone());
+                                } else {
+                                    busy = true;
+                                }
+                                still_stopping.insert(name, since);
+                            }
+                            _ => {}
+                        }
                     }
-                }
+                    stopping = still_stopping;
+                    Ok(if busy {
+                        None
+                    } else {
+                        Some(tasks::IdleReport { tasks, stuck })
+                    })
+                },
+            )
+            .await
+        };
+        time::timeout(timeout, wait).await?
+    }
+
+    /// Empties the task queue, as before decommissioning the host: tells every attached project
+    /// to send no more work, then yields the progress of the tasks, polled every
+    /// `poll_interval`, until all of them are done and reported.
+    ///
+    /// With `abort_after` set in `options`, tasks not done by then are aborted, and with
+    /// `detach` set the projects are detached from at the end. Errors of the stream are handled
+    /// as by `stream_messages_since`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the projects cannot be listed, or if one of them cannot be told to send no more
+    /// work, before anything is yielded.
+    pub async fn drain(
+        &self,
+        options: tasks::DrainOptions,
+        poll_interval: std::time::Duration,
+    ) -> Result<impl futures::Stream<Item = Result<tasks::DrainEvent, Error>> + '_, Error> {
+        let mut projects = Vec::new();
+        for project in self.get_project_status().await? {
+            if let Some(url) = project.master_url {
+                self.project_op(&url, models::ProjectOp::NoMoreWork).await?;
+                projects.push(url);
             }
         }
-        v.ok_or_else(|| Error::DataParseError("acct_mgr_rpc_reply node not found".into()))
-    }
+        let drain = tasks::Drain {
+            detach: if options.detach { projects } else { Vec::new() },
+            abort_at: options
+                .abort_after
+                .map(|after| time::Instant::now() + after),
+            pending: std::collections::VecDeque::new(),
+            polled: false,
+            done: false,
+        };
 
-    pub async fn connect_to_account_manager(
-        &mut self,
-        url: &str,
-        name: &str,
-        password: &str,
-    ) -> Result<bool, Error> {
-        let mut req_node = treexml::Element::new("acct_mgr_rpc");
-        req_node.children = vec![
-            {
-                let mut node = treexml::Element::new("url");
-                node.text = Some(url.into());
-                node
-            },
-            {
-                let mut node = treexml::Element::new("name");
-                node.text = Some(name.into());
-                node
-            },
-            {
-                let mut node = treexml::Element::new("password");
-                node.text = Some(password.into());
-                node
+        Ok(futures::stream::unfold(
+            drain,
+            move |mut drain| async move {
+                loop {
+                    if let Some(event) = drain.pending.pop_front() {
+                        return Some((Ok(event), drain));
+                    }
+                    if drain.done {
+                        return None;
+                    }
+                    if drain.polled {
+                        time::delay_for(poll_interval).await;
+                    }
+                    drain.polled = true;
+
+                    if let Err(e) = self.drain_step(&mut drain, poll_interval).await {
+                        drain.done = true;
+                        return Some((Err(e), drain));
+                    }
+                }
             },
-        ];
-        self.transport.ready().await?;
-        let root_node = self.transport.call(vec![req_node]).await?;
-        Ok(verify_rpc_reply_contents(&root_node)?)
+        ))
     }
 
-    pub async fn exchange_versions(
-        &mut self,
-        info: &models::VersionInfo,
-    ) -> Result<models::VersionInfo, Error> {
-        let mut content_node = treexml::Element::new("exchange_versions");
-        {
-            let mut node = treexml::Element::new("major");
-            node.text = info.minor.map(|v| format!("{}", v));
-            content_node.children.push(node);
-        }
-        {
-            let mut node = treexml::Element::new("minor");
-            node.text = info.major.map(|v| format!("{}", v));
-            content_node.children.push(node);
-        }
-        {
-            let mut node = treexml::Element::new("release");
-            node.text = info.release.map(|v| format!("{}", v));
-            content_node.children.push(node);
+    /// Polls the task list once for `drain`, acting on what is found. Requests are retried every
+    /// `poll_interval` as by `time::poll_until`.
+    async fn drain_step(
+        &self,
+        drain: &mut tasks::Drain,
+        poll_interval: std::time::Duration,
+    ) -> Result<(), Error> {
+        let remaining = time::poll_until(
+            poll_interval,
+            || self.get_results(false),
+            |tasks| Ok(Some(tasks)),
+        )
+        .await?;
+        drain.pending.push_back(tasks::DrainEvent::Progress {
+            tasks_remaining: remaining.len(),
+            cpu_time_remaining: tasks::cpu_time_remaining(&remaining),
+        });
+
+        if remaining.is_empty() {
+            // One by one, so that none is detached from twice when retrying
+            while let Some(url) = drain.detach.first() {
+                time::poll_until(
+                    poll_interval,
+                    || self.project_op(url, models::ProjectOp::Detach),
+                    |()| Ok(Some(())),
+                )
+                .await?;
+                let url = drain.detach.remove(0);
+                drain.pending.push_back(tasks::DrainEvent::Detached(url));
+            }
+            drain.done = true;
+        } else if matches!(drain.abort_at, Some(at) if time::Instant::now() >= at) {
+            // Tasks done already are left to be reported
+            let report = time::poll_until(
+                poll_interval,
+                || self.abort_tasks_matching(|task| task.ready_to_report != Some(true), false),
+                |report| Ok(Some(report)),
+            )
+            .await?;
+            drain.abort_at = None;
+            drain.pending.push_back(tasks::DrainEvent::Aborted(report));
         }
-        self.get_object(vec![content_node], "server_version").await
+        Ok(())
     }
 
-    pub async fn get_results(
-        &mut self,
-        active_only: bool,
-    ) -> Result<Vec<models::TaskResult>, Error> {
-        self.get_vec(
-            vec![{
-                let mut node = treexml::Element::new("get_results");
-                if active_only {
-                    let mut ao_node = treexml::Element::new("active_only");
-                    ao_node.text = Some("1".into());
-                    node.children.push(ao_node);
-                }
-                node
-            }],
-            "results",
-            "result",
+    /// Changes to the file transfers, as found by comparing snapshots taken every
+    /// `poll_interval`. See `watch_file_transfers_with`.
+    pub fn watch_file_transfers(
+        &self,
+        poll_interval: std::time::Duration,
+    ) -> impl futures::Stream<Item = Result<watch::TransferEvent, Error>> + '_ {
+        self.watch_file_transfers_with(watch::TransferDiff::default(), poll_interval)
+    }
+
+    /// Changes to the file transfers, as `diff` finds them in snapshots taken every
+    /// `poll_interval`. All transfers are reported as added at first. Errors are handled as by
+    /// `stream_messages_since`.
+    pub fn watch_file_transfers_with(
+        &self,
+        diff: watch::TransferDiff,
+        poll_interval: std::time::Duration,
+    ) -> impl futures::Stream<Item = Result<watch::TransferEvent, Error>> + '_ {
+        self.polling_stream(
+            diff,
+            poll_interval,
+            |client, _| client.get_file_transfers(),
+            |diff, transfers| diff.update(&transfers),
         )
-        .await
     }
 
-    pub async fn set_mode(
-        &mut self,
-        c: models::Component,
-        m: models::RunMode,
-        duration: f64,
-    ) -> Result<(), Error> {
-        self.transport.ready().await?;
-        let rsp_root = self
-            .transport
-            .call(vec![{
-                let comp_desc = match c {
-                    models::Component::CPU => "run",
-                    models::Component::GPU => "gpu",
-                    models::Component::Network => "network",
-                }
-                .to_string();
-                let mode_desc = match m {
-                    models::RunMode::Always => "always",
-                    models::RunMode::Auto => "auto",
-                    models::RunMode::Never => "never",
-                    models::RunMode::Restore => "restore",
-                }
-                .to_string();
-
-                let mut node = treexml::Element::new(format!("set_{}_mode", &comp_desc));
-                let mut dur_node = treexml::Element::new("duration");
-                dur_node.text = Some(format!("{}", duration));
-                node.children.push(dur_node);
-                node.children.push(treexml::Element::new(mode_desc));
-                node
-            }])
-            .await?;
-        verify_rpc_reply_contents(&rsp_root)?;
-        Ok(())
+    /// Credit of each project, sampled every `poll_interval`. See
+    /// `watch::CreditSample::delta_since` for the credit earned between samples. Errors are
+    /// handled as by `stream_messages_since`.
+    pub fn sample_statistics(
+        &self,
+        poll_interval: std::time::Duration,
+    ) -> impl futures::Stream<Item = Result<watch::CreditSample, Error>> + '_ {
+        self.polling_stream(
+            (),
+            poll_interval,
+            |client, ()| client.get_statistics(),
+            |(), statistics| vec![watch::CreditSample::new(util::unix_time(), &statistics)],
+        )
+    }
+
+    /// Calls `fetch` every `poll_interval`, yielding the items that `receive` makes of the
+    /// replies.
+    fn polling_stream<'a, St, R, T, F, Fut, G>(
+        &'a self,
+        state: St,
+        poll_interval: std::time::Duration,
+        fetch: F,
+        receive: G,
+    ) -> impl futures::Stream<Item = Result<T, Error>> + 'a
+    where
+        St: 'a,
+        T: 'a,
+        F: Fn(&'a Self, &St) -> Fut + Copy + 'a,
+        Fut: std::future::Future<Output = Result<R, Error>> + 'a,
+        G: Fn(&mut St, R) -> Vec<T> + Copy + 'a,
+    {
+        futures::stream::unfold(watch::Polling::new(state), move |mut polling| async move {
+            loop {
+                if let Some(item) = polling.pending.pop_front() {
+                    return Some((Ok(item), polling));
+                }
+                if polling.done {
+                    return None;
+                }
+                if polling.polled {
+                    time::delay_for(poll_interval).await;
+                }
+                polling.polled = true;
+
+                let state = &polling.state;
+                match time::poll_until(
+                    poll_interval,
+                    || fetch(self, state),
+                    |reply| Ok(Some(reply)),
+                )
+                .await
+                {
+                    Ok(reply) => {
+                        let items = receive(&mut polling.state, reply);
+                        polling.pending.extend(items);
+                    }
+                    Err(e) => {
+                        polling.done = true;
+                        return Some((Err(e), polling));
+                    }
+                }
+            }
+        })
     }
 
-    pub async fn get_host_info(&mut self) -> Result<models::HostInfo, Error> {
-        self.get_object_by_req_tag("get_host_info", "host_info")
+    /// Projects listed by the BOINC website, as the daemon has them.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` for a reply without `projects`, and as any request
+    /// otherwise, see `Client`.
+    pub async fn get_projects(&self) -> Result<Vec<models::ProjectInfo>, Error> {
+        self.get_vec(Request::GetAllProjectsList.into(), "projects", "project")
             .await
     }
 
-    pub async fn set_language(&mut self, v: &str) -> Result<(), Error> {
-        self.transport.ready().await?;
-        verify_rpc_reply_contents(
-            &self
-                .transport
-                .call(vec![{
-                    let mut node = treexml::Element::new("set_language");
-                    let mut language_node = treexml::Element::new("language");
-                    language_node.text = Some(v.into());
-                    node.children.push(language_node);
-                    node
-                }])
-                .await?,
-        )?;
-        Ok(())
+    /// Projects the client is attached to.
+    ///
+    /// # Errors
+    ///
+    /// Fails as any request does, see `Client`. A reply without `projects` makes for an empty list.
+    pub async fn get_project_status(&self) -> Result<Vec<models::Project>, Error> {
+        self.get_vec_or_empty(Request::GetProjectStatus.into(), "projects", "project")
+            .await
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::errors::Error;
+    /// Attached project of the master URL `url`, if any, fetching the whole project list. URLs
+    /// match regardless of the scheme, a trailing slash and the case of the host name.
+    ///
+    /// # Errors
+    ///
+    /// As `get_project_status`.
+    pub async fn get_attached_project(&self, url: &str) -> Result<Option<models::Project>, Error> {
+        Ok(self.get_project_status().await?.into_iter().find(|project| {
+            matches!(&project.master_url, Some(master_url) if util::same_master_url(master_url, url))
+        }))
+    }
 
-    #[test]
-    fn verify_rpc_reply_contents() {
-        let mut fixture = treexml::Element::new("error");
-        fixture.text = Some("Missing authenticator".into());
-        let fixture = vec![fixture];
-        assert_eq!(
-            super::verify_rpc_reply_contents(&fixture).err().unwrap(),
-            Error::AuthError("Missing authenticator".to_string())
-        );
+    /// Account manager the client uses, with its fields empty if there is none.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` for a reply without `acct_mgr_info`, and as any request
+    /// otherwise, see `Client`.
+    pub async fn get_account_manager_info(&self) -> Result<models::AccountManagerInfo, Error> {
+        self.get_object(Request::AcctMgrInfo.into(), "acct_mgr_info")
+            .await
+    }
+
+    async fn poll<T, F>(
+        &self,
+        request: Request,
+        reply_tag: &str,
+        status_required: bool,
+        done: F,
+    ) -> Result<PollResult<T>, Error>
+    where
+        F: FnOnce(&treexml::Element, Vec<String>) -> Result<T, Error>,
+    {
+        let req_tag = request.name();
+        self.call(request.into(), |_, data| {
+            verify_rpc_reply_contents(&data)?;
+            check_reply_nodes(req_tag, &data, reply_tag)?;
+
+            let node = data
+                .iter()
+                .find(|child| child.name == reply_tag)
+                .ok_or_else(|| Error::NodeNotFound {
+                    request: req_tag.into(),
+                    node: reply_tag.into(),
+                })?;
+            PollResult::from_reply(req_tag, node, status_required, done)
+        })
+        .await
+    }
+
+    /// Raw `error_num` of the last account manager RPC. See `poll_account_manager_rpc`.
+    ///
+    /// # Errors
+    ///
+    /// As `poll_account_manager_rpc`, except that failures of the account manager RPC are returned
+    /// as their code.
+    pub async fn get_account_manager_rpc_status(&self) -> Result<i32, Error> {
+        Ok(match self.poll_account_manager_rpc().await? {
+            PollResult::InProgress => BoincErrorCode::InProgress.code(),
+            PollResult::Done(_) => 0,
+            PollResult::Failed { code, .. } => code.code(),
+        })
+    }
+
+    /// Polls the account manager RPC started by `connect_to_account_manager`.
+    /// Messages from the account manager are returned on completion.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` for a reply without `acct_mgr_rpc_reply`, and as any
+    /// request otherwise, see `Client`. Failures of the account manager RPC itself are
+    /// `PollResult::Failed`.
+    pub async fn poll_account_manager_rpc(&self) -> Result<PollResult<Vec<String>>, Error> {
+        self.poll(
+            Request::AcctMgrRpcPoll,
+            "acct_mgr_rpc_reply",
+            true,
+            |_, m| Ok(m),
+        )
+        .await
+    }
+
+    /// Polls a pending project attach, returning the project's messages on completion.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` for a reply without `project_attach_reply`, and as any
+    /// request otherwise, see `Client`. Failures of the attach itself are `PollResult::Failed`.
+    pub async fn poll_project_attach(&self) -> Result<PollResult<Vec<String>>, Error> {
+        self.poll(
+            Request::ProjectAttachPoll,
+            "project_attach_reply",
+            true,
+            |_, m| Ok(m),
+        )
+        .await
+    }
+
+    /// Starts attaching to the project at `url` with the account of `authenticator`, which
+    /// `poll_project_attach` follows up on. See `attach_project` for the whole of it.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` unless the daemon acknowledges the request with
+    /// `<success/>`, and as any request otherwise, see `Client`.
+    pub async fn start_project_attach<A: Into<Secret>>(
+        &self,
+        url: &str,
+        authenticator: A,
+        name: &str,
+    ) -> Result<(), Error> {
+        self.do_write(
+            Request::ProjectAttach {
+                url: url.into(),
+                authenticator: authenticator.into(),
+                name: name.into(),
+            }
+            .into(),
+        )
+        .await
+    }
+
+    /// Attaches to the project at `url`, as `start_project_attach` does, polling every
+    /// `poll_interval` until the daemon is done. The project is then looked for among
+    /// `get_project_status`.
+    ///
+    /// # Errors
+    ///
+    /// A bad URL fails with `Error::InvalidURLError`, a bad authenticator with
+    /// `Error::StatusError(BoincErrorCode::Authenticator)` and a project attached already with
+    /// `Error::AlreadyAttachedError`. Any of it taking longer than `timeout` fails with
+    /// `Error::Timeout`.
+    pub async fn attach_project<A: Into<Secret>>(
+        &self,
+        url: &str,
+        authenticator: A,
+        name: &str,
+        poll_interval: std::time::Duration,
+        timeout: std::time::Duration,
+    ) -> Result<(), Error> {
+        let authenticator = authenticator.into();
+        let attach = async {
+            self.start_project_attach(url, authenticator, name).await?;
+            self.poll_until_done(
+                poll_interval,
+                Self::poll_project_attach,
+                |code, messages| {
+                    let detail = if messages.is_empty() {
+                        url.to_owned()
+                    } else {
+                        messages.join("; ")
+                    };
+                    match code {
+                        BoincErrorCode::InvalidUrl => Error::InvalidURLError(detail),
+                        BoincErrorCode::AlreadyAttached => Error::AlreadyAttachedError(detail),
+                        code => Error::StatusError(code),
+                    }
+                },
+            )
+            .await?;
+
+            if self.get_attached_project(url).await?.is_some() {
+                Ok(())
+            } else {
+                Err(Error::DaemonError(format!(
+                    "{url} is not among the projects after attaching"
+                )))
+            }
+        };
+        time::timeout(timeout, attach).await?
+    }
+
+    /// Applies `op` to the project at `url`, which must be as the daemon knows it.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` unless the daemon acknowledges the request with
+    /// `<success/>`, and as any request otherwise, see `Client`.
+    pub async fn project_op(&self, url: &str, op: models::ProjectOp) -> Result<(), Error> {
+        self.do_write(
+            Request::ProjectOp {
+                op,
+                url: url.into(),
+            }
+            .into(),
+        )
+        .await
+    }
+
+    /// Asks the project at `url` for work, as `project_op` with `ProjectOp::Update` does, then
+    /// polls every `poll_interval` until the scheduler RPC is done. Returns the project as it is
+    /// afterwards, for instance to look for new tasks.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::SchedulerRpcDeferred` once it is clear that the client backs off from
+    /// the scheduler beyond `timeout`, and with `Error::Timeout` after `timeout` otherwise. A
+    /// project not attached fails with `Error::DaemonError`.
+    pub async fn wait_for_project_update(
+        &self,
+        url: &str,
+        poll_interval: std::time::Duration,
+        timeout: std::time::Duration,
+    ) -> Result<models::Project, Error> {
+        let deadline = util::unix_time() + timeout.as_secs_f64();
+        let not_attached = || Error::DaemonError(format!("{url} is not among the projects"));
+        let update = async {
+            let project = self
+                .get_attached_project(url)
+                .await?
+                .ok_or_else(not_attached)?;
+            let last_rpc_time = project.last_rpc_time;
+            let master_url = project.master_url.unwrap_or_default();
+            self.project_op(&master_url, models::ProjectOp::Update)
+                .await?;
+            time::delay_for(poll_interval).await;
+            time::poll_until(
+                poll_interval,
+                || self.get_attached_project(&master_url),
+                |updated| {
+                    let updated = updated.ok_or_else(not_attached)?;
+                    let pending = updated.sched_rpc_pending.unwrap_or(0) != 0;
+                    if !pending && updated.last_rpc_time > last_rpc_time {
+                        return Ok(Some(updated));
+                    }
+                    match updated.min_rpc_time {
+                        Some(min_rpc_time) if pending && min_rpc_time > deadline => {
+                            Err(Error::SchedulerRpcDeferred {
+                                master_url: master_url.clone(),
+                                min_rpc_time,
+                            })
+                        }
+                        _ => Ok(None),
+                    }
+                },
+            )
+            .await
+        };
+        time::timeout(timeout, update).await?
+    }
+
+    /// Attaches and detaches projects until the host is attached as `plan` has it, carrying on
+    /// past changes that fail. With `dry_run`, only reports what would change.
+    ///
+    /// Projects are attached first, so that a host is not left without any when attaching
+    /// fails, then the projects not planned are dealt with as the plan says.
+    ///
+    /// # Errors
+    ///
+    /// Fails only if the attached projects cannot be listed. Changes that fail are listed in the
+    /// report instead.
+    pub async fn apply_project_plan(
+        &self,
+        plan: &account::ProjectPlan,
+        dry_run: bool,
+    ) -> Result<account::PlanReport, Error> {
+        let (changes, share_mismatches) = plan.diff(&self.get_project_status().await?);
+        let mut report = account::PlanReport {
+            dry_run,
+            share_mismatches,
+            ..account::PlanReport::default()
+        };
+        for change in changes {
+            if dry_run {
+                report.changes.push(change);
+                continue;
+            }
+            match self.apply_project_change(plan, &change).await {
+                Ok(()) => report.changes.push(change),
+                Err(e) => report.failed.push((change, e)),
+            }
+        }
+        Ok(report)
+    }
+
+    async fn apply_project_change(
+        &self,
+        plan: &account::ProjectPlan,
+        change: &account::ProjectChange,
+    ) -> Result<(), Error> {
+        use account::{Credentials, ProjectChange};
+
+        match change {
+            ProjectChange::Attach(url) => {
+                let planned = plan
+                    .planned(url)
+                    .ok_or_else(|| Error::DaemonError(format!("{url} is not planned")))?;
+                match &planned.credentials {
+                    Credentials::Authenticator(authenticator) => {
+                        self.attach_project(
+                            url,
+                            authenticator.clone(),
+                            planned.name.as_deref().unwrap_or(url),
+                            plan.options.poll_interval,
+                            plan.options.timeout,
+                        )
+                        .await
+                    }
+                    Credentials::Account {
+                        email_or_user,
+                        password,
+                    } => self
+                        .add_project_by_account(url, email_or_user, password.clone(), &plan.options)
+                        .await
+                        .map_err(|e| e.error),
+                }
+            }
+            ProjectChange::Detach(url) => self.project_op(url, models::ProjectOp::Detach).await,
+            ProjectChange::DetachWhenDone(url) => {
+                self.project_op(url, models::ProjectOp::DetachWhenDone)
+                    .await
+            }
+            ProjectChange::KeepAttached(url) => {
+                self.project_op(url, models::ProjectOp::DontDetachWhenDone)
+                    .await
+            }
+        }
+    }
+
+    /// Calls `poll` every `poll_interval` until the operation is done, turning its failure
+    /// into an error with `failed`. See `time::poll_until`.
+    async fn poll_until_done<'a, T, F, Fut, E>(
+        &'a self,
+        poll_interval: std::time::Duration,
+        poll: F,
+        failed: E,
+    ) -> Result<T, Error>
+    where
+        F: Fn(&'a Self) -> Fut,
+        Fut: std::future::Future<Output = Result<PollResult<T>, Error>>,
+        E: Fn(BoincErrorCode, Vec<String>) -> Error,
+    {
+        time::poll_until(
+            poll_interval,
+            || poll(self),
+            |res| match res {
+                PollResult::InProgress => Ok(None),
+                PollResult::Done(result) => Ok(Some(result)),
+                PollResult::Failed { code, messages } => Err(failed(code, messages)),
+            },
+        )
+        .await
+    }
+
+    /// Attaches to the project at `url` with an existing account, as the Manager's wizard does:
+    /// fetches the project's configuration, looks up the account of `email_or_user` with
+    /// `password` and attaches with the authenticator found. Each stage may take up to the
+    /// timeout of `options`.
+    ///
+    /// Failures tell the stage they happened at, though never the password.
+    ///
+    /// # Errors
+    ///
+    /// Fails with an `AddProjectError` telling the stage and its error, `Error::Timeout` for a
+    /// stage that took too long.
+    pub async fn add_project_by_account<P: Into<Secret>>(
+        &self,
+        url: &str,
+        email_or_user: &str,
+        password: P,
+        options: &account::AddProjectOptions,
+    ) -> Result<(), account::AddProjectError> {
+        use account::{AddProjectError, AddProjectStage};
+
+        let password = password.into();
+        let poll_interval = options.poll_interval;
+
+        let config: models::ProjectConfig = AddProjectStage::ProjectConfig
+            .run(options.timeout, async {
+                self.start_project_config(url).await?;
+                self.poll_until_done(poll_interval, Self::poll_project_config, |code, _| {
+                    Error::StatusError(code)
+                })
+                .await
+            })
+            .await?;
+
+        let uses_username = config.uses_username.unwrap_or(false);
+        let authenticator = AddProjectStage::LookupAccount
+            .run(options.timeout, async {
+                self.start_lookup_account(url, email_or_user, password, uses_username)
+                    .await?;
+                self.poll_until_done(poll_interval, Self::poll_lookup_account, |code, _| {
+                    Error::StatusError(code)
+                })
+                .await
+            })
+            .await?;
+
+        let name = config.name.as_deref().unwrap_or(url);
+        self.attach_project(url, authenticator, name, poll_interval, options.timeout)
+            .await
+            .map_err(|error| AddProjectError {
+                stage: AddProjectStage::Attach,
+                error,
+            })
+    }
+
+    /// Starts looking up the account of `email_or_user` with `password` at the project at
+    /// `url`, which `poll_lookup_account` follows up on. Only a hash of the password is sent.
+    ///
+    /// Projects that identify accounts by user name, as `ProjectConfig::uses_username` tells,
+    /// take it as is, while email addresses are not case-sensitive.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` unless the daemon acknowledges the request with
+    /// `<success/>`, and as any request otherwise, see `Client`.
+    pub async fn start_lookup_account<P: Into<Secret>>(
+        &self,
+        url: &str,
+        email_or_user: &str,
+        password: P,
+        uses_username: bool,
+    ) -> Result<(), Error> {
+        let account = if uses_username {
+            email_or_user.to_owned()
+        } else {
+            email_or_user.to_lowercase()
+        };
+        let passwd_hash = compute_passwd_hash(password.into().expose(), &account);
+
+        self.do_write(
+            Request::LookupAccount {
+                url: url.into(),
+                email_addr: account,
+                passwd_hash: passwd_hash.into(),
+            }
+            .into(),
+        )
+        .await
+    }
+
+    /// Polls a pending account lookup, returning the account authenticator on completion.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` for a reply without `account_out`, and as any request
+    /// otherwise, see `Client`. A lookup done without an authenticator fails with
+    /// `Error::NodeNotFound` as well.
+    pub async fn poll_lookup_account(&self) -> Result<PollResult<Secret>, Error> {
+        self.poll(
+            Request::LookupAccountPoll,
+            "account_out",
+            false,
+            |node, _| {
+                node.children
+                    .iter()
+                    .find(|n| n.name == "authenticator")
+                    .and_then(|n| util::trimmed_optional(&n.text))
+                    .map(Secret::from)
+                    .ok_or_else(|| Error::NodeNotFound {
+                        request: "lookup_account_poll".into(),
+                        node: "account_out/authenticator".into(),
+                    })
+            },
+        )
+        .await
+    }
+
+    /// Starts fetching the configuration of the project at `url`, which `poll_project_config`
+    /// follows up on.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` unless the daemon acknowledges the request with
+    /// `<success/>`, and as any request otherwise, see `Client`.
+    pub async fn start_project_config(&self, url: &str) -> Result<(), Error> {
+        self.do_write(Request::GetProjectConfig { url: url.into() }.into())
+            .await
+    }
+
+    /// Polls a pending project configuration fetch.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` for a reply without `project_config`, and as any request
+    /// otherwise, see `Client`.
+    pub async fn poll_project_config(&self) -> Result<PollResult<models::ProjectConfig>, Error> {
+        self.poll(
+            Request::GetProjectConfigPoll,
+            "project_config",
+            false,
+            |node, _| Ok(node.into()),
+        )
+        .await
+    }
+
+    /// Starts attaching to the account manager at `url` with the account `name`, which
+    /// `poll_account_manager_rpc` follows up on. Returns whether the daemon took the request. See
+    /// `attach_account_manager` for the whole of it.
+    ///
+    /// # Errors
+    ///
+    /// Fails as any request does, see `Client`. A refusal of the daemon is `Ok(false)` rather than
+    /// an error.
+    pub async fn connect_to_account_manager(
+        &self,
+        url: &str,
+        name: &str,
+        password: &str,
+    ) -> Result<bool, Error> {
+        let request = Request::AcctMgrRpc {
+            url: url.into(),
+            name: name.into(),
+            password: password.into(),
+        };
+        self.call(request.into(), |_, data| verify_rpc_reply_contents(&data))
+            .await
+    }
+
+    /// Attaches to the account manager at `url`, as `connect_to_account_manager` does, polling
+    /// every `poll_interval` until the account manager RPC is done, as the Manager's wizard
+    /// does.
+    ///
+    /// # Errors
+    ///
+    /// Failures of the RPC, such as a wrong password, come as
+    /// `Error::AccountManagerFailed` with the account manager's messages. Taking longer than
+    /// `timeout` fails with `Error::Timeout`.
+    pub async fn attach_account_manager(
+        &self,
+        url: &str,
+        name: &str,
+        password: &str,
+        poll_interval: std::time::Duration,
+        timeout: std::time::Duration,
+    ) -> Result<(), Error> {
+        let attach = async {
+            if !self.connect_to_account_manager(url, name, password).await? {
+                return Err(Error::NodeNotFound {
+                    request: "acct_mgr_rpc".into(),
+                    node: "success".into(),
+                });
+            }
+            self.poll_until_done(
+                poll_interval,
+                Self::poll_account_manager_rpc,
+                |code, messages| Error::AccountManagerFailed { code, messages },
+            )
+            .await
+            .map(drop)
+        };
+        time::timeout(timeout, attach).await?
+    }
+
+    /// Tells the daemon the GUI RPC version of this side, `VersionInfo::current()` unless given,
+    /// and returns the daemon's.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` for a reply without `server_version`, and as any request
+    /// otherwise, see `Client`.
+    pub async fn exchange_versions(
+        &self,
+        info: Option<&models::VersionInfo>,
+    ) -> Result<models::VersionInfo, Error> {
+        let info = info.cloned().unwrap_or_else(models::VersionInfo::current);
+        let version: models::VersionInfo = self
+            .get_object(Request::ExchangeVersions(info).into(), "server_version")
+            .await?;
+        self.cache().server_version = Some(version.clone());
+        Ok(version)
+    }
+
+    /// Tasks on the host, or only those running if `active_only`.
+    ///
+    /// # Errors
+    ///
+    /// Fails as any request does, see `Client`. A reply without `results` makes for an empty list.
+    pub async fn get_results(&self, active_only: bool) -> Result<Vec<models::TaskResult>, Error> {
+        self.get_vec_or_empty(
+            Request::GetResults { active_only }.into(),
+            "results",
+            "result",
+        )
+        .await
+    }
+
+    /// Aborts the task `name` of the project at `project_url`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` unless the daemon acknowledges the request with
+    /// `<success/>`, and as any request otherwise, see `Client`.
+    pub async fn abort_result(&self, project_url: &str, name: &str) -> Result<(), Error> {
+        self.do_write(
+            Request::AbortResult {
+                project_url: project_url.into(),
+                name: name.into(),
+            }
+            .into(),
+        )
+        .await
+    }
+
+    /// Suspends the task `name` of the project at `project_url`, as the Manager's tasks tab does.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` unless the daemon acknowledges the request with
+    /// `<success/>`, and as any request otherwise, see `Client`.
+    pub async fn suspend_result(&self, project_url: &str, name: &str) -> Result<(), Error> {
+        self.do_write(
+            Request::SuspendResult {
+                project_url: project_url.into(),
+                name: name.into(),
+            }
+            .into(),
+        )
+        .await
+    }
+
+    /// Resumes a task suspended with `suspend_result`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` unless the daemon acknowledges the request with
+    /// `<success/>`, and as any request otherwise, see `Client`.
+    pub async fn resume_result(&self, project_url: &str, name: &str) -> Result<(), Error> {
+        self.do_write(
+            Request::ResumeResult {
+                project_url: project_url.into(),
+                name: name.into(),
+            }
+            .into(),
+        )
+        .await
+    }
+
+    /// Aborts every task that `filter` matches, one by one, carrying on past tasks the daemon
+    /// refuses to abort. With `dry_run`, only reports what would be aborted.
+    ///
+    /// Tasks missing a name or project URL cannot be aborted and are passed over.
+    ///
+    /// # Errors
+    ///
+    /// Fails only if the tasks cannot be listed. Tasks the daemon refuses to abort are listed in
+    /// the report instead.
+    pub async fn abort_tasks_matching<F>(
+        &self,
+        filter: F,
+        dry_run: bool,
+    ) -> Result<tasks::AbortReport, Error>
+    where
+        F: Fn(&models::TaskResult) -> bool,
+    {
+        let mut report = tasks::AbortReport {
+            dry_run,
+            ..tasks::AbortReport::default()
+        };
+        for task in self.get_results(false).await? {
+            if !filter(&task) {
+                continue;
+            }
+            if let (Some(project_url), Some(name)) = (task.project_url, task.name) {
+                if dry_run {
+                    report.aborted.push(name);
+                } else {
+                    match self.abort_result(&project_url, &name).await {
+                        Ok(()) => report.aborted.push(name),
+                        Err(e) => report.failed.push((name, e)),
+                    }
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Tasks that `filter` matches. The daemon has no filters of its own, so the whole list is
+    /// fetched, or the active tasks only if `filter` only matches those.
+    ///
+    /// # Errors
+    ///
+    /// As `get_results`.
+    pub async fn get_tasks(
+        &self,
+        filter: &models::TaskFilter,
+    ) -> Result<Vec<models::TaskResult>, Error> {
+        let mut tasks = self.get_results(filter.is_active_only()).await?;
+        tasks.retain(|task| filter.matches(task));
+        Ok(tasks)
+    }
+
+    /// Standing of each task against its report deadline, the tasks most at risk first. See
+    /// `deadline_report_with`.
+    ///
+    /// # Errors
+    ///
+    /// As `get_results`.
+    pub async fn deadline_report(&self) -> Result<Vec<deadline::TaskDeadline>, Error> {
+        self.deadline_report_with(&deadline::DeadlineOptions::default())
+            .await
+    }
+
+    /// Standing of each task against its report deadline as of now, judged as `options` say.
+    /// See `deadline::report`.
+    ///
+    /// # Errors
+    ///
+    /// As `get_results`.
+    pub async fn deadline_report_with(
+        &self,
+        options: &deadline::DeadlineOptions,
+    ) -> Result<Vec<deadline::TaskDeadline>, Error> {
+        let tasks = self.get_results(false).await?;
+        Ok(deadline::report(
+            &tasks,
+            std::time::SystemTime::now(),
+            options,
+        ))
+    }
+
+    /// Task of the given name, if on the task list. The daemon has no lookup of its own, so the
+    /// whole list is fetched.
+    ///
+    /// # Errors
+    ///
+    /// As `get_results`.
+    pub async fn get_task(&self, name: &str) -> Result<Option<models::TaskResult>, Error> {
+        Ok(self
+            .get_results(false)
+            .await?
+            .into_iter()
+            .find(|task| task.name.as_deref() == Some(name)))
+    }
+
+    /// File transfers in progress or waiting to be retried.
+    ///
+    /// # Errors
+    ///
+    /// Fails as any request does, see `Client`. A reply without `file_transfers` makes for an empty
+    /// list.
+    pub async fn get_file_transfers(&self) -> Result<Vec<models::FileTransfer>, Error> {
+        self.get_vec_or_empty(
+            Request::GetFileTransfers.into(),
+            "file_transfers",
+            "file_transfer",
+        )
+        .await
+    }
+
+    /// Credit history of each attached project.
+    ///
+    /// # Errors
+    ///
+    /// Fails as any request does, see `Client`. A reply without `statistics` makes for an empty
+    /// list.
+    pub async fn get_statistics(&self) -> Result<Vec<models::ProjectStatistics>, Error> {
+        self.get_vec_or_empty(
+            Request::GetStatistics.into(),
+            "statistics",
+            "project_statistics",
+        )
+        .await
+    }
+
+    /// Sets the mode of `c` to `m` for `duration`, or for good if `None`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidArgument`, without sending anything, for a timed
+    /// `RunMode::Restore` or a zero duration.
+    pub async fn set_mode_for(
+        &self,
+        c: models::Component,
+        m: models::RunMode,
+        duration: Option<std::time::Duration>,
+    ) -> Result<(), Error> {
+        self.do_write(Request::set_mode(c, m, duration)?.into())
+            .await
+    }
+
+    /// Sets the mode of `c` to `m` for `duration` seconds, 0 meaning for good.
+    ///
+    /// # Errors
+    ///
+    /// As `set_mode_for`, and with `Error::InvalidArgument` for negative or non-finite seconds.
+    #[deprecated(note = "use `set_mode_for`, which takes the duration as `Option<Duration>`")]
+    pub async fn set_mode(
+        &self,
+        c: models::Component,
+        m: models::RunMode,
+        duration: f64,
+    ) -> Result<(), Error> {
+        self.set_mode_for(c, m, rpc::request::mode_duration(duration)?)
+            .await
+    }
+
+    /// Suspends computing on CPUs and GPUs for `duration`, as the Manager's snooze does.
+    ///
+    /// # Errors
+    ///
+    /// Should suspending GPUs fail, CPUs are resumed again. Should that fail too, the error
+    /// says that CPUs are left suspended. A zero `duration` fails with `Error::InvalidArgument`.
+    pub async fn snooze(&self, duration: std::time::Duration) -> Result<(), Error> {
+        let duration = Some(duration);
+        self.set_mode_for(models::Component::CPU, models::RunMode::Never, duration)
+            .await?;
+        if let Err(e) = self
+            .set_mode_for(models::Component::GPU, models::RunMode::Never, duration)
+            .await
+        {
+            if let Err(undo) = self
+                .set_mode_for(models::Component::CPU, models::RunMode::Restore, None)
+                .await
+            {
+                return Err(Error::DaemonError(format!(
+                    "Snoozing GPUs failed ({e}), and CPUs are left snoozed as resuming them failed \
+                     ({undo})"
+                )));
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Ends a `snooze`, restoring the run modes of CPUs and GPUs from before.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the mode of CPUs or GPUs cannot be restored, see `set_mode_for`. GPUs are restored
+    /// even if CPUs fail to be.
+    pub async fn unsnooze(&self) -> Result<(), Error> {
+        self.set_mode_for(models::Component::CPU, models::RunMode::Restore, None)
+            .await?;
+        self.set_mode_for(models::Component::GPU, models::RunMode::Restore, None)
+            .await
+    }
+
+    /// Hardware and operating system of the host.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` for a reply without `host_info`, and as any request
+    /// otherwise, see `Client`.
+    pub async fn get_host_info(&self) -> Result<models::HostInfo, Error> {
+        let host_info: models::HostInfo = self
+            .get_object(Request::GetHostInfo.into(), "host_info")
+            .await?;
+        self.cache().host_info = Some(host_info.clone());
+        Ok(host_info)
+    }
+
+    /// Run modes and network state of the client, with the reasons for anything suspended.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` for a reply without `cc_status`, and as any request
+    /// otherwise, see `Client`.
+    pub async fn get_cc_status(&self) -> Result<models::CcStatus, Error> {
+        self.get_object(Request::GetCcStatus.into(), "cc_status")
+            .await
+    }
+
+    /// Starts the CPU benchmarks, suspending tasks until they are done. See
+    /// `run_benchmarks_and_wait`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` unless the daemon acknowledges the request with
+    /// `<success/>`, and as any request otherwise, see `Client`.
+    pub async fn run_benchmarks(&self) -> Result<(), Error> {
+        self.do_write(Request::RunBenchmarks.into()).await
+    }
+
+    /// Runs the CPU benchmarks, as `run_benchmarks` does, polling every `poll_interval` until
+    /// the client is no longer suspended for them and `HostInfo::p_calculated` moved on. Returns
+    /// the host info with the new numbers.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::Timeout` after `timeout`, and with the first error that is not
+    /// transient.
+    pub async fn run_benchmarks_and_wait(
+        &self,
+        poll_interval: std::time::Duration,
+        timeout: std::time::Duration,
+    ) -> Result<models::HostInfo, Error> {
+        let run = async {
+            let calculated = self.get_host_info().await?.p_calculated;
+            self.run_benchmarks().await?;
+            time::delay_for(poll_interval).await;
+            time::poll_until(
+                poll_interval,
+                || async {
+                    let status = self.get_cc_status().await?;
+                    if status.task_suspend_reason == Some(models::SuspendReason::Benchmarks) {
+                        return Ok(None);
+                    }
+                    self.get_host_info().await.map(Some)
+                },
+                |host_info| Ok(host_info.filter(|host_info| host_info.p_calculated > calculated)),
+            )
+            .await
+        };
+        time::timeout(timeout, run).await?
+    }
+
+    /// The whole state of the client: projects, apps, tasks and more, as the Manager first fetches
+    /// it.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` for a reply without `client_state`, and as any request
+    /// otherwise, see `Client`.
+    pub async fn get_state(&self) -> Result<models::ClientState, Error> {
+        self.get_object(Request::GetState.into(), "client_state")
+            .await
+    }
+
+    /// Sets the language of the messages and notices the daemon passes on.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` unless the daemon acknowledges the request with
+    /// `<success/>`, and as any request otherwise, see `Client`.
+    pub async fn set_language(&self, v: &str) -> Result<(), Error> {
+        self.do_write(Request::SetLanguage { language: v.into() }.into())
+            .await
+    }
+
+    /// Preferences in effect: those of the account, as its source project sent them, with the
+    /// override file applied on top.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` for a reply without `global_preferences`, and as any
+    /// request otherwise, see `Client`.
+    pub async fn get_global_prefs_working(&self) -> Result<models::GlobalPreferences, Error> {
+        self.get_object(Request::GetGlobalPrefsWorking.into(), "global_preferences")
+            .await
+    }
+
+    /// Contents of the override file, `None` if there is none.
+    ///
+    /// # Errors
+    ///
+    /// Fails as any request does, see `Client`. No override file is `Ok(None)`, not an error.
+    pub async fn get_global_prefs_override(
+        &self,
+    ) -> Result<Option<models::GlobalPreferences>, Error> {
+        self.call(
+            Request::GetGlobalPrefsOverride.into(),
+            |request, data| match parse_object(request, &data, "global_preferences") {
+                Ok(prefs) => Ok(Some(prefs)),
+                Err(Error::DaemonError(e)) if e == "no prefs override file" => Ok(None),
+                Err(e) => Err(e),
+            },
+        )
+        .await
+    }
+
+    /// Writes the override file, or removes it for `None`. The daemon only applies it once
+    /// told to with `read_global_prefs_override`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` unless the daemon acknowledges the request with
+    /// `<success/>`, and as any request otherwise, see `Client`.
+    pub async fn set_global_prefs_override(
+        &self,
+        prefs: Option<&models::GlobalPreferences>,
+    ) -> Result<(), Error> {
+        self.do_write(
+            Request::SetGlobalPrefsOverride {
+                prefs: prefs.cloned(),
+            }
+            .into(),
+        )
+        .await
+    }
+
+    /// Makes the daemon read the override file anew and apply it.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NodeNotFound` unless the daemon acknowledges the request with
+    /// `<success/>`, and as any request otherwise, see `Client`.
+    pub async fn read_global_prefs_override(&self) -> Result<(), Error> {
+        self.do_write(Request::ReadGlobalPrefsOverride.into()).await
+    }
+
+    /// Changes some preferences with `update`, keeping the rest, as the Manager's computing
+    /// preferences dialog does. Returns the preferences in effect afterwards.
+    ///
+    /// `update` is handed the override file if there is one, or else the preferences in effect.
+    /// What it makes of them is written as the override file, which the daemon is then told to
+    /// read.
+    ///
+    /// The daemon applies the override on top of the account's preferences, field by field:
+    /// fields in the override win, while those left out keep the account's value. As `None`
+    /// fields are left out, clearing one hands it back to the account preferences rather than
+    /// to a default. Starting from the preferences in effect puts all of them in the override,
+    /// so later changes to the account preferences on the project website are masked until the
+    /// override is removed, as with the Manager.
+    ///
+    /// Nothing stops others, such as the Manager, from changing the override file in between
+    /// reading and writing it.
+    ///
+    /// # Errors
+    ///
+    /// Fails if reading the preferences, writing the override file or having the daemon read it
+    /// does. The override file may be left written in the last case.
+    pub async fn update_global_prefs<F>(
+        &self,
+        update: F,
+    ) -> Result<models::GlobalPreferences, Error>
+    where
+        F: FnOnce(&mut models::GlobalPreferences),
+    {
+        let mut prefs = match self.get_global_prefs_override().await? {
+            Some(prefs) => prefs,
+            None => self.get_global_prefs_working().await?,
+        };
+        update(&mut prefs);
+        self.set_global_prefs_override(Some(&prefs)).await?;
+        self.read_global_prefs_override().await?;
+        self.get_global_prefs_working().await
+    }
+
+    /// Changes some preferences with `changes` until `restore` is called on the returned guard,
+    /// as for throttling the host for a couple of hours.
+    ///
+    /// `changes` is handed the override file if there is one, or else the preferences in
+    /// effect, as by `update_global_prefs`. If the daemon cannot be made to apply the override,
+    /// the override file is restored right away. See `prefs::PrefsGuard` for what becomes of the
+    /// override if the guard is never restored.
+    ///
+    /// # Errors
+    ///
+    /// Fails if reading the preferences or writing the override file does, or if the daemon cannot
+    /// be made to apply the override, after putting the file back as it was.
+    pub async fn temporary_prefs_override<F>(
+        &self,
+        changes: F,
+    ) -> Result<prefs::PrefsGuard<'_, S>, Error>
+    where
+        F: FnOnce(&mut models::GlobalPreferences),
+    {
+        let previous = self.get_global_prefs_override().await?;
+        let mut prefs = match &previous {
+            Some(prefs) => prefs.clone(),
+            None => self.get_global_prefs_working().await?,
+        };
+        changes(&mut prefs);
+        self.set_global_prefs_override(Some(&prefs)).await?;
+
+        let mut guard = prefs::PrefsGuard::new(self, previous, prefs);
+        if let Err(e) = self.read_global_prefs_override().await {
+            if let Err(e) = guard.restore().await {
+                tracing::debug!("Restoring the preferences override failed: {:?}", e);
+            }
+            return Err(e);
+        }
+        Ok(guard)
+    }
+}
+
+// `Send` is up to the transport, as above
+#[allow(clippy::future_not_send)]
+impl<S> Client<S>
+where
+    S: tower_service::Service<Vec<treexml::Element>, Response = Vec<treexml::Element>>
+        + connection::ConnectionSource
+        + Clone,
+    S::Error: Into<Error>,
+{
+    /// Puts together what dashboards show of the client, with one request for each of its
+    /// status, tasks, file transfers and projects. Host info and the daemon version are
+    /// requested only while not known yet, and kept from `get_host_info` and
+    /// `exchange_versions` otherwise, or from the version handshake of the transport.
+    ///
+    /// The transport is asked which connection it is on once the other requests are done, and
+    /// what was kept is requested again if the transport has connected anew since, as the daemon
+    /// may have restarted.
+    ///
+    /// Requests that fail leave their sections as errors rather than failing the summary.
+    pub async fn summary(&self) -> models::Summary {
+        // First, so that a new connection is made before relying on what was kept
+        let status = self.get_cc_status().await;
+        let tasks = self.get_results(false).await;
+        let transfers = self.get_file_transfers().await;
+        let projects = self.get_project_status().await;
+        self.cache().connected(self.transport.connection_info());
+        let cached_version = self.cache().server_version.clone();
+        let version = match cached_version {
+            Some(version) => Ok(version),
+            None => self.exchange_versions(None).await,
+        };
+        let cached_host_info = self.cache().host_info.clone();
+        let host_info = match cached_host_info {
+            Some(host_info) => Ok(host_info),
+            None => self.get_host_info().await,
+        };
+
+        let reason = status
+            .as_ref()
+            .ok()
+            .and_then(|status| status.task_suspend_reason);
+        models::Summary {
+            version: version.map(|version| version.to_string()).into(),
+            host: host_info.map(|host_info| (&host_info).into()).into(),
+            status: status.map(|status| (&status).into()).into(),
+            tasks: tasks
+                .map(|tasks| {
+                    models::TaskSummary::new(
+                        &tasks,
+                        reason.unwrap_or(models::SuspendReason::NotSuspended),
+                    )
+                })
+                .into(),
+            transfers: transfers
+                .map(|transfers| transfers.as_slice().into())
+                .into(),
+            projects: projects
+                .map(|projects| {
+                    projects
+                        .into_iter()
+                        .filter_map(|project| project.project_name.or(project.master_url))
+                        .collect()
+                })
+                .into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        errors::{AuthFailure, BoincErrorCode, Error},
+        models,
+        rpc::PollResult,
+        util, Client,
+    };
+    use std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex},
+        task::{Context, Poll},
+        time::Duration,
+    };
+
+    /// Transport replaying canned replies and recording the requests it receives.
+    #[derive(Clone, Default)]
+    struct Canned {
+        replies: Arc<Mutex<VecDeque<Result<Vec<treexml::Element>, Error>>>>,
+        requests: Arc<Mutex<Vec<Vec<treexml::Element>>>>,
+    }
+
+    impl Canned {
+        fn new(replies: &[&str]) -> Self {
+            let v = Self::default();
+            for reply in replies {
+                v.replies
+                    .lock()
+                    .unwrap()
+                    .push_back(Ok(util::parse_node(reply).unwrap().children));
+            }
+            v
+        }
+    }
+
+    impl tower_service::Service<Vec<treexml::Element>> for Canned {
+        type Response = Vec<treexml::Element>;
+        type Error = Error;
+        type Future = futures::future::Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: Vec<treexml::Element>) -> Self::Future {
+            self.requests.lock().unwrap().push(req);
+            futures::future::ready(
+                self.replies
+                    .lock()
+                    .unwrap()
+                    .pop_front()
+                    .expect("no canned reply left"),
+            )
+        }
+    }
+
+    /// Transport that never replies.
+    #[cfg(feature = "tokio")]
+    #[derive(Clone)]
+    struct Stalled;
+
+    #[cfg(feature = "tokio")]
+    impl tower_service::Service<Vec<treexml::Element>> for Stalled {
+        type Response = Vec<treexml::Element>;
+        type Error = Error;
+        type Future = futures::future::Pending<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: Vec<treexml::Element>) -> Self::Future {
+            futures::future::pending()
+        }
+    }
+
+    #[test]
+    fn verify_rpc_reply_contents() {
+        let mut fixture = treexml::Element::new("error");
+        fixture.text = Some("Missing authenticator".into());
+        let fixture = vec![fixture];
+        assert_eq!(
+            super::verify_rpc_reply_contents(&fixture).err().unwrap(),
+            Error::AuthError(AuthFailure::Rejected("Missing authenticator".to_string()))
+        );
+    }
+
+    #[test]
+    fn project_info_platforms() {
+        let fixture = util::parse_node(
+            r"<project>
+    <name>Einstein@Home</name>
+    <id>5</id>
+    <url>https://einsteinathome.org/</url>
+    <web_url>https://einsteinathome.org/</web_url>
+    <general_area>Astrophysics</general_area>
+    <specific_area>Astrophysics</specific_area>
+    <description><![CDATA[Search for spinning neutron stars (also called pulsars) using data from the LIGO gravitational wave detectors, the Arecibo radio observatory, and the Fermi gamma-ray satellite]]></description>
+    <home>University of Wisconsin - Milwaukee, Max Planck Institute for Gravitational Physics</home>
+    <platforms>
+        <name>windows_intelx86</name>
+        <name>windows_x86_64</name>
+        <name>x86_64-pc-linux-gnu</name>
+        <name>x86_64-pc-linux-gnu</name>
+        <name>aarch64-unknown-linux-gnu</name>
+        <name>arm-unknown-linux-gnueabihf</name>
+        <name>windows_intelx86</name>
+    </platforms>
+    <image>https://boinc.berkeley.edu/images/einstein.jpg</image>
+    <summary><![CDATA[Search for neutron stars]]></summary>
+</project>",
+        )
+        .unwrap();
+
+        let info = models::ProjectInfo::from(&fixture);
+        assert_eq!(info.name.as_deref(), Some("Einstein@Home"));
+        let names = info
+            .platforms
+            .unwrap()
+            .into_iter()
+            .map(|p| p.name.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "windows_intelx86",
+                "windows_x86_64",
+                "x86_64-pc-linux-gnu",
+                "aarch64-unknown-linux-gnu",
+                "arm-unknown-linux-gnueabihf",
+            ]
+        );
+    }
+
+    #[test]
+    fn project_info_platforms_with_friendly_names() {
+        let fixture = util::parse_node(
+            r"<project>
+    <platforms>
+        <platform>
+            <platform_name>x86_64-pc-linux-gnu</platform_name>
+            <user_friendly_name>Linux running on an AMD x86_64 or Intel EM64T CPU</user_friendly_name>
+        </platform>
+        <platform>
+            <platform_name>x86_64-pc-linux-gnu</platform_name>
+            <user_friendly_name>Linux running on an AMD x86_64 or Intel EM64T CPU</user_friendly_name>
+            <plan_class>opencl-nvidia</plan_class>
+        </platform>
+        <platform>
+            <platform_name>aarch64-unknown-linux-gnu</platform_name>
+        </platform>
+    </platforms>
+</project>",
+        )
+        .unwrap();
+
+        assert_eq!(
+            models::ProjectInfo::from(&fixture).platforms,
+            Some(vec![
+                models::PlatformInfo {
+                    name: Some("x86_64-pc-linux-gnu".into()),
+                    user_friendly_name: Some(
+                        "Linux running on an AMD x86_64 or Intel EM64T CPU".into()
+                    ),
+                },
+                models::PlatformInfo {
+                    name: Some("aarch64-unknown-linux-gnu".into()),
+                    user_friendly_name: None,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn account_manager_info() {
+        let fixture = util::parse_node(
+            r"<acct_mgr_info>
+    <acct_mgr_url>https://bam.boincstats.com/</acct_mgr_url>
+    <acct_mgr_name>BAM!</acct_mgr_name>
+    <have_credentials/>
+    <user_name>cruncher42</user_name>
+    <team_name><![CDATA[Team & Friends]]></team_name>
+    <previous_host_cpid>2a9e87cd1f3a4b0e8c6d5f4e3b2a1c0d</previous_host_cpid>
+    <host_velocity>0.750000</host_velocity>
+    <dynamic/>
+</acct_mgr_info>",
+        )
+        .unwrap();
+
+        let info = models::AccountManagerInfo::from(&fixture);
+        assert_eq!(info.url.as_deref(), Some("https://bam.boincstats.com/"));
+        assert_eq!(info.name.as_deref(), Some("BAM!"));
+        assert_eq!(info.have_credentials, Some(true));
+        assert_eq!(info.cookie_required, None);
+        assert_eq!(info.user_name.as_deref(), Some("cruncher42"));
+        assert_eq!(info.team_name.as_deref(), Some("Team & Friends"));
+        assert_eq!(
+            info.previous_host_cpid.as_deref(),
+            Some("2a9e87cd1f3a4b0e8c6d5f4e3b2a1c0d")
+        );
+        assert_eq!(info.host_velocity, Some(0.75));
+        assert_eq!(info.dynamic, Some(true));
+    }
+
+    #[test]
+    fn account_manager_info_not_attached() {
+        let fixture = util::parse_node("<acct_mgr_info>\n</acct_mgr_info>").unwrap();
+
+        let info = models::AccountManagerInfo::from(&fixture);
+        assert!(info.url.is_none());
+        assert!(info.name.is_none());
+        assert!(info.have_credentials.is_none());
+        assert!(info.user_name.is_none());
+        assert!(info.team_name.is_none());
+        assert!(info.previous_host_cpid.is_none());
+        assert!(info.host_velocity.is_none());
+        assert!(info.dynamic.is_none());
+    }
+
+    #[test]
+    fn message_blank_project() {
+        let fixture = util::parse_node(
+            r"<msg>
+    <project></project>
+    <pri>1</pri>
+    <seqno>42</seqno>
+    <body><![CDATA[
+Starting BOINC client version 7.20.2 for x86_64-pc-linux-gnu
+]]></body>
+    <time>1700000000</time>
+</msg>",
+        )
+        .unwrap();
+
+        let msg = models::Message::from(&fixture);
+        assert_eq!(msg.project_name, None);
+        assert_eq!(
+            msg.body.as_deref(),
+            Some("Starting BOINC client version 7.20.2 for x86_64-pc-linux-gnu")
+        );
+        assert_eq!(msg.msg_number, Some(42));
+    }
+
+    #[test]
+    fn host_info_blank_strings() {
+        let fixture = util::parse_node(
+            r"<host_info>
+    <domain_name>  worker01 </domain_name>
+    <ip_addr> </ip_addr>
+    <product_name></product_name>
+    <virtualbox_version/>
+</host_info>",
+        )
+        .unwrap();
+
+        let info = models::HostInfo::from(&fixture);
+        assert_eq!(info.domain_name.as_deref(), Some("worker01"));
+        assert_eq!(info.ip_addr, None);
+        assert_eq!(info.product_name, None);
+        assert_eq!(info.virtualbox_version, None);
+    }
+
+    #[test]
+    fn verify_rpc_reply_error_num_child() {
+        let fixture = util::parse_node(
+            "<boinc_gui_rpc_reply><error><error_num>-136</error_num></error></boinc_gui_rpc_reply>",
+        )
+        .unwrap()
+        .children;
+        assert_eq!(
+            super::verify_rpc_reply_contents(&fixture).err().unwrap(),
+            Error::StatusError(BoincErrorCode::DbNotFound)
+        );
+    }
+
+    #[test]
+    fn verify_rpc_reply_numeric_error_text() {
+        let fixture =
+            util::parse_node("<boinc_gui_rpc_reply><error> -189\n</error></boinc_gui_rpc_reply>")
+                .unwrap()
+                .children;
+        assert_eq!(
+            super::verify_rpc_reply_contents(&fixture).err().unwrap(),
+            Error::StatusError(BoincErrorCode::InvalidUrl)
+        );
+    }
+
+    #[test]
+    fn verify_rpc_reply_unknown_error_text() {
+        let fixture = util::parse_node(
+            "<boinc_gui_rpc_reply><error>no such project</error></boinc_gui_rpc_reply>",
+        )
+        .unwrap()
+        .children;
+        assert_eq!(
+            super::verify_rpc_reply_contents(&fixture).err().unwrap(),
+            Error::DaemonError("no such project".into())
+        );
+    }
+
+    #[test]
+    fn verify_rpc_reply_unauthorized() {
+        let fixture = vec![treexml::Element::new("unauthorized")];
+        assert_eq!(
+            super::verify_rpc_reply_contents(&fixture).err().unwrap(),
+            Error::AuthError(AuthFailure::Unauthorized)
+        );
+    }
+
+    #[tokio::test]
+    async fn get_object_not_found() {
+        let client = Client::new(Canned::new(&[
+            "<boinc_gui_rpc_reply><something_else/></boinc_gui_rpc_reply>",
+        ]));
+
+        assert_eq!(
+            client.get_host_info().await.unwrap_err(),
+            Error::NodeNotFound {
+                request: "get_host_info".into(),
+                node: "host_info".into(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn get_vec_not_found() {
+        let client = Client::new(Canned::new(&[
+            "<boinc_gui_rpc_reply><something_else/></boinc_gui_rpc_reply>",
+        ]));
+
+        assert_eq!(
+            client.get_projects().await.unwrap_err(),
+            Error::NodeNotFound {
+                request: "get_all_projects_list".into(),
+                node: "projects".into(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn account_manager_rpc_status_invalid() {
+        let client = Client::new(Canned::new(&[
+            "<boinc_gui_rpc_reply><acct_mgr_rpc_reply><error_num>oops</error_num></acct_mgr_rpc_reply></boinc_gui_rpc_reply>",
+            "<boinc_gui_rpc_reply><acct_mgr_rpc_reply/></boinc_gui_rpc_reply>",
+        ]));
+
+        assert_eq!(
+            client.get_account_manager_rpc_status().await.unwrap_err(),
+            Error::InvalidNodeValue {
+                node: "error_num".into(),
+                value: "oops".into(),
+            }
+        );
+        assert_eq!(
+            client.get_account_manager_rpc_status().await.unwrap_err(),
+            Error::NodeNotFound {
+                request: "acct_mgr_rpc_poll".into(),
+                node: "acct_mgr_rpc_reply/error_num".into(),
+            }
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    fn assert_send<T: Send>(_: &T) {}
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn futures_are_send() {
+        let client = Client::new(crate::Transport::new("localhost:31416", None::<&str>));
+        let version = models::VersionInfo::current();
+        assert_send(&client.get_messages(0));
+        assert_send(&client.stream_messages(Duration::from_secs(1)));
+        assert_send(&client.stream_messages_since(0, Duration::from_secs(1)));
+        assert_send(&client.get_notices(0));
+        assert_send(&client.stream_notices(Duration::from_secs(1)));
+        assert_send(&client.watch_tasks(Duration::from_secs(1)));
+        assert_send(&client.wait_for_task(
+            "task",
+            crate::watch::task_is_reported(),
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+        ));
+        assert_send(&client.wait_until_idle(Duration::from_secs(1), Duration::from_secs(1)));
+        assert_send(&client.drain(crate::tasks::DrainOptions::new(), Duration::from_secs(1)));
+        assert_send(&client.get_projects());
+        assert_send(&client.get_account_manager_info());
+        assert_send(&client.get_account_manager_rpc_status());
+        assert_send(&client.poll_account_manager_rpc());
+        assert_send(&client.poll_project_attach());
+        assert_send(&client.get_project_status());
+        assert_send(&client.start_project_config("url"));
+        assert_send(&client.start_lookup_account("url", "user", "password", true));
+        assert_send(&client.add_project_by_account(
+            "url",
+            "user",
+            "password",
+            &crate::account::AddProjectOptions::new(),
+        ));
+        assert_send(&client.start_project_attach("url", "authenticator", "name"));
+        assert_send(&client.attach_project(
+            "url",
+            "authenticator",
+            "name",
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+        ));
+        assert_send(&client.poll_lookup_account());
+        assert_send(&client.poll_project_config());
+        assert_send(&client.connect_to_account_manager("url", "name", "password"));
+        assert_send(&client.attach_account_manager(
+            "url",
+            "name",
+            "password",
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+        ));
+        assert_send(&client.exchange_versions(Some(&version)));
+        assert_send(&client.get_results(false));
+        assert_send(&client.get_file_transfers());
+        assert_send(&client.watch_file_transfers(Duration::from_secs(1)));
+        assert_send(&client.get_statistics());
+        assert_send(&client.sample_statistics(Duration::from_secs(1)));
+        assert_send(&client.set_mode_for(models::Component::CPU, models::RunMode::Auto, None));
+        assert_send(&client.snooze(Duration::from_secs(1)));
+        assert_send(&client.unsnooze());
+        assert_send(&client.get_host_info());
+        assert_send(&client.get_state());
+        assert_send(&client.get_cc_status());
+        assert_send(&client.ping());
+        assert_send(&client.abort_result("", ""));
+        assert_send(&client.suspend_result("", ""));
+        assert_send(&client.resume_result("", ""));
+        assert_send(&client.summary());
+        assert_send(&client.wait_for_project_update(
+            "",
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+        ));
+        assert_send(&client.get_tasks(&models::TaskFilter::new()));
+        assert_send(&client.get_task(""));
+        assert_send(&client.deadline_report());
+        assert_send(&client.get_attached_project(""));
+        assert_send(&client.project_op("", models::ProjectOp::Update));
+        assert_send(&client.apply_project_plan(&crate::account::ProjectPlan::new(), true));
+        assert_send(&client.abort_tasks_matching(|_| true, false));
+        assert_send(&client.run_benchmarks());
+        assert_send(
+            &client.run_benchmarks_and_wait(Duration::from_secs(1), Duration::from_secs(1)),
+        );
+        assert_send(&client.set_language("de_DE"));
+        assert_send(&client.get_global_prefs_working());
+        assert_send(&client.get_global_prefs_override());
+        assert_send(&client.set_global_prefs_override(None));
+        assert_send(&client.read_global_prefs_override());
+        assert_send(&client.update_global_prefs(|prefs| prefs.cpu_usage_limit = Some(50.0)));
+        assert_send(&client.temporary_prefs_override(|prefs| prefs.cpu_usage_limit = Some(50.0)));
+        assert_send(&client.close());
+        assert_send(&Client::connect("localhost:31416", None::<&str>));
+
+        let mut fleet = crate::fleet::MultiClient::new();
+        fleet.insert("localhost", client);
+        assert_send(&fleet.get_results_all(false));
+        assert_send(&fleet.set_mode_for_all(models::Component::CPU, models::RunMode::Auto, None));
+    }
+
+    #[tokio::test]
+    async fn shared_client() {
+        let transport = Canned::new(&[
+            "<boinc_gui_rpc_reply><success/></boinc_gui_rpc_reply>",
+            "<boinc_gui_rpc_reply><success/></boinc_gui_rpc_reply>",
+        ]);
+        let client = Arc::new(Client::new(transport.clone()));
+
+        let tasks = (0..2).map(|_| {
+            let client = client.clone();
+            tokio::spawn(async move { client.set_language("de_DE").await })
+        });
+        for res in futures::future::join_all(tasks).await {
+            assert_eq!(res.unwrap(), Ok(()));
+        }
+        assert_eq!(transport.requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn write_requires_success() {
+        let transport = Canned::new(&[
+            "<boinc_gui_rpc_reply><success/></boinc_gui_rpc_reply>",
+            "<boinc_gui_rpc_reply></boinc_gui_rpc_reply>",
+            "<boinc_gui_rpc_reply><something_else/></boinc_gui_rpc_reply>",
+        ]);
+        let client = Client::new(transport.clone());
+
+        assert_eq!(client.set_language("de_DE").await, Ok(()));
+        assert_eq!(
+            client.set_language("de_DE").await,
+            Err(Error::NodeNotFound {
+                request: "set_language".into(),
+                node: "success".into(),
+            })
+        );
+        assert_eq!(
+            client
+                .set_mode_for(models::Component::GPU, models::RunMode::Never, None)
+                .await,
+            Err(Error::NodeNotFound {
+                request: "set_gpu_mode".into(),
+                node: "success".into(),
+            })
+        );
+        assert_eq!(transport.requests.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn empty_lists() {
+        let client = Client::new(Canned::new(&[
+            "<boinc_gui_rpc_reply><results/></boinc_gui_rpc_reply>",
+            "<boinc_gui_rpc_reply>\n</boinc_gui_rpc_reply>",
+            "<boinc_gui_rpc_reply><msgs>\n</msgs></boinc_gui_rpc_reply>",
+            "<boinc_gui_rpc_reply/>",
+            "<boinc_gui_rpc_reply><error>unauthorized</error></boinc_gui_rpc_reply>",
+        ]));
+
+        assert!(client.get_results(false).await.unwrap().is_empty());
+        assert!(client.get_results(true).await.unwrap().is_empty());
+        assert!(client.get_messages(0).await.unwrap().is_empty());
+        assert!(client.get_messages(10).await.unwrap().is_empty());
+        assert_eq!(
+            client.get_results(false).await.unwrap_err(),
+            Error::AuthError(AuthFailure::Rejected("unauthorized".into()))
+        );
+    }
+
+    #[tokio::test]
+    async fn poll_states() {
+        let client = Client::new(Canned::new(&[
+            "<boinc_gui_rpc_reply><acct_mgr_rpc_reply><error_num>-204</error_num></acct_mgr_rpc_reply></boinc_gui_rpc_reply>",
+            "<boinc_gui_rpc_reply><acct_mgr_rpc_reply><error_num>0</error_num><message>Welcome</message><message> </message></acct_mgr_rpc_reply></boinc_gui_rpc_reply>",
+            "<boinc_gui_rpc_reply><acct_mgr_rpc_reply><error_num>-206</error_num><message>Wrong password</message></acct_mgr_rpc_reply></boinc_gui_rpc_reply>",
+            "<boinc_gui_rpc_reply><acct_mgr_rpc_reply><error_num>-206</error_num></acct_mgr_rpc_reply></boinc_gui_rpc_reply>",
+        ]));
+
+        assert_eq!(
+            client.poll_account_manager_rpc().await.unwrap(),
+            PollResult::InProgress
+        );
+        assert_eq!(
+            client.poll_account_manager_rpc().await.unwrap(),
+            PollResult::Done(vec!["Welcome".to_string()])
+        );
+        assert_eq!(
+            client.poll_account_manager_rpc().await.unwrap(),
+            PollResult::Failed {
+                code: BoincErrorCode::BadPasswd,
+                messages: vec!["Wrong password".into()],
+            }
+        );
+        assert_eq!(client.get_account_manager_rpc_status().await.unwrap(), -206);
+    }
+
+    #[tokio::test]
+    async fn poll_lookup_account() {
+        let client = Client::new(Canned::new(&[
+            "<boinc_gui_rpc_reply><account_out><error_num>-204</error_num></account_out></boinc_gui_rpc_reply>",
+            "<boinc_gui_rpc_reply><account_out><authenticator> 0123abcd </authenticator></account_out></boinc_gui_rpc_reply>",
+            "<boinc_gui_rpc_reply><account_out><error_num>-161</error_num><error_msg>No such user</error_msg></account_out></boinc_gui_rpc_reply>",
+            "<boinc_gui_rpc_reply><account_out/></boinc_gui_rpc_reply>",
+        ]));
+
+        assert!(client.poll_lookup_account().await.unwrap().is_in_progress());
+        match client.poll_lookup_account().await.unwrap() {
+            PollResult::Done(authenticator) => assert_eq!(authenticator.expose(), "0123abcd"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match client.poll_lookup_account().await.unwrap() {
+            PollResult::Failed { code, messages } => {
+                assert_eq!(code, BoincErrorCode::NotFound);
+                assert_eq!(messages, ["No such user"]);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(
+            client.poll_lookup_account().await.unwrap_err(),
+            Error::NodeNotFound {
+                request: "lookup_account_poll".into(),
+                node: "account_out/authenticator".into(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn poll_project_config() {
+        let client = Client::new(Canned::new(&[
+            "<boinc_gui_rpc_reply><project_config><error_num>-204</error_num></project_config></boinc_gui_rpc_reply>",
+            "<boinc_gui_rpc_reply><project_config><name>Einstein@Home</name><min_passwd_length>6</min_passwd_length><uses_username/></project_config></boinc_gui_rpc_reply>",
+            "<boinc_gui_rpc_reply><project_attach_reply><error_num>-189</error_num></project_attach_reply></boinc_gui_rpc_reply>",
+        ]));
+
+        assert!(client.poll_project_config().await.unwrap().is_in_progress());
+        match client.poll_project_config().await.unwrap() {
+            PollResult::Done(config) => {
+                assert_eq!(config.name, Some("Einstein@Home".into()));
+                assert_eq!(config.min_passwd_length, Some(6));
+                assert_eq!(config.uses_username, Some(true));
+            }
+            other => panic!("unexpected poll result: {:?}", other),
+        }
+        assert_eq!(
+            client.poll_project_attach().await.unwrap(),
+            PollResult::Failed {
+                code: BoincErrorCode::InvalidUrl,
+                messages: vec![],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_reply_nodes() {
+        let client = Client::new(Canned::new(&[
+            "<boinc_gui_rpc_reply><host_info/><host_info_ext/></boinc_gui_rpc_reply>",
+        ]));
+
+        let res = client.get_host_info().await;
+        if cfg!(feature = "strict-unknown") {
+            assert_eq!(
+                res.unwrap_err(),
+                Error::UnexpectedNode {
+                    request: "get_host_info".into(),
+                    node: "host_info_ext".into(),
+                }
+            );
+        } else {
+            assert!(res.is_ok());
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn middleware() {
+        let client = Client::new(
+            tower::ServiceBuilder::new()
+                .timeout(Duration::from_millis(10))
+                .service(Stalled),
+        );
+        assert_eq!(client.get_messages(0).await.unwrap_err(), Error::Timeout);
+
+        let client = Client::new(
+            tower::ServiceBuilder::new()
+                .timeout(Duration::from_secs(10))
+                .service(Canned::new(&[
+                    "<boinc_gui_rpc_reply><error>unauthorized</error></boinc_gui_rpc_reply>",
+                ])),
+        );
+        assert_eq!(
+            client.get_messages(0).await.unwrap_err(),
+            Error::AuthError(AuthFailure::Rejected("unauthorized".into()))
+        );
+    }
+
+    #[tokio::test]
+    async fn message_count() {
+        let client = Client::new(Canned::new(&[
+            "<boinc_gui_rpc_reply><seqno>42</seqno></boinc_gui_rpc_reply>",
+            "<boinc_gui_rpc_reply><seqno>n/a</seqno></boinc_gui_rpc_reply>",
+        ]));
+        assert_eq!(client.get_message_count().await.unwrap(), 42);
+        assert_eq!(
+            client.get_message_count().await.unwrap_err(),
+            Error::InvalidNodeValue {
+                node: "seqno".into(),
+                value: "n/a".into(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn notices() {
+        let client = Client::new(Canned::new(&["<boinc_gui_rpc_reply>
+<notices>
+<notice>
+   <title>Scheduled maintenance</title>
+   <description><![CDATA[The project will be down <b>today</b>]]></description>
+   <create_time>1700000000.000000</create_time>
+   <arrival_time>1700000100.500000</arrival_time>
+   <is_private>0</is_private>
+   <project_name>Einstein@Home</project_name>
+   <category>server</category>
+   <link><![CDATA[https://einsteinathome.org/news]]></link>
+   <seqno>7</seqno>
+</notice>
+</notices>
+</boinc_gui_rpc_reply>"]));
+
+        let notices = client.get_notices(6).await.unwrap();
+        assert_eq!(notices.len(), 1);
+        let notice = &notices[0];
+        assert_eq!(notice.seqno, Some(7));
+        assert_eq!(notice.title.as_deref(), Some("Scheduled maintenance"));
+        assert_eq!(
+            notice.description.as_deref(),
+            Some("The project will be down <b>today</b>")
+        );
+        assert_eq!(notice.arrival_time, Some(1_700_000_100.5));
+        assert_eq!(notice.is_private, Some(false));
+        assert_eq!(notice.project_name.as_deref(), Some("Einstein@Home"));
+        assert_eq!(notice.category.as_deref(), Some("server"));
+        assert_eq!(
+            notice.link.as_deref(),
+            Some("https://einsteinathome.org/news")
+        );
+
+        let requests = client.transport().requests.lock().unwrap().clone();
+        assert_eq!(requests[0][0].children[0].name, "seqno");
+        assert_eq!(requests[0][0].children[0].text.as_deref(), Some("6"));
+    }
+
+    #[tokio::test]
+    async fn single_item_lookups() {
+        let results = "<boinc_gui_rpc_reply>
+<results>
+<result><name>a_0</name><state>2</state></result>
+<result><name>b_0</name><state>4</state></result>
+</results>
+</boinc_gui_rpc_reply>";
+        let projects = "<boinc_gui_rpc_reply>
+<projects>
+<project><master_url>https://einsteinathome.org/</master_url></project>
+<project><master_url>http://milkyway.cs.rpi.edu/milkyway/</master_url></project>
+</projects>
+</boinc_gui_rpc_reply>";
+        let client = Client::new(Canned::new(&[
+            results, results, projects, projects, projects, projects,
+        ]));
+
+        let task = client.get_task("b_0").await.unwrap().unwrap();
+        assert_eq!(
+            task.result_state(),
+            Some(models::ResultState::FilesUploading)
+        );
+        assert!(client.get_task("c_0").await.unwrap().is_none());
+
+        for (url, found) in &[
+            (
+                "http://EinsteinAtHome.org",
+                Some("https://einsteinathome.org/"),
+            ),
+            (
+                "https://milkyway.cs.rpi.edu/milkyway",
+                Some("http://milkyway.cs.rpi.edu/milkyway/"),
+            ),
+            ("https://milkyway.cs.rpi.edu/", None),
+            ("https://milkyway.cs.rpi.edu/MilkyWay/", None),
+        ] {
+            let project = client.get_attached_project(url).await.unwrap();
+            assert_eq!(
+                project.and_then(|project| project.master_url).as_deref(),
+                *found,
+                "{url}"
+            );
+        }
+
+        // Failures are errors still
+        client
+            .transport()
+            .replies
+            .lock()
+            .unwrap()
+            .push_back(Err(Error::DataParseError("garbage".into())));
+        assert_eq!(
+            client.get_task("a_0").await.unwrap_err(),
+            Error::DataParseError("garbage".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn file_transfers() {
+        let client = Client::new(Canned::new(&["<boinc_gui_rpc_reply>
+<file_transfers>
+<file_transfer>
+    <project_url>https://einsteinathome.org/</project_url>
+    <project_name>Einstein@Home</project_name>
+    <name>h1_0001.00_O3aC01Cl1In0__O3AS1a_1.00Hz_1_0</name>
+    <nbytes>5672.000000</nbytes>
+    <max_nbytes>0.000000</max_nbytes>
+    <status>0</status>
+    <persistent_file_xfer>
+        <num_retries>2</num_retries>
+        <first_request_time>1700000000.000000</first_request_time>
+        <next_request_time>1700000600.000000</next_request_time>
+        <time_so_far>31.500000</time_so_far>
+        <last_bytes_xferred>2048.000000</last_bytes_xferred>
+        <is_upload>1</is_upload>
+    </persistent_file_xfer>
+    <file_xfer>
+        <bytes_xferred>4096.000000</bytes_xferred>
+        <file_offset>2048.000000</file_offset>
+        <xfer_speed>512.000000</xfer_speed>
+        <url>https://einstein-up.aei.uni-hannover.de/cgi-bin/file_upload_handler</url>
+    </file_xfer>
+</file_transfer>
+<file_transfer>
+    <name>h1_0002.00</name>
+    <persistent_file_xfer>
+        <last_bytes_xferred>1024.000000</last_bytes_xferred>
+        <is_upload>0</is_upload>
+    </persistent_file_xfer>
+    <project_backoff>300.000000</project_backoff>
+</file_transfer>
+</file_transfers>
+</boinc_gui_rpc_reply>"]));
+
+        let transfers = client.get_file_transfers().await.unwrap();
+        assert_eq!(transfers.len(), 2);
+        let uploading = &transfers[0];
+        assert_eq!(
+            uploading.name.as_deref(),
+            Some("h1_0001.00_O3aC01Cl1In0__O3AS1a_1.00Hz_1_0")
+        );
+        assert_eq!(uploading.project_name.as_deref(), Some("Einstein@Home"));
+        assert_eq!(uploading.nbytes, Some(5672.0));
+        assert_eq!(uploading.is_upload, Some(true));
+        assert_eq!(uploading.num_retries, Some(2));
+        assert_eq!(uploading.next_request_time, Some(1_700_000_600.0));
+        assert!(uploading.xfer_active);
+        assert_eq!(uploading.xfer_speed, Some(512.0));
+        assert_eq!(uploading.bytes_so_far(), Some(4096.0));
+
+        let waiting = &transfers[1];
+        assert_eq!(waiting.is_upload, Some(false));
+        assert!(!waiting.xfer_active);
+        assert_eq!(waiting.project_backoff, Some(300.0));
+        assert_eq!(waiting.bytes_so_far(), Some(1024.0));
+    }
+
+    #[tokio::test]
+    async fn message_stream() {
+        use futures::StreamExt;
+
+        fn msgs(seqnos: &[i64]) -> String {
+            use std::fmt::Write;
+
+            let mut reply = String::from("<boinc_gui_rpc_reply><msgs>");
+            for n in seqnos {
+                write!(reply, "<msg><seqno>{n}</seqno><body>m{n}</body></msg>").unwrap();
+            }
+            reply + "</msgs></boinc_gui_rpc_reply>"
+        }
+
+        let transport = Canned::new(&[
+            "<boinc_gui_rpc_reply><seqno>2</seqno></boinc_gui_rpc_reply>",
+            &msgs(&[]),
+            &msgs(&[1, 2, 3, 4]),
+        ]);
+        {
+            let mut replies = transport.replies.lock().unwrap();
+            replies.push_back(Err(Error::ConnectionClosed));
+            replies.push_back(Ok(util::parse_node(&msgs(&[6, 5])).unwrap().children));
+            replies.push_back(Err(Error::AuthError(AuthFailure::Unauthorized)));
+        }
+        let client = Client::new(transport);
+
+        let stream = client
+            .stream_messages(Duration::from_millis(1))
+            .await
+            .unwrap();
+        let items: Vec<_> = stream.collect().await;
+        let seqnos: Vec<_> = items
+            .iter()
+            .map(|item| item.as_ref().map(|message| message.msg_number.unwrap()))
+            .collect();
+        assert_eq!(
+            seqnos,
+            [
+                Ok(3),
+                Ok(4),
+                Ok(5),
+                Ok(6),
+                Err(&Error::AuthError(AuthFailure::Unauthorized))
+            ]
+        );
+
+        let polled: Vec<_> = client.transport().requests.lock().unwrap()[1..]
+            .iter()
+            .map(|request| request[0].text.clone().unwrap())
+            .collect();
+        assert_eq!(polled, ["2", "2", "4", "4", "6"]);
+    }
+
+    #[tokio::test]
+    async fn accessors() {
+        let mut client = Client::new(Canned::new(&[
+            "<boinc_gui_rpc_reply><msgs/></boinc_gui_rpc_reply>",
+        ]));
+        client.get_messages(0).await.unwrap();
+        assert_eq!(client.transport().requests.lock().unwrap().len(), 1);
+        client
+            .transport_mut()
+            .replies
+            .lock()
+            .unwrap()
+            .push_back(Err(Error::Timeout));
+        assert_eq!(client.get_messages(0).await.unwrap_err(), Error::Timeout);
+        assert_eq!(client.into_inner().requests.lock().unwrap().len(), 2);
     }
 }
@@ -1,13 +1,59 @@
-use super::util;
+use super::{errors::Error, util};
+use std::{cmp::Ordering, fmt, str::FromStr, time::SystemTime};
 
-#[derive(Clone, Copy, Debug)]
+pub mod describe;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Component {
     CPU,
     GPU,
     Network,
 }
 
-#[derive(Clone, Copy, Debug)]
+impl Component {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::CPU => "cpu",
+            Self::GPU => "gpu",
+            Self::Network => "network",
+        }
+    }
+
+    /// Request tag switching the mode of this component. The CPU one is historically named `run`.
+    pub(crate) const fn set_mode_tag(self) -> &'static str {
+        match self {
+            Self::CPU => "set_run_mode",
+            Self::GPU => "set_gpu_mode",
+            Self::Network => "set_network_mode",
+        }
+    }
+}
+
+impl fmt::Display for Component {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Case-insensitive. `run` is accepted as an alias of `cpu`.
+impl FromStr for Component {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_ascii_lowercase();
+        match &*s {
+            "cpu" | "run" => Ok(Self::CPU),
+            "gpu" => Ok(Self::GPU),
+            "network" => Ok(Self::Network),
+            _ => Err(Error::DataParseError(format!("Unknown component: {s}"))),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum RunMode {
     Always,
     Auto,
@@ -15,14 +61,145 @@ pub enum RunMode {
     Restore,
 }
 
-#[derive(Clone, Copy, Debug)]
+impl RunMode {
+    /// Mode as `get_cc_status` reports it.
+    #[must_use]
+    pub const fn from_code(v: i64) -> Option<Self> {
+        match v {
+            1 => Some(Self::Always),
+            2 => Some(Self::Auto),
+            3 => Some(Self::Never),
+            4 => Some(Self::Restore),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub const fn code(self) -> i64 {
+        match self {
+            Self::Always => 1,
+            Self::Auto => 2,
+            Self::Never => 3,
+            Self::Restore => 4,
+        }
+    }
+
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Always => "always",
+            Self::Auto => "auto",
+            Self::Never => "never",
+            Self::Restore => "restore",
+        }
+    }
+}
+
+impl fmt::Display for RunMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Case-insensitive.
+impl FromStr for RunMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_ascii_lowercase();
+        match &*s {
+            "always" => Ok(Self::Always),
+            "auto" => Ok(Self::Auto),
+            "never" => Ok(Self::Never),
+            "restore" => Ok(Self::Restore),
+            _ => Err(Error::DataParseError(format!("Unknown run mode: {s}"))),
+        }
+    }
+}
+
+/// Operation on an attached project, as in the Manager's projects tab.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ProjectOp {
+    /// Contacts the project's scheduler now.
+    Update,
+    Suspend,
+    Resume,
+    /// Stops fetching new tasks.
+    NoMoreWork,
+    AllowMoreWork,
+    /// Discards all tasks and files of the project.
+    Reset,
+    /// Detaches at once, abandoning the project's tasks.
+    Detach,
+    /// Detaches once the project's tasks are done.
+    DetachWhenDone,
+    DontDetachWhenDone,
+}
+
+impl ProjectOp {
+    /// Request tag of the operation.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Update => "project_update",
+            Self::Suspend => "project_suspend",
+            Self::Resume => "project_resume",
+            Self::NoMoreWork => "project_nomorework",
+            Self::AllowMoreWork => "project_allowmorework",
+            Self::Reset => "project_reset",
+            Self::Detach => "project_detach",
+            Self::DetachWhenDone => "project_detach_when_done",
+            Self::DontDetachWhenDone => "project_dont_detach_when_done",
+        }
+    }
+}
+
+/// Case-insensitive, by the names `boinccmd --project` takes, such as `nomorework`.
+impl FromStr for ProjectOp {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_ascii_lowercase();
+        match &*s {
+            "update" => Ok(Self::Update),
+            "suspend" => Ok(Self::Suspend),
+            "resume" => Ok(Self::Resume),
+            "nomorework" => Ok(Self::NoMoreWork),
+            "allowmorework" => Ok(Self::AllowMoreWork),
+            "reset" => Ok(Self::Reset),
+            "detach" => Ok(Self::Detach),
+            "detach_when_done" => Ok(Self::DetachWhenDone),
+            "dont_detach_when_done" => Ok(Self::DontDetachWhenDone),
+            _ => Err(Error::DataParseError(format!(
+                "Unknown project operation: {s}"
+            ))),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum CpuSched {
     Uninitialized,
     Preempted,
     Scheduled,
 }
 
-#[derive(Clone, Copy, Debug)]
+impl CpuSched {
+    #[must_use]
+    pub const fn from_code(v: i64) -> Option<Self> {
+        match v {
+            0 => Some(Self::Uninitialized),
+            1 => Some(Self::Preempted),
+            2 => Some(Self::Scheduled),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ResultState {
     New,
     FilesDownloading,
@@ -34,7 +211,220 @@ pub enum ResultState {
     UploadFailed,
 }
 
-#[derive(Clone, Copy, Debug)]
+impl ResultState {
+    #[must_use]
+    pub const fn from_code(v: i64) -> Option<Self> {
+        match v {
+            0 => Some(Self::New),
+            1 => Some(Self::FilesDownloading),
+            2 => Some(Self::FilesDownloaded),
+            3 => Some(Self::ComputeError),
+            4 => Some(Self::FilesUploading),
+            5 => Some(Self::FilesUploaded),
+            6 => Some(Self::Aborted),
+            7 => Some(Self::UploadFailed),
+            _ => None,
+        }
+    }
+}
+
+/// Why the client suspended computation or network activity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum SuspendReason {
+    NotSuspended,
+    Batteries,
+    UserActive,
+    UserRequest,
+    TimeOfDay,
+    Benchmarks,
+    DiskSize,
+    CpuThrottle,
+    NoRecentInput,
+    InitialDelay,
+    ExclusiveAppRunning,
+    CpuUsage,
+    NetworkQuotaExceeded,
+    Os,
+    WifiState,
+    BatteryCharging,
+    BatteryOverheated,
+    NoGuiKeepalive,
+    Unknown(i32),
+}
+
+impl SuspendReason {
+    #[must_use]
+    pub const fn code(self) -> i32 {
+        match self {
+            Self::NotSuspended => 0,
+            Self::Batteries => 1,
+            Self::UserActive => 2,
+            Self::UserRequest => 4,
+            Self::TimeOfDay => 8,
+            Self::Benchmarks => 16,
+            Self::DiskSize => 32,
+            Self::CpuThrottle => 64,
+            Self::NoRecentInput => 128,
+            Self::InitialDelay => 256,
+            Self::ExclusiveAppRunning => 512,
+            Self::CpuUsage => 1024,
+            Self::NetworkQuotaExceeded => 2048,
+            Self::Os => 4096,
+            Self::WifiState => 4097,
+            Self::BatteryCharging => 4098,
+            Self::BatteryOverheated => 4099,
+            Self::NoGuiKeepalive => 4100,
+            Self::Unknown(v) => v,
+        }
+    }
+}
+
+impl From<i32> for SuspendReason {
+    fn from(v: i32) -> Self {
+        match v {
+            0 => Self::NotSuspended,
+            1 => Self::Batteries,
+            2 => Self::UserActive,
+            4 => Self::UserRequest,
+            8 => Self::TimeOfDay,
+            16 => Self::Benchmarks,
+            32 => Self::DiskSize,
+            64 => Self::CpuThrottle,
+            128 => Self::NoRecentInput,
+            256 => Self::InitialDelay,
+            512 => Self::ExclusiveAppRunning,
+            1024 => Self::CpuUsage,
+            2048 => Self::NetworkQuotaExceeded,
+            4096 => Self::Os,
+            4097 => Self::WifiState,
+            4098 => Self::BatteryCharging,
+            4099 => Self::BatteryOverheated,
+            4100 => Self::NoGuiKeepalive,
+            other => Self::Unknown(other),
+        }
+    }
+}
+
+/// Run modes of the client and why it suspended activity, if it did, as `get_cc_status`
+/// returns them.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CcStatus {
+    pub network_status: Option<i64>,
+    pub ams_password_error: Option<bool>,
+    pub task_suspend_reason: Option<SuspendReason>,
+    pub task_mode: Option<RunMode>,
+    pub task_mode_perm: Option<RunMode>,
+    /// Seconds until the temporary task mode ends.
+    pub task_mode_delay: Option<f64>,
+    pub gpu_suspend_reason: Option<SuspendReason>,
+    pub gpu_mode: Option<RunMode>,
+    pub gpu_mode_perm: Option<RunMode>,
+    pub gpu_mode_delay: Option<f64>,
+    pub network_suspend_reason: Option<SuspendReason>,
+    pub network_mode: Option<RunMode>,
+    pub network_mode_perm: Option<RunMode>,
+    pub network_mode_delay: Option<f64>,
+    pub disallow_attach: Option<bool>,
+    pub simple_gui_only: Option<bool>,
+    pub max_event_log_lines: Option<i64>,
+}
+
+impl From<&treexml::Element> for CcStatus {
+    fn from(node: &treexml::Element) -> Self {
+        let reason = |n| util::eval_node_contents::<i32>(n).map(SuspendReason::from);
+        let mode = |n| util::eval_node_contents(n).and_then(RunMode::from_code);
+        let mut e = Self::default();
+        for n in &node.children {
+            match &*n.name {
+                "network_status" => e.network_status = util::eval_node_contents(n),
+                "ams_password_error" => e.ams_password_error = util::eval_bool(n),
+                "task_suspend_reason" => e.task_suspend_reason = reason(n),
+                "task_mode" => e.task_mode = mode(n),
+                "task_mode_perm" => e.task_mode_perm = mode(n),
+                "task_mode_delay" => e.task_mode_delay = util::eval_node_contents(n),
+                "gpu_suspend_reason" => e.gpu_suspend_reason = reason(n),
+                "gpu_mode" => e.gpu_mode = mode(n),
+                "gpu_mode_perm" => e.gpu_mode_perm = mode(n),
+                "gpu_mode_delay" => e.gpu_mode_delay = util::eval_node_contents(n),
+                "network_suspend_reason" => e.network_suspend_reason = reason(n),
+                "network_mode" => e.network_mode = mode(n),
+                "network_mode_perm" => e.network_mode_perm = mode(n),
+                "network_mode_delay" => e.network_mode_delay = util::eval_node_contents(n),
+                "disallow_attach" => e.disallow_attach = util::eval_bool(n),
+                "simple_gui_only" => e.simple_gui_only = util::eval_bool(n),
+                "max_event_log_lines" => e.max_event_log_lines = util::eval_node_contents(n),
+                _ => util::unknown_tag("CcStatus", n),
+            }
+        }
+        e
+    }
+}
+
+impl From<&CcStatus> for treexml::Element {
+    fn from(v: &CcStatus) -> Self {
+        let mut node = Self::new("cc_status");
+        util::push_node(&mut node, "network_status", v.network_status);
+        util::push_node(
+            &mut node,
+            "ams_password_error",
+            v.ams_password_error.map(u8::from),
+        );
+        util::push_node(
+            &mut node,
+            "task_suspend_reason",
+            v.task_suspend_reason.map(SuspendReason::code),
+        );
+        util::push_node(&mut node, "task_mode", v.task_mode.map(RunMode::code));
+        util::push_node(
+            &mut node,
+            "task_mode_perm",
+            v.task_mode_perm.map(RunMode::code),
+        );
+        util::push_node(&mut node, "task_mode_delay", v.task_mode_delay);
+        util::push_node(
+            &mut node,
+            "gpu_suspend_reason",
+            v.gpu_suspend_reason.map(SuspendReason::code),
+        );
+        util::push_node(&mut node, "gpu_mode", v.gpu_mode.map(RunMode::code));
+        util::push_node(
+            &mut node,
+            "gpu_mode_perm",
+            v.gpu_mode_perm.map(RunMode::code),
+        );
+        util::push_node(&mut node, "gpu_mode_delay", v.gpu_mode_delay);
+        util::push_node(
+            &mut node,
+            "network_suspend_reason",
+            v.network_suspend_reason.map(SuspendReason::code),
+        );
+        util::push_node(&mut node, "network_mode", v.network_mode.map(RunMode::code));
+        util::push_node(
+            &mut node,
+            "network_mode_perm",
+            v.network_mode_perm.map(RunMode::code),
+        );
+        util::push_node(&mut node, "network_mode_delay", v.network_mode_delay);
+        util::push_node(
+            &mut node,
+            "disallow_attach",
+            v.disallow_attach.map(u8::from),
+        );
+        util::push_node(
+            &mut node,
+            "simple_gui_only",
+            v.simple_gui_only.map(u8::from),
+        );
+        util::push_node(&mut node, "max_event_log_lines", v.max_event_log_lines);
+        node
+    }
+}
+
+/// State of the process of an active task.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Process {
     Uninitialized = 0,
     Executing = 1,
@@ -44,14 +434,96 @@ pub enum Process {
     CopyPending = 10,
 }
 
+impl Process {
+    #[must_use]
+    pub const fn from_code(v: i64) -> Option<Self> {
+        match v {
+            0 => Some(Self::Uninitialized),
+            1 => Some(Self::Executing),
+            9 => Some(Self::Suspended),
+            5 => Some(Self::AbortPending),
+            8 => Some(Self::QuitPending),
+            10 => Some(Self::CopyPending),
+            _ => None,
+        }
+    }
+}
+
+/// Version triple. Missing parts compare as zero.
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct VersionInfo {
     pub major: Option<i64>,
     pub minor: Option<i64>,
     pub release: Option<i64>,
 }
 
-#[derive(Clone, Debug, Default)]
+impl VersionInfo {
+    #[must_use]
+    pub const fn new(major: i64, minor: i64, release: i64) -> Self {
+        Self {
+            major: Some(major),
+            minor: Some(minor),
+            release: Some(release),
+        }
+    }
+
+    /// GUI RPC version spoken by this crate.
+    #[must_use]
+    pub const fn current() -> Self {
+        Self::new(7, 16, 0)
+    }
+
+    #[must_use]
+    pub fn at_least(&self, major: i64, minor: i64, release: i64) -> bool {
+        *self >= Self::new(major, minor, release)
+    }
+
+    fn key(&self) -> (i64, i64, i64) {
+        (
+            self.major.unwrap_or(0),
+            self.minor.unwrap_or(0),
+            self.release.unwrap_or(0),
+        )
+    }
+}
+
+impl PartialEq for VersionInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for VersionInfo {}
+
+impl PartialOrd for VersionInfo {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for VersionInfo {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl fmt::Display for VersionInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts = [self.major, self.minor, self.release];
+        let len = parts.iter().rposition(Option::is_some).map_or(0, |i| i + 1);
+        for (i, part) in parts[..len].iter().enumerate() {
+            if i > 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", part.unwrap_or(0))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct HostInfo {
     pub tz_shift: Option<i64>,
     pub domain_name: Option<String>,
@@ -85,7 +557,37 @@ pub struct HostInfo {
     pub virtualbox_version: Option<String>,
 }
 
+/// Platform supported by a project.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PlatformInfo {
+    pub name: Option<String>,
+    pub user_friendly_name: Option<String>,
+}
+
+impl From<&treexml::Element> for PlatformInfo {
+    fn from(node: &treexml::Element) -> Self {
+        let mut e = Self::default();
+        for n in &node.children {
+            match &*n.name {
+                "name" | "platform_name" => {
+                    e.name = util::trimmed_optional(&n.text);
+                }
+                "user_friendly_name" => {
+                    e.user_friendly_name = util::trimmed_optional(&n.text);
+                }
+                _ => util::unknown_tag("PlatformInfo", n),
+            }
+        }
+        if e.name.is_none() {
+            e.name = util::trimmed_optional(&node.text);
+        }
+        e
+    }
+}
+
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ProjectInfo {
     pub name: Option<String>,
     pub summary: Option<String>,
@@ -94,20 +596,134 @@ pub struct ProjectInfo {
     pub specific_area: Option<String>,
     pub description: Option<String>,
     pub home: Option<String>,
-    pub platforms: Option<Vec<String>>,
+    pub platforms: Option<Vec<PlatformInfo>>,
     pub image: Option<String>,
 }
 
+/// Project the client is attached to, as in the Manager's projects tab.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Project {
+    pub master_url: Option<String>,
+    pub project_name: Option<String>,
+    pub user_name: Option<String>,
+    pub team_name: Option<String>,
+    pub host_venue: Option<String>,
+    pub userid: Option<i64>,
+    pub teamid: Option<i64>,
+    pub hostid: Option<i64>,
+    pub user_total_credit: Option<f64>,
+    pub user_expavg_credit: Option<f64>,
+    pub host_total_credit: Option<f64>,
+    pub host_expavg_credit: Option<f64>,
+    pub resource_share: Option<f64>,
+    pub nrpc_failures: Option<i64>,
+    pub min_rpc_time: Option<f64>,
+    pub last_rpc_time: Option<f64>,
+    pub sched_rpc_pending: Option<i64>,
+    pub suspended_via_gui: Option<bool>,
+    pub dont_request_more_work: Option<bool>,
+    pub attached_via_acct_mgr: Option<bool>,
+    pub detach_when_done: Option<bool>,
+    pub ended: Option<bool>,
+}
+
+impl From<&treexml::Element> for Project {
+    fn from(node: &treexml::Element) -> Self {
+        let mut e = Self::default();
+        for n in &node.children {
+            match &*n.name {
+                "master_url" => e.master_url = util::trimmed_optional(&n.text),
+                "project_name" => e.project_name = util::trimmed_optional(&n.text),
+                "user_name" => e.user_name = util::trimmed_optional(&n.text),
+                "team_name" => e.team_name = util::trimmed_optional(&n.text),
+                "host_venue" => e.host_venue = util::trimmed_optional(&n.text),
+                "userid" => e.userid = util::eval_node_contents(n),
+                "teamid" => e.teamid = util::eval_node_contents(n),
+                "hostid" => e.hostid = util::eval_node_contents(n),
+                "user_total_credit" => e.user_total_credit = util::eval_node_contents(n),
+                "user_expavg_credit" => e.user_expavg_credit = util::eval_node_contents(n),
+                "host_total_credit" => e.host_total_credit = util::eval_node_contents(n),
+                "host_expavg_credit" => e.host_expavg_credit = util::eval_node_contents(n),
+                "resource_share" => e.resource_share = util::eval_node_contents(n),
+                "nrpc_failures" => e.nrpc_failures = util::eval_node_contents(n),
+                "min_rpc_time" => e.min_rpc_time = util::eval_node_contents(n),
+                "last_rpc_time" => e.last_rpc_time = util::eval_node_contents(n),
+                "sched_rpc_pending" => e.sched_rpc_pending = util::eval_node_contents(n),
+                "suspended_via_gui" => e.suspended_via_gui = util::eval_bool(n),
+                "dont_request_more_work" => e.dont_request_more_work = util::eval_bool(n),
+                "attached_via_acct_mgr" => e.attached_via_acct_mgr = util::eval_bool(n),
+                "detach_when_done" => e.detach_when_done = util::eval_bool(n),
+                "ended" => e.ended = util::eval_bool(n),
+                _ => util::unknown_tag("Project", n),
+            }
+        }
+        e
+    }
+}
+
+impl From<&Project> for treexml::Element {
+    fn from(v: &Project) -> Self {
+        let mut node = Self::new("project");
+        util::push_node(&mut node, "master_url", v.master_url.as_ref());
+        util::push_node(&mut node, "project_name", v.project_name.as_ref());
+        util::push_node(&mut node, "user_name", v.user_name.as_ref());
+        util::push_node(&mut node, "team_name", v.team_name.as_ref());
+        util::push_node(&mut node, "host_venue", v.host_venue.as_ref());
+        util::push_node(&mut node, "userid", v.userid);
+        util::push_node(&mut node, "teamid", v.teamid);
+        util::push_node(&mut node, "hostid", v.hostid);
+        util::push_node(&mut node, "user_total_credit", v.user_total_credit);
+        util::push_node(&mut node, "user_expavg_credit", v.user_expavg_credit);
+        util::push_node(&mut node, "host_total_credit", v.host_total_credit);
+        util::push_node(&mut node, "host_expavg_credit", v.host_expavg_credit);
+        util::push_node(&mut node, "resource_share", v.resource_share);
+        util::push_node(&mut node, "nrpc_failures", v.nrpc_failures);
+        util::push_node(&mut node, "min_rpc_time", v.min_rpc_time);
+        util::push_node(&mut node, "last_rpc_time", v.last_rpc_time);
+        util::push_node(&mut node, "sched_rpc_pending", v.sched_rpc_pending);
+        util::push_node(
+            &mut node,
+            "suspended_via_gui",
+            v.suspended_via_gui.map(u8::from),
+        );
+        util::push_node(
+            &mut node,
+            "dont_request_more_work",
+            v.dont_request_more_work.map(u8::from),
+        );
+        util::push_node(
+            &mut node,
+            "attached_via_acct_mgr",
+            v.attached_via_acct_mgr.map(u8::from),
+        );
+        util::push_node(
+            &mut node,
+            "detach_when_done",
+            v.detach_when_done.map(u8::from),
+        );
+        util::push_node(&mut node, "ended", v.ended.map(u8::from));
+        node
+    }
+}
+
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AccountManagerInfo {
     pub url: Option<String>,
     pub name: Option<String>,
     pub have_credentials: Option<bool>,
     pub cookie_required: Option<bool>,
     pub cookie_failure_url: Option<String>,
+    pub user_name: Option<String>,
+    pub team_name: Option<String>,
+    pub previous_host_cpid: Option<String>,
+    pub host_velocity: Option<f64>,
+    pub dynamic: Option<bool>,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Message {
     pub project_name: Option<String>,
     pub priority: Option<i64>,
@@ -116,7 +732,23 @@ pub struct Message {
     pub timestamp: Option<i64>,
 }
 
-#[derive(Clone, Debug, Default)]
+/// Notice from a project or the client itself, as shown in the Manager's notices tab.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Notice {
+    pub seqno: Option<i64>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub create_time: Option<f64>,
+    pub arrival_time: Option<f64>,
+    pub is_private: Option<bool>,
+    pub project_name: Option<String>,
+    pub category: Option<String>,
+    pub link: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TaskResult {
     pub name: Option<String>,
     pub wu_name: Option<String>,
@@ -132,10 +764,127 @@ pub struct TaskResult {
     pub received_time: Option<f64>,
     pub estimated_cpu_time_remaining: Option<f64>,
     pub completed_time: Option<f64>,
+    pub suspended_via_gui: Option<bool>,
+    pub project_suspended_via_gui: Option<bool>,
+    pub ready_to_report: Option<bool>,
+    pub got_server_ack: Option<bool>,
     pub active_task: Option<ActiveTask>,
 }
 
+impl TaskResult {
+    #[must_use]
+    pub fn result_state(&self) -> Option<ResultState> {
+        self.state.and_then(ResultState::from_code)
+    }
+
+    #[must_use]
+    pub fn report_deadline_at(&self) -> Option<SystemTime> {
+        self.report_deadline.and_then(util::system_time)
+    }
+
+    #[must_use]
+    pub fn received_at(&self) -> Option<SystemTime> {
+        self.received_time.and_then(util::system_time)
+    }
+
+    /// When the task was done computing, `None` until then.
+    #[must_use]
+    pub fn completed_at(&self) -> Option<SystemTime> {
+        self.completed_time.and_then(util::system_time)
+    }
+}
+
+/// Criteria that tasks must all meet, for `Client::get_tasks`, or for testing tasks one by one
+/// with `matches`, such as those that `Client::watch_tasks` reports.
 #[derive(Clone, Debug, Default)]
+pub struct TaskFilter {
+    project_url: Option<String>,
+    state: Option<ResultState>,
+    deadline_before: Option<f64>,
+    active_only: bool,
+    name_pattern: Option<String>,
+}
+
+impl TaskFilter {
+    /// Matches every task.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tasks of the project at `url`, compared as master URLs are, whatever the scheme, trailing
+    /// slash and case of the host name.
+    #[must_use]
+    pub fn project(mut self, url: &str) -> Self {
+        self.project_url = Some(url.into());
+        self
+    }
+
+    /// Tasks in `state`.
+    #[must_use]
+    pub const fn state(mut self, state: ResultState) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    /// Tasks due before `timestamp`, in seconds since the Unix epoch as `report_deadline` is.
+    /// Tasks without a deadline do not match.
+    #[must_use]
+    pub const fn deadline_before(mut self, timestamp: f64) -> Self {
+        self.deadline_before = Some(timestamp);
+        self
+    }
+
+    /// Tasks started and not yet done, whether running or suspended, as `Client::get_results`
+    /// lists with `active_only`.
+    #[must_use]
+    pub const fn active_only(mut self) -> Self {
+        self.active_only = true;
+        self
+    }
+
+    /// Tasks named after `pattern`, in which `*` stands for any run of characters, such as
+    /// `"h1_*"` for the tasks named starting with `h1_`.
+    #[must_use]
+    pub fn name_matches(mut self, pattern: &str) -> Self {
+        self.name_pattern = Some(pattern.into());
+        self
+    }
+
+    /// Whether the filter only matches active tasks, which the daemon can be asked for alone.
+    pub(crate) const fn is_active_only(&self) -> bool {
+        self.active_only
+    }
+
+    #[must_use]
+    pub fn matches(&self, task: &TaskResult) -> bool {
+        if let Some(url) = &self.project_url {
+            if !matches!(&task.project_url, Some(project_url) if util::same_master_url(project_url, url))
+            {
+                return false;
+            }
+        }
+        if let Some(pattern) = &self.name_pattern {
+            if !matches!(&task.name, Some(name) if util::glob_match(pattern, name)) {
+                return false;
+            }
+        }
+        if let Some(state) = self.state {
+            if task.result_state() != Some(state) {
+                return false;
+            }
+        }
+        if let Some(timestamp) = self.deadline_before {
+            if !matches!(task.report_deadline, Some(deadline) if deadline < timestamp) {
+                return false;
+            }
+        }
+        !self.active_only || task.active_task.is_some()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ActiveTask {
     pub active_task_state: Option<String>,
     pub app_version_num: Option<String>,
@@ -153,9 +902,29 @@ pub struct ActiveTask {
     pub bytes_sent: Option<f64>,
     pub bytes_received: Option<f64>,
     pub progress_rate: Option<f64>,
+    pub too_large: Option<bool>,
+    pub needs_shmem: Option<bool>,
+}
+
+impl ActiveTask {
+    #[must_use]
+    pub fn cpu_sched(&self) -> Option<CpuSched> {
+        self.scheduler_state
+            .as_ref()
+            .and_then(|v| v.parse().ok())
+            .and_then(CpuSched::from_code)
+    }
+
+    #[must_use]
+    pub fn process_state(&self) -> Option<Process> {
+        self.active_task_state
+            .as_ref()
+            .and_then(|v| v.parse().ok())
+            .and_then(Process::from_code)
+    }
 }
 
-impl<'a> From<&'a treexml::Element> for ActiveTask {
+impl From<&treexml::Element> for ActiveTask {
     fn from(node: &treexml::Element) -> Self {
         let mut e = Self::default();
         for n in &node.children {
@@ -208,9 +977,1866 @@ impl<'a> From<&'a treexml::Element> for ActiveTask {
                 "progress_rate" => {
                     e.progress_rate = util::eval_node_contents(n);
                 }
-                _ => {}
+                "too_large" => {
+                    e.too_large = util::eval_bool(n);
+                }
+                "needs_shmem" => {
+                    e.needs_shmem = util::eval_bool(n);
+                }
+                _ => util::unknown_tag("ActiveTask", n),
+            }
+        }
+        e
+    }
+}
+
+impl From<&ActiveTask> for treexml::Element {
+    fn from(v: &ActiveTask) -> Self {
+        let mut node = Self::new("active_task");
+        util::push_node(&mut node, "active_task_state", v.active_task_state.as_ref());
+        util::push_node(&mut node, "app_version_num", v.app_version_num.as_ref());
+        util::push_node(&mut node, "slot", v.slot);
+        util::push_node(&mut node, "pid", v.pid);
+        util::push_node(&mut node, "scheduler_state", v.scheduler_state.as_ref());
+        util::push_node(&mut node, "checkpoint_cpu_time", v.checkpoint_cpu_time);
+        util::push_node(&mut node, "fraction_done", v.fraction_done);
+        util::push_node(&mut node, "current_cpu_time", v.current_cpu_time);
+        util::push_node(&mut node, "elapsed_time", v.elapsed_time);
+        util::push_node(&mut node, "swap_size", v.swap_size);
+        util::push_node(&mut node, "working_set_size", v.working_set_size);
+        util::push_node(
+            &mut node,
+            "working_set_size_smoothed",
+            v.working_set_size_smoothed,
+        );
+        util::push_node(&mut node, "page_fault_rate", v.page_fault_rate);
+        util::push_node(&mut node, "bytes_sent", v.bytes_sent);
+        util::push_node(&mut node, "bytes_received", v.bytes_received);
+        util::push_node(&mut node, "progress_rate", v.progress_rate);
+        util::push_node(&mut node, "too_large", v.too_large.map(u8::from));
+        util::push_node(&mut node, "needs_shmem", v.needs_shmem.map(u8::from));
+        node
+    }
+}
+
+/// File waiting to be uploaded or downloaded, or being so.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FileTransfer {
+    pub name: Option<String>,
+    pub project_url: Option<String>,
+    pub project_name: Option<String>,
+    pub nbytes: Option<f64>,
+    pub max_nbytes: Option<f64>,
+    pub status: Option<i64>,
+    pub is_upload: Option<bool>,
+    pub num_retries: Option<i64>,
+    pub first_request_time: Option<f64>,
+    /// When the next attempt is due, if the transfer is backed off after failing.
+    pub next_request_time: Option<f64>,
+    pub time_so_far: Option<f64>,
+    pub last_bytes_xferred: Option<f64>,
+    /// Whether an attempt is under way, which the fields below describe.
+    pub xfer_active: bool,
+    pub bytes_xferred: Option<f64>,
+    pub file_offset: Option<f64>,
+    pub xfer_speed: Option<f64>,
+    pub url: Option<String>,
+    pub project_backoff: Option<f64>,
+}
+
+impl FileTransfer {
+    /// Bytes transferred so far, by the current attempt if there is one.
+    #[must_use]
+    pub const fn bytes_so_far(&self) -> Option<f64> {
+        if self.xfer_active {
+            self.bytes_xferred
+        } else {
+            self.last_bytes_xferred
+        }
+    }
+
+    fn parse_field(&mut self, n: &treexml::Element) {
+        match &*n.name {
+            "name" => self.name = util::trimmed_optional(&n.text),
+            "project_url" => self.project_url = util::trimmed_optional(&n.text),
+            "project_name" => self.project_name = util::trimmed_optional(&n.text),
+            "nbytes" => self.nbytes = util::eval_node_contents(n),
+            "max_nbytes" => self.max_nbytes = util::eval_node_contents(n),
+            "status" => self.status = util::eval_node_contents(n),
+            "is_upload" => self.is_upload = util::eval_bool(n),
+            "num_retries" => self.num_retries = util::eval_node_contents(n),
+            "first_request_time" => self.first_request_time = util::eval_node_contents(n),
+            "next_request_time" => self.next_request_time = util::eval_node_contents(n),
+            "time_so_far" => self.time_so_far = util::eval_node_contents(n),
+            "last_bytes_xferred" => self.last_bytes_xferred = util::eval_node_contents(n),
+            "bytes_xferred" => self.bytes_xferred = util::eval_node_contents(n),
+            "file_offset" => self.file_offset = util::eval_node_contents(n),
+            "xfer_speed" => self.xfer_speed = util::eval_node_contents(n),
+            "url" => self.url = util::trimmed_optional(&n.text),
+            "project_backoff" => self.project_backoff = util::eval_node_contents(n),
+            _ => util::unknown_tag("FileTransfer", n),
+        }
+    }
+}
+
+impl From<&treexml::Element> for FileTransfer {
+    fn from(node: &treexml::Element) -> Self {
+        let mut e = Self::default();
+        for n in &node.children {
+            // The daemon nests the state kept across attempts, and that of the current attempt
+            match &*n.name {
+                "persistent_file_xfer" => n.children.iter().for_each(|n| e.parse_field(n)),
+                "file_xfer" => {
+                    e.xfer_active = true;
+                    n.children.iter().for_each(|n| e.parse_field(n));
+                }
+                _ => e.parse_field(n),
+            }
+        }
+        e
+    }
+}
+
+/// Nests the fields as the daemon does. Those of the current attempt are only written if
+/// `xfer_active` is set.
+impl From<&FileTransfer> for treexml::Element {
+    fn from(v: &FileTransfer) -> Self {
+        let mut node = Self::new("file_transfer");
+        util::push_node(&mut node, "project_url", v.project_url.as_ref());
+        util::push_node(&mut node, "project_name", v.project_name.as_ref());
+        util::push_node(&mut node, "name", v.name.as_ref());
+        util::push_node(&mut node, "nbytes", v.nbytes);
+        util::push_node(&mut node, "max_nbytes", v.max_nbytes);
+        util::push_node(&mut node, "status", v.status);
+
+        let mut persistent = Self::new("persistent_file_xfer");
+        util::push_node(&mut persistent, "is_upload", v.is_upload.map(u8::from));
+        util::push_node(&mut persistent, "num_retries", v.num_retries);
+        util::push_node(&mut persistent, "first_request_time", v.first_request_time);
+        util::push_node(&mut persistent, "next_request_time", v.next_request_time);
+        util::push_node(&mut persistent, "time_so_far", v.time_so_far);
+        util::push_node(&mut persistent, "last_bytes_xferred", v.last_bytes_xferred);
+        node.children.push(persistent);
+
+        if v.xfer_active {
+            let mut xfer = Self::new("file_xfer");
+            util::push_node(&mut xfer, "bytes_xferred", v.bytes_xferred);
+            util::push_node(&mut xfer, "file_offset", v.file_offset);
+            util::push_node(&mut xfer, "xfer_speed", v.xfer_speed);
+            util::push_node(&mut xfer, "url", v.url.as_ref());
+            node.children.push(xfer);
+        }
+        util::push_node(&mut node, "project_backoff", v.project_backoff);
+        node
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct App {
+    pub name: Option<String>,
+    pub user_friendly_name: Option<String>,
+    pub non_cpu_intensive: Option<bool>,
+}
+
+impl From<&treexml::Element> for App {
+    fn from(node: &treexml::Element) -> Self {
+        let mut e = Self::default();
+        for n in &node.children {
+            match &*n.name {
+                "name" => {
+                    e.name = util::trimmed_optional(&n.text);
+                }
+                "user_friendly_name" => {
+                    e.user_friendly_name = util::trimmed_optional(&n.text);
+                }
+                "non_cpu_intensive" => {
+                    e.non_cpu_intensive = util::eval_bool(n);
+                }
+                _ => util::unknown_tag("App", n),
+            }
+        }
+        e
+    }
+}
+
+impl From<&App> for treexml::Element {
+    fn from(v: &App) -> Self {
+        let mut node = Self::new("app");
+        util::push_node(&mut node, "name", v.name.as_ref());
+        util::push_node(
+            &mut node,
+            "user_friendly_name",
+            v.user_friendly_name.as_ref(),
+        );
+        util::push_node(
+            &mut node,
+            "non_cpu_intensive",
+            v.non_cpu_intensive.map(u8::from),
+        );
+        node
+    }
+}
+
+/// Coprocessor (GPU) usage of an app version.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CoprocUsage {
+    pub coproc_type: Option<String>,
+    pub count: Option<f64>,
+}
+
+impl From<&treexml::Element> for CoprocUsage {
+    fn from(node: &treexml::Element) -> Self {
+        let mut e = Self::default();
+        for n in &node.children {
+            match &*n.name {
+                "type" => {
+                    e.coproc_type = util::trimmed_optional(&n.text);
+                }
+                "count" => {
+                    e.count = util::eval_node_contents(n);
+                }
+                _ => util::unknown_tag("CoprocUsage", n),
             }
         }
         e
     }
 }
+
+impl From<&CoprocUsage> for treexml::Element {
+    fn from(v: &CoprocUsage) -> Self {
+        let mut node = Self::new("coproc");
+        util::push_node(&mut node, "type", v.coproc_type.as_ref());
+        util::push_node(&mut node, "count", v.count);
+        node
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AppVersion {
+    pub app_name: Option<String>,
+    pub version_num: Option<i64>,
+    pub platform: Option<String>,
+    pub plan_class: Option<String>,
+    pub avg_ncpus: Option<f64>,
+    pub flops: Option<f64>,
+    /// One for every `<coproc>` of the app version, in the order the daemon listed them.
+    pub coprocs: Vec<CoprocUsage>,
+}
+
+impl From<&treexml::Element> for AppVersion {
+    fn from(node: &treexml::Element) -> Self {
+        let mut e = Self::default();
+        for n in &node.children {
+            match &*n.name {
+                "app_name" => {
+                    e.app_name = util::trimmed_optional(&n.text);
+                }
+                "version_num" => {
+                    e.version_num = util::eval_node_contents(n);
+                }
+                "platform" => {
+                    e.platform = util::trimmed_optional(&n.text);
+                }
+                "plan_class" => {
+                    e.plan_class = util::trimmed_optional(&n.text);
+                }
+                "avg_ncpus" => {
+                    e.avg_ncpus = util::eval_node_contents(n);
+                }
+                "flops" => {
+                    e.flops = util::eval_node_contents(n);
+                }
+                "coproc" => {
+                    e.coprocs.push(CoprocUsage::from(n));
+                }
+                _ => util::unknown_tag("AppVersion", n),
+            }
+        }
+        e
+    }
+}
+
+impl From<&AppVersion> for treexml::Element {
+    fn from(v: &AppVersion) -> Self {
+        let mut node = Self::new("app_version");
+        util::push_node(&mut node, "app_name", v.app_name.as_ref());
+        util::push_node(&mut node, "version_num", v.version_num);
+        util::push_node(&mut node, "platform", v.platform.as_ref());
+        util::push_node(&mut node, "plan_class", v.plan_class.as_ref());
+        util::push_node(&mut node, "avg_ncpus", v.avg_ncpus);
+        util::push_node(&mut node, "flops", v.flops);
+        node.children.extend(v.coprocs.iter().map(Self::from));
+        node
+    }
+}
+
+/// Network transfer rate statistics.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct NetStats {
+    pub bwup: Option<f64>,
+    pub bwdown: Option<f64>,
+    pub avg_up: Option<f64>,
+    pub avg_down: Option<f64>,
+    pub avg_time_up: Option<f64>,
+    pub avg_time_down: Option<f64>,
+}
+
+impl From<&treexml::Element> for NetStats {
+    fn from(node: &treexml::Element) -> Self {
+        let mut e = Self::default();
+        for n in &node.children {
+            match &*n.name {
+                "bwup" => e.bwup = util::eval_node_contents(n),
+                "bwdown" => e.bwdown = util::eval_node_contents(n),
+                "avg_up" => e.avg_up = util::eval_node_contents(n),
+                "avg_down" => e.avg_down = util::eval_node_contents(n),
+                "avg_time_up" => e.avg_time_up = util::eval_node_contents(n),
+                "avg_time_down" => e.avg_time_down = util::eval_node_contents(n),
+                _ => util::unknown_tag("NetStats", n),
+            }
+        }
+        e
+    }
+}
+
+impl From<&NetStats> for treexml::Element {
+    fn from(v: &NetStats) -> Self {
+        let mut node = Self::new("net_stats");
+        util::push_node(&mut node, "bwup", v.bwup);
+        util::push_node(&mut node, "bwdown", v.bwdown);
+        util::push_node(&mut node, "avg_up", v.avg_up);
+        util::push_node(&mut node, "avg_down", v.avg_down);
+        util::push_node(&mut node, "avg_time_up", v.avg_time_up);
+        util::push_node(&mut node, "avg_time_down", v.avg_time_down);
+        node
+    }
+}
+
+/// Credit of the user and the host with a project as of some day.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DailyStatistics {
+    pub day: Option<f64>,
+    pub user_total_credit: Option<f64>,
+    pub user_expavg_credit: Option<f64>,
+    pub host_total_credit: Option<f64>,
+    pub host_expavg_credit: Option<f64>,
+}
+
+impl From<&treexml::Element> for DailyStatistics {
+    fn from(node: &treexml::Element) -> Self {
+        let mut e = Self::default();
+        for n in &node.children {
+            match &*n.name {
+                "day" => e.day = util::eval_node_contents(n),
+                "user_total_credit" => e.user_total_credit = util::eval_node_contents(n),
+                "user_expavg_credit" => e.user_expavg_credit = util::eval_node_contents(n),
+                "host_total_credit" => e.host_total_credit = util::eval_node_contents(n),
+                "host_expavg_credit" => e.host_expavg_credit = util::eval_node_contents(n),
+                _ => util::unknown_tag("DailyStatistics", n),
+            }
+        }
+        e
+    }
+}
+
+impl From<&DailyStatistics> for treexml::Element {
+    fn from(v: &DailyStatistics) -> Self {
+        let mut node = Self::new("daily_statistics");
+        util::push_node(&mut node, "day", v.day);
+        util::push_node(&mut node, "user_total_credit", v.user_total_credit);
+        util::push_node(&mut node, "user_expavg_credit", v.user_expavg_credit);
+        util::push_node(&mut node, "host_total_credit", v.host_total_credit);
+        util::push_node(&mut node, "host_expavg_credit", v.host_expavg_credit);
+        node
+    }
+}
+
+/// Credit history with a project, as the Manager's statistics tab graphs it.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ProjectStatistics {
+    pub master_url: Option<String>,
+    /// Oldest first. Today's entry is updated as credit comes in.
+    pub daily_statistics: Vec<DailyStatistics>,
+}
+
+impl From<&treexml::Element> for ProjectStatistics {
+    fn from(node: &treexml::Element) -> Self {
+        let mut e = Self::default();
+        for n in &node.children {
+            match &*n.name {
+                "master_url" => e.master_url = util::trimmed_optional(&n.text),
+                "daily_statistics" => e.daily_statistics.push(DailyStatistics::from(n)),
+                _ => util::unknown_tag("ProjectStatistics", n),
+            }
+        }
+        e
+    }
+}
+
+impl From<&ProjectStatistics> for treexml::Element {
+    fn from(v: &ProjectStatistics) -> Self {
+        let mut node = Self::new("project_statistics");
+        util::push_node(&mut node, "master_url", v.master_url.as_ref());
+        for day in &v.daily_statistics {
+            node.children.push(day.into());
+        }
+        node
+    }
+}
+
+/// Snapshot of the whole client as returned by `get_state`.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ClientState {
+    pub platform_name: Option<String>,
+    pub core_client_version: VersionInfo,
+    pub executing_as_daemon: Option<bool>,
+    pub host_info: Option<HostInfo>,
+    pub net_stats: Option<NetStats>,
+    pub apps: Vec<App>,
+    pub app_versions: Vec<AppVersion>,
+    pub results: Vec<TaskResult>,
+}
+
+impl From<&treexml::Element> for ClientState {
+    fn from(node: &treexml::Element) -> Self {
+        let mut e = Self::default();
+        for n in &node.children {
+            match &*n.name {
+                "platform_name" => {
+                    e.platform_name = util::trimmed_optional(&n.text);
+                }
+                "core_client_major_version" => {
+                    e.core_client_version.major = util::eval_node_contents(n);
+                }
+                "core_client_minor_version" => {
+                    e.core_client_version.minor = util::eval_node_contents(n);
+                }
+                "core_client_release" => {
+                    e.core_client_version.release = util::eval_node_contents(n);
+                }
+                "executing_as_daemon" => {
+                    e.executing_as_daemon = util::eval_bool(n);
+                }
+                "host_info" => {
+                    e.host_info = Some(HostInfo::from(n));
+                }
+                "net_stats" => {
+                    e.net_stats = Some(NetStats::from(n));
+                }
+                "app" => {
+                    e.apps.push(App::from(n));
+                }
+                "app_version" => {
+                    e.app_versions.push(AppVersion::from(n));
+                }
+                "result" => {
+                    e.results.push(TaskResult::from(n));
+                }
+                _ => util::unknown_tag("ClientState", n),
+            }
+        }
+        e
+    }
+}
+
+impl From<&ClientState> for treexml::Element {
+    fn from(v: &ClientState) -> Self {
+        let mut node = Self::new("client_state");
+        util::push_node(&mut node, "platform_name", v.platform_name.as_ref());
+        util::push_node(
+            &mut node,
+            "core_client_major_version",
+            v.core_client_version.major,
+        );
+        util::push_node(
+            &mut node,
+            "core_client_minor_version",
+            v.core_client_version.minor,
+        );
+        util::push_node(
+            &mut node,
+            "core_client_release",
+            v.core_client_version.release,
+        );
+        util::push_node(
+            &mut node,
+            "executing_as_daemon",
+            v.executing_as_daemon.map(u8::from),
+        );
+        if let Some(host_info) = &v.host_info {
+            node.children.push(host_info.into());
+        }
+        if let Some(net_stats) = &v.net_stats {
+            node.children.push(net_stats.into());
+        }
+        for app in &v.apps {
+            node.children.push(app.into());
+        }
+        for app_version in &v.app_versions {
+            node.children.push(app_version.into());
+        }
+        for result in &v.results {
+            node.children.push(result.into());
+        }
+        node
+    }
+}
+
+/// Per-weekday computation and network time windows.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DayPrefs {
+    /// Day of the week, 0 being Sunday.
+    pub day_of_week: Option<i64>,
+    pub start_hour: Option<f64>,
+    pub end_hour: Option<f64>,
+    pub net_start_hour: Option<f64>,
+    pub net_end_hour: Option<f64>,
+}
+
+impl From<&treexml::Element> for DayPrefs {
+    fn from(node: &treexml::Element) -> Self {
+        let mut e = Self::default();
+        for n in &node.children {
+            match &*n.name {
+                "day_of_week" => e.day_of_week = util::eval_node_contents(n),
+                "start_hour" => e.start_hour = util::eval_node_contents(n),
+                "end_hour" => e.end_hour = util::eval_node_contents(n),
+                "net_start_hour" => e.net_start_hour = util::eval_node_contents(n),
+                "net_end_hour" => e.net_end_hour = util::eval_node_contents(n),
+                _ => util::unknown_tag("DayPrefs", n),
+            }
+        }
+        e
+    }
+}
+
+impl From<&DayPrefs> for treexml::Element {
+    fn from(v: &DayPrefs) -> Self {
+        let mut node = Self::new("day_prefs");
+        util::push_node(&mut node, "day_of_week", v.day_of_week);
+        util::push_node(&mut node, "start_hour", v.start_hour);
+        util::push_node(&mut node, "end_hour", v.end_hour);
+        util::push_node(&mut node, "net_start_hour", v.net_start_hour);
+        util::push_node(&mut node, "net_end_hour", v.net_end_hour);
+        node
+    }
+}
+
+/// Computing preferences, as used by `global_prefs_working` and `global_prefs_override`.
+///
+/// Fields left as `None` are omitted when serialized, which makes the daemon
+/// fall back to its own defaults for them.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct GlobalPreferences {
+    pub source_project: Option<String>,
+    pub mod_time: Option<f64>,
+    pub battery_charge_min_pct: Option<f64>,
+    pub battery_max_temperature: Option<f64>,
+    pub run_on_batteries: Option<bool>,
+    pub run_if_user_active: Option<bool>,
+    pub run_gpu_if_user_active: Option<bool>,
+    pub suspend_if_no_recent_input: Option<f64>,
+    pub suspend_cpu_usage: Option<f64>,
+    pub idle_time_to_run: Option<f64>,
+    pub start_hour: Option<f64>,
+    pub end_hour: Option<f64>,
+    pub net_start_hour: Option<f64>,
+    pub net_end_hour: Option<f64>,
+    pub leave_apps_in_memory: Option<bool>,
+    pub confirm_before_connecting: Option<bool>,
+    pub hangup_if_dialed: Option<bool>,
+    pub dont_verify_images: Option<bool>,
+    pub network_wifi_only: Option<bool>,
+    pub work_buf_min_days: Option<f64>,
+    pub work_buf_additional_days: Option<f64>,
+    pub max_ncpus_pct: Option<f64>,
+    pub cpu_scheduling_period_minutes: Option<f64>,
+    pub disk_interval: Option<f64>,
+    pub disk_max_used_gb: Option<f64>,
+    pub disk_max_used_pct: Option<f64>,
+    pub disk_min_free_gb: Option<f64>,
+    pub vm_max_used_pct: Option<f64>,
+    pub ram_max_used_busy_pct: Option<f64>,
+    pub ram_max_used_idle_pct: Option<f64>,
+    pub max_bytes_sec_up: Option<f64>,
+    pub max_bytes_sec_down: Option<f64>,
+    pub cpu_usage_limit: Option<f64>,
+    pub daily_xfer_limit_mb: Option<f64>,
+    pub daily_xfer_period_days: Option<f64>,
+    pub day_prefs: Vec<DayPrefs>,
+}
+
+impl From<&treexml::Element> for GlobalPreferences {
+    fn from(node: &treexml::Element) -> Self {
+        let mut e = Self::default();
+        for n in &node.children {
+            match &*n.name {
+                "source_project" => e.source_project = util::trimmed_optional(&n.text),
+                "mod_time" => e.mod_time = util::eval_node_contents(n),
+                "battery_charge_min_pct" => e.battery_charge_min_pct = util::eval_node_contents(n),
+                "battery_max_temperature" => {
+                    e.battery_max_temperature = util::eval_node_contents(n);
+                }
+                "run_on_batteries" => e.run_on_batteries = util::eval_bool(n),
+                "run_if_user_active" => e.run_if_user_active = util::eval_bool(n),
+                "run_gpu_if_user_active" => e.run_gpu_if_user_active = util::eval_bool(n),
+                "suspend_if_no_recent_input" => {
+                    e.suspend_if_no_recent_input = util::eval_node_contents(n);
+                }
+                "suspend_cpu_usage" => e.suspend_cpu_usage = util::eval_node_contents(n),
+                "idle_time_to_run" => e.idle_time_to_run = util::eval_node_contents(n),
+                "start_hour" => e.start_hour = util::eval_node_contents(n),
+                "end_hour" => e.end_hour = util::eval_node_contents(n),
+                "net_start_hour" => e.net_start_hour = util::eval_node_contents(n),
+                "net_end_hour" => e.net_end_hour = util::eval_node_contents(n),
+                "leave_apps_in_memory" => e.leave_apps_in_memory = util::eval_bool(n),
+                "confirm_before_connecting" => e.confirm_before_connecting = util::eval_bool(n),
+                "hangup_if_dialed" => e.hangup_if_dialed = util::eval_bool(n),
+                "dont_verify_images" => e.dont_verify_images = util::eval_bool(n),
+                "network_wifi_only" => e.network_wifi_only = util::eval_bool(n),
+                "work_buf_min_days" => e.work_buf_min_days = util::eval_node_contents(n),
+                "work_buf_additional_days" => {
+                    e.work_buf_additional_days = util::eval_node_contents(n);
+                }
+                "max_ncpus_pct" => e.max_ncpus_pct = util::eval_node_contents(n),
+                "cpu_scheduling_period_minutes" => {
+                    e.cpu_scheduling_period_minutes = util::eval_node_contents(n);
+                }
+                "disk_interval" => e.disk_interval = util::eval_node_contents(n),
+                "disk_max_used_gb" => e.disk_max_used_gb = util::eval_node_contents(n),
+                "disk_max_used_pct" => e.disk_max_used_pct = util::eval_node_contents(n),
+                "disk_min_free_gb" => e.disk_min_free_gb = util::eval_node_contents(n),
+                "vm_max_used_pct" => e.vm_max_used_pct = util::eval_node_contents(n),
+                "ram_max_used_busy_pct" => e.ram_max_used_busy_pct = util::eval_node_contents(n),
+                "ram_max_used_idle_pct" => e.ram_max_used_idle_pct = util::eval_node_contents(n),
+                "max_bytes_sec_up" => e.max_bytes_sec_up = util::eval_node_contents(n),
+                "max_bytes_sec_down" => e.max_bytes_sec_down = util::eval_node_contents(n),
+                "cpu_usage_limit" => e.cpu_usage_limit = util::eval_node_contents(n),
+                "daily_xfer_limit_mb" => e.daily_xfer_limit_mb = util::eval_node_contents(n),
+                "daily_xfer_period_days" => e.daily_xfer_period_days = util::eval_node_contents(n),
+                "day_prefs" => e.day_prefs.push(DayPrefs::from(n)),
+                _ => util::unknown_tag("GlobalPreferences", n),
+            }
+        }
+        e
+    }
+}
+
+impl From<&GlobalPreferences> for treexml::Element {
+    fn from(v: &GlobalPreferences) -> Self {
+        let mut node = Self::new("global_preferences");
+        util::push_node(&mut node, "source_project", v.source_project.as_ref());
+        util::push_node(&mut node, "mod_time", v.mod_time);
+        util::push_node(
+            &mut node,
+            "battery_charge_min_pct",
+            v.battery_charge_min_pct,
+        );
+        util::push_node(
+            &mut node,
+            "battery_max_temperature",
+            v.battery_max_temperature,
+        );
+        util::push_node(
+            &mut node,
+            "run_on_batteries",
+            v.run_on_batteries.map(u8::from),
+        );
+        util::push_node(
+            &mut node,
+            "run_if_user_active",
+            v.run_if_user_active.map(u8::from),
+        );
+        util::push_node(
+            &mut node,
+            "run_gpu_if_user_active",
+            v.run_gpu_if_user_active.map(u8::from),
+        );
+        util::push_node(
+            &mut node,
+            "suspend_if_no_recent_input",
+            v.suspend_if_no_recent_input,
+        );
+        util::push_node(&mut node, "suspend_cpu_usage", v.suspend_cpu_usage);
+        util::push_node(&mut node, "idle_time_to_run", v.idle_time_to_run);
+        util::push_node(&mut node, "start_hour", v.start_hour);
+        util::push_node(&mut node, "end_hour", v.end_hour);
+        util::push_node(&mut node, "net_start_hour", v.net_start_hour);
+        util::push_node(&mut node, "net_end_hour", v.net_end_hour);
+        util::push_node(
+            &mut node,
+            "leave_apps_in_memory",
+            v.leave_apps_in_memory.map(u8::from),
+        );
+        util::push_node(
+            &mut node,
+            "confirm_before_connecting",
+            v.confirm_before_connecting.map(u8::from),
+        );
+        util::push_node(
+            &mut node,
+            "hangup_if_dialed",
+            v.hangup_if_dialed.map(u8::from),
+        );
+        util::push_node(
+            &mut node,
+            "dont_verify_images",
+            v.dont_verify_images.map(u8::from),
+        );
+        util::push_node(
+            &mut node,
+            "network_wifi_only",
+            v.network_wifi_only.map(u8::from),
+        );
+        util::push_node(&mut node, "work_buf_min_days", v.work_buf_min_days);
+        util::push_node(
+            &mut node,
+            "work_buf_additional_days",
+            v.work_buf_additional_days,
+        );
+        util::push_node(&mut node, "max_ncpus_pct", v.max_ncpus_pct);
+        util::push_node(
+            &mut node,
+            "cpu_scheduling_period_minutes",
+            v.cpu_scheduling_period_minutes,
+        );
+        util::push_node(&mut node, "disk_interval", v.disk_interval);
+        util::push_node(&mut node, "disk_max_used_gb", v.disk_max_used_gb);
+        util::push_node(&mut node, "disk_max_used_pct", v.disk_max_used_pct);
+        util::push_node(&mut node, "disk_min_free_gb", v.disk_min_free_gb);
+        util::push_node(&mut node, "vm_max_used_pct", v.vm_max_used_pct);
+        util::push_node(&mut node, "ram_max_used_busy_pct", v.ram_max_used_busy_pct);
+        util::push_node(&mut node, "ram_max_used_idle_pct", v.ram_max_used_idle_pct);
+        util::push_node(&mut node, "max_bytes_sec_up", v.max_bytes_sec_up);
+        util::push_node(&mut node, "max_bytes_sec_down", v.max_bytes_sec_down);
+        util::push_node(&mut node, "cpu_usage_limit", v.cpu_usage_limit);
+        util::push_node(&mut node, "daily_xfer_limit_mb", v.daily_xfer_limit_mb);
+        util::push_node(
+            &mut node,
+            "daily_xfer_period_days",
+            v.daily_xfer_period_days,
+        );
+        for day_prefs in &v.day_prefs {
+            node.children.push(day_prefs.into());
+        }
+        node
+    }
+}
+
+/// Account setup details a project publishes, as returned by `get_project_config_poll`.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ProjectConfig {
+    pub name: Option<String>,
+    pub master_url: Option<String>,
+    pub web_rpc_url_base: Option<String>,
+    pub min_passwd_length: Option<i32>,
+    pub uses_username: Option<bool>,
+    pub account_manager: Option<bool>,
+    pub account_creation_disabled: Option<bool>,
+    pub client_account_creation_disabled: Option<bool>,
+    pub terms_of_use: Option<String>,
+}
+
+impl From<&treexml::Element> for ProjectConfig {
+    fn from(node: &treexml::Element) -> Self {
+        let mut e = Self::default();
+        for n in &node.children {
+            match &*n.name {
+                "name" => {
+                    e.name = util::trimmed_optional(&util::any_text(n));
+                }
+                "master_url" => {
+                    e.master_url = util::trimmed_optional(&n.text);
+                }
+                "web_rpc_url_base" => {
+                    e.web_rpc_url_base = util::trimmed_optional(&n.text);
+                }
+                "min_passwd_length" => {
+                    e.min_passwd_length = util::eval_node_contents(n);
+                }
+                "uses_username" => {
+                    e.uses_username = util::eval_bool(n);
+                }
+                "account_manager" => {
+                    e.account_manager = util::eval_bool(n);
+                }
+                "account_creation_disabled" => {
+                    e.account_creation_disabled = util::eval_bool(n);
+                }
+                "client_account_creation_disabled" => {
+                    e.client_account_creation_disabled = util::eval_bool(n);
+                }
+                "terms_of_use" => {
+                    e.terms_of_use = util::trimmed_optional(&util::any_text(n));
+                }
+                _ => util::unknown_tag("ProjectConfig", n),
+            }
+        }
+        e
+    }
+}
+
+/// Part of a `Summary`, or why it is missing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum SummarySection<T> {
+    Ok(T),
+    Error(String),
+}
+
+impl<T> SummarySection<T> {
+    #[must_use]
+    pub const fn ok(&self) -> Option<&T> {
+        match self {
+            Self::Ok(v) => Some(v),
+            Self::Error(_) => None,
+        }
+    }
+}
+
+impl<T> From<Result<T, Error>> for SummarySection<T> {
+    fn from(result: Result<T, Error>) -> Self {
+        match result {
+            Ok(v) => Self::Ok(v),
+            Err(e) => Self::Error(e.to_string()),
+        }
+    }
+}
+
+/// State of a client at a glance, for dashboards, as `Client::summary` puts it together. Each
+/// section stands on its own, so one failing leaves the others be.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Summary {
+    /// Version of the daemon, as in `7.16.11`.
+    pub version: SummarySection<String>,
+    pub host: SummarySection<HostSummary>,
+    pub status: SummarySection<StatusSummary>,
+    pub tasks: SummarySection<TaskSummary>,
+    pub transfers: SummarySection<TransferSummary>,
+    /// Names of the attached projects, or the URLs of those without one.
+    pub projects: SummarySection<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct HostSummary {
+    pub domain_name: Option<String>,
+    pub os_name: Option<String>,
+    pub os_version: Option<String>,
+    pub p_model: Option<String>,
+    pub p_ncpus: Option<i64>,
+    pub p_fpops: Option<f64>,
+    pub m_nbytes: Option<f64>,
+    pub d_free: Option<f64>,
+}
+
+impl From<&HostInfo> for HostSummary {
+    fn from(host_info: &HostInfo) -> Self {
+        Self {
+            domain_name: host_info.domain_name.clone(),
+            os_name: host_info.os_name.clone(),
+            os_version: host_info.os_version.clone(),
+            p_model: host_info.p_model.clone(),
+            p_ncpus: host_info.p_ncpus,
+            p_fpops: host_info.p_fpops,
+            m_nbytes: host_info.m_nbytes,
+            d_free: host_info.d_free,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StatusSummary {
+    pub task_mode: Option<RunMode>,
+    pub task_suspend_reason: Option<SuspendReason>,
+    pub gpu_mode: Option<RunMode>,
+    pub gpu_suspend_reason: Option<SuspendReason>,
+    pub network_mode: Option<RunMode>,
+    pub network_suspend_reason: Option<SuspendReason>,
+}
+
+impl From<&CcStatus> for StatusSummary {
+    fn from(status: &CcStatus) -> Self {
+        Self {
+            task_mode: status.task_mode,
+            task_suspend_reason: status.task_suspend_reason,
+            gpu_mode: status.gpu_mode,
+            gpu_suspend_reason: status.gpu_suspend_reason,
+            network_mode: status.network_mode,
+            network_suspend_reason: status.network_suspend_reason,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TaskSummary {
+    /// Number of tasks in each state. Tasks in states unknown to the crate are left out.
+    pub by_state: std::collections::BTreeMap<ResultState, usize>,
+    /// Tasks that have started running, suspended or not.
+    pub active: Vec<ActiveTaskSummary>,
+}
+
+impl TaskSummary {
+    /// Summary of `tasks`, with statuses given as though suspended for `reason`.
+    #[must_use]
+    pub fn new(tasks: &[TaskResult], reason: SuspendReason) -> Self {
+        let mut summary = Self::default();
+        for task in tasks {
+            if let Some(state) = task.result_state() {
+                *summary.by_state.entry(state).or_default() += 1;
+            }
+            if let Some(active_task) = &task.active_task {
+                summary.active.push(ActiveTaskSummary {
+                    name: task.name.clone(),
+                    project_url: task.project_url.clone(),
+                    status: describe::task_status_line_suspended(task, reason),
+                    fraction_done: active_task.fraction_done,
+                    elapsed_time: active_task.elapsed_time,
+                });
+            }
+        }
+        summary
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ActiveTaskSummary {
+    pub name: Option<String>,
+    pub project_url: Option<String>,
+    /// As in the Manager's task list.
+    pub status: String,
+    pub fraction_done: Option<f64>,
+    pub elapsed_time: Option<f64>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TransferSummary {
+    pub uploads: usize,
+    pub downloads: usize,
+    /// Bytes left to transfer, across all files.
+    pub bytes_remaining: f64,
+}
+
+impl From<&[FileTransfer]> for TransferSummary {
+    fn from(transfers: &[FileTransfer]) -> Self {
+        let mut summary = Self::default();
+        for transfer in transfers {
+            if transfer.is_upload == Some(true) {
+                summary.uploads += 1;
+            } else {
+                summary.downloads += 1;
+            }
+            let remaining = transfer.nbytes.unwrap_or(0.0) - transfer.bytes_so_far().unwrap_or(0.0);
+            summary.bytes_remaining += remaining.max(0.0);
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const CLIENT_STATE: &str = r"<client_state>
+<host_info>
+    <domain_name>worker01</domain_name>
+    <p_ncpus>8</p_ncpus>
+</host_info>
+<net_stats>
+    <bwup>7129.25</bwup>
+    <avg_up>1203.75</avg_up>
+    <avg_time_up>1700000123.5</avg_time_up>
+    <bwdown>18374.5</bwdown>
+    <avg_down>5401.5</avg_down>
+    <avg_time_down>1700000987.25</avg_time_down>
+</net_stats>
+<app>
+    <name>einstein_O3AS</name>
+    <user_friendly_name>All-Sky Gravitational Wave search on O3</user_friendly_name>
+    <non_cpu_intensive>0</non_cpu_intensive>
+</app>
+<app_version>
+    <app_name>einstein_O3AS</app_name>
+    <version_num>107</version_num>
+    <platform>x86_64-pc-linux-gnu</platform>
+    <avg_ncpus>0.900000</avg_ncpus>
+    <flops>114781734537.540070</flops>
+    <plan_class>GW-opencl-nvidia-2</plan_class>
+    <coproc>
+        <type>NVIDIA</type>
+        <count>1.000000</count>
+    </coproc>
+</app_version>
+<app_version>
+    <app_name>einstein_O3AS</app_name>
+    <version_num>106</version_num>
+    <platform>x86_64-pc-linux-gnu</platform>
+    <avg_ncpus>1.000000</avg_ncpus>
+</app_version>
+<result>
+    <name>h1_0001.00_O3aC01Cl1In0__O3AS1a_1001.00Hz_1_0</name>
+    <version_num>107</version_num>
+</result>
+<platform_name>x86_64-pc-linux-gnu</platform_name>
+<core_client_major_version>7</core_client_major_version>
+<core_client_minor_version>20</core_client_minor_version>
+<core_client_release>2</core_client_release>
+<executing_as_daemon>1</executing_as_daemon>
+</client_state>";
+
+    #[test]
+    fn version_info_display() {
+        assert_eq!(VersionInfo::new(7, 22, 2).to_string(), "7.22.2");
+        assert_eq!(
+            VersionInfo {
+                major: Some(7),
+                minor: Some(16),
+                release: None,
+            }
+            .to_string(),
+            "7.16"
+        );
+        assert_eq!(
+            VersionInfo {
+                major: Some(8),
+                ..VersionInfo::default()
+            }
+            .to_string(),
+            "8"
+        );
+        assert_eq!(
+            VersionInfo {
+                major: None,
+                minor: Some(2),
+                release: None,
+            }
+            .to_string(),
+            "0.2"
+        );
+        assert_eq!(VersionInfo::default().to_string(), "");
+    }
+
+    #[test]
+    fn version_info_ordering() {
+        let short = VersionInfo {
+            major: Some(7),
+            minor: Some(16),
+            release: None,
+        };
+
+        assert_eq!(short, VersionInfo::new(7, 16, 0));
+        assert!(short < VersionInfo::new(7, 16, 1));
+        assert!(VersionInfo::new(7, 9, 5) < short);
+        assert!(VersionInfo::new(8, 0, 0) > VersionInfo::new(7, 24, 99));
+        assert_eq!(VersionInfo::default(), VersionInfo::new(0, 0, 0));
+        assert_eq!(
+            VersionInfo {
+                major: Some(7),
+                ..VersionInfo::default()
+            }
+            .cmp(&short),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn version_info_at_least() {
+        let v = VersionInfo::new(7, 16, 11);
+
+        assert!(v.at_least(7, 16, 0));
+        assert!(v.at_least(7, 16, 11));
+        assert!(v.at_least(6, 99, 99));
+        assert!(!v.at_least(7, 16, 12));
+        assert!(!v.at_least(7, 17, 0));
+        assert!(!VersionInfo::default().at_least(0, 0, 1));
+        assert!(VersionInfo::current().at_least(7, 0, 0));
+    }
+
+    #[test]
+    fn client_state() {
+        let state = ClientState::from(&util::parse_node(CLIENT_STATE).unwrap());
+
+        assert_eq!(state.platform_name.as_deref(), Some("x86_64-pc-linux-gnu"));
+        assert_eq!(state.core_client_version.major, Some(7));
+        assert_eq!(state.core_client_version.minor, Some(20));
+        assert_eq!(state.core_client_version.release, Some(2));
+        assert_eq!(state.executing_as_daemon, Some(true));
+        assert_eq!(state.host_info.unwrap().p_ncpus, Some(8));
+        assert_eq!(state.results.len(), 1);
+
+        let net_stats = state.net_stats.unwrap();
+        assert_eq!(net_stats.bwup, Some(7129.25));
+        assert_eq!(net_stats.bwdown, Some(18374.5));
+        assert_eq!(net_stats.avg_up, Some(1203.75));
+        assert_eq!(net_stats.avg_down, Some(5401.5));
+        assert_eq!(net_stats.avg_time_up, Some(1_700_000_123.5));
+        assert_eq!(net_stats.avg_time_down, Some(1_700_000_987.25));
+
+        assert_eq!(state.apps.len(), 1);
+        assert_eq!(state.apps[0].name.as_deref(), Some("einstein_O3AS"));
+        assert_eq!(state.apps[0].non_cpu_intensive, Some(false));
+
+        assert_eq!(state.app_versions.len(), 2);
+        let gpu = &state.app_versions[0];
+        assert_eq!(gpu.version_num, Some(107));
+        assert_eq!(gpu.plan_class.as_deref(), Some("GW-opencl-nvidia-2"));
+        assert_eq!(gpu.avg_ncpus, Some(0.9));
+        assert_eq!(gpu.coprocs.len(), 1);
+        assert_eq!(gpu.coprocs[0].coproc_type.as_deref(), Some("NVIDIA"));
+        assert_eq!(gpu.coprocs[0].count, Some(1.0));
+        assert!(state.app_versions[1].coprocs.is_empty());
+    }
+
+    #[test]
+    fn app_version_coprocs() {
+        let version = AppVersion::from(
+            &util::parse_node(
+                "<app_version>\
+                 <coproc><type>NVIDIA</type><count>1</count></coproc>\
+                 <coproc><type>intel_gpu</type><count>0.5</count></coproc>\
+                 </app_version>",
+            )
+            .unwrap(),
+        );
+        assert_eq!(
+            version
+                .coprocs
+                .iter()
+                .map(|c| (c.coproc_type.as_deref(), c.count))
+                .collect::<Vec<_>>(),
+            [(Some("NVIDIA"), Some(1.0)), (Some("intel_gpu"), Some(0.5))]
+        );
+        assert_eq!(
+            treexml::Element::from(&version)
+                .children
+                .iter()
+                .filter(|n| n.name == "coproc")
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn app_blank_strings() {
+        let app = App::from(
+            &util::parse_node(
+                "<app><name>uppercase</name><user_friendly_name>  </user_friendly_name></app>",
+            )
+            .unwrap(),
+        );
+
+        assert_eq!(app.name.as_deref(), Some("uppercase"));
+        assert_eq!(app.user_friendly_name, None);
+    }
+
+    #[test]
+    fn net_stats_partial() {
+        let net_stats = NetStats::from(
+            &util::parse_node("<net_stats><bwup>512.5</bwup><bwdown>1024</bwdown></net_stats>")
+                .unwrap(),
+        );
+
+        assert_eq!(net_stats.bwup, Some(512.5));
+        assert_eq!(net_stats.bwdown, Some(1024.0));
+        assert!(net_stats.avg_up.is_none());
+        assert!(net_stats.avg_time_down.is_none());
+    }
+
+    const GLOBAL_PREFERENCES: &str = r"<global_preferences>
+   <source_project>https://einsteinathome.org/</source_project>
+   <mod_time>1699990000.000000</mod_time>
+   <run_on_batteries>0</run_on_batteries>
+   <run_if_user_active>1</run_if_user_active>
+   <run_gpu_if_user_active>0</run_gpu_if_user_active>
+   <idle_time_to_run>3.000000</idle_time_to_run>
+   <suspend_cpu_usage>25.000000</suspend_cpu_usage>
+   <start_hour>22.000000</start_hour>
+   <end_hour>7.500000</end_hour>
+   <net_start_hour>0.000000</net_start_hour>
+   <net_end_hour>0.000000</net_end_hour>
+   <work_buf_min_days>0.100000</work_buf_min_days>
+   <work_buf_additional_days>0.500000</work_buf_additional_days>
+   <max_ncpus_pct>75.000000</max_ncpus_pct>
+   <cpu_scheduling_period_minutes>60.000000</cpu_scheduling_period_minutes>
+   <disk_max_used_gb>10.000000</disk_max_used_gb>
+   <disk_max_used_pct>90.000000</disk_max_used_pct>
+   <disk_min_free_gb>1.000000</disk_min_free_gb>
+   <ram_max_used_busy_pct>50.000000</ram_max_used_busy_pct>
+   <ram_max_used_idle_pct>90.000000</ram_max_used_idle_pct>
+   <max_bytes_sec_up>25600.000000</max_bytes_sec_up>
+   <max_bytes_sec_down>0.000000</max_bytes_sec_down>
+   <cpu_usage_limit>80.000000</cpu_usage_limit>
+   <day_prefs>
+      <day_of_week>0</day_of_week>
+      <start_hour>0.000000</start_hour>
+      <end_hour>24.000000</end_hour>
+   </day_prefs>
+   <day_prefs>
+      <day_of_week>3</day_of_week>
+      <net_start_hour>1.000000</net_start_hour>
+      <net_end_hour>5.000000</net_end_hour>
+   </day_prefs>
+</global_preferences>";
+
+    #[test]
+    fn global_preferences() {
+        let prefs = GlobalPreferences::from(&util::parse_node(GLOBAL_PREFERENCES).unwrap());
+
+        assert_eq!(
+            prefs.source_project.as_deref(),
+            Some("https://einsteinathome.org/")
+        );
+        assert_eq!(prefs.run_on_batteries, Some(false));
+        assert_eq!(prefs.run_if_user_active, Some(true));
+        assert_eq!(prefs.start_hour, Some(22.0));
+        assert_eq!(prefs.end_hour, Some(7.5));
+        assert_eq!(prefs.max_ncpus_pct, Some(75.0));
+        assert_eq!(prefs.cpu_usage_limit, Some(80.0));
+        assert_eq!(prefs.max_bytes_sec_up, Some(25600.0));
+        assert!(prefs.vm_max_used_pct.is_none());
+        assert!(prefs.network_wifi_only.is_none());
+
+        assert_eq!(prefs.day_prefs.len(), 2);
+        assert_eq!(prefs.day_prefs[0].day_of_week, Some(0));
+        assert_eq!(prefs.day_prefs[0].end_hour, Some(24.0));
+        assert!(prefs.day_prefs[0].net_start_hour.is_none());
+        assert_eq!(prefs.day_prefs[1].day_of_week, Some(3));
+        assert_eq!(prefs.day_prefs[1].net_end_hour, Some(5.0));
+        assert!(prefs.day_prefs[1].start_hour.is_none());
+    }
+
+    #[test]
+    fn global_preferences_round_trip() {
+        let prefs = GlobalPreferences::from(&util::parse_node(GLOBAL_PREFERENCES).unwrap());
+        let node = treexml::Element::from(&prefs);
+
+        assert_eq!(GlobalPreferences::from(&node), prefs);
+        assert_eq!(
+            node.find_child(|n| n.name == "run_if_user_active")
+                .unwrap()
+                .text
+                .as_deref(),
+            Some("1")
+        );
+    }
+
+    #[test]
+    fn global_preferences_omit_unset_fields() {
+        let node = treexml::Element::from(&GlobalPreferences::default());
+        assert_eq!(node.name, "global_preferences");
+        assert!(node.children.is_empty());
+
+        let prefs = GlobalPreferences {
+            cpu_usage_limit: Some(50.0),
+            day_prefs: vec![DayPrefs {
+                day_of_week: Some(6),
+                start_hour: Some(9.0),
+                ..DayPrefs::default()
+            }],
+            ..GlobalPreferences::default()
+        };
+        let node = treexml::Element::from(&prefs);
+
+        assert_eq!(
+            node.children
+                .iter()
+                .map(|n| n.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["cpu_usage_limit", "day_prefs"]
+        );
+        assert_eq!(node.children[1].children.len(), 2);
+        assert_eq!(GlobalPreferences::from(&node), prefs);
+    }
+
+    #[test]
+    fn run_mode_strings() {
+        for &mode in &[
+            RunMode::Always,
+            RunMode::Auto,
+            RunMode::Never,
+            RunMode::Restore,
+        ] {
+            assert_eq!(mode.to_string().parse::<RunMode>().unwrap(), mode);
+            assert_eq!(
+                mode.to_string().to_uppercase().parse::<RunMode>().unwrap(),
+                mode
+            );
+        }
+        assert_eq!(
+            "sometimes".parse::<RunMode>().unwrap_err(),
+            Error::DataParseError("Unknown run mode: sometimes".into())
+        );
+    }
+
+    #[test]
+    fn component_strings() {
+        for &component in &[Component::CPU, Component::GPU, Component::Network] {
+            assert_eq!(
+                component.to_string().parse::<Component>().unwrap(),
+                component
+            );
+        }
+        assert_eq!(Component::CPU.to_string(), "cpu");
+        assert_eq!("Run".parse::<Component>().unwrap(), Component::CPU);
+        assert_eq!(
+            "disk".parse::<Component>().unwrap_err(),
+            Error::DataParseError("Unknown component: disk".into())
+        );
+    }
+
+    #[test]
+    fn project_op_strings() {
+        for &op in &[
+            ProjectOp::Update,
+            ProjectOp::Suspend,
+            ProjectOp::Resume,
+            ProjectOp::NoMoreWork,
+            ProjectOp::AllowMoreWork,
+            ProjectOp::Reset,
+            ProjectOp::Detach,
+            ProjectOp::DetachWhenDone,
+            ProjectOp::DontDetachWhenDone,
+        ] {
+            let name = op.as_str().trim_start_matches("project_");
+            assert_eq!(name.parse::<ProjectOp>().unwrap(), op);
+        }
+        assert_eq!(
+            "NoMoreWork".parse::<ProjectOp>().unwrap(),
+            ProjectOp::NoMoreWork
+        );
+        assert_eq!(
+            "project_update".parse::<ProjectOp>().unwrap_err(),
+            Error::DataParseError("Unknown project operation: project_update".into())
+        );
+    }
+
+    #[test]
+    fn active_task_process_state() {
+        let task = |state: &str| ActiveTask {
+            active_task_state: Some(state.into()),
+            ..ActiveTask::default()
+        };
+        assert_eq!(task("1").process_state(), Some(Process::Executing));
+        assert_eq!(task("8").process_state(), Some(Process::QuitPending));
+        assert_eq!(task("3").process_state(), None);
+        assert_eq!(ActiveTask::default().process_state(), None);
+    }
+
+    /// Tasks of two projects, in various states, due at various times.
+    fn fixture() -> Vec<TaskResult> {
+        let task = |name: &str, url: &str, state: ResultState, deadline: Option<f64>| TaskResult {
+            name: Some(name.into()),
+            project_url: Some(url.into()),
+            state: Some(state as i64),
+            report_deadline: deadline,
+            ..TaskResult::default()
+        };
+        let running = |task: TaskResult| TaskResult {
+            active_task: Some(ActiveTask::default()),
+            ..task
+        };
+        vec![
+            running(task(
+                "h1_0001_0",
+                "https://einsteinathome.org/",
+                ResultState::FilesDownloaded,
+                Some(1000.0),
+            )),
+            task(
+                "h1_0002_0",
+                "https://einsteinathome.org/",
+                ResultState::FilesDownloaded,
+                Some(5000.0),
+            ),
+            task(
+                "l1_0001_1",
+                "https://einsteinathome.org/",
+                ResultState::FilesUploaded,
+                Some(2000.0),
+            ),
+            running(task(
+                "de_modfit_1_0",
+                "https://milkyway.cs.rpi.edu/milkyway/",
+                ResultState::FilesDownloaded,
+                Some(3000.0),
+            )),
+            task(
+                "de_modfit_2_0",
+                "https://milkyway.cs.rpi.edu/milkyway/",
+                ResultState::FilesDownloading,
+                None,
+            ),
+            TaskResult::default(),
+        ]
+    }
+
+    fn matching(filter: &TaskFilter) -> Vec<String> {
+        fixture()
+            .iter()
+            .filter(|task| filter.matches(task))
+            .map(|task| task.name.clone().unwrap_or_default())
+            .collect()
+    }
+
+    #[test]
+    fn task_filters() {
+        assert_eq!(matching(&TaskFilter::new()).len(), 6);
+        assert_eq!(
+            matching(&TaskFilter::new().project("http://EinsteinAtHome.org")),
+            ["h1_0001_0", "h1_0002_0", "l1_0001_1"]
+        );
+        assert_eq!(
+            matching(&TaskFilter::new().state(ResultState::FilesDownloaded)),
+            ["h1_0001_0", "h1_0002_0", "de_modfit_1_0"]
+        );
+        // Strictly before, and never for tasks without a deadline
+        assert_eq!(
+            matching(&TaskFilter::new().deadline_before(3000.0)),
+            ["h1_0001_0", "l1_0001_1"]
+        );
+        assert_eq!(
+            matching(&TaskFilter::new().active_only()),
+            ["h1_0001_0", "de_modfit_1_0"]
+        );
+        assert_eq!(
+            matching(&TaskFilter::new().name_matches("*_0")),
+            ["h1_0001_0", "h1_0002_0", "de_modfit_1_0", "de_modfit_2_0"]
+        );
+        assert_eq!(
+            matching(
+                &TaskFilter::new()
+                    .project("https://einsteinathome.org/")
+                    .state(ResultState::FilesDownloaded)
+                    .name_matches("h1_*")
+                    .deadline_before(6000.0)
+            ),
+            ["h1_0001_0", "h1_0002_0"]
+        );
+        assert!(matching(
+            &TaskFilter::new()
+                .project("https://milkyway.cs.rpi.edu/milkyway/")
+                .name_matches("h1_*")
+        )
+        .is_empty());
+    }
+
+    /// Strategy for a `$model` with the fields listed drawn from their strategies, and the rest
+    /// left at their defaults.
+    macro_rules! model {
+        ($model:ident { $($field:ident: $strategy:expr),* $(,)? }) => {{
+            let strategy = Just($model::default()).boxed();
+            $(
+                let strategy = (strategy, $strategy)
+                    .prop_map(|(mut model, value)| {
+                        model.$field = value;
+                        model
+                    })
+                    .boxed();
+            )*
+            strategy
+        }};
+    }
+
+    /// Text as the parsers keep it: not blank, nor padded.
+    fn text() -> impl Strategy<Value = Option<String>> {
+        prop::option::of("[a-zA-Z0-9_.:/-]([a-zA-Z0-9 _.:/&<>'-]{0,10}[a-zA-Z0-9_.:/-])?")
+    }
+
+    fn float() -> impl Strategy<Value = Option<f64>> {
+        prop::option::of(prop::num::f64::NORMAL | prop::num::f64::SUBNORMAL | prop::num::f64::ZERO)
+    }
+
+    fn int() -> impl Strategy<Value = Option<i64>> {
+        prop::option::of(any::<i64>())
+    }
+
+    fn flag() -> impl Strategy<Value = Option<bool>> {
+        prop::option::of(any::<bool>())
+    }
+
+    fn reason() -> impl Strategy<Value = Option<SuspendReason>> {
+        prop::option::of(any::<i32>().prop_map(SuspendReason::from))
+    }
+
+    fn mode() -> impl Strategy<Value = Option<RunMode>> {
+        prop::option::of(prop::sample::select(vec![
+            RunMode::Always,
+            RunMode::Auto,
+            RunMode::Never,
+            RunMode::Restore,
+        ]))
+    }
+
+    fn cc_statuses() -> BoxedStrategy<CcStatus> {
+        model!(CcStatus {
+            network_status: int(),
+            ams_password_error: flag(),
+            task_suspend_reason: reason(),
+            task_mode: mode(),
+            task_mode_perm: mode(),
+            task_mode_delay: float(),
+            gpu_suspend_reason: reason(),
+            gpu_mode: mode(),
+            gpu_mode_perm: mode(),
+            gpu_mode_delay: float(),
+            network_suspend_reason: reason(),
+            network_mode: mode(),
+            network_mode_perm: mode(),
+            network_mode_delay: float(),
+            disallow_attach: flag(),
+            simple_gui_only: flag(),
+            max_event_log_lines: int(),
+        })
+    }
+
+    fn version_infos() -> BoxedStrategy<VersionInfo> {
+        model!(VersionInfo {
+            major: int(),
+            minor: int(),
+            release: int(),
+        })
+    }
+
+    fn host_infos() -> BoxedStrategy<HostInfo> {
+        model!(HostInfo {
+            tz_shift: int(),
+            domain_name: text(),
+            serialnum: text(),
+            ip_addr: text(),
+            host_cpid: text(),
+            p_ncpus: int(),
+            p_vendor: text(),
+            p_model: text(),
+            p_features: text(),
+            p_fpops: float(),
+            p_iops: float(),
+            p_membw: float(),
+            p_calculated: float(),
+            p_vm_extensions_disabled: flag(),
+            m_nbytes: float(),
+            m_cache: float(),
+            m_swap: float(),
+            d_total: float(),
+            d_free: float(),
+            os_name: text(),
+            os_version: text(),
+            product_name: text(),
+            mac_address: text(),
+            virtualbox_version: text(),
+        })
+    }
+
+    fn projects() -> BoxedStrategy<Project> {
+        model!(Project {
+            master_url: text(),
+            project_name: text(),
+            user_name: text(),
+            team_name: text(),
+            host_venue: text(),
+            userid: int(),
+            teamid: int(),
+            hostid: int(),
+            user_total_credit: float(),
+            user_expavg_credit: float(),
+            host_total_credit: float(),
+            host_expavg_credit: float(),
+            resource_share: float(),
+            nrpc_failures: int(),
+            min_rpc_time: float(),
+            last_rpc_time: float(),
+            sched_rpc_pending: int(),
+            suspended_via_gui: flag(),
+            dont_request_more_work: flag(),
+            attached_via_acct_mgr: flag(),
+            detach_when_done: flag(),
+            ended: flag(),
+        })
+    }
+
+    fn messages() -> BoxedStrategy<Message> {
+        model!(Message {
+            project_name: text(),
+            priority: int(),
+            msg_number: int(),
+            body: text(),
+            timestamp: int(),
+        })
+    }
+
+    fn notices() -> BoxedStrategy<Notice> {
+        model!(Notice {
+            seqno: int(),
+            title: text(),
+            description: text(),
+            create_time: float(),
+            arrival_time: float(),
+            is_private: flag(),
+            project_name: text(),
+            category: text(),
+            link: text(),
+        })
+    }
+
+    fn active_tasks() -> BoxedStrategy<ActiveTask> {
+        model!(ActiveTask {
+            active_task_state: text(),
+            app_version_num: text(),
+            slot: prop::option::of(any::<u64>()),
+            pid: prop::option::of(any::<u64>()),
+            scheduler_state: text(),
+            checkpoint_cpu_time: float(),
+            fraction_done: float(),
+            current_cpu_time: float(),
+            elapsed_time: float(),
+            swap_size: float(),
+            working_set_size: float(),
+            working_set_size_smoothed: float(),
+            page_fault_rate: float(),
+            bytes_sent: float(),
+            bytes_received: float(),
+            progress_rate: float(),
+            too_large: flag(),
+            needs_shmem: flag(),
+        })
+    }
+
+    fn task_results() -> BoxedStrategy<TaskResult> {
+        model!(TaskResult {
+            name: text(),
+            wu_name: text(),
+            platform: text(),
+            version_num: int(),
+            plan_class: text(),
+            project_url: text(),
+            final_cpu_time: float(),
+            final_elapsed_time: float(),
+            exit_status: int(),
+            state: int(),
+            report_deadline: float(),
+            received_time: float(),
+            estimated_cpu_time_remaining: float(),
+            completed_time: float(),
+            suspended_via_gui: flag(),
+            project_suspended_via_gui: flag(),
+            ready_to_report: flag(),
+            got_server_ack: flag(),
+            active_task: prop::option::of(active_tasks()),
+        })
+    }
+
+    fn file_transfers() -> BoxedStrategy<FileTransfer> {
+        model!(FileTransfer {
+            name: text(),
+            project_url: text(),
+            project_name: text(),
+            nbytes: float(),
+            max_nbytes: float(),
+            status: int(),
+            is_upload: flag(),
+            num_retries: int(),
+            first_request_time: float(),
+            next_request_time: float(),
+            time_so_far: float(),
+            last_bytes_xferred: float(),
+            xfer_active: any::<bool>(),
+            bytes_xferred: float(),
+            file_offset: float(),
+            xfer_speed: float(),
+            url: text(),
+            project_backoff: float(),
+        })
+        // Only an attempt under way has these
+        .prop_map(|transfer| {
+            if transfer.xfer_active {
+                transfer
+            } else {
+                FileTransfer {
+                    bytes_xferred: None,
+                    file_offset: None,
+                    xfer_speed: None,
+                    url: None,
+                    ..transfer
+                }
+            }
+        })
+        .boxed()
+    }
+
+    fn app_versions() -> BoxedStrategy<AppVersion> {
+        let coproc = model!(CoprocUsage {
+            coproc_type: text(),
+            count: float(),
+        });
+        model!(AppVersion {
+            app_name: text(),
+            version_num: int(),
+            platform: text(),
+            plan_class: text(),
+            avg_ncpus: float(),
+            flops: float(),
+            coprocs: prop::collection::vec(coproc, 0..3),
+        })
+    }
+
+    fn project_statistics() -> BoxedStrategy<ProjectStatistics> {
+        let day = model!(DailyStatistics {
+            day: float(),
+            user_total_credit: float(),
+            user_expavg_credit: float(),
+            host_total_credit: float(),
+            host_expavg_credit: float(),
+        });
+        model!(ProjectStatistics {
+            master_url: text(),
+            daily_statistics: prop::collection::vec(day, 0..3),
+        })
+    }
+
+    fn client_states() -> BoxedStrategy<ClientState> {
+        let app = model!(App {
+            name: text(),
+            user_friendly_name: text(),
+            non_cpu_intensive: flag(),
+        });
+        let net_stats = model!(NetStats {
+            bwup: float(),
+            bwdown: float(),
+            avg_up: float(),
+            avg_down: float(),
+            avg_time_up: float(),
+            avg_time_down: float(),
+        });
+        model!(ClientState {
+            platform_name: text(),
+            core_client_version: version_infos(),
+            executing_as_daemon: flag(),
+            host_info: prop::option::of(host_infos()),
+            net_stats: prop::option::of(net_stats),
+            apps: prop::collection::vec(app, 0..3),
+            app_versions: prop::collection::vec(app_versions(), 0..3),
+            results: prop::collection::vec(task_results(), 0..3),
+        })
+    }
+
+    /// `model` as read back from the element made of it.
+    fn round_trip<T>(model: &T) -> T
+    where
+        for<'a> T: From<&'a treexml::Element>,
+        for<'a> treexml::Element: From<&'a T>,
+    {
+        T::from(&treexml::Element::from(model))
+    }
+
+    proptest! {
+        #[test]
+        fn cc_status_round_trips(v in cc_statuses()) {
+            prop_assert_eq!(round_trip(&v), v);
+        }
+
+        #[test]
+        fn version_info_round_trips(v in version_infos()) {
+            prop_assert_eq!(round_trip(&v), v);
+        }
+
+        #[test]
+        fn project_round_trips(v in projects()) {
+            prop_assert_eq!(round_trip(&v), v);
+        }
+
+        #[test]
+        fn message_round_trips(v in messages()) {
+            prop_assert_eq!(round_trip(&v), v);
+        }
+
+        #[test]
+        fn notice_round_trips(v in notices()) {
+            prop_assert_eq!(round_trip(&v), v);
+        }
+
+        #[test]
+        fn host_info_round_trips(v in host_infos()) {
+            prop_assert_eq!(round_trip(&v), v);
+        }
+
+        #[test]
+        fn task_result_round_trips(v in task_results()) {
+            prop_assert_eq!(round_trip(&v), v);
+        }
+
+        #[test]
+        fn app_version_round_trips(v in app_versions()) {
+            prop_assert_eq!(round_trip(&v), v);
+        }
+
+        #[test]
+        fn file_transfer_round_trips(v in file_transfers()) {
+            prop_assert_eq!(round_trip(&v), v);
+        }
+
+        #[test]
+        fn project_statistics_round_trips(v in project_statistics()) {
+            prop_assert_eq!(round_trip(&v), v);
+        }
+
+        #[test]
+        fn client_state_round_trips(v in client_states()) {
+            prop_assert_eq!(round_trip(&v), v);
+        }
+    }
+
+    #[test]
+    fn defaults_round_trip() {
+        assert_eq!(round_trip(&CcStatus::default()), CcStatus::default());
+        assert_eq!(round_trip(&VersionInfo::default()), VersionInfo::default());
+        assert_eq!(round_trip(&HostInfo::default()), HostInfo::default());
+        assert_eq!(round_trip(&Project::default()), Project::default());
+        assert_eq!(round_trip(&Message::default()), Message::default());
+        assert_eq!(round_trip(&Notice::default()), Notice::default());
+        assert_eq!(round_trip(&TaskResult::default()), TaskResult::default());
+        assert_eq!(round_trip(&ActiveTask::default()), ActiveTask::default());
+        assert_eq!(
+            round_trip(&FileTransfer::default()),
+            FileTransfer::default()
+        );
+        assert_eq!(round_trip(&App::default()), App::default());
+        assert_eq!(round_trip(&CoprocUsage::default()), CoprocUsage::default());
+        assert_eq!(round_trip(&AppVersion::default()), AppVersion::default());
+        assert_eq!(round_trip(&NetStats::default()), NetStats::default());
+        assert_eq!(
+            round_trip(&DailyStatistics::default()),
+            DailyStatistics::default()
+        );
+        assert_eq!(
+            round_trip(&ProjectStatistics::default()),
+            ProjectStatistics::default()
+        );
+        assert_eq!(round_trip(&ClientState::default()), ClientState::default());
+    }
+
+    #[test]
+    fn client_state_fixture_round_trips() {
+        let state = ClientState::from(&util::parse_node(CLIENT_STATE).unwrap());
+        assert_eq!(round_trip(&state), state);
+    }
+}
@@ -0,0 +1,241 @@
+//! Human-readable descriptions of task and client states, phrased the way BOINC Manager does.
+
+use super::{CpuSched, ResultState, SuspendReason, TaskResult};
+
+const EXIT_DISK_LIMIT_EXCEEDED: i64 = 196;
+const EXIT_TIME_LIMIT_EXCEEDED: i64 = 197;
+const EXIT_MEM_LIMIT_EXCEEDED: i64 = 198;
+const EXIT_UNSTARTED_LATE: i64 = 200;
+const EXIT_ABORTED_BY_PROJECT: i64 = 202;
+const EXIT_ABORTED_VIA_GUI: i64 = 203;
+
+#[must_use]
+pub const fn suspend_reason_text(reason: SuspendReason) -> &'static str {
+    match reason {
+        SuspendReason::NotSuspended => "not suspended",
+        SuspendReason::Batteries => "on batteries",
+        SuspendReason::UserActive => "computer is in use",
+        SuspendReason::UserRequest => "user request",
+        SuspendReason::TimeOfDay => "time of day",
+        SuspendReason::Benchmarks => "CPU benchmarks in progress",
+        SuspendReason::DiskSize => "need disk space - check preferences",
+        SuspendReason::CpuThrottle => "CPU throttled",
+        SuspendReason::NoRecentInput => "no recent user activity",
+        SuspendReason::InitialDelay => "initial delay",
+        SuspendReason::ExclusiveAppRunning => "an exclusive app is running",
+        SuspendReason::CpuUsage => "CPU is busy",
+        SuspendReason::NetworkQuotaExceeded => "network transfer limit exceeded",
+        SuspendReason::Os => "requested by operating system",
+        SuspendReason::WifiState => "not connected to WiFi network",
+        SuspendReason::BatteryCharging => "battery low",
+        SuspendReason::BatteryOverheated => "battery thermal protection",
+        SuspendReason::NoGuiKeepalive => "GUI not active",
+        SuspendReason::Unknown(_) => "unknown reason",
+    }
+}
+
+/// Status column text for a task, as shown in the Manager's task list.
+#[must_use]
+pub fn task_status_line(task: &TaskResult) -> String {
+    task_status_line_suspended(task, SuspendReason::NotSuspended)
+}
+
+/// Like `task_status_line`, taking into account why the client suspended computation.
+#[must_use]
+pub fn task_status_line_suspended(task: &TaskResult, reason: SuspendReason) -> String {
+    let ready_to_report = task.ready_to_report.unwrap_or(false);
+    match task.result_state() {
+        Some(ResultState::New) => "New".into(),
+        Some(ResultState::FilesDownloading) => if ready_to_report {
+            "Download failed"
+        } else {
+            "Downloading"
+        }
+        .into(),
+        Some(ResultState::FilesDownloaded) => downloaded_status(task, reason),
+        Some(ResultState::ComputeError) => "Computation error".into(),
+        Some(ResultState::FilesUploading) => if ready_to_report {
+            "Upload failed"
+        } else {
+            "Uploading"
+        }
+        .into(),
+        Some(ResultState::Aborted) => match task.exit_status {
+            Some(EXIT_ABORTED_VIA_GUI) => "Aborted by user",
+            Some(EXIT_ABORTED_BY_PROJECT) => "Aborted by project",
+            Some(EXIT_UNSTARTED_LATE) => "Aborted: not started by deadline",
+            Some(EXIT_DISK_LIMIT_EXCEEDED) => "Aborted: task disk limit exceeded",
+            Some(EXIT_TIME_LIMIT_EXCEEDED) => "Aborted: run time limit exceeded",
+            Some(EXIT_MEM_LIMIT_EXCEEDED) => "Aborted: memory limit exceeded",
+            _ => "Aborted",
+        }
+        .into(),
+        _ => if task.got_server_ack.unwrap_or(false) {
+            "Acknowledged"
+        } else if ready_to_report {
+            "Ready to report"
+        } else {
+            "Error: invalid state"
+        }
+        .into(),
+    }
+}
+
+fn downloaded_status(task: &TaskResult, reason: SuspendReason) -> String {
+    if task.project_suspended_via_gui.unwrap_or(false) {
+        return "Project suspended by user".into();
+    }
+    if task.suspended_via_gui.unwrap_or(false) {
+        return "Task suspended by user".into();
+    }
+    match reason {
+        SuspendReason::NotSuspended | SuspendReason::CpuThrottle => {}
+        reason => return format!("Suspended - {}", suspend_reason_text(reason)),
+    }
+    match &task.active_task {
+        Some(t) if t.too_large.unwrap_or(false) => "Waiting for memory".into(),
+        Some(t) if t.needs_shmem.unwrap_or(false) => "Waiting for shared memory".into(),
+        Some(t) => match t.cpu_sched() {
+            Some(CpuSched::Scheduled) => "Running".into(),
+            Some(CpuSched::Preempted) => "Waiting to run".into(),
+            _ => "Ready to start".into(),
+        },
+        None => "Ready to start".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ActiveTask;
+
+    fn task(state: i64) -> TaskResult {
+        TaskResult {
+            state: Some(state),
+            ..TaskResult::default()
+        }
+    }
+
+    fn running(scheduler_state: &str) -> TaskResult {
+        TaskResult {
+            active_task: Some(ActiveTask {
+                scheduler_state: Some(scheduler_state.into()),
+                ..ActiveTask::default()
+            }),
+            ..task(2)
+        }
+    }
+
+    #[test]
+    fn status_lines() {
+        let fixtures = vec![
+            (task(0), SuspendReason::NotSuspended, "New"),
+            (task(1), SuspendReason::NotSuspended, "Downloading"),
+            (
+                TaskResult {
+                    ready_to_report: Some(true),
+                    ..task(1)
+                },
+                SuspendReason::NotSuspended,
+                "Download failed",
+            ),
+            (task(2), SuspendReason::NotSuspended, "Ready to start"),
+            (running("2"), SuspendReason::NotSuspended, "Running"),
+            (running("2"), SuspendReason::CpuThrottle, "Running"),
+            (running("1"), SuspendReason::NotSuspended, "Waiting to run"),
+            (running("0"), SuspendReason::NotSuspended, "Ready to start"),
+            (
+                TaskResult {
+                    active_task: Some(ActiveTask {
+                        scheduler_state: Some("1".into()),
+                        too_large: Some(true),
+                        ..ActiveTask::default()
+                    }),
+                    ..task(2)
+                },
+                SuspendReason::NotSuspended,
+                "Waiting for memory",
+            ),
+            (
+                running("2"),
+                SuspendReason::UserActive,
+                "Suspended - computer is in use",
+            ),
+            (
+                TaskResult {
+                    suspended_via_gui: Some(true),
+                    ..running("2")
+                },
+                SuspendReason::UserActive,
+                "Task suspended by user",
+            ),
+            (
+                TaskResult {
+                    project_suspended_via_gui: Some(true),
+                    suspended_via_gui: Some(true),
+                    ..task(2)
+                },
+                SuspendReason::NotSuspended,
+                "Project suspended by user",
+            ),
+            (task(3), SuspendReason::NotSuspended, "Computation error"),
+            (task(4), SuspendReason::NotSuspended, "Uploading"),
+            (
+                TaskResult {
+                    ready_to_report: Some(true),
+                    ..task(5)
+                },
+                SuspendReason::NotSuspended,
+                "Ready to report",
+            ),
+            (
+                TaskResult {
+                    ready_to_report: Some(true),
+                    got_server_ack: Some(true),
+                    ..task(5)
+                },
+                SuspendReason::NotSuspended,
+                "Acknowledged",
+            ),
+            (
+                TaskResult {
+                    exit_status: Some(203),
+                    ..task(6)
+                },
+                SuspendReason::NotSuspended,
+                "Aborted by user",
+            ),
+            (
+                TaskResult {
+                    exit_status: Some(200),
+                    ..task(6)
+                },
+                SuspendReason::NotSuspended,
+                "Aborted: not started by deadline",
+            ),
+            (task(6), SuspendReason::NotSuspended, "Aborted"),
+            (task(5), SuspendReason::NotSuspended, "Error: invalid state"),
+        ];
+
+        for (task, reason, expected) in fixtures {
+            assert_eq!(task_status_line_suspended(&task, reason), expected);
+        }
+    }
+
+    #[test]
+    fn suspend_reasons() {
+        assert_eq!(
+            suspend_reason_text(SuspendReason::from(4)),
+            suspend_reason_text(SuspendReason::UserRequest)
+        );
+        assert_eq!(
+            suspend_reason_text(SuspendReason::from(4099)),
+            "battery thermal protection"
+        );
+        assert_eq!(
+            suspend_reason_text(SuspendReason::from(3)),
+            "unknown reason"
+        );
+        assert_eq!(SuspendReason::from(1024).code(), 1024);
+    }
+}
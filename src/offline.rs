@@ -0,0 +1,192 @@
+//! Reading the state that the daemon keeps on disk, for looking into hosts whose daemon is not
+//! running.
+
+use crate::{
+    models::{ActiveTask, ClientState},
+    util, Error,
+};
+use encoding::{all::ISO_8859_1, DecoderTrap, Encoding};
+use std::{io::Read, path::Path};
+
+/// Reads `client_state.xml` from the BOINC data directory, or wherever `path` points, as
+/// `parse_client_state` does.
+///
+/// # Errors
+///
+/// Fails with `Error::DataParseError` naming the file if it cannot be read, and as
+/// `parse_client_state` otherwise.
+pub fn read_client_state(path: impl AsRef<Path>) -> Result<ClientState, Error> {
+    let path = path.as_ref();
+    let data = std::fs::read(path)
+        .map_err(|e| Error::DataParseError(format!("{}: {}", path.display(), e)))?;
+    parse_client_state(&data[..])
+}
+
+/// Parses `client_state.xml` as written by the daemon into the `ClientState` that `get_state`
+/// would have returned.
+///
+/// The file holds much that replies do not, such as workunits and files, which is left out. Tasks
+/// are given the URL of the project listed before them, as the file leaves it out, and their
+/// `ActiveTask` from the `active_task_set` if they were running. Files that are not UTF-8 are read
+/// as Latin-1.
+///
+/// # Errors
+///
+/// Fails with `Error::DataParseError` for a file that is not well-formed XML, or lacks the
+/// `client_state` root.
+pub fn parse_client_state(mut reader: impl Read) -> Result<ClientState, Error> {
+    let mut data = Vec::new();
+    reader
+        .read_to_end(&mut data)
+        .map_err(|e| Error::DataParseError(format!("Reading client state: {e}")))?;
+    let text = match String::from_utf8(data) {
+        Ok(text) => text,
+        Err(e) => ISO_8859_1
+            .decode(e.as_bytes(), DecoderTrap::Strict)
+            .map_err(|e| Error::DataParseError(format!("Invalid client state: {e}")))?,
+    };
+
+    let root = util::parse_node_lenient(&text, util::Limits::DEFAULT)?;
+    if root.name != "client_state" {
+        return Err(Error::DataParseError(format!(
+            "Invalid root: {}. Expected: client_state",
+            root.name
+        )));
+    }
+    Ok(client_state(&root))
+}
+
+fn client_state(root: &treexml::Element) -> ClientState {
+    let child_text = |node: &treexml::Element, tag: &str| {
+        node.children
+            .iter()
+            .find(|n| n.name == tag)
+            .and_then(|n| util::trimmed_optional(&n.text))
+    };
+    let mut state = ClientState::from(root);
+
+    // Results come after the project they belong to, in the order they are parsed in
+    let mut master_url = None;
+    let mut results = state.results.iter_mut();
+    for node in &root.children {
+        match &*node.name {
+            "project" => master_url = child_text(node, "master_url"),
+            "result" => {
+                if let Some(result) = results.next().filter(|r| r.project_url.is_none()) {
+                    result.project_url.clone_from(&master_url);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let tasks = root
+        .children
+        .iter()
+        .filter(|node| node.name == "active_task_set")
+        .flat_map(|node| &node.children)
+        .filter(|node| node.name == "active_task");
+    for task in tasks {
+        let name = child_text(task, "result_name");
+        let url = child_text(task, "project_master_url");
+        let result = state.results.iter_mut().find(|result| {
+            result.name.is_some()
+                && result.name == name
+                && match (&result.project_url, &url) {
+                    (Some(a), Some(b)) => util::same_master_url(a, b),
+                    _ => true,
+                }
+        });
+        if let Some(result) = result {
+            result.active_task = Some(ActiveTask::from(task));
+        }
+    }
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(xml: &str) -> ClientState {
+        parse_client_state(xml.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn project_urls() {
+        let state = parse(
+            "<client_state>\
+             <result><name>orphan</name></result>\
+             <project><master_url>https://a.example.com/</master_url></project>\
+             <result><name>a1</name></result>\
+             <result><name>a2</name></result>\
+             <project><master_url>https://b.example.com/</master_url></project>\
+             <result><name>b1</name><project_url>https://c.example.com/</project_url></result>\
+             </client_state>",
+        );
+        assert_eq!(
+            state
+                .results
+                .iter()
+                .map(|r| r.project_url.as_deref())
+                .collect::<Vec<_>>(),
+            [
+                None,
+                Some("https://a.example.com/"),
+                Some("https://a.example.com/"),
+                Some("https://c.example.com/"),
+            ]
+        );
+    }
+
+    #[test]
+    fn active_tasks() {
+        // Tasks of the same name at two projects, the second spelling its URL differently
+        let state = parse(
+            "<client_state>\
+             <active_task_set><active_task>\
+             <project_master_url>http://b.example.com</project_master_url>\
+             <result_name>task</result_name><slot>3</slot>\
+             </active_task>\
+             <active_task><result_name>gone</result_name><slot>4</slot></active_task>\
+             </active_task_set>\
+             <project><master_url>https://a.example.com/</master_url></project>\
+             <result><name>task</name></result>\
+             <project><master_url>https://b.example.com/</master_url></project>\
+             <result><name>task</name></result>\
+             </client_state>",
+        );
+        assert_eq!(state.results[0].active_task, None);
+        assert_eq!(
+            state.results[1].active_task.as_ref().and_then(|t| t.slot),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn latin1() {
+        let state = parse_client_state(
+            &b"<client_state><app><user_friendly_name>Caf\xe9</user_friendly_name></app>\
+               </client_state>"[..],
+        )
+        .unwrap();
+        assert_eq!(
+            state.apps[0].user_friendly_name.as_deref(),
+            Some("Caf\u{e9}")
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        for xml in &["<boinc_gui_rpc_reply/>", "<client_state>", ""] {
+            assert!(
+                matches!(
+                    parse_client_state(xml.as_bytes()),
+                    Err(Error::DataParseError(_) | Error::NullError(_))
+                ),
+                "{}",
+                xml
+            );
+        }
+    }
+}
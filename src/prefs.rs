@@ -0,0 +1,126 @@
+//! Changing preferences for a while.
+
+use crate::{
+    errors::Error,
+    models::{Component, GlobalPreferences, RunMode},
+    Client,
+};
+use std::time::Duration;
+
+/// Preferences override in place until `restore` is called, made with
+/// `Client::temporary_prefs_override`.
+///
+/// The guard remembers whether there was an override file before, and what it said, so that
+/// `restore` can put it back as it was, or remove it if there was none. Restoring takes
+/// requests to the daemon, which `Drop` cannot await: a guard dropped without having been
+/// restored, as when the program stops short, leaves the override in place, and only logs a
+/// warning.
+///
+/// The daemon keeps preferences until told otherwise, with nothing like the duration of run
+/// modes, so it cannot undo the override by itself. What it can undo is a timed run mode:
+/// `timed_mode` sets one alongside the override, as a fallback on the daemon's side for changes
+/// that a run mode covers, such as holding back GPU work during a throttle. The daemon goes back
+/// to the previous mode once the duration is up, whatever becomes of the program.
+#[must_use = "the override stays in place until `restore` is called"]
+pub struct PrefsGuard<'a, S> {
+    client: &'a Client<S>,
+    /// The override file as it was, `None` if there was none.
+    previous: Option<GlobalPreferences>,
+    prefs: GlobalPreferences,
+    /// Components set to a timed mode, to be put back by `restore`.
+    timed_modes: Vec<Component>,
+    restored: bool,
+}
+
+impl<'a, S> PrefsGuard<'a, S> {
+    pub(crate) const fn new(
+        client: &'a Client<S>,
+        previous: Option<GlobalPreferences>,
+        prefs: GlobalPreferences,
+    ) -> Self {
+        Self {
+            client,
+            previous,
+            prefs,
+            timed_modes: Vec::new(),
+            restored: false,
+        }
+    }
+
+    /// The override file as written.
+    #[must_use]
+    pub const fn prefs(&self) -> &GlobalPreferences {
+        &self.prefs
+    }
+
+    /// The override file as it was before, `None` if there was none.
+    #[must_use]
+    pub const fn previous(&self) -> Option<&GlobalPreferences> {
+        self.previous.as_ref()
+    }
+
+    /// Whether `restore` succeeded.
+    #[must_use]
+    pub const fn is_restored(&self) -> bool {
+        self.restored
+    }
+}
+
+// As with `Client`, the futures are `Send` if the transport is
+#[allow(clippy::future_not_send)]
+impl<S> PrefsGuard<'_, S>
+where
+    S: tower_service::Service<Vec<treexml::Element>, Response = Vec<treexml::Element>> + Clone,
+    S::Error: Into<Error>,
+{
+    /// Sets the mode of `c` to `m` for `until`, as `Client::set_mode_for` does, and has
+    /// `restore` put the previous mode back should it come first.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidArgument`, without sending anything, for `RunMode::Restore` or
+    /// a zero duration, and as `Client::set_mode_for` otherwise.
+    pub async fn timed_mode(
+        &mut self,
+        c: Component,
+        m: RunMode,
+        until: Duration,
+    ) -> Result<(), Error> {
+        self.client.set_mode_for(c, m, Some(until)).await?;
+        if !self.timed_modes.contains(&c) {
+            self.timed_modes.push(c);
+        }
+        Ok(())
+    }
+
+    /// Puts the override file back as it was, or removes it if there was none, and makes the
+    /// daemon apply that. The modes set with `timed_mode` go back to what they were before too.
+    /// May be called again if it fails.
+    ///
+    /// Changes made to the override file by others, such as the Manager, since the guard was
+    /// made are lost.
+    ///
+    /// # Errors
+    ///
+    /// Fails with the first request that does, leaving what comes after it for the next call.
+    pub async fn restore(&mut self) -> Result<(), Error> {
+        self.client
+            .set_global_prefs_override(self.previous.as_ref())
+            .await?;
+        self.client.read_global_prefs_override().await?;
+        while let Some(&c) = self.timed_modes.last() {
+            self.client.set_mode_for(c, RunMode::Restore, None).await?;
+            self.timed_modes.pop();
+        }
+        self.restored = true;
+        Ok(())
+    }
+}
+
+impl<S> Drop for PrefsGuard<'_, S> {
+    fn drop(&mut self) {
+        if !self.restored {
+            tracing::warn!("Preferences override dropped without being restored");
+        }
+    }
+}
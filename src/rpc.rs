@@ -2,25 +2,68 @@ extern crate crypto;
 
 use bytes::BytesMut;
 use crypto::digest::Digest;
-use encoding::{all::ISO_8859_1, DecoderTrap, EncoderTrap, Encoding};
-use futures::SinkExt;
-use tokio::{
-    io::{AsyncRead, AsyncWrite},
-    net::TcpStream,
-    stream::StreamExt,
+use encoding::{all::ISO_8859_1, DecoderTrap, Encoding};
+use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use std::{borrow::Cow, convert::TryFrom, fmt, io, sync::Arc};
+#[cfg(feature = "tokio")]
+use tokio::net::TcpStream;
+#[cfg(feature = "tokio")]
+use tokio_util::{
+    codec::{Decoder, Encoder},
+    compat::Tokio02AsyncReadCompatExt,
 };
-use tokio_util::codec::{Decoder, Encoder, Framed};
 use tracing::*;
 
-use crate::{errors::Error, util};
+use crate::{
+    errors::{AuthFailure, BoincErrorCode, Error},
+    util, xml, Secret,
+};
+
+pub mod request;
 
-fn compute_nonce_hash(pass: &str, nonce: &str) -> String {
+pub(crate) fn compute_nonce_hash(pass: &str, nonce: &str) -> String {
     let mut digest = crypto::md5::Md5::new();
-    digest.input_str(&format!("{}{}", nonce, pass));
+    // Fed in two parts rather than concatenated, to leave no extra copy of the password behind
+    digest.input_str(nonce);
+    digest.input_str(pass);
     digest.result_str()
 }
 
-const TERMCHAR: u8 = 3;
+/// Password hash that projects know accounts by, salted with the email address or user name.
+pub(crate) fn compute_passwd_hash(pass: &str, account: &str) -> String {
+    let mut digest = crypto::md5::Md5::new();
+    digest.input_str(pass);
+    digest.input_str(account);
+    digest.result_str()
+}
+
+pub(crate) const TERMCHAR: u8 = 3;
+
+/// Children of the root of `frame`, a message without its terminator, failing with
+/// `Error::DataParseError` unless the root is `expected_root`. The XML declaration that some
+/// daemons start with is skipped.
+pub(crate) fn parse_frame(
+    frame: &str,
+    expected_root: &str,
+    lenient: bool,
+    limits: util::Limits,
+) -> Result<Vec<treexml::Element>, Error> {
+    let frame = frame.trim_start_matches("<?xml version=\"1.0\" encoding=\"ISO-8859-1\" ?>");
+    let root_node = if lenient {
+        util::parse_node_lenient(frame, limits)?
+    } else {
+        util::parse_node_limited(frame, limits)?
+    };
+
+    if root_node.name != expected_root {
+        return Err(Error::DataParseError(format!(
+            "Invalid root: {}. Expected: {}",
+            root_node.name, expected_root
+        )));
+    }
+
+    Ok(root_node.children)
+}
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum CodecMode {
@@ -28,9 +71,67 @@ pub enum CodecMode {
     Server,
 }
 
+/// Character encoding of the text on the wire.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Charset {
+    /// UTF-8, as current daemons and projects use. Replies that are not valid UTF-8 are read as
+    /// Latin-1 instead.
+    #[default]
+    Utf8,
+    /// ISO-8859-1, as the GUI RPC protocol originally specified. Characters outside of it are
+    /// sent as XML character references.
+    Latin1,
+}
+
+/// Which way a message went, as told to `BoincCodec::wire_inspector`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+#[derive(Clone)]
+struct WireInspector(Arc<dyn Fn(Direction, &str) + Send + Sync>);
+
+impl fmt::Debug for WireInspector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("WireInspector")
+    }
+}
+
+/// Default of `BoincCodec::max_frame_length`, ample for `get_state` on busy hosts.
+pub const DEFAULT_MAX_FRAME_LENGTH: usize = 64 * 1024 * 1024;
+
+/// Default of `BoincCodec::max_depth`. Replies nest a handful of levels deep.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+/// Default of `BoincCodec::max_elements`, several times what `get_state` takes on busy hosts.
+pub const DEFAULT_MAX_ELEMENTS: usize = 1_000_000;
+
+/// Frames GUI RPC messages: XML documents, each terminated by a `0x03` byte.
+///
+/// Text and attribute values are entity-escaped when encoding, so that they arrive as given.
+/// CDATA is written as is, a `]]>` in it being split across two sections. Characters that XML
+/// does not allow, most control characters among them, fail the message with
+/// `Error::DataParseError` instead of being sent. So do characters outside of Latin-1 in CDATA
+/// with `Charset::Latin1`, as only text can refer to them. Text that is empty or only whitespace
+/// is read back as none.
+///
+/// `decode_frame` and `encode_frame` work on buffers alone, for use with any I/O. With the `tokio`
+/// feature, the codec is also a tokio-util `Decoder` and `Encoder`.
+#[derive(Clone, Debug)]
 pub struct BoincCodec {
     mode: CodecMode,
+    charset: Charset,
+    max_frame_length: usize,
+    limits: util::Limits,
+    lenient: bool,
+    inspector: Option<WireInspector>,
     next_index: usize,
+    /// Set once a frame exceeded `max_frame_length`, after which all input is discarded.
+    overflowed: bool,
+    /// The last message was an authentication request, so the next one answers it.
+    auth_answer_due: bool,
 }
 
 impl BoincCodec {
@@ -38,172 +139,1244 @@ impl BoincCodec {
     pub const fn new(mode: CodecMode) -> Self {
         Self {
             mode,
+            charset: Charset::Utf8,
+            max_frame_length: DEFAULT_MAX_FRAME_LENGTH,
+            limits: util::Limits::DEFAULT,
+            lenient: true,
+            inspector: None,
             next_index: 0,
+            overflowed: false,
+            auth_answer_due: false,
         }
     }
-}
 
-impl Decoder for BoincCodec {
-    type Item = Vec<treexml::Element>;
-    type Error = Error;
+    /// Fails with `Error::FrameTooLong` on frames longer than `len` bytes, rather than buffering
+    /// them without bound. The stream is out of step with the peer afterwards and should be
+    /// dropped. `DEFAULT_MAX_FRAME_LENGTH` by default.
+    #[must_use]
+    pub const fn max_frame_length(mut self, len: usize) -> Self {
+        self.max_frame_length = len;
+        self
+    }
 
-    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        let read_to = src.len();
+    /// Fails with `Error::DataParseError` on messages with elements nested deeper than `depth`,
+    /// before parsing them. `DEFAULT_MAX_DEPTH` by default.
+    #[must_use]
+    pub const fn max_depth(mut self, depth: usize) -> Self {
+        self.limits.max_depth = depth;
+        self
+    }
+
+    /// Fails with `Error::DataParseError` on messages with more than `count` elements, before
+    /// parsing them. `DEFAULT_MAX_ELEMENTS` by default.
+    #[must_use]
+    pub const fn max_elements(mut self, count: usize) -> Self {
+        self.limits.max_elements = count;
+        self
+    }
 
-        if let Some(offset) = src[self.next_index..read_to]
+    /// UTF-8 by default.
+    #[must_use]
+    pub const fn charset(mut self, charset: Charset) -> Self {
+        self.charset = charset;
+        self
+    }
+
+    /// Repairs malformed XML in received messages where possible, such as unescaped ampersands
+    /// and control characters, rather than failing the whole request over it. On by default.
+    #[must_use]
+    pub const fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Calls `inspector` with the text of every message sent and received, such as for dumping
+    /// the XML of a request that fails to parse. Credentials, such as the nonce hash of the
+    /// authentication handshake and account authenticators, are replaced with `***`.
+    #[must_use]
+    pub fn wire_inspector<F>(mut self, inspector: F) -> Self
+    where
+        F: Fn(Direction, &str) + Send + Sync + 'static,
+    {
+        self.inspector = Some(WireInspector(Arc::new(inspector)));
+        self
+    }
+
+    fn inspect(&self, direction: Direction, text: &str) {
+        if let Some(inspector) = &self.inspector {
+            (inspector.0)(direction, &util::redact_secrets(text));
+        }
+    }
+
+    /// Borrows the text from the frame, unless it needs converting from Latin-1.
+    fn decode_text<'a>(&self, data: &'a [u8]) -> Result<Cow<'a, str>, Error> {
+        match std::str::from_utf8(data) {
+            // Latin-1 and UTF-8 agree on ASCII
+            Ok(text) if self.charset == Charset::Utf8 || text.is_ascii() => Ok(Cow::Borrowed(text)),
+            _ => ISO_8859_1
+                .decode(data, DecoderTrap::Strict)
+                .map(Cow::Owned)
+                .map_err(|e| Error::DataParseError(format!("Invalid data received: {e}"))),
+        }
+    }
+}
+
+impl BoincCodec {
+    /// Takes the first message off `src`, or returns `None` until it has been received whole.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::FrameTooLong` once the message outgrows the limit, after which every call
+    /// fails the same, and with `Error::DataParseError` for a message that is not well-formed or
+    /// not in the charset. Errors reported by the daemon are left in the message.
+    pub fn decode_frame(
+        &mut self,
+        src: &mut BytesMut,
+    ) -> Result<Option<Vec<treexml::Element>>, Error> {
+        let read_to = src.len();
+        let frame_end = src[self.next_index..read_to]
             .iter()
             .position(|b| *b == TERMCHAR)
-        {
-            let newline_index = offset + self.next_index;
-            self.next_index = 0;
-            let line = src.split_to(newline_index + 1);
-            let line = &line[..line.len() - 1];
-            let line = ISO_8859_1
-                .decode(line, DecoderTrap::Strict)
-                .map_err(|e| Error::DataParseError(format!("Invalid data received: {}", e)))?;
+            .map(|offset| offset + self.next_index);
 
-            trace!("Received data: {}", line);
+        if self.overflowed || frame_end.unwrap_or(read_to) > self.max_frame_length {
+            // Drop the buffer, so that it stops growing
+            self.overflowed = true;
+            self.next_index = 0;
+            *src = BytesMut::new();
+            return Err(Error::FrameTooLong {
+                limit: self.max_frame_length,
+            });
+        }
 
-            let line = line.trim_start_matches("<?xml version=\"1.0\" encoding=\"ISO-8859-1\" ?>");
-            let root_node = util::parse_node(line)?;
+        if let Some(newline_index) = frame_end {
+            self.next_index = 0;
+            let frame = src.split_to(newline_index + 1);
+            let line = self.decode_text(&frame[..newline_index])?;
+            self.inspect(Direction::Received, &line);
 
             let expected_root = match self.mode {
                 CodecMode::Client => "boinc_gui_rpc_reply",
                 CodecMode::Server => "boinc_gui_rpc_request",
             };
-
-            if root_node.name != expected_root {
-                return Err(Error::DataParseError(format!(
-                    "Invalid root: {}. Expected: {}",
-                    root_node.name, expected_root
-                )));
+            let parsed = parse_frame(&line, expected_root, self.lenient, self.limits);
+            let answer = std::mem::take(&mut self.auth_answer_due);
+            self.auth_answer_due = matches!(&parsed, Ok(items) if is_auth_request(items));
+            if !answer && !self.auth_answer_due {
+                trace!("Received data: {}", util::redact_secrets(&line));
             }
-
-            Ok(Some(root_node.children))
+            parsed.map(Some)
         } else {
             self.next_index = read_to;
+            // Make room for reads to grow along with the frame, as the framing may only ask for
+            // a little more at a time
+            if src.capacity() - read_to < read_to / 2 {
+                src.reserve(read_to.min(self.max_frame_length - read_to));
+            }
             Ok(None)
         }
     }
-}
-
-impl Encoder<Vec<treexml::Element>> for BoincCodec {
-    type Error = Error;
 
-    fn encode(
+    /// Appends `item` to `dst` as a message.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::DataParseError` for characters the charset or XML cannot carry, leaving
+    /// `dst` as it was.
+    pub fn encode_frame(
         &mut self,
         item: Vec<treexml::Element>,
         dst: &mut BytesMut,
-    ) -> Result<(), Self::Error> {
+    ) -> Result<(), Error> {
         let mut out = treexml::Element::new(match self.mode {
             CodecMode::Client => "boinc_gui_rpc_request",
             CodecMode::Server => "boinc_gui_rpc_reply",
         });
         out.children = item;
+        let request = is_auth_request(&out.children);
 
-        let data = format!("{}", out)
-            .replace("<?xml version='1.0'?>", "")
-            .replace(" />", "/>");
+        let start = dst.len();
+        let written = match self.charset {
+            Charset::Utf8 => xml::write_element(dst, &out, char::MAX),
+            Charset::Latin1 => xml::write_element(&mut Latin1Writer(dst), &out, '\u{ff}'),
+        };
+        if let Err(e) = written {
+            // Leave nothing of it to be sent
+            dst.truncate(start);
+            return Err(e);
+        }
 
-        trace!("Sending data: {}", data);
-        dst.extend_from_slice(
-            &ISO_8859_1
-                .encode(&data, EncoderTrap::Strict)
-                .expect("Our data should always be correct"),
-        );
+        let answer = std::mem::replace(&mut self.auth_answer_due, request);
+        if !answer && !request {
+            trace!(
+                "Sending data: {}",
+                util::redact_secrets(&String::from_utf8_lossy(&dst[start..]))
+            );
+        }
+        if self.inspector.is_some() {
+            self.inspect(Direction::Sent, &self.decode_text(&dst[start..])?);
+        }
         dst.extend_from_slice(&[TERMCHAR]);
         Ok(())
     }
 }
 
+#[cfg(feature = "tokio")]
+impl Decoder for BoincCodec {
+    type Item = Vec<treexml::Element>;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.decode_frame(src)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Encoder<Vec<treexml::Element>> for BoincCodec {
+    type Error = Error;
+
+    fn encode(
+        &mut self,
+        item: Vec<treexml::Element>,
+        dst: &mut BytesMut,
+    ) -> Result<(), Self::Error> {
+        self.encode_frame(item, dst)
+    }
+}
+
+/// Whether the message is a step of the authentication handshake, which is kept out of traces
+/// along with its answer, even with the nonce hash redacted.
+fn is_auth_request(items: &[treexml::Element]) -> bool {
+    matches!(items.first().map(|e| &*e.name), Some("auth1" | "auth2"))
+}
+
+/// Encodes text as Latin-1 on the fly. The serializer turns characters outside of it into
+/// character references beforehand, which the daemon's XML parser resolves.
+struct Latin1Writer<'a>(&'a mut BytesMut);
+
+impl fmt::Write for Latin1Writer<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if s.is_ascii() {
+            self.0.extend_from_slice(s.as_bytes());
+            return Ok(());
+        }
+        for c in s.chars() {
+            let byte = u8::try_from(u32::from(c)).map_err(|_| fmt::Error)?;
+            self.0.extend_from_slice(&[byte]);
+        }
+        Ok(())
+    }
+}
+
+/// Authenticated connection to the daemon, for exchanging requests and replies directly.
+///
+/// This is the building block of `Transport`, without reconnection or any other policy. It works
+/// over the `futures` I/O traits, as implemented by the streams of async-std and smol, with no
+/// runtime of its own. Streams of tokio are adapted with `from_tokio_io`.
+///
+/// Dropping a `query`, `send` or `next_reply` future before it completes, for example on a
+/// timeout, may leave a request half written or a reply unread. The stream is then out of step
+/// with the daemon and should be dropped as well. A reply that fails to parse is consumed whole,
+/// on the other hand, so that only its own request fails with `Error::DataParseError`.
+///
+/// # Example
+///
+/// ```rust
+/// # #[cfg(feature = "tokio")]
+/// # fn main() {
+/// # use futures::{SinkExt, TryStreamExt};
+/// # use tokio_util::codec::Decoder;
+/// use boinc_rpc::{
+///     models::HostInfo,
+///     rpc::{BoincCodec, CodecMode},
+///     DaemonStream,
+/// };
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let (client, server) = tokio::io::duplex(4096);
+///
+/// // Stand-in for the daemon
+/// tokio::spawn(async move {
+///     let mut conn = BoincCodec::new(CodecMode::Server).framed(server);
+///     conn.try_next().await.unwrap();
+///     conn.send(vec![treexml::Element::new("authorized")]).await.unwrap();
+///     conn.try_next().await.unwrap();
+///     let mut host_info = treexml::Element::new("host_info");
+///     let mut domain_name = treexml::Element::new("domain_name");
+///     domain_name.text = Some("cruncher".into());
+///     host_info.children.push(domain_name);
+///     conn.send(vec![host_info]).await.unwrap();
+/// });
+///
+/// let mut stream = DaemonStream::from_tokio_io(client, None).await.unwrap();
+/// let reply = stream
+///     .query(vec![treexml::Element::new("get_host_info")])
+///     .await
+///     .unwrap();
+/// let info = HostInfo::from(&reply[0]);
+/// assert_eq!(info.domain_name.as_deref(), Some("cruncher"));
+/// # })
+/// # }
+/// # #[cfg(not(feature = "tokio"))]
+/// # fn main() {}
+/// ```
 pub struct DaemonStream<Io> {
-    conn: Framed<Io, BoincCodec>,
+    io: Io,
+    codec: BoincCodec,
+    /// Received data not decoded into a reply yet.
+    read_buf: BytesMut,
+    /// Request being sent, kept for its allocation.
+    write_buf: BytesMut,
+}
+
+/// Bytes to make room for before each read.
+const READ_CHUNK: usize = 8 * 1024;
+
+impl<Io> DaemonStream<Io> {
+    /// The underlying stream.
+    pub const fn get_ref(&self) -> &Io {
+        &self.io
+    }
+}
+
+/// Stream implementing the tokio I/O traits, adapted for `DaemonStream`.
+#[cfg(feature = "tokio")]
+pub type TokioIo<Io> = tokio_util::compat::Compat<Io>;
+
+#[cfg(feature = "tokio")]
+impl DaemonStream<TokioIo<TcpStream>> {
+    /// Connects with `TCP_NODELAY` set, as every request is a small write followed by waiting
+    /// for the reply.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::ConnectError` if the address does not resolve, with `Error::NetworkError`
+    /// if the connection cannot be made, and as `from_io` while authenticating.
+    pub async fn connect(host: String, password: Option<Secret>) -> Result<Self, Error> {
+        let stream = TcpStream::connect(host).await?;
+        stream.set_nodelay(true)?;
+        Self::from_tokio_io(stream, password).await
+    }
 }
 
-impl DaemonStream<TcpStream> {
-    pub async fn connect(host: String, password: Option<String>) -> Result<Self, Error> {
-        Self::authenticate(TcpStream::connect(host).await?, password).await
+#[cfg(feature = "tokio")]
+impl<Io> DaemonStream<TokioIo<Io>>
+where
+    Io: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    /// Like `from_io`, for streams implementing the tokio I/O traits.
+    ///
+    /// # Errors
+    ///
+    /// As `from_io`.
+    pub async fn from_tokio_io(io: Io, password: Option<Secret>) -> Result<Self, Error> {
+        Self::from_io(io.compat(), password).await
+    }
+}
+
+#[cfg(feature = "async-std")]
+impl DaemonStream<async_std::net::TcpStream> {
+    /// Connects over TCP on the async-std runtime.
+    ///
+    /// # Errors
+    ///
+    /// As `connect`.
+    pub async fn connect_async_std<A>(addr: A, password: Option<Secret>) -> Result<Self, Error>
+    where
+        A: async_std::net::ToSocketAddrs,
+    {
+        let stream = async_std::net::TcpStream::connect(addr).await?;
+        Self::from_io(stream, password).await
     }
 }
 
 impl<Io: AsyncRead + AsyncWrite + Unpin> DaemonStream<Io> {
-    async fn authenticate(io: Io, password: Option<String>) -> Result<Self, Error> {
-        let mut conn = BoincCodec::new(CodecMode::Client).framed(io);
+    /// Authenticates over an already established stream, such as an SSH port forward.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::AuthError` if the daemon asks for a password that is missing or refuses
+    /// it, and with the errors of the stream.
+    pub async fn from_io(io: Io, password: Option<Secret>) -> Result<Self, Error> {
+        Self::with_codec(io, BoincCodec::new(CodecMode::Client), password).await
+    }
+
+    /// Like `from_io`, with the codec configured otherwise, such as for a different charset.
+    ///
+    /// # Errors
+    ///
+    /// As `from_io`.
+    pub async fn with_codec(
+        io: Io,
+        codec: BoincCodec,
+        password: Option<Secret>,
+    ) -> Result<Self, Error> {
+        let mut conn = Self {
+            io,
+            codec,
+            read_buf: BytesMut::new(),
+            write_buf: BytesMut::new(),
+        };
+
+        // The daemon answers auth1 with a nonce to hash the password with, or lets the client in
+        // right away when it has no password
+        let nonce = match conn
+            .authenticate_step(treexml::Element::new("auth1"))
+            .await?
+        {
+            AuthReply::Authorized => return Ok(conn),
+            AuthReply::Unauthorized => return Err(Error::AuthError(AuthFailure::Unauthorized)),
+            AuthReply::Nonce(nonce) => nonce,
+        };
+        let pwd = password
+            .as_ref()
+            .ok_or(Error::AuthError(AuthFailure::PasswordRequired))?;
+        let nonce = nonce.ok_or(Error::AuthError(AuthFailure::MalformedNonce))?;
+
+        let mut nonce_node = treexml::Element::new("nonce_hash");
+        nonce_node.text = Some(compute_nonce_hash(pwd.expose(), &nonce));
+        let mut auth2_node = treexml::Element::new("auth2");
+        auth2_node.children.push(nonce_node);
 
-        let mut out = Some(vec![treexml::Element::new("auth1")]);
+        match conn.authenticate_step(auth2_node).await? {
+            AuthReply::Authorized => Ok(conn),
+            AuthReply::Unauthorized => Err(Error::AuthError(AuthFailure::WrongPassword)),
+            AuthReply::Nonce(_) => Err(Error::DaemonError(
+                "Daemon requested nonce again - could be a bug".into(),
+            )),
+        }
+    }
 
-        let mut nonce_sent = false;
+    /// Flushes pending data and shuts down the stream.
+    ///
+    /// # Errors
+    ///
+    /// Fails with the error of the stream.
+    pub async fn close(&mut self) -> Result<(), Error> {
+        Ok(self.io.close().await?)
+    }
+
+    /// Sends a request and waits for the reply.
+    ///
+    /// # Errors
+    ///
+    /// As `send` and `next_reply`.
+    pub async fn query(
+        &mut self,
+        request_data: Vec<treexml::Element>,
+    ) -> Result<Vec<treexml::Element>, Error> {
+        self.send(request_data).await?;
+        self.next_reply().await
+    }
+
+    /// Sends a request without waiting for the reply. The daemon answers requests in order.
+    ///
+    /// # Errors
+    ///
+    /// Fails with the error of the stream, or with `Error::DataParseError` for a request the codec
+    /// cannot encode, in which case nothing is sent.
+    pub async fn send(&mut self, request_data: Vec<treexml::Element>) -> Result<(), Error> {
+        self.write_buf.clear();
+        self.codec.encode_frame(request_data, &mut self.write_buf)?;
+        self.io.write_all(&self.write_buf).await?;
+        self.io.flush().await?;
+        Ok(())
+    }
+
+    /// Waits for the reply to the oldest request not answered yet.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::ConnectionClosed` if the daemon hangs up between messages, with
+    /// `Error::NetworkError` if it does midway through one, and as `BoincCodec::decode_frame`
+    /// otherwise.
+    pub async fn next_reply(&mut self) -> Result<Vec<treexml::Element>, Error> {
         loop {
-            if let Some(data) = out.take() {
-                conn.send(data).await?;
-
-                let data = conn
-                    .try_next()
-                    .await?
-                    .ok_or_else(|| Error::DaemonError("EOF".into()))?;
-
-                for node in data {
-                    match &*node.name {
-                        "nonce" => {
-                            if nonce_sent {
-                                return Err(Error::DaemonError(
-                                    "Daemon requested nonce again - could be a bug".into(),
-                                ));
-                            }
-                            let mut nonce_node = treexml::Element::new("nonce_hash");
-                            let pwd = password.clone().ok_or_else(|| {
-                                Error::AuthError("Password required for nonce".to_string())
-                            })?;
-                            nonce_node.text = Some(compute_nonce_hash(
-                                &pwd,
-                                &node
-                                    .text
-                                    .ok_or_else(|| Error::AuthError("Invalid nonce".into()))?,
-                            ));
-
-                            let mut auth2_node = treexml::Element::new("auth2");
-                            auth2_node.children.push(nonce_node);
-
-                            out = Some(vec![auth2_node]);
-                            nonce_sent = true;
-                        }
-                        "unauthorized" => {
-                            return Err(Error::AuthError("unauthorized".to_string()));
-                        }
-                        "error" => {
-                            return Err(Error::DaemonError(format!(
-                                "BOINC daemon returned error: {:?}",
-                                node.text
-                            )));
-                        }
-                        "authorized" => {
-                            return Ok(Self { conn });
-                        }
-                        _ => {
-                            return Err(Error::DaemonError(format!(
-                                "Invalid response from daemon: {}",
-                                node.name
-                            )));
-                        }
+            if let Some(reply) = self.codec.decode_frame(&mut self.read_buf)? {
+                return Ok(reply);
+            }
+
+            let len = self.read_buf.len();
+            if self.read_buf.capacity() - len < READ_CHUNK {
+                self.read_buf.reserve(READ_CHUNK);
+            }
+            self.read_buf.resize(len + READ_CHUNK, 0);
+            let res = self.io.read(&mut self.read_buf[len..]).await;
+            self.read_buf
+                .truncate(len + res.as_ref().map_or(0, |read| *read));
+            if res? == 0 {
+                return Err(if self.read_buf.is_empty() {
+                    Error::ConnectionClosed
+                } else {
+                    io::Error::other("bytes remaining on stream").into()
+                });
+            }
+        }
+    }
+
+    /// Sends `request`, one of `auth1` and `auth2`, and reads the answer to it. Nodes that have
+    /// no part in the handshake are skipped.
+    async fn authenticate_step(&mut self, request: treexml::Element) -> Result<AuthReply, Error> {
+        let step = request.name.clone();
+        let reply = self.query(vec![request]).await?;
+
+        for node in &reply {
+            match &*node.name {
+                "nonce" => return Ok(AuthReply::Nonce(util::trimmed_optional(&node.text))),
+                "authorized" => return Ok(AuthReply::Authorized),
+                "unauthorized" => return Ok(AuthReply::Unauthorized),
+                "error" => {
+                    return Err(Error::DaemonError(format!(
+                        "BOINC daemon returned error to {}: {}",
+                        step,
+                        util::trimmed_optional(&node.text).unwrap_or_default()
+                    )))
+                }
+                _ => {}
+            }
+        }
+        Err(Error::DaemonError(if reply.is_empty() {
+            format!("Empty reply to {step}")
+        } else {
+            let names: Vec<_> = reply.iter().map(|node| &*node.name).collect();
+            format!("Unexpected reply to {}: {}", step, names.join(", "))
+        }))
+    }
+}
+
+/// Answer to a step of the authentication handshake.
+enum AuthReply {
+    /// Nonce to hash the password with, `None` if empty.
+    Nonce(Option<String>),
+    Authorized,
+    Unauthorized,
+}
+
+/// Outcome of polling an operation that the daemon completes asynchronously.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PollResult<T> {
+    /// The daemon is still working on it, poll again later.
+    InProgress,
+    Done(T),
+    Failed {
+        code: BoincErrorCode,
+        messages: Vec<String>,
+    },
+}
+
+impl<T> PollResult<T> {
+    #[must_use]
+    pub const fn is_in_progress(&self) -> bool {
+        matches!(self, Self::InProgress)
+    }
+
+    /// Interprets the `error_num` and `message` children of a poll reply, handing the reply and
+    /// its messages to `done` once the operation succeeded.
+    ///
+    /// A missing `error_num` counts as success unless `status_required` is set.
+    pub(crate) fn from_reply<F>(
+        request: &str,
+        node: &treexml::Element,
+        status_required: bool,
+        done: F,
+    ) -> Result<Self, Error>
+    where
+        F: FnOnce(&treexml::Element, Vec<String>) -> Result<T, Error>,
+    {
+        let mut error_num = None;
+        let mut messages = Vec::new();
+        for child in &node.children {
+            match &*child.name {
+                "error_num" => {
+                    error_num = Some(util::parse_node_contents::<i32>(child)?);
+                }
+                "message" | "error_msg" => {
+                    if let Some(msg) = util::trimmed_optional(&util::any_text(child)) {
+                        messages.push(msg);
                     }
                 }
-            } else {
-                return Err(Error::DaemonError("Empty response".into()));
+                _ => {}
             }
         }
+
+        match error_num {
+            None if status_required => Err(Error::NodeNotFound {
+                request: request.into(),
+                node: format!("{}/error_num", node.name),
+            }),
+            None | Some(0) => Ok(Self::Done(done(node, messages)?)),
+            Some(code) => match BoincErrorCode::from(code) {
+                BoincErrorCode::InProgress => Ok(Self::InProgress),
+                code => Ok(Self::Failed { code, messages }),
+            },
+        }
     }
+}
 
-    pub(crate) async fn query(
-        &mut self,
-        request_data: Vec<treexml::Element>,
-    ) -> Result<Vec<treexml::Element>, Error> {
-        self.conn.send(request_data).await?;
-        let data = self
-            .conn
-            .try_next()
-            .await?
-            .ok_or_else(|| Error::DaemonError("EOF".into()))?;
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use super::*;
+    use futures::{SinkExt, TryStreamExt};
+    use proptest::prelude::*;
+    use std::future::Future;
+    use tokio::net::TcpListener;
+    use tokio_util::codec::Framed;
+
+    /// Accepts a single connection and runs `script` against the server side of it.
+    async fn scripted_peer<F, Fut>(script: F) -> String
+    where
+        F: FnOnce(Framed<TcpStream, BoincCodec>) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            script(BoincCodec::new(CodecMode::Server).framed(stream)).await;
+        });
+        addr
+    }
+
+    /// Runs the server side of the nonce handshake, accepting only `password`.
+    async fn nonce_auth(conn: &mut Framed<TcpStream, BoincCodec>, nonce: &str, password: &str) {
+        conn.try_next().await.unwrap();
+        let mut nonce_node = treexml::Element::new("nonce");
+        nonce_node.text = Some(nonce.into());
+        conn.send(vec![nonce_node]).await.unwrap();
+
+        let auth2 = conn.try_next().await.unwrap().unwrap();
+        let hash = auth2[0].children[0].text.clone().unwrap();
+        let reply = if hash == compute_nonce_hash(password, nonce) {
+            "authorized"
+        } else {
+            "unauthorized"
+        };
+        conn.send(vec![treexml::Element::new(reply)]).await.unwrap();
+    }
+
+    /// Answers each request with the next of `replies`, whatever the request.
+    async fn replying_peer(replies: Vec<Vec<treexml::Element>>) -> String {
+        scripted_peer(|mut conn| async move {
+            for reply in replies {
+                conn.try_next().await.unwrap();
+                conn.send(reply).await.unwrap();
+            }
+        })
+        .await
+    }
+
+    fn nonce(nonce: &str) -> treexml::Element {
+        let mut node = treexml::Element::new("nonce");
+        node.text = Some(nonce.into());
+        node
+    }
+
+    /// Decodes a single message body from a reply frame in the given bytes.
+    fn message_body(codec: &mut BoincCodec, body: &[u8]) -> String {
+        let mut frame = BytesMut::from(&b"<boinc_gui_rpc_reply><msgs><msg><body>"[..]);
+        frame.extend_from_slice(body);
+        frame.extend_from_slice(b"</body></msg></msgs></boinc_gui_rpc_reply>\x03");
+        let reply = codec.decode(&mut frame).unwrap().unwrap();
+        assert!(frame.is_empty());
+        reply[0].children[0].children[0].text.clone().unwrap()
+    }
+
+    #[test]
+    fn charsets() {
+        let utf8 = "Привет, café".as_bytes();
+        let latin1 = b"caf\xe9";
+
+        let mut codec = BoincCodec::new(CodecMode::Client);
+        assert_eq!(message_body(&mut codec, utf8), "Привет, café");
+        assert_eq!(message_body(&mut codec, latin1), "café");
+
+        let mut codec = BoincCodec::new(CodecMode::Client).charset(Charset::Latin1);
+        assert_eq!(message_body(&mut codec, latin1), "café");
+        assert_eq!(message_body(&mut codec, "café".as_bytes()), "cafÃ©");
+    }
+
+    #[test]
+    fn utf8_round_trip() {
+        let mut body = treexml::Element::new("body");
+        body.text = Some("Привет, café".into());
+        let mut frame = BytesMut::new();
+        BoincCodec::new(CodecMode::Server)
+            .encode(vec![body], &mut frame)
+            .unwrap();
+        assert!(std::str::from_utf8(&frame)
+            .unwrap()
+            .contains("Привет, café"));
+
+        let reply = BoincCodec::new(CodecMode::Client)
+            .decode(&mut frame)
+            .unwrap()
+            .unwrap();
+        assert_eq!(reply[0].text.as_deref(), Some("Привет, café"));
+    }
+
+    #[test]
+    fn markup_like_payloads() {
+        let mut cmdline = treexml::Element::new("cmdline");
+        cmdline.text = Some("--flag /> <?xml version='1.0'?>".into());
+        let mut prefs = treexml::Element::new("global_preferences");
+        prefs.cdata = Some("<?xml version='1.0'?>\n<global_preferences />".into());
+        let mut request = treexml::Element::new("set_app_config");
+        request.children = vec![cmdline, prefs];
+        let request = vec![request];
+
+        let mut frame = BytesMut::new();
+        BoincCodec::new(CodecMode::Client)
+            .encode(request.clone(), &mut frame)
+            .unwrap();
+        let text = std::str::from_utf8(&frame).unwrap();
+        assert!(text.contains("<cmdline>--flag /&gt; &lt;?xml version='1.0'?&gt;</cmdline>"));
+        assert!(text.contains("<![CDATA[<?xml version='1.0'?>\n<global_preferences />]]>"));
+
+        let decoded = BoincCodec::new(CodecMode::Server)
+            .decode(&mut frame)
+            .unwrap()
+            .unwrap();
+        assert_eq!(decoded, request);
+    }
+
+    #[test]
+    fn latin1_escapes_other_characters() {
+        let mut language = treexml::Element::new("set_language");
+        language.text = Some("日本語 😀 é".into());
+        let mut frame = BytesMut::new();
+        BoincCodec::new(CodecMode::Client)
+            .charset(Charset::Latin1)
+            .encode(vec![language], &mut frame)
+            .unwrap();
+        let expected = b"<set_language>&#26085;&#26412;&#35486; &#128512; \xe9</set_language>";
+        assert!(frame.windows(expected.len()).any(|w| w == &expected[..]));
+
+        let request = BoincCodec::new(CodecMode::Server)
+            .charset(Charset::Latin1)
+            .decode(&mut frame)
+            .unwrap()
+            .unwrap();
+        assert_eq!(request[0].text.as_deref(), Some("日本語 😀 é"));
+    }
+
+    #[test]
+    fn lenient_decoding() {
+        let frame = &b"<boinc_gui_rpc_reply><msgs><msg><project>Cosmology & Friends</project><body><![CDATA[Done\x07]]></body></msg></msgs></boinc_gui_rpc_reply>\x03"[..];
+
+        let reply = BoincCodec::new(CodecMode::Client)
+            .decode(&mut BytesMut::from(frame))
+            .unwrap()
+            .unwrap();
+        let msg = crate::models::Message::from(&reply[0].children[0]);
+        assert_eq!(msg.project_name.as_deref(), Some("Cosmology & Friends"));
+        assert_eq!(msg.body.as_deref(), Some("Done"));
+
+        let res = BoincCodec::new(CodecMode::Client)
+            .lenient(false)
+            .decode(&mut BytesMut::from(frame));
+        assert_eq!(res.unwrap_err().kind(), crate::ErrorKind::Protocol);
+    }
+
+    /// Text that XML can hold.
+    fn xml_text() -> impl Strategy<Value = String> {
+        any::<String>().prop_map(|s| s.chars().filter(|&c| util::is_xml_char(c)).collect())
+    }
+
+    /// Element trees up to `depth` levels deep, with text, CDATA and attributes.
+    fn element(depth: u32, cdata: BoxedStrategy<String>) -> BoxedStrategy<treexml::Element> {
+        let children = if depth == 0 {
+            Just(Vec::new()).boxed()
+        } else {
+            prop::collection::vec(element(depth - 1, cdata.clone()), 0..4).boxed()
+        };
+        (
+            "[a-z_][a-z0-9_.-]{0,8}",
+            prop::option::of(xml_text()),
+            prop::option::of(cdata),
+            prop::collection::hash_map("[a-z_][a-z0-9_]{0,8}", xml_text(), 0..3),
+            children,
+        )
+            .prop_map(|(name, text, cdata, attributes, children)| {
+                let mut node = treexml::Element::new(name);
+                node.text = text;
+                node.cdata = cdata;
+                node.attributes = attributes;
+                node.children = children;
+                node
+            })
+            .boxed()
+    }
+
+    /// Drops text that does not survive parsing, being empty or whitespace, which the parser
+    /// takes for indentation.
+    fn normalized(mut node: treexml::Element) -> treexml::Element {
+        let is_space = |c| matches!(c, ' ' | '\t' | '\n' | '\r');
+        node.text = node.text.filter(|text| !text.chars().all(is_space));
+        node.children = node.children.into_iter().map(normalized).collect();
+        node
+    }
+
+    fn round_trip(charset: Charset, request: &[treexml::Element]) -> Vec<treexml::Element> {
+        let mut frame = BytesMut::new();
+        BoincCodec::new(CodecMode::Client)
+            .charset(charset)
+            .encode(request.to_vec(), &mut frame)
+            .unwrap();
+        let decoded = BoincCodec::new(CodecMode::Server)
+            .charset(charset)
+            .lenient(false)
+            .decode(&mut frame)
+            .unwrap()
+            .unwrap();
+        assert!(frame.is_empty());
+        decoded
+    }
+
+    proptest! {
+        #[test]
+        fn utf8_round_trips(request in prop::collection::vec(element(2, xml_text().boxed()), 1..3)) {
+            let expected: Vec<_> = request.iter().cloned().map(normalized).collect();
+            prop_assert_eq!(round_trip(Charset::Utf8, &request), expected);
+        }
+
+        #[test]
+        fn latin1_round_trips(request in prop::collection::vec(
+            element(2, xml_text().prop_map(|s| s.chars().filter(|&c| c <= '\u{ff}').collect()).boxed()),
+            1..3,
+        )) {
+            let expected: Vec<_> = request.iter().cloned().map(normalized).collect();
+            prop_assert_eq!(round_trip(Charset::Latin1, &request), expected);
+        }
+    }
+
+    #[test]
+    fn unencodable_requests() {
+        let mut frame = BytesMut::from(&b"pending"[..]);
+        let mut name = treexml::Element::new("name");
+        name.text = Some("bell\u{7}".into());
+        assert_eq!(
+            BoincCodec::new(CodecMode::Client).encode(vec![name], &mut frame),
+            Err(Error::DataParseError(
+                "Cannot encode request: U+0007 is not allowed in XML".into()
+            ))
+        );
+        assert_eq!(&frame[..], b"pending");
+
+        // Character references are of no use inside CDATA
+        let mut script = treexml::Element::new("script");
+        script.cdata = Some("日本".into());
+        assert_eq!(
+            BoincCodec::new(CodecMode::Client)
+                .charset(Charset::Latin1)
+                .encode(vec![script], &mut frame),
+            Err(Error::DataParseError(
+                "Cannot encode request: U+65E5 cannot be encoded inside CDATA".into()
+            ))
+        );
+        assert_eq!(&frame[..], b"pending");
+    }
+
+    #[test]
+    fn max_frame_length() {
+        let mut codec = BoincCodec::new(CodecMode::Client).max_frame_length(64);
+        let mut frame =
+            BytesMut::from(&b"<boinc_gui_rpc_reply><success/></boinc_gui_rpc_reply>\x03"[..]);
+        assert!(codec.decode(&mut frame).unwrap().is_some());
+
+        let mut frame = BytesMut::from(&[b'x'; 60][..]);
+        assert_eq!(codec.decode(&mut frame), Ok(None));
+        frame.extend_from_slice(&[b'x'; 10]);
+        assert_eq!(
+            codec.decode(&mut frame),
+            Err(Error::FrameTooLong { limit: 64 })
+        );
+        assert!(frame.is_empty());
+
+        // Whatever follows is discarded
+        frame.extend_from_slice(b"<boinc_gui_rpc_reply><success/></boinc_gui_rpc_reply>\x03");
+        assert_eq!(
+            codec.decode(&mut frame),
+            Err(Error::FrameTooLong { limit: 64 })
+        );
+        assert!(frame.is_empty());
+    }
+
+    #[test]
+    fn nesting_limits() {
+        let mut codec = BoincCodec::new(CodecMode::Client).max_depth(3);
+        let mut frame = BytesMut::from(
+            &b"<boinc_gui_rpc_reply><a><b><c/></b></a></boinc_gui_rpc_reply>\x03\
+               <boinc_gui_rpc_reply><success/></boinc_gui_rpc_reply>\x03"[..],
+        );
+        assert!(matches!(
+            codec.decode(&mut frame),
+            Err(Error::DataParseError(_))
+        ));
+        // Only the offending frame is lost
+        assert_eq!(
+            codec.decode(&mut frame).unwrap().unwrap()[0].name,
+            "success"
+        );
+
+        let mut codec = BoincCodec::new(CodecMode::Client).max_elements(2);
+        let mut frame =
+            BytesMut::from(&b"<boinc_gui_rpc_reply><a/><b/></boinc_gui_rpc_reply>\x03"[..]);
+        assert!(matches!(
+            codec.decode(&mut frame),
+            Err(Error::DataParseError(_))
+        ));
+    }
+
+    #[test]
+    fn malformed_frames() {
+        let mut codec = BoincCodec::new(CodecMode::Client);
+        for garbage in &[
+            &b"<boinc_gui_rpc_reply><results>"[..],
+            b"<html><body>Not found</body></html>",
+            b"<boinc_gui_rpc_reply><a></b></boinc_gui_rpc_reply>",
+            b"",
+        ] {
+            let mut buf = BytesMut::from(*garbage);
+            buf.extend_from_slice(b"\x03<boinc_gui_rpc_reply><success/></boinc_gui_rpc_reply>\x03");
+            assert!(matches!(
+                codec.decode(&mut buf),
+                Err(Error::DataParseError(_))
+            ));
+            assert_eq!(codec.decode(&mut buf).unwrap().unwrap()[0].name, "success");
+            assert!(buf.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn endless_frame() {
+        use tokio::io::AsyncWriteExt;
+
+        let addr = scripted_peer(|mut conn| async move {
+            conn.try_next().await.unwrap();
+            conn.send(vec![treexml::Element::new("authorized")])
+                .await
+                .unwrap();
+            conn.try_next().await.unwrap();
+            let mut stream = conn.into_inner();
+            let chunk = [b'x'; 4096];
+            while stream.write_all(&chunk).await.is_ok() {}
+        })
+        .await;
+
+        let codec = BoincCodec::new(CodecMode::Client).max_frame_length(1024 * 1024);
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut stream = DaemonStream::with_codec(stream.compat(), codec, None)
+            .await
+            .unwrap();
+        let res = tokio::time::timeout(
+            std::time::Duration::from_secs(10),
+            stream.query(vec![treexml::Element::new("get_state")]),
+        )
+        .await
+        .unwrap();
+        assert_eq!(res, Err(Error::FrameTooLong { limit: 1024 * 1024 }));
+    }
+
+    #[test]
+    fn frame_split_across_reads() {
+        use std::fmt::Write;
+
+        let mut results = String::new();
+        for i in 0..50 {
+            write!(results, "<result><name>Задача_{i}_café</name></result>").unwrap();
+        }
+        let input = format!(
+            "<boinc_gui_rpc_reply><results>{results}</results></boinc_gui_rpc_reply>\x03\
+             <boinc_gui_rpc_reply><success/></boinc_gui_rpc_reply>\x03"
+        )
+        .into_bytes();
+
+        // Pieces of 37 bytes also split the two-byte characters now and then
+        let mut codec = BoincCodec::new(CodecMode::Client);
+        let mut buf = BytesMut::new();
+        let mut replies = Vec::new();
+        for piece in input.chunks(37) {
+            buf.extend_from_slice(piece);
+            while let Some(reply) = codec.decode(&mut buf).unwrap() {
+                replies.push(reply);
+            }
+        }
+        assert!(buf.is_empty());
+        assert_eq!(replies.len(), 2);
+
+        let results = &replies[0][0].children;
+        assert_eq!(results.len(), 50);
+        for (i, result) in results.iter().enumerate() {
+            assert_eq!(
+                result.children[0].text.as_deref(),
+                Some(&*format!("Задача_{i}_café"))
+            );
+        }
+        assert_eq!(replies[1][0].name, "success");
+    }
+
+    #[tokio::test]
+    async fn auth_success() {
+        let addr = scripted_peer(|mut conn| async move {
+            nonce_auth(&mut conn, "1700000000.123456", "secret").await;
+        })
+        .await;
+
+        let stream = DaemonStream::connect(addr, Some("secret".into()))
+            .await
+            .unwrap();
+        assert!(stream.get_ref().get_ref().nodelay().unwrap());
+    }
+
+    #[tokio::test]
+    async fn wire_inspector() {
+        let addr = scripted_peer(|mut conn| async move {
+            nonce_auth(&mut conn, "1700000000.123456", "secret").await;
+            conn.try_next().await.unwrap();
+            conn.send(vec![treexml::Element::new("success")])
+                .await
+                .unwrap();
+        })
+        .await;
+
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let codec = BoincCodec::new(CodecMode::Client).wire_inspector({
+            let seen = seen.clone();
+            move |direction, text: &str| seen.lock().unwrap().push((direction, text.to_string()))
+        });
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut stream = DaemonStream::with_codec(stream.compat(), codec, Some("secret".into()))
+            .await
+            .unwrap();
+        stream
+            .query(vec![treexml::Element::new("run_benchmarks")])
+            .await
+            .unwrap();
+
+        let seen = seen.lock().unwrap();
+        let directions: Vec<_> = seen.iter().map(|(direction, _)| *direction).collect();
+        assert_eq!(
+            directions,
+            [
+                Direction::Sent,
+                Direction::Received,
+                Direction::Sent,
+                Direction::Received,
+                Direction::Sent,
+                Direction::Received
+            ]
+        );
+        assert!(seen[1].1.contains("<nonce>1700000000.123456</nonce>"));
+        assert!(seen[2].1.contains("<nonce_hash>***</nonce_hash>"));
+        assert!(!seen[2]
+            .1
+            .contains(&compute_nonce_hash("secret", "1700000000.123456")));
+        assert!(seen[4].1.contains("<run_benchmarks/>"));
+        assert!(seen[5].1.contains("<success/>"));
+    }
+
+    #[tokio::test]
+    async fn auth_wrong_password() {
+        let addr = scripted_peer(|mut conn| async move {
+            nonce_auth(&mut conn, "1700000000.123456", "secret").await;
+        })
+        .await;
+
+        assert_eq!(
+            DaemonStream::connect(addr, Some("guess".into()))
+                .await
+                .err()
+                .unwrap(),
+            Error::AuthError(AuthFailure::WrongPassword)
+        );
+    }
+
+    #[tokio::test]
+    async fn auth_password_required() {
+        let addr = scripted_peer(|mut conn| async move {
+            nonce_auth(&mut conn, "1700000000.123456", "secret").await;
+        })
+        .await;
+
+        assert_eq!(
+            DaemonStream::connect(addr, None).await.err().unwrap(),
+            Error::AuthError(AuthFailure::PasswordRequired)
+        );
+    }
+
+    #[tokio::test]
+    async fn auth_malformed_nonce() {
+        let addr = scripted_peer(|mut conn| async move {
+            conn.try_next().await.unwrap();
+            conn.send(vec![treexml::Element::new("nonce")])
+                .await
+                .unwrap();
+        })
+        .await;
+
+        assert_eq!(
+            DaemonStream::connect(addr, Some("secret".into()))
+                .await
+                .err()
+                .unwrap(),
+            Error::AuthError(AuthFailure::MalformedNonce)
+        );
+    }
+
+    #[tokio::test]
+    async fn auth_unauthorized_without_nonce() {
+        let addr = scripted_peer(|mut conn| async move {
+            conn.try_next().await.unwrap();
+            conn.send(vec![treexml::Element::new("unauthorized")])
+                .await
+                .unwrap();
+        })
+        .await;
+
+        assert_eq!(
+            DaemonStream::connect(addr, Some("secret".into()))
+                .await
+                .err()
+                .unwrap(),
+            Error::AuthError(AuthFailure::Unauthorized)
+        );
+    }
+
+    #[tokio::test]
+    async fn auth_junk_nodes() {
+        let junk = || treexml::Element::new("junk");
+        let addr = replying_peer(vec![
+            vec![junk(), nonce("1700000000.123456"), junk()],
+            vec![junk(), junk(), treexml::Element::new("authorized")],
+        ])
+        .await;
+        assert!(DaemonStream::connect(addr, Some("secret".into()))
+            .await
+            .is_ok());
+
+        let addr = replying_peer(vec![vec![treexml::Element::new("authorized"), junk()]]).await;
+        assert!(DaemonStream::connect(addr, None).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn auth_repeated_nonce() {
+        let addr = replying_peer(vec![
+            vec![nonce("1700000000.123456")],
+            vec![nonce("1700000000.654321")],
+        ])
+        .await;
+        assert_eq!(
+            DaemonStream::connect(addr, Some("secret".into()))
+                .await
+                .err()
+                .unwrap(),
+            Error::DaemonError("Daemon requested nonce again - could be a bug".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn auth_unexpected_replies() {
+        let mut error = treexml::Element::new("error");
+        error.text = Some("unrecognized op".into());
+        let fixtures = vec![
+            (vec![vec![]], "Empty reply to auth1"),
+            (
+                vec![vec![
+                    treexml::Element::new("junk"),
+                    treexml::Element::new("success"),
+                ]],
+                "Unexpected reply to auth1: junk, success",
+            ),
+            (
+                vec![vec![error.clone()]],
+                "BOINC daemon returned error to auth1: unrecognized op",
+            ),
+            (
+                vec![vec![nonce("1700000000.123456")], vec![]],
+                "Empty reply to auth2",
+            ),
+            (
+                vec![
+                    vec![nonce("1700000000.123456")],
+                    vec![treexml::Element::new("success")],
+                ],
+                "Unexpected reply to auth2: success",
+            ),
+            (
+                vec![vec![nonce("1700000000.123456")], vec![error]],
+                "BOINC daemon returned error to auth2: unrecognized op",
+            ),
+        ];
+        for (replies, expected) in fixtures {
+            let addr = replying_peer(replies).await;
+            assert_eq!(
+                DaemonStream::connect(addr, Some("secret".into()))
+                    .await
+                    .err()
+                    .unwrap(),
+                Error::DaemonError(expected.into())
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn eof_during_auth() {
+        let addr = scripted_peer(|mut conn| async move {
+            conn.try_next().await.unwrap();
+        })
+        .await;
+
+        assert_eq!(
+            DaemonStream::connect(addr, None).await.err().unwrap(),
+            Error::ConnectionClosed
+        );
+    }
+
+    #[tokio::test]
+    async fn eof_during_query() {
+        let addr = scripted_peer(|mut conn| async move {
+            conn.try_next().await.unwrap();
+            conn.send(vec![treexml::Element::new("authorized")])
+                .await
+                .unwrap();
+            conn.try_next().await.unwrap();
+        })
+        .await;
+
+        let mut stream = DaemonStream::connect(addr, None).await.unwrap();
+        assert_eq!(
+            stream
+                .query(vec![treexml::Element::new("get_host_info")])
+                .await
+                .err()
+                .unwrap(),
+            Error::ConnectionClosed
+        );
+    }
+
+    #[tokio::test]
+    async fn pipelined_requests() {
+        let addr = scripted_peer(|mut conn| async move {
+            conn.try_next().await.unwrap();
+            conn.send(vec![treexml::Element::new("authorized")])
+                .await
+                .unwrap();
+            while let Ok(Some(req)) = conn.try_next().await {
+                conn.send(req).await.unwrap();
+            }
+        })
+        .await;
 
-        Ok(data)
+        let mut stream = DaemonStream::connect(addr, None).await.unwrap();
+        stream
+            .send(vec![treexml::Element::new("get_state")])
+            .await
+            .unwrap();
+        stream
+            .send(vec![treexml::Element::new("get_results")])
+            .await
+            .unwrap();
+        assert_eq!(stream.next_reply().await.unwrap()[0].name, "get_state");
+        assert_eq!(stream.next_reply().await.unwrap()[0].name, "get_results");
     }
 }
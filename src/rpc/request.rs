@@ -0,0 +1,476 @@
+//! Typed requests, so that the XML sent for each operation is put together in one place.
+
+use crate::{
+    models::{Component, GlobalPreferences, ProjectOp, RunMode, VersionInfo},
+    util, Error, Secret,
+};
+use std::time::Duration;
+
+/// Operation to ask of the daemon, as `Client` sends them.
+///
+/// Converts into the elements that transports take, see `to_element`. Credentials are kept as
+/// `Secret`, so that they are left out of `Debug` output.
+///
+/// Each request frame carries one operation. The daemon looks up a handler for the first tag of
+/// a frame only, ignoring any after it, so operations cannot be batched into one frame.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug)]
+pub enum Request {
+    /// Messages after sequence number `seqno`.
+    GetMessages {
+        seqno: i64,
+    },
+    GetMessageCount,
+    /// Notices after sequence number `seqno`.
+    GetNotices {
+        seqno: i64,
+    },
+    /// Projects listed by the BOINC website.
+    GetAllProjectsList,
+    GetProjectStatus,
+    AcctMgrInfo,
+    AcctMgrRpc {
+        url: String,
+        name: String,
+        password: Secret,
+    },
+    AcctMgrRpcPoll,
+    ProjectAttach {
+        url: String,
+        authenticator: Secret,
+        name: String,
+    },
+    ProjectAttachPoll,
+    ProjectOp {
+        op: ProjectOp,
+        url: String,
+    },
+    /// `passwd_hash` as `rpc::compute_passwd_hash` makes it.
+    LookupAccount {
+        url: String,
+        email_addr: String,
+        passwd_hash: Secret,
+    },
+    LookupAccountPoll,
+    GetProjectConfig {
+        url: String,
+    },
+    GetProjectConfigPoll,
+    ExchangeVersions(VersionInfo),
+    GetResults {
+        active_only: bool,
+    },
+    AbortResult {
+        project_url: String,
+        name: String,
+    },
+    SuspendResult {
+        project_url: String,
+        name: String,
+    },
+    ResumeResult {
+        project_url: String,
+        name: String,
+    },
+    GetFileTransfers,
+    GetStatistics,
+    /// Mode for `duration`, or for good if `None`. See `Request::set_mode` for the combinations
+    /// that make sense.
+    SetMode {
+        component: Component,
+        mode: RunMode,
+        duration: Option<Duration>,
+    },
+    GetHostInfo,
+    GetCcStatus,
+    RunBenchmarks,
+    GetState,
+    SetLanguage {
+        language: String,
+    },
+    GetGlobalPrefsWorking,
+    GetGlobalPrefsOverride,
+    /// Writes the override file, or removes it for `None`. It takes effect with
+    /// `ReadGlobalPrefsOverride`.
+    SetGlobalPrefsOverride {
+        prefs: Option<GlobalPreferences>,
+    },
+    ReadGlobalPrefsOverride,
+}
+
+impl Request {
+    /// Tag of the request element, such as `get_state`.
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::GetMessages { .. } => "get_messages",
+            Self::GetMessageCount => "get_message_count",
+            Self::GetNotices { .. } => "get_notices",
+            Self::GetAllProjectsList => "get_all_projects_list",
+            Self::GetProjectStatus => "get_project_status",
+            Self::AcctMgrInfo => "acct_mgr_info",
+            Self::AcctMgrRpc { .. } => "acct_mgr_rpc",
+            Self::AcctMgrRpcPoll => "acct_mgr_rpc_poll",
+            Self::ProjectAttach { .. } => "project_attach",
+            Self::ProjectAttachPoll => "project_attach_poll",
+            Self::ProjectOp { op, .. } => op.as_str(),
+            Self::LookupAccount { .. } => "lookup_account",
+            Self::LookupAccountPoll => "lookup_account_poll",
+            Self::GetProjectConfig { .. } => "get_project_config",
+            Self::GetProjectConfigPoll => "get_project_config_poll",
+            Self::ExchangeVersions(_) => "exchange_versions",
+            Self::GetResults { .. } => "get_results",
+            Self::AbortResult { .. } => "abort_result",
+            Self::SuspendResult { .. } => "suspend_result",
+            Self::ResumeResult { .. } => "resume_result",
+            Self::GetFileTransfers => "get_file_transfers",
+            Self::GetStatistics => "get_statistics",
+            Self::SetMode { component, .. } => component.set_mode_tag(),
+            Self::GetHostInfo => "get_host_info",
+            Self::GetCcStatus => "get_cc_status",
+            Self::RunBenchmarks => "run_benchmarks",
+            Self::GetState => "get_state",
+            Self::SetLanguage { .. } => "set_language",
+            Self::GetGlobalPrefsWorking => "get_global_prefs_working",
+            Self::GetGlobalPrefsOverride => "get_global_prefs_override",
+            Self::SetGlobalPrefsOverride { .. } => "set_global_prefs_override",
+            Self::ReadGlobalPrefsOverride => "read_global_prefs_override",
+        }
+    }
+
+    /// The request element as sent to the daemon.
+    #[must_use]
+    pub fn to_element(&self) -> treexml::Element {
+        let mut node = treexml::Element::new(self.name());
+        match self {
+            Self::GetMessages { seqno } => node.text = Some(seqno.to_string()),
+            Self::GetNotices { seqno } => util::push_node(&mut node, "seqno", Some(seqno)),
+            Self::AcctMgrRpc {
+                url,
+                name,
+                password,
+            } => {
+                util::push_node(&mut node, "url", Some(url));
+                util::push_node(&mut node, "name", Some(name));
+                util::push_node(&mut node, "password", Some(password.expose()));
+            }
+            Self::ProjectAttach {
+                url,
+                authenticator,
+                name,
+            } => {
+                util::push_node(&mut node, "project_url", Some(url));
+                util::push_node(&mut node, "authenticator", Some(authenticator.expose()));
+                util::push_node(&mut node, "project_name", Some(name));
+            }
+            Self::ProjectOp { url, .. } => util::push_node(&mut node, "project_url", Some(url)),
+            Self::LookupAccount {
+                url,
+                email_addr,
+                passwd_hash,
+            } => {
+                util::push_node(&mut node, "url", Some(url));
+                util::push_node(&mut node, "email_addr", Some(email_addr));
+                util::push_node(&mut node, "passwd_hash", Some(passwd_hash.expose()));
+                util::push_node(&mut node, "ldap_auth", Some(0));
+            }
+            Self::GetProjectConfig { url } => util::push_node(&mut node, "url", Some(url)),
+            // Missing parts are sent empty
+            Self::ExchangeVersions(info) => {
+                for (tag, value) in &[
+                    ("major", info.major),
+                    ("minor", info.minor),
+                    ("release", info.release),
+                ] {
+                    let mut child = treexml::Element::new(*tag);
+                    child.text = value.map(|v| v.to_string());
+                    node.children.push(child);
+                }
+            }
+            Self::GetResults { active_only } => {
+                if *active_only {
+                    util::push_node(&mut node, "active_only", Some(1));
+                }
+            }
+            Self::AbortResult { project_url, name }
+            | Self::SuspendResult { project_url, name }
+            | Self::ResumeResult { project_url, name } => {
+                util::push_node(&mut node, "project_url", Some(project_url));
+                util::push_node(&mut node, "name", Some(name));
+            }
+            Self::SetMode { mode, duration, .. } => {
+                // The daemon takes 0 for good
+                let seconds = duration.unwrap_or_default().as_secs_f64();
+                util::push_node(&mut node, "duration", Some(seconds));
+                node.children.push(treexml::Element::new(mode.as_str()));
+            }
+            Self::SetLanguage { language } => {
+                util::push_node(&mut node, "language", Some(language));
+            }
+            Self::SetGlobalPrefsOverride { prefs: Some(prefs) } => node.children.push(prefs.into()),
+            // Written with an end tag, which the daemon looks for
+            Self::SetGlobalPrefsOverride { prefs: None } => node.text = Some(String::new()),
+            Self::GetMessageCount
+            | Self::GetAllProjectsList
+            | Self::GetProjectStatus
+            | Self::AcctMgrInfo
+            | Self::AcctMgrRpcPoll
+            | Self::ProjectAttachPoll
+            | Self::LookupAccountPoll
+            | Self::GetProjectConfigPoll
+            | Self::GetFileTransfers
+            | Self::GetStatistics
+            | Self::GetHostInfo
+            | Self::GetCcStatus
+            | Self::RunBenchmarks
+            | Self::GetState
+            | Self::GetGlobalPrefsWorking
+            | Self::GetGlobalPrefsOverride
+            | Self::ReadGlobalPrefsOverride => {}
+        }
+        node
+    }
+}
+
+impl Request {
+    /// Cheapest request the daemon answers, for telling that it is there: sent by `Client::ping`
+    /// and the keepalive pings of `Transport`.
+    pub const PING: Self = Self::GetMessageCount;
+
+    /// `Request::SetMode`, checked for a `duration` that makes sense.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::InvalidArgument` for a timed `RunMode::Restore`, which goes back to the
+    /// mode from before a timed one and cannot be timed itself, and for a zero duration, which
+    /// the daemon would take to mean for good.
+    pub fn set_mode(
+        component: Component,
+        mode: RunMode,
+        duration: Option<Duration>,
+    ) -> Result<Self, Error> {
+        match duration {
+            Some(_) if mode == RunMode::Restore => Err(Error::InvalidArgument(
+                "restoring the previous mode cannot be timed".into(),
+            )),
+            Some(duration) if duration == Duration::from_secs(0) => Err(Error::InvalidArgument(
+                "zero duration, use None to set the mode for good".into(),
+            )),
+            _ => Ok(Self::SetMode {
+                component,
+                mode,
+                duration,
+            }),
+        }
+    }
+}
+
+/// Duration of the deprecated `set_mode` taking seconds, 0 meaning for good.
+pub(crate) fn mode_duration(seconds: f64) -> Result<Option<Duration>, Error> {
+    let duration = Duration::try_from_secs_f64(seconds)
+        .map_err(|_| Error::InvalidArgument(format!("duration of {seconds} seconds")))?;
+    Ok(if seconds == 0.0 { None } else { Some(duration) })
+}
+
+impl From<Request> for Vec<treexml::Element> {
+    fn from(request: Request) -> Self {
+        vec![request.to_element()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xml;
+
+    fn show(request: &Request) -> String {
+        let mut out = String::new();
+        xml::write_element(&mut out, &request.to_element(), char::MAX).unwrap();
+        out
+    }
+
+    fn check(cases: Vec<(Request, &str)>) {
+        for (request, expected) in cases {
+            assert_eq!(show(&request), expected, "{request:?}");
+            assert_eq!(request.to_element().name, request.name());
+        }
+    }
+
+    #[test]
+    fn reads() {
+        check(vec![
+            (Request::GetState, "<get_state/>"),
+            (
+                Request::GetMessages { seqno: 42 },
+                "<get_messages>42</get_messages>",
+            ),
+            (
+                Request::GetNotices { seqno: 7 },
+                "<get_notices>\n  <seqno>7</seqno>\n</get_notices>",
+            ),
+            (Request::GetResults { active_only: false }, "<get_results/>"),
+            (
+                Request::GetResults { active_only: true },
+                "<get_results>\n  <active_only>1</active_only>\n</get_results>",
+            ),
+            (
+                Request::ExchangeVersions(VersionInfo {
+                    major: Some(7),
+                    minor: Some(16),
+                    release: None,
+                }),
+                "<exchange_versions>\n  <major>7</major>\n  <minor>16</minor>\n  <release/>\n\
+                 </exchange_versions>",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn writes() {
+        check(vec![
+            (
+                Request::ProjectOp {
+                    op: ProjectOp::NoMoreWork,
+                    url: "https://example.com/".into(),
+                },
+                "<project_nomorework>\n  <project_url>https://example.com/</project_url>\n\
+                 </project_nomorework>",
+            ),
+            (
+                Request::AbortResult {
+                    project_url: "https://example.com/".into(),
+                    name: "wu_1_0".into(),
+                },
+                "<abort_result>\n  <project_url>https://example.com/</project_url>\n  \
+                 <name>wu_1_0</name>\n</abort_result>",
+            ),
+            (
+                Request::SuspendResult {
+                    project_url: "https://example.com/".into(),
+                    name: "wu_1_0".into(),
+                },
+                "<suspend_result>\n  <project_url>https://example.com/</project_url>\n  \
+                 <name>wu_1_0</name>\n</suspend_result>",
+            ),
+            (
+                Request::SetMode {
+                    component: Component::CPU,
+                    mode: RunMode::Never,
+                    duration: Some(Duration::from_secs(3600)),
+                },
+                "<set_run_mode>\n  <duration>3600</duration>\n  <never/>\n</set_run_mode>",
+            ),
+            (
+                Request::SetMode {
+                    component: Component::GPU,
+                    mode: RunMode::Auto,
+                    duration: Some(Duration::from_millis(1500)),
+                },
+                "<set_gpu_mode>\n  <duration>1.5</duration>\n  <auto/>\n</set_gpu_mode>",
+            ),
+            (
+                Request::SetMode {
+                    component: Component::Network,
+                    mode: RunMode::Restore,
+                    duration: None,
+                },
+                "<set_network_mode>\n  <duration>0</duration>\n  <restore/>\n\
+                 </set_network_mode>",
+            ),
+            (
+                Request::SetLanguage {
+                    language: "de_DE".into(),
+                },
+                "<set_language>\n  <language>de_DE</language>\n</set_language>",
+            ),
+            (
+                Request::SetGlobalPrefsOverride {
+                    prefs: Some(GlobalPreferences {
+                        cpu_usage_limit: Some(50.0),
+                        ..GlobalPreferences::default()
+                    }),
+                },
+                "<set_global_prefs_override>\n  <global_preferences>\n    \
+                 <cpu_usage_limit>50</cpu_usage_limit>\n  </global_preferences>\n\
+                 </set_global_prefs_override>",
+            ),
+            (
+                Request::SetGlobalPrefsOverride { prefs: None },
+                "<set_global_prefs_override></set_global_prefs_override>",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn accounts() {
+        check(vec![
+            (
+                Request::ProjectAttach {
+                    url: "https://example.com/".into(),
+                    authenticator: "abc123".into(),
+                    name: "Example & Co".into(),
+                },
+                "<project_attach>\n  <project_url>https://example.com/</project_url>\n  \
+                 <authenticator>abc123</authenticator>\n  \
+                 <project_name>Example &amp; Co</project_name>\n</project_attach>",
+            ),
+            (
+                Request::LookupAccount {
+                    url: "https://example.com/".into(),
+                    email_addr: "me@example.com".into(),
+                    passwd_hash: "0123abcd".into(),
+                },
+                "<lookup_account>\n  <url>https://example.com/</url>\n  \
+                 <email_addr>me@example.com</email_addr>\n  \
+                 <passwd_hash>0123abcd</passwd_hash>\n  <ldap_auth>0</ldap_auth>\n\
+                 </lookup_account>",
+            ),
+            (
+                Request::AcctMgrRpc {
+                    url: "https://bam.example.com/".into(),
+                    name: "me".into(),
+                    password: "hunter2".into(),
+                },
+                "<acct_mgr_rpc>\n  <url>https://bam.example.com/</url>\n  <name>me</name>\n  \
+                 <password>hunter2</password>\n</acct_mgr_rpc>",
+            ),
+            (
+                Request::GetProjectConfig {
+                    url: "https://example.com/".into(),
+                },
+                "<get_project_config>\n  <url>https://example.com/</url>\n\
+                 </get_project_config>",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn credentials_redacted() {
+        let request = Request::AcctMgrRpc {
+            url: "https://bam.example.com/".into(),
+            name: "me".into(),
+            password: "hunter2".into(),
+        };
+        assert!(!format!("{request:?}").contains("hunter2"));
+    }
+
+    #[test]
+    fn set_mode_validated() {
+        let hour = Some(Duration::from_secs(3600));
+        assert!(Request::set_mode(Component::CPU, RunMode::Never, hour).is_ok());
+        assert!(Request::set_mode(Component::CPU, RunMode::Restore, None).is_ok());
+        for (mode, duration) in &[
+            (RunMode::Restore, hour),
+            (RunMode::Auto, Some(Duration::from_secs(0))),
+        ] {
+            let e = Request::set_mode(Component::GPU, *mode, *duration).unwrap_err();
+            assert_eq!(e.kind(), crate::ErrorKind::InvalidInput, "{e:?}");
+        }
+
+        assert_eq!(mode_duration(0.0), Ok(None));
+        assert_eq!(mode_duration(90.5), Ok(Some(Duration::from_millis(90_500))));
+        for seconds in &[-1.0, f64::NAN, f64::INFINITY, 1e20, f64::MAX] {
+            assert!(mode_duration(*seconds).is_err(), "{}", seconds);
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use std::fmt;
+use zeroize::Zeroizing;
+
+/// Password or other credential, wiped from memory when dropped and left out of `Debug`
+/// output.
+///
+/// Only the buffer the secret ends up in is wiped, by `zeroize`. Copies made before it was
+/// wrapped, such as a `String` that grew and moved before being passed in or the `&str` a
+/// `Secret` was made from, are out of its reach. Secrets do not implement `PartialEq`, so that
+/// they are not compared in time that depends on their contents; compare `expose` if need be.
+#[derive(Clone, Default)]
+pub struct Secret(Zeroizing<String>);
+
+impl Secret {
+    #[must_use]
+    pub fn new<S: Into<String>>(secret: S) -> Self {
+        Self(Zeroizing::new(secret.into()))
+    }
+
+    /// The secret itself. Take care not to log it.
+    #[must_use]
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Secret {
+    fn from(secret: String) -> Self {
+        Self::new(secret)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(secret: &str) -> Self {
+        Self::new(secret)
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("***")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::compute_nonce_hash;
+
+    #[test]
+    fn redacted() {
+        let secret = Secret::from("hunter2");
+        assert_eq!(format!("{secret:?}"), "***");
+        assert_eq!(format!("{:?}", Some(secret.clone())), "Some(***)");
+        assert_eq!(secret.expose(), "hunter2");
+    }
+
+    #[test]
+    fn nonce_hash() {
+        let secret = Secret::new(String::from("secret"));
+        assert_eq!(
+            compute_nonce_hash(secret.expose(), "1700000000.5"),
+            "69f972e9283ef3fa59c69ab48c1e4af9"
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! Transport for any async runtime.
+
+use crate::{
+    connection::{ConnectionInfo, ConnectionSource},
+    errors::Error,
+    rpc::DaemonStream,
+};
+use futures::{
+    io::{AsyncRead, AsyncWrite},
+    lock::Mutex,
+    Future,
+};
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+/// `DaemonStream` shared between clones, for use as the transport of a `Client` on runtimes
+/// other than tokio.
+///
+/// Requests are sent one at a time. There is no reconnection or any other policy: after a
+/// failed or cancelled request, the connection is dropped and all further requests fail with
+/// `Error::ConnectionClosed`. Replies that fail to parse are the exception, they only fail their
+/// own request. On tokio, `Transport` is usually the better choice.
+///
+/// ```rust
+/// use boinc_rpc::{Client, DaemonStream, Error, SharedStream};
+///
+/// async fn host_name<Io>(io: Io) -> Result<Option<String>, Error>
+/// where
+///     Io: futures::io::AsyncRead + futures::io::AsyncWrite + Unpin + Send + 'static,
+/// {
+///     let conn = DaemonStream::from_io(io, Some("pass".into())).await?;
+///     let client = Client::new(SharedStream::new(conn));
+///     Ok(client.get_host_info().await?.domain_name)
+/// }
+/// ```
+pub struct SharedStream<Io> {
+    /// `None` once the connection is lost.
+    conn: Arc<Mutex<Option<DaemonStream<Io>>>>,
+}
+
+impl<Io> Clone for SharedStream<Io> {
+    fn clone(&self) -> Self {
+        Self {
+            conn: self.conn.clone(),
+        }
+    }
+}
+
+impl<Io> SharedStream<Io> {
+    #[must_use]
+    pub fn new(conn: DaemonStream<Io>) -> Self {
+        Self {
+            conn: Arc::new(Mutex::new(Some(conn))),
+        }
+    }
+}
+
+/// There is but the one connection.
+impl<Io> ConnectionSource for SharedStream<Io> {
+    fn connection_info(&self) -> ConnectionInfo {
+        ConnectionInfo {
+            number: 1,
+            version: None,
+        }
+    }
+}
+
+impl<Io> tower_service::Service<Vec<treexml::Element>> for SharedStream<Io>
+where
+    Io: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    type Response = Vec<treexml::Element>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Vec<treexml::Element>) -> Self::Future {
+        let conn = self.conn.clone();
+        Box::pin(async move {
+            let mut conn = conn.lock().await;
+            // Taken for the duration of the request, so that it stays gone if the request
+            // fails or is cancelled midway
+            let mut stream = conn.take().ok_or(Error::ConnectionClosed)?;
+            let res = stream.query(req).await;
+            if matches!(&res, Err(e) if !e.keeps_connection()) {
+                return res;
+            }
+            *conn = Some(stream);
+            res
+        })
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use super::*;
+    use crate::{
+        rpc::{BoincCodec, CodecMode},
+        Client,
+    };
+    use futures::{SinkExt, TryStreamExt};
+    use tokio::io::DuplexStream;
+    use tokio_util::{codec::Decoder, compat::Tokio02AsyncReadCompatExt};
+
+    /// Lets the client in and answers `get_host_info` once before hanging up.
+    async fn daemon(io: DuplexStream) {
+        let mut conn = BoincCodec::new(CodecMode::Server).framed(io);
+        conn.try_next().await.unwrap();
+        conn.send(vec![treexml::Element::new("authorized")])
+            .await
+            .unwrap();
+        conn.try_next().await.unwrap();
+        let mut host_info = treexml::Element::new("host_info");
+        let mut domain_name = treexml::Element::new("domain_name");
+        domain_name.text = Some("cruncher".into());
+        host_info.children.push(domain_name);
+        conn.send(vec![host_info]).await.unwrap();
+    }
+
+    /// Queries over the `futures` I/O traits, as async-std and smol streams implement them.
+    async fn query(io: DuplexStream) {
+        let conn = DaemonStream::from_io(io.compat(), None).await.unwrap();
+        let client = Client::new(SharedStream::new(conn));
+        let info = client.get_host_info().await.unwrap();
+        assert_eq!(info.domain_name.as_deref(), Some("cruncher"));
+        assert_eq!(
+            client.get_host_info().await.unwrap_err(),
+            Error::ConnectionClosed
+        );
+        assert_eq!(
+            client.get_host_info().await.unwrap_err(),
+            Error::ConnectionClosed
+        );
+    }
+
+    #[test]
+    fn without_runtime() {
+        let (client, server) = tokio::io::duplex(4096);
+        futures::executor::block_on(async {
+            futures::join!(daemon(server), query(client));
+        });
+    }
+
+    #[tokio::test]
+    async fn tokio_runtime() {
+        let (client, server) = tokio::io::duplex(4096);
+        tokio::spawn(daemon(server));
+        query(client).await;
+    }
+
+    #[cfg(feature = "async-std")]
+    #[test]
+    fn async_std_runtime() {
+        let (client, server) = tokio::io::duplex(4096);
+        async_std::task::block_on(async {
+            async_std::task::spawn(daemon(server));
+            query(client).await;
+        });
+    }
+}
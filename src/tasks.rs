@@ -0,0 +1,148 @@
+//! Acting on many tasks at once.
+
+use crate::{errors::Error, models};
+use std::time::Duration;
+
+/// Outcome of `Client::abort_tasks_matching`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AbortReport {
+    /// Nothing was aborted, `aborted` lists what would have been.
+    pub dry_run: bool,
+    /// Names of the tasks aborted.
+    pub aborted: Vec<String>,
+    /// Names of the tasks the daemon refused to abort, and why.
+    pub failed: Vec<(String, Error)>,
+}
+
+impl AbortReport {
+    /// Whether every matching task was aborted.
+    #[must_use]
+    pub const fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// How `Client::wait_until_idle_with` goes about waiting.
+#[derive(Clone, Debug)]
+pub struct IdleOptions {
+    pub(crate) suspend: bool,
+    pub(crate) grace_period: Duration,
+}
+
+impl Default for IdleOptions {
+    fn default() -> Self {
+        Self {
+            suspend: false,
+            grace_period: Duration::from_secs(60),
+        }
+    }
+}
+
+impl IdleOptions {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether to set the run mode to `RunMode::Never` for good first, so that running tasks
+    /// checkpoint and stop. Off by default, leaving it to the caller.
+    #[must_use]
+    pub const fn suspend(mut self, suspend: bool) -> Self {
+        self.suspend = suspend;
+        self
+    }
+
+    /// How long a task may take to quit or abort before it counts as stuck, rather than as
+    /// running. One minute by default.
+    #[must_use]
+    pub const fn grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+}
+
+/// How `Client::drain` goes about emptying the task queue.
+#[derive(Clone, Debug, Default)]
+pub struct DrainOptions {
+    pub(crate) detach: bool,
+    pub(crate) abort_after: Option<Duration>,
+}
+
+impl DrainOptions {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether to detach from the projects once the queue is empty. Off by default.
+    #[must_use]
+    pub const fn detach(mut self, detach: bool) -> Self {
+        self.detach = detach;
+        self
+    }
+
+    /// How long to let tasks run before aborting those not yet done, so that they are reported
+    /// and the queue empties all the same. `None`, waiting for every task, by default.
+    #[must_use]
+    pub const fn abort_after(mut self, abort_after: Option<Duration>) -> Self {
+        self.abort_after = abort_after;
+        self
+    }
+}
+
+/// Step in draining the task queue, as yielded by `Client::drain`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DrainEvent {
+    /// Tasks on the list as of a poll, including those done but not yet reported.
+    Progress {
+        tasks_remaining: usize,
+        /// Total of the estimates of the tasks, as if they ran one after another. Tasks running
+        /// side by side on several cores finish sooner.
+        cpu_time_remaining: Duration,
+    },
+    /// Tasks not done by the `abort_after` deadline were aborted.
+    Aborted(AbortReport),
+    /// Detached from the project at this URL, once the queue was empty.
+    Detached(String),
+}
+
+/// Progress of `Client::drain`.
+pub(crate) struct Drain {
+    /// Projects to detach from once the queue is empty, or none.
+    pub(crate) detach: Vec<String>,
+    /// When to abort the tasks not yet done, unless already done.
+    pub(crate) abort_at: Option<crate::time::Instant>,
+    /// Events not yet yielded.
+    pub(crate) pending: std::collections::VecDeque<DrainEvent>,
+    /// Whether to wait before polling again.
+    pub(crate) polled: bool,
+    /// The queue is empty, or an error ended the stream.
+    pub(crate) done: bool,
+}
+
+/// Total of the CPU time the tasks are estimated to need still.
+pub(crate) fn cpu_time_remaining(tasks: &[models::TaskResult]) -> Duration {
+    let seconds: f64 = tasks
+        .iter()
+        .filter_map(|task| task.estimated_cpu_time_remaining)
+        .filter(|&seconds| seconds > 0.0)
+        .sum();
+    Duration::from_secs_f64(seconds)
+}
+
+/// Outcome of `Client::wait_until_idle`.
+#[derive(Clone, Debug, Default)]
+pub struct IdleReport {
+    /// Active tasks as of the last poll, none of them executing.
+    pub tasks: Vec<models::TaskResult>,
+    /// Names of the tasks still quitting or aborting after the grace period.
+    pub stuck: Vec<String>,
+}
+
+impl IdleReport {
+    /// Whether every task stopped in time.
+    #[must_use]
+    pub const fn is_complete(&self) -> bool {
+        self.stuck.is_empty()
+    }
+}
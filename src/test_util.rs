@@ -0,0 +1,1063 @@
+//! Stand-ins for the daemon, for testing code that talks to it. Needs the `test-util` feature.
+//!
+//! `MockTransport` is the recommended way to unit test code taking a `Client`: it checks the
+//! requests that the code makes and answers them as scripted, without sockets. `MockDaemon`
+//! runs a daemon on a local port instead, for testing against the whole client stack.
+//!
+//! Replies for either are made of `treexml` elements, which `node` and `parent` build, and
+//! `TaskNode` for the tasks of a `get_results` reply.
+
+use crate::{
+    account,
+    api::{sealed, BoincRpc, PrefsUpdate, RpcFuture, TaskPredicate},
+    connection::{ConnectionInfo, ConnectionSource},
+    deadline,
+    errors::Error,
+    models,
+    rpc::{compute_nonce_hash, BoincCodec, CodecMode, PollResult, TERMCHAR},
+    tasks, util, xml, Secret,
+};
+use futures::{
+    future::{self, AbortHandle, Abortable},
+    pin_mut, Future, SinkExt, TryStreamExt,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    task::{Context, Poll},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    io::AsyncWriteExt,
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::broadcast,
+};
+use tokio_util::codec::Decoder;
+
+type Handler = Arc<dyn Fn(&treexml::Element) -> Reply + Send + Sync>;
+
+enum Reply {
+    Elements(Vec<treexml::Element>),
+    /// Frame to send as is.
+    Raw(String),
+}
+
+#[derive(Default)]
+struct Settings {
+    password: Option<Secret>,
+    handlers: HashMap<String, Handler>,
+    latency: Duration,
+}
+
+#[derive(Default)]
+struct State {
+    settings: Mutex<Settings>,
+    connections: AtomicUsize,
+}
+
+impl State {
+    fn settings(&self) -> std::sync::MutexGuard<'_, Settings> {
+        self.settings.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Daemon listening on TCP, for testing the whole client stack against, reconnection and
+/// authentication included, without a BOINC installation.
+///
+/// Connections are authenticated with the nonce handshake. Requests are then answered by the
+/// handler registered for their tag, or with `<error>unrecognized op</error>` as the daemon
+/// does. Before authenticating, all requests are answered with `<unauthorized/>`, unless there
+/// is no password.
+///
+/// Dropping the `MockDaemon` stops listening and closes all connections.
+///
+/// ```rust
+/// use boinc_rpc::{
+///     test_util::{node, parent, MockDaemon},
+///     Client, Transport,
+/// };
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let daemon = MockDaemon::bind("127.0.0.1:0").await.unwrap();
+/// daemon.set_password(Some("secret"));
+/// daemon.handle("get_host_info", |_| {
+///     vec![parent("host_info", vec![node("domain_name", "cruncher")])]
+/// });
+///
+/// let client = Client::new(Transport::new(daemon.local_addr(), Some("secret")));
+/// let info = client.get_host_info().await.unwrap();
+/// assert_eq!(info.domain_name.as_deref(), Some("cruncher"));
+/// # })
+/// ```
+pub struct MockDaemon {
+    addr: SocketAddr,
+    state: Arc<State>,
+    /// Closes the connections when sent to or dropped.
+    hangup: broadcast::Sender<()>,
+    accept: AbortHandle,
+}
+
+impl MockDaemon {
+    /// Listens on `addr`, such as `"127.0.0.1:0"` for any free port.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NetworkError` if the address cannot be listened on.
+    pub async fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self, Error> {
+        Self::from_listener(TcpListener::bind(addr).await?)
+    }
+
+    /// Accepts connections on an already bound listener.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::NetworkError` if the address of the listener cannot be had.
+    pub fn from_listener(mut listener: TcpListener) -> Result<Self, Error> {
+        let addr = listener.local_addr()?;
+        let state = Arc::new(State::default());
+        let (hangup, _) = broadcast::channel(1);
+        let (accept, registration) = AbortHandle::new_pair();
+
+        let accept_loop = {
+            let state = state.clone();
+            let hangup = hangup.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    state.connections.fetch_add(1, Ordering::SeqCst);
+                    let serve = serve(stream, state.clone());
+                    let mut hangup = hangup.subscribe();
+                    tokio::spawn(async move {
+                        let hangup = hangup.recv();
+                        pin_mut!(hangup, serve);
+                        // Hanging up first, so that no request is answered after it
+                        future::select(hangup, serve).await;
+                    });
+                }
+            }
+        };
+        tokio::spawn(Abortable::new(accept_loop, registration));
+
+        Ok(Self {
+            addr,
+            state,
+            hangup,
+            accept,
+        })
+    }
+
+    #[must_use]
+    pub const fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Password that clients must authenticate with, or `None` to let them in without one. Applies
+    /// to new connections.
+    pub fn set_password<P: Into<Secret>>(&self, password: Option<P>) {
+        self.state.settings().password = password.map(Into::into);
+    }
+
+    /// Answers requests tagged `op` with what `handler` returns for them, in place of any
+    /// handler registered for it before.
+    pub fn handle<F>(&self, op: &str, handler: F)
+    where
+        F: Fn(&treexml::Element) -> Vec<treexml::Element> + Send + Sync + 'static,
+    {
+        self.state
+            .settings()
+            .handlers
+            .insert(op.into(), Arc::new(move |op| Reply::Elements(handler(op))));
+    }
+
+    /// Answers requests tagged `op` with the frame that `handler` returns, sent as is but for
+    /// the terminating byte, such as for testing how clients cope with malformed replies.
+    pub fn handle_raw<F>(&self, op: &str, handler: F)
+    where
+        F: Fn(&treexml::Element) -> String + Send + Sync + 'static,
+    {
+        self.state
+            .settings()
+            .handlers
+            .insert(op.into(), Arc::new(move |op| Reply::Raw(handler(op))));
+    }
+
+    /// Waits for `latency` before every reply, such as for making requests time out.
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.settings().latency = latency;
+    }
+
+    /// Closes all connections, as a daemon restart would. New ones are accepted as before.
+    pub fn disconnect_all(&self) {
+        let _ = self.hangup.send(());
+    }
+
+    /// Number of connections accepted so far.
+    #[must_use]
+    pub fn connections(&self) -> usize {
+        self.state.connections.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for MockDaemon {
+    fn drop(&mut self) {
+        self.accept.abort();
+    }
+}
+
+/// Answers requests on a connection until the client hangs up.
+async fn serve(stream: TcpStream, state: Arc<State>) {
+    let mut conn = BoincCodec::new(CodecMode::Server).framed(stream);
+    let mut session = Session::new(state.settings().password.clone());
+
+    while let Ok(Some(request)) = conn.try_next().await {
+        let reply = match request.first() {
+            Some(op) => session.answer(op, &state),
+            None => continue,
+        };
+
+        let latency = state.settings().latency;
+        if latency > Duration::from_secs(0) {
+            tokio::time::delay_for(latency).await;
+        }
+        let sent = match reply {
+            Reply::Elements(reply) => conn.send(reply).await,
+            Reply::Raw(frame) => {
+                let mut frame = frame.into_bytes();
+                frame.push(TERMCHAR);
+                conn.get_mut().write_all(&frame).await.map_err(Error::from)
+            }
+        };
+        if sent.is_err() {
+            return;
+        }
+    }
+}
+
+/// Authentication progress of a connection.
+struct Session {
+    password: Option<Secret>,
+    authorized: bool,
+    /// Sent in reply to `auth1`, awaiting `auth2`.
+    nonce: Option<String>,
+}
+
+impl Session {
+    const fn new(password: Option<Secret>) -> Self {
+        Self {
+            authorized: password.is_none(),
+            password,
+            nonce: None,
+        }
+    }
+
+    fn answer(&mut self, op: &treexml::Element, state: &State) -> Reply {
+        let reply = match &*op.name {
+            "auth1" => {
+                if self.password.is_none() {
+                    return Reply::Elements(vec![treexml::Element::new("authorized")]);
+                }
+                let nonce = new_nonce();
+                let reply = vec![node("nonce", &nonce)];
+                self.nonce = Some(nonce);
+                reply
+            }
+            "auth2" => {
+                let hash = op
+                    .children
+                    .iter()
+                    .find(|n| n.name == "nonce_hash")
+                    .and_then(|n| util::trimmed_optional(&n.text));
+                self.authorized = match (&self.password, self.nonce.take(), hash) {
+                    (Some(password), Some(nonce), Some(hash)) => {
+                        hash == compute_nonce_hash(password.expose(), &nonce)
+                    }
+                    _ => false,
+                };
+                vec![treexml::Element::new(if self.authorized {
+                    "authorized"
+                } else {
+                    "unauthorized"
+                })]
+            }
+            _ if !self.authorized => vec![treexml::Element::new("unauthorized")],
+            name => {
+                let handler = state.settings().handlers.get(name).cloned();
+                if let Some(handler) = handler {
+                    return handler(op);
+                }
+                vec![node("error", "unrecognized op")]
+            }
+        };
+        Reply::Elements(reply)
+    }
+}
+
+fn new_nonce() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}.{:06}", now.as_secs(), now.subsec_micros())
+}
+
+/// Element holding `text`, such as `<domain_name>cruncher</domain_name>`.
+#[must_use]
+pub fn node(name: &str, text: &str) -> treexml::Element {
+    let mut node = treexml::Element::new(name);
+    node.text = Some(text.into());
+    node
+}
+
+/// Element holding `children`.
+#[must_use]
+pub fn parent(name: &str, children: Vec<treexml::Element>) -> treexml::Element {
+    let mut node = treexml::Element::new(name);
+    node.children = children;
+    node
+}
+
+/// `<result>` of a `get_results` reply, holding only the fields a test sets.
+///
+/// ```
+/// use boinc_rpc::test_util::TaskNode;
+///
+/// let result = TaskNode::new("wu_1_0").state(2).fraction_done(0.5).build();
+/// assert_eq!(result.children.len(), 3);
+/// ```
+#[derive(Clone, Debug)]
+#[must_use]
+pub struct TaskNode {
+    result: treexml::Element,
+    active_task: Option<treexml::Element>,
+}
+
+impl TaskNode {
+    pub fn new(name: &str) -> Self {
+        Self::unnamed().field("name", name)
+    }
+
+    /// Result without a `<name>`, which a daemon should never send.
+    pub fn unnamed() -> Self {
+        Self {
+            result: treexml::Element::new("result"),
+            active_task: None,
+        }
+    }
+
+    /// Adds `<name>text</name>` to the result.
+    pub fn field(mut self, name: &str, text: &str) -> Self {
+        self.result.children.push(node(name, text));
+        self
+    }
+
+    pub fn project_url(self, url: &str) -> Self {
+        self.field("project_url", url)
+    }
+
+    pub fn state(self, state: i64) -> Self {
+        self.field("state", &state.to_string())
+    }
+
+    /// Estimated CPU time remaining, in seconds.
+    pub fn remaining(self, remaining: f64) -> Self {
+        self.field("estimated_cpu_time_remaining", &remaining.to_string())
+    }
+
+    /// Done and waiting to be reported.
+    pub fn ready_to_report(self) -> Self {
+        self.field("ready_to_report", "1")
+    }
+
+    /// Reported and acknowledged by the project.
+    pub fn reported(self) -> Self {
+        self.ready_to_report().field("got_server_ack", "1")
+    }
+
+    /// Gives the result an `<active_task>`, empty unless set up further.
+    pub fn active(mut self) -> Self {
+        self.active_task
+            .get_or_insert_with(|| treexml::Element::new("active_task"));
+        self
+    }
+
+    pub fn active_task_state(self, state: i64) -> Self {
+        self.active_field("active_task_state", &state.to_string())
+    }
+
+    pub fn fraction_done(self, fraction_done: f64) -> Self {
+        self.active_field("fraction_done", &fraction_done.to_string())
+    }
+
+    fn active_field(mut self, name: &str, text: &str) -> Self {
+        self.active_task
+            .get_or_insert_with(|| treexml::Element::new("active_task"))
+            .children
+            .push(node(name, text));
+        self
+    }
+
+    #[must_use]
+    pub fn build(self) -> treexml::Element {
+        let mut result = self.result;
+        result.children.extend(self.active_task);
+        result
+    }
+}
+
+/// Request that `MockTransport` expects, and its outcome.
+pub struct Expect {
+    matcher: Matcher,
+    reply: Result<Vec<treexml::Element>, Error>,
+    latency: Duration,
+}
+
+enum Matcher {
+    Op(String),
+    Request(Vec<treexml::Element>),
+}
+
+impl Matcher {
+    fn matches(&self, request: &[treexml::Element]) -> bool {
+        match self {
+            Self::Op(op) => matches!(request.first(), Some(node) if node.name == *op),
+            Self::Request(expected) => expected.as_slice() == request,
+        }
+    }
+}
+
+impl fmt::Display for Matcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Op(op) => write!(f, "request tagged {op}"),
+            Self::Request(expected) => f.write_str(&show_request(expected)),
+        }
+    }
+}
+
+impl Expect {
+    /// Request tagged `op`, whatever its contents.
+    #[must_use]
+    pub fn op(op: &str) -> Self {
+        Self::with_matcher(Matcher::Op(op.into()))
+    }
+
+    /// Request consisting of exactly these elements.
+    #[must_use]
+    pub fn request(request: Vec<treexml::Element>) -> Self {
+        Self::with_matcher(Matcher::Request(request))
+    }
+
+    fn with_matcher(matcher: Matcher) -> Self {
+        Self {
+            matcher,
+            reply: Ok(vec![treexml::Element::new("success")]),
+            latency: Duration::from_secs(0),
+        }
+    }
+
+    /// Contents of the reply. `<success/>` by default.
+    #[must_use]
+    pub fn reply(mut self, reply: Vec<treexml::Element>) -> Self {
+        self.reply = Ok(reply);
+        self
+    }
+
+    /// Fails the request with `error`, as a transport error rather than a reply.
+    #[must_use]
+    pub fn error(mut self, error: Error) -> Self {
+        self.reply = Err(error);
+        self
+    }
+
+    /// Waits for `latency` before replying, such as for testing timeouts.
+    #[must_use]
+    pub const fn latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+}
+
+/// Expectations not met yet, shared between clones.
+#[derive(Default)]
+struct Expectations(Mutex<VecDeque<Expect>>);
+
+impl Drop for Expectations {
+    fn drop(&mut self) {
+        let left = self.0.get_mut().unwrap_or_else(PoisonError::into_inner);
+        assert!(
+            left.is_empty() || std::thread::panicking(),
+            "MockTransport dropped with {} expected requests left, the first being a {}",
+            left.len(),
+            left[0].matcher
+        );
+    }
+}
+
+/// Transport answering requests as scripted, for unit testing code that takes a `Client`.
+///
+/// Requests must arrive in the order of the `Expect`ations, or else the transport panics. So
+/// does dropping it, the last of its clones that is, with expected requests left over.
+///
+/// ```rust
+/// use boinc_rpc::{
+///     test_util::{node, parent, Expect, MockTransport},
+///     Client, Error,
+/// };
+///
+/// # futures::executor::block_on(async {
+/// let host_info = parent("host_info", vec![node("domain_name", "cruncher")]);
+///
+/// let client = Client::new(
+///     MockTransport::new()
+///         .expect(Expect::op("get_host_info").reply(vec![host_info]))
+///         .expect(Expect::op("set_language").error(Error::ConnectionClosed)),
+/// );
+///
+/// let info = client.get_host_info().await.unwrap();
+/// assert_eq!(info.domain_name.as_deref(), Some("cruncher"));
+/// assert_eq!(client.set_language("de").await, Err(Error::ConnectionClosed));
+/// # })
+/// ```
+#[derive(Clone, Default)]
+pub struct MockTransport {
+    expected: Arc<Expectations>,
+}
+
+impl MockTransport {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expects a request after all those expected so far.
+    #[must_use]
+    pub fn expect(self, expect: Expect) -> Self {
+        self.push(expect);
+        self
+    }
+
+    /// Like `expect`, for scripting further requests once the transport is in use.
+    pub fn push(&self, expect: Expect) {
+        self.expected().push_back(expect);
+    }
+
+    /// Number of expected requests not made yet.
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.expected().len()
+    }
+
+    fn expected(&self) -> std::sync::MutexGuard<'_, VecDeque<Expect>> {
+        self.expected
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Never reconnects, so replies kept for `Client::summary` stay.
+impl ConnectionSource for MockTransport {
+    fn connection_info(&self) -> ConnectionInfo {
+        ConnectionInfo {
+            number: 1,
+            version: None,
+        }
+    }
+}
+
+impl tower::Service<Vec<treexml::Element>> for MockTransport {
+    type Response = Vec<treexml::Element>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Vec<treexml::Element>) -> Self::Future {
+        let expect = {
+            let mut expected = self.expected();
+            match expected.front() {
+                Some(expect) if expect.matcher.matches(&req) => expected.pop_front(),
+                Some(expect) => {
+                    let message = format!(
+                        "MockTransport expected a {}, got {}",
+                        expect.matcher,
+                        show_request(&req)
+                    );
+                    // Released first, so that the panic does not poison it
+                    drop(expected);
+                    panic!("{}", message);
+                }
+                None => None,
+            }
+        };
+        let expect = expect.unwrap_or_else(|| {
+            panic!(
+                "MockTransport expected no more requests, got {}",
+                show_request(&req)
+            )
+        });
+
+        Box::pin(async move {
+            if expect.latency > Duration::from_secs(0) {
+                tokio::time::delay_for(expect.latency).await;
+            }
+            expect.reply
+        })
+    }
+}
+
+/// `BoincRpc` failing every request with the same error.
+///
+/// Meant for testing how code copes with the daemon being out of reach. Code that expects
+/// answers is better off with a `Client` over a `MockTransport`, which is a `BoincRpc` too.
+///
+/// ```rust
+/// use boinc_rpc::{test_util::StubRpc, BoincRpc, Error};
+///
+/// # futures::executor::block_on(async {
+/// let rpc: &dyn BoincRpc = &StubRpc::new(Error::ConnectionClosed);
+/// assert_eq!(rpc.get_results(false).await.unwrap_err(), Error::ConnectionClosed);
+/// # })
+/// ```
+#[derive(Clone, Debug)]
+pub struct StubRpc {
+    error: Error,
+}
+
+impl StubRpc {
+    #[must_use]
+    pub const fn new(error: Error) -> Self {
+        Self { error }
+    }
+
+    fn fail<'a, T: Send + 'a>(&self) -> RpcFuture<'a, Result<T, Error>> {
+        Box::pin(future::ready(Err(self.error.clone())))
+    }
+}
+
+impl Default for StubRpc {
+    /// Fails with `Error::ConnectionClosed`.
+    fn default() -> Self {
+        Self::new(Error::ConnectionClosed)
+    }
+}
+
+impl sealed::Sealed for StubRpc {}
+
+impl BoincRpc for StubRpc {
+    fn get_messages(&self, _: i64) -> RpcFuture<'_, Result<Vec<models::Message>, Error>> {
+        self.fail()
+    }
+
+    fn get_message_count(&self) -> RpcFuture<'_, Result<i64, Error>> {
+        self.fail()
+    }
+
+    fn ping(&self) -> RpcFuture<'_, Result<Duration, Error>> {
+        self.fail()
+    }
+
+    fn get_notices(&self, _: i64) -> RpcFuture<'_, Result<Vec<models::Notice>, Error>> {
+        self.fail()
+    }
+
+    fn wait_for_task<'a>(
+        &'a self,
+        _: &'a str,
+        _: TaskPredicate<'a>,
+        _: Duration,
+        _: Duration,
+    ) -> RpcFuture<'a, Result<models::TaskResult, Error>> {
+        self.fail()
+    }
+
+    fn wait_until_idle(
+        &self,
+        _: Duration,
+        _: Duration,
+    ) -> RpcFuture<'_, Result<tasks::IdleReport, Error>> {
+        self.fail()
+    }
+
+    fn wait_until_idle_with(
+        &self,
+        _: tasks::IdleOptions,
+        _: Duration,
+        _: Duration,
+    ) -> RpcFuture<'_, Result<tasks::IdleReport, Error>> {
+        self.fail()
+    }
+
+    fn get_projects(&self) -> RpcFuture<'_, Result<Vec<models::ProjectInfo>, Error>> {
+        self.fail()
+    }
+
+    fn get_project_status(&self) -> RpcFuture<'_, Result<Vec<models::Project>, Error>> {
+        self.fail()
+    }
+
+    fn get_attached_project<'a>(
+        &'a self,
+        _: &'a str,
+    ) -> RpcFuture<'a, Result<Option<models::Project>, Error>> {
+        self.fail()
+    }
+
+    fn get_account_manager_info(&self) -> RpcFuture<'_, Result<models::AccountManagerInfo, Error>> {
+        self.fail()
+    }
+
+    fn get_account_manager_rpc_status(&self) -> RpcFuture<'_, Result<i32, Error>> {
+        self.fail()
+    }
+
+    fn poll_account_manager_rpc(&self) -> RpcFuture<'_, Result<PollResult<Vec<String>>, Error>> {
+        self.fail()
+    }
+
+    fn poll_project_attach(&self) -> RpcFuture<'_, Result<PollResult<Vec<String>>, Error>> {
+        self.fail()
+    }
+
+    fn start_project_attach<'a>(
+        &'a self,
+        _: &'a str,
+        _: Secret,
+        _: &'a str,
+    ) -> RpcFuture<'a, Result<(), Error>> {
+        self.fail()
+    }
+
+    fn attach_project<'a>(
+        &'a self,
+        _: &'a str,
+        _: Secret,
+        _: &'a str,
+        _: Duration,
+        _: Duration,
+    ) -> RpcFuture<'a, Result<(), Error>> {
+        self.fail()
+    }
+
+    fn project_op<'a>(
+        &'a self,
+        _: &'a str,
+        _: models::ProjectOp,
+    ) -> RpcFuture<'a, Result<(), Error>> {
+        self.fail()
+    }
+
+    fn wait_for_project_update<'a>(
+        &'a self,
+        _: &'a str,
+        _: Duration,
+        _: Duration,
+    ) -> RpcFuture<'a, Result<models::Project, Error>> {
+        self.fail()
+    }
+
+    fn apply_project_plan<'a>(
+        &'a self,
+        _: &'a account::ProjectPlan,
+        _: bool,
+    ) -> RpcFuture<'a, Result<account::PlanReport, Error>> {
+        self.fail()
+    }
+
+    fn add_project_by_account<'a>(
+        &'a self,
+        _: &'a str,
+        _: &'a str,
+        _: Secret,
+        _: &'a account::AddProjectOptions,
+    ) -> RpcFuture<'a, Result<(), account::AddProjectError>> {
+        Box::pin(future::ready(Err(account::AddProjectError {
+            stage: account::AddProjectStage::ProjectConfig,
+            error: self.error.clone(),
+        })))
+    }
+
+    fn start_lookup_account<'a>(
+        &'a self,
+        _: &'a str,
+        _: &'a str,
+        _: Secret,
+        _: bool,
+    ) -> RpcFuture<'a, Result<(), Error>> {
+        self.fail()
+    }
+
+    fn poll_lookup_account(&self) -> RpcFuture<'_, Result<PollResult<Secret>, Error>> {
+        self.fail()
+    }
+
+    fn start_project_config<'a>(&'a self, _: &'a str) -> RpcFuture<'a, Result<(), Error>> {
+        self.fail()
+    }
+
+    fn poll_project_config(
+        &self,
+    ) -> RpcFuture<'_, Result<PollResult<models::ProjectConfig>, Error>> {
+        self.fail()
+    }
+
+    fn connect_to_account_manager<'a>(
+        &'a self,
+        _: &'a str,
+        _: &'a str,
+        _: &'a str,
+    ) -> RpcFuture<'a, Result<bool, Error>> {
+        self.fail()
+    }
+
+    fn attach_account_manager<'a>(
+        &'a self,
+        _: &'a str,
+        _: &'a str,
+        _: &'a str,
+        _: Duration,
+        _: Duration,
+    ) -> RpcFuture<'a, Result<(), Error>> {
+        self.fail()
+    }
+
+    fn exchange_versions<'a>(
+        &'a self,
+        _: Option<&'a models::VersionInfo>,
+    ) -> RpcFuture<'a, Result<models::VersionInfo, Error>> {
+        self.fail()
+    }
+
+    fn get_results(&self, _: bool) -> RpcFuture<'_, Result<Vec<models::TaskResult>, Error>> {
+        self.fail()
+    }
+
+    fn abort_result<'a>(&'a self, _: &'a str, _: &'a str) -> RpcFuture<'a, Result<(), Error>> {
+        self.fail()
+    }
+
+    fn suspend_result<'a>(&'a self, _: &'a str, _: &'a str) -> RpcFuture<'a, Result<(), Error>> {
+        self.fail()
+    }
+
+    fn resume_result<'a>(&'a self, _: &'a str, _: &'a str) -> RpcFuture<'a, Result<(), Error>> {
+        self.fail()
+    }
+
+    fn abort_tasks_matching<'a>(
+        &'a self,
+        _: TaskPredicate<'a>,
+        _: bool,
+    ) -> RpcFuture<'a, Result<tasks::AbortReport, Error>> {
+        self.fail()
+    }
+
+    fn get_tasks<'a>(
+        &'a self,
+        _: &'a models::TaskFilter,
+    ) -> RpcFuture<'a, Result<Vec<models::TaskResult>, Error>> {
+        self.fail()
+    }
+
+    fn deadline_report(&self) -> RpcFuture<'_, Result<Vec<deadline::TaskDeadline>, Error>> {
+        self.fail()
+    }
+
+    fn deadline_report_with<'a>(
+        &'a self,
+        _: &'a deadline::DeadlineOptions,
+    ) -> RpcFuture<'a, Result<Vec<deadline::TaskDeadline>, Error>> {
+        self.fail()
+    }
+
+    fn get_task<'a>(
+        &'a self,
+        _: &'a str,
+    ) -> RpcFuture<'a, Result<Option<models::TaskResult>, Error>> {
+        self.fail()
+    }
+
+    fn get_file_transfers(&self) -> RpcFuture<'_, Result<Vec<models::FileTransfer>, Error>> {
+        self.fail()
+    }
+
+    fn get_statistics(&self) -> RpcFuture<'_, Result<Vec<models::ProjectStatistics>, Error>> {
+        self.fail()
+    }
+
+    fn set_mode_for(
+        &self,
+        _: models::Component,
+        _: models::RunMode,
+        _: Option<Duration>,
+    ) -> RpcFuture<'_, Result<(), Error>> {
+        self.fail()
+    }
+
+    fn set_mode(
+        &self,
+        _: models::Component,
+        _: models::RunMode,
+        _: f64,
+    ) -> RpcFuture<'_, Result<(), Error>> {
+        self.fail()
+    }
+
+    fn snooze(&self, _: Duration) -> RpcFuture<'_, Result<(), Error>> {
+        self.fail()
+    }
+
+    fn unsnooze(&self) -> RpcFuture<'_, Result<(), Error>> {
+        self.fail()
+    }
+
+    fn get_host_info(&self) -> RpcFuture<'_, Result<models::HostInfo, Error>> {
+        self.fail()
+    }
+
+    /// Every section holds the error.
+    fn summary(&self) -> RpcFuture<'_, models::Summary> {
+        use models::SummarySection::Error as Failed;
+        let error = self.error.to_string();
+        Box::pin(future::ready(models::Summary {
+            version: Failed(error.clone()),
+            host: Failed(error.clone()),
+            status: Failed(error.clone()),
+            tasks: Failed(error.clone()),
+            transfers: Failed(error.clone()),
+            projects: Failed(error),
+        }))
+    }
+
+    fn get_cc_status(&self) -> RpcFuture<'_, Result<models::CcStatus, Error>> {
+        self.fail()
+    }
+
+    fn run_benchmarks(&self) -> RpcFuture<'_, Result<(), Error>> {
+        self.fail()
+    }
+
+    fn run_benchmarks_and_wait(
+        &self,
+        _: Duration,
+        _: Duration,
+    ) -> RpcFuture<'_, Result<models::HostInfo, Error>> {
+        self.fail()
+    }
+
+    fn get_state(&self) -> RpcFuture<'_, Result<models::ClientState, Error>> {
+        self.fail()
+    }
+
+    fn set_language<'a>(&'a self, _: &'a str) -> RpcFuture<'a, Result<(), Error>> {
+        self.fail()
+    }
+
+    fn get_global_prefs_working(&self) -> RpcFuture<'_, Result<models::GlobalPreferences, Error>> {
+        self.fail()
+    }
+
+    fn get_global_prefs_override(
+        &self,
+    ) -> RpcFuture<'_, Result<Option<models::GlobalPreferences>, Error>> {
+        self.fail()
+    }
+
+    fn set_global_prefs_override<'a>(
+        &'a self,
+        _: Option<&'a models::GlobalPreferences>,
+    ) -> RpcFuture<'a, Result<(), Error>> {
+        self.fail()
+    }
+
+    fn read_global_prefs_override(&self) -> RpcFuture<'_, Result<(), Error>> {
+        self.fail()
+    }
+
+    fn update_global_prefs<'a>(
+        &'a self,
+        _: PrefsUpdate<'a>,
+    ) -> RpcFuture<'a, Result<models::GlobalPreferences, Error>> {
+        self.fail()
+    }
+}
+
+fn show_request(request: &[treexml::Element]) -> String {
+    let mut out = String::new();
+    for node in request {
+        let _ = xml::write_element(&mut out, node, char::MAX);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Client;
+
+    fn language(code: &str) -> treexml::Element {
+        parent("set_language", vec![node("language", code)])
+    }
+
+    #[tokio::test]
+    async fn scripted_replies() {
+        let host_info = parent("host_info", vec![node("p_ncpus", "8")]);
+
+        let transport = MockTransport::new()
+            .expect(Expect::op("get_host_info").reply(vec![host_info]))
+            .expect(Expect::request(vec![language("de")]))
+            .expect(Expect::op("set_language").error(Error::Timeout));
+        let client = Client::new(transport.clone());
+
+        assert_eq!(client.get_host_info().await.unwrap().p_ncpus, Some(8));
+        assert_eq!(transport.remaining(), 2);
+        client.set_language("de").await.unwrap();
+        assert_eq!(client.set_language("fr").await, Err(Error::Timeout));
+
+        transport.push(Expect::op("set_language"));
+        client.set_language("en").await.unwrap();
+        assert_eq!(transport.remaining(), 0);
+    }
+
+    #[tokio::test]
+    async fn latency() {
+        let client = Client::new(
+            MockTransport::new()
+                .expect(Expect::op("get_host_info").latency(Duration::from_secs(10))),
+        );
+        assert!(
+            tokio::time::timeout(Duration::from_millis(10), client.get_host_info())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "expected a <set_language>")]
+    async fn request_mismatch() {
+        let client =
+            Client::new(MockTransport::new().expect(Expect::request(vec![language("de")])));
+        let _ = client.set_language("fr").await;
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "expected no more requests, got <get_host_info/>")]
+    async fn unexpected_request() {
+        let client = Client::new(MockTransport::new());
+        let _ = client.get_host_info().await;
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "1 expected requests left, the first being a request tagged get_state"
+    )]
+    fn requests_left() {
+        let _ = MockTransport::new().expect(Expect::op("get_state"));
+    }
+}
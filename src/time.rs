@@ -0,0 +1,86 @@
+//! Timers for polling and timeouts, on whichever runtime the caller is on.
+//!
+//! Within a tokio runtime, tokio's timers are used, so that pausing its clock in tests applies.
+//! Elsewhere, `futures-timer` keeps time on a thread of its own, which suits any executor.
+
+use crate::errors::Error;
+use futures::future::{self, Either};
+use std::{future::Future, time::Duration};
+
+#[cfg(not(feature = "tokio"))]
+pub use std::time::Instant;
+#[cfg(feature = "tokio")]
+pub use tokio::time::Instant;
+
+/// Waits for `duration`.
+pub async fn delay_for(duration: Duration) {
+    #[cfg(feature = "tokio")]
+    {
+        if tokio::runtime::Handle::try_current().is_ok() {
+            return tokio::time::delay_for(duration).await;
+        }
+    }
+    futures_timer::Delay::new(duration).await;
+}
+
+/// Calls `fetch` every `poll_interval`, handing each reply to `check` until it makes a result of
+/// one. Transient errors of `fetch` are retried at the next poll. Any other error, of either,
+/// ends polling.
+pub async fn poll_until<R, T, F, Fut, C>(
+    poll_interval: Duration,
+    mut fetch: F,
+    mut check: C,
+) -> Result<T, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<R, Error>>,
+    C: FnMut(R) -> Result<Option<T>, Error>,
+{
+    loop {
+        match fetch().await {
+            Ok(reply) => {
+                if let Some(result) = check(reply)? {
+                    return Ok(result);
+                }
+            }
+            Err(e) if e.is_transient() => tracing::debug!("Polling failed, retrying: {:?}", e),
+            Err(e) => return Err(e),
+        }
+        delay_for(poll_interval).await;
+    }
+}
+
+/// Runs `future` for up to `duration`, failing with `Error::Timeout` after that.
+pub async fn timeout<F: Future>(duration: Duration, future: F) -> Result<F::Output, Error> {
+    let delay = delay_for(duration);
+    futures::pin_mut!(future, delay);
+    match future::select(future, delay).await {
+        Either::Left((output, _)) => Ok(output),
+        Either::Right(((), _)) => Err(Error::Timeout),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn without_tokio() {
+        let res = futures::executor::block_on(timeout(
+            Duration::from_millis(10),
+            future::pending::<()>(),
+        ));
+        assert_eq!(res, Err(Error::Timeout));
+        let res = futures::executor::block_on(timeout(Duration::from_secs(10), async { 1 }));
+        assert_eq!(res, Ok(1));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn paused_clock() {
+        tokio::time::pause();
+        let started = Instant::now();
+        delay_for(Duration::from_secs(3600)).await;
+        assert!(started.elapsed() >= Duration::from_secs(3600));
+    }
+}
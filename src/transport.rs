@@ -0,0 +1,3359 @@
+//! Tower service carrying requests over a daemon connection.
+
+use crate::{
+    connection::{ConnectionInfo, ConnectionSource},
+    errors::{Error, ErrorKind},
+    models::VersionInfo,
+    rpc::{request::Request, BoincCodec, Charset, CodecMode, DaemonStream, Direction, TokioIo},
+    util, Secret,
+};
+use std::path::Path;
+use std::{
+    borrow::Cow,
+    collections::hash_map::RandomState,
+    fmt,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, ToSocketAddrs},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+#[cfg(unix)]
+use tokio::net::UnixStream;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+    sync::{mpsc, oneshot, watch, Mutex, Semaphore},
+    task::JoinHandle,
+};
+#[cfg(feature = "tls")]
+pub use tokio_rustls;
+#[cfg(feature = "tls")]
+use tokio_rustls::{client::TlsStream, webpki::DNSNameRef, TlsConnector};
+use tokio_util::compat::Tokio02AsyncReadCompatExt;
+use tracing::{debug, Instrument};
+
+type IoFuture<Io> = Pin<Box<dyn Future<Output = Result<Io, Error>> + Send + 'static>>;
+
+/// Opens a new stream to the daemon for every connection attempt.
+type Connector<Io> = Box<dyn Fn(TcpOptions) -> IoFuture<Io> + Send + Sync>;
+
+/// Socket options for TCP connections.
+#[derive(Clone, Copy, Debug)]
+struct TcpOptions {
+    nodelay: bool,
+    keepalive: Option<Duration>,
+}
+
+impl Default for TcpOptions {
+    fn default() -> Self {
+        Self {
+            nodelay: true,
+            keepalive: None,
+        }
+    }
+}
+
+type DaemonStreamFuture<Io> =
+    Pin<Box<dyn Future<Output = Result<Connected<Io>, Error>> + Send + 'static>>;
+
+/// Connection lifecycle as seen by `Transport::state_watcher`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConnectionState {
+    /// First connection attempt, made with the first request or `Transport::connect`.
+    Connecting,
+    Ready,
+    /// Connecting again after losing the connection or failing to establish it. `attempt`
+    /// counts consecutive connection attempts, starting at 1 after a lost connection.
+    Reconnecting {
+        attempt: u32,
+    },
+    /// Connection attempt failed or the connection was lost. The next request reconnects.
+    Failed(Error),
+    /// Connection attempts exhausted the `ReconnectPolicy`, never to reconnect.
+    GaveUp(Error),
+    /// Closed with `Transport::close`.
+    Closed,
+}
+
+/// Freshly authenticated connection.
+struct Connected<Io> {
+    conn: DaemonStream<TokioIo<Io>>,
+    /// Daemon version, if the version handshake is enabled.
+    version: Option<VersionInfo>,
+}
+
+/// What is known about the current connection.
+#[derive(Clone, Default)]
+struct Session {
+    addrs: Option<Addrs>,
+    version: Option<VersionInfo>,
+}
+
+enum ConnState<Io> {
+    Connecting(DaemonStreamFuture<Io>),
+    Ready(DaemonStream<TokioIo<Io>>),
+    /// Connection attempt failed in `poll_ready`, to be reported by the following `call`.
+    Failed(Error),
+    /// Closed deliberately, never to reconnect.
+    Closed,
+}
+
+/// Connection to the daemon.
+///
+/// The address and password are kept so that after a failure, such as the daemon restarting,
+/// the next request connects and authenticates anew. The request that hit the failure still
+/// returns the error.
+///
+/// Clones share the connection, so requests from all of them are sent one at a time.
+///
+/// `Io` is the underlying stream, TCP unless created with `from_io`.
+pub struct Transport<Io = TcpStream> {
+    config: Arc<Config<Io>>,
+    request_timeout: Option<Duration>,
+    /// `None` when disconnected.
+    state: Arc<Mutex<Option<ConnState<Io>>>>,
+}
+
+impl<Io> Clone for Transport<Io> {
+    fn clone(&self) -> Self {
+        Self {
+            config: self.config.clone(),
+            request_timeout: self.request_timeout,
+            state: self.state.clone(),
+        }
+    }
+}
+
+/// Told about every RPC a transport makes, for keeping metrics on request counts, latency and
+/// errors.
+///
+/// Requests are named after their tag, such as `get_state`.
+/// Every attempt is told about on its own, retries and keepalive pings included, from sending
+/// the request until the reply is in or the attempt failed. A reply reporting an error counts
+/// as failed. Connecting is told about as `auth` for the authentication handshake, and as
+/// `exchange_versions` for the version handshake if enabled.
+///
+/// Times are taken with `tokio::time::Instant`, so that tests can pause the clock. Closures
+/// taking the same arguments implement this.
+pub trait RpcObserver: Send + Sync {
+    fn on_complete(&self, rpc: &str, duration: Duration, outcome: Result<(), ErrorKind>);
+}
+
+impl<F> RpcObserver for F
+where
+    F: Fn(&str, Duration, Result<(), ErrorKind>) + Send + Sync,
+{
+    fn on_complete(&self, rpc: &str, duration: Duration, outcome: Result<(), ErrorKind>) {
+        self(rpc, duration, outcome);
+    }
+}
+
+#[derive(Clone, Default)]
+struct Observer(Option<Arc<dyn RpcObserver>>);
+
+impl Observer {
+    /// Tells the observer, if any, about `rpc` started at `started`.
+    fn observe(&self, rpc: &str, started: tokio::time::Instant, outcome: Result<(), ErrorKind>) {
+        if let Some(observer) = &self.0 {
+            observer.on_complete(rpc, started.elapsed(), outcome);
+        }
+    }
+}
+
+/// Outcome of a request as told to `RpcObserver`, errors reported in the reply included.
+fn reply_outcome(res: &Result<Vec<treexml::Element>, Error>) -> Result<(), ErrorKind> {
+    match res {
+        Ok(reply) => crate::verify_rpc_reply_contents(reply)
+            .map(drop)
+            .map_err(|e| e.kind()),
+        Err(e) => Err(e.kind()),
+    }
+}
+
+/// Local and peer address of a connection.
+type Addrs = (SocketAddr, SocketAddr);
+
+/// Progress of `ReconnectPolicy`.
+#[derive(Default)]
+struct Reconnects {
+    /// Consecutive failed connection attempts.
+    failures: u32,
+    /// Error of the last attempt, once there are to be no more.
+    gave_up: Option<Error>,
+}
+
+struct Config<Io> {
+    /// Daemon address as configured, for display.
+    endpoint: String,
+    connector: Connector<Io>,
+    /// Finds the addresses of a new connection.
+    addrs_of: fn(&Io) -> Option<Addrs>,
+    version_handshake: bool,
+    /// Fresh codec for new connections.
+    codec: BoincCodec,
+    session: std::sync::Mutex<Session>,
+    /// Connections made so far.
+    connections: AtomicU64,
+    state_tx: watch::Sender<ConnectionState>,
+    /// Kept for handing out clones, never read.
+    state_rx: watch::Receiver<ConnectionState>,
+    password: Option<Secret>,
+    connect_timeout: Option<Duration>,
+    request_timeout: Option<Duration>,
+    retry: Option<RetryPolicy>,
+    reconnect: ReconnectPolicy,
+    reconnects: std::sync::Mutex<Reconnects>,
+    rate_limit: Option<RateLimit>,
+    /// Progress of `rate_limit`, see `RateLimit::delay`.
+    rate_schedule: std::sync::Mutex<Option<tokio::time::Instant>>,
+    keepalive: Option<Duration>,
+    tcp: TcpOptions,
+    observer: Observer,
+}
+
+impl Transport {
+    /// `addr` is an `Address`, such as `"localhost:31416"`, `"localhost"` for the default port,
+    /// or `("::1", 31416)`. Every resolved address is tried in turn when connecting.
+    pub fn new<A, P>(addr: A, password: Option<P>) -> Self
+    where
+        A: Into<Address>,
+        P: Into<Secret>,
+    {
+        let builder = Self::builder(addr);
+        match password {
+            Some(password) => builder.password(password),
+            None => builder,
+        }
+        .build()
+    }
+
+    /// Keeps up to `max_conns` connections to the daemon, so that a slow request does not hold
+    /// up others. See `Pool`.
+    pub fn pooled<A, P>(addr: A, password: Option<P>, max_conns: usize) -> Pool
+    where
+        A: Into<Address>,
+        P: Into<Secret>,
+    {
+        let builder = Self::builder(addr);
+        match password {
+            Some(password) => builder.password(password),
+            None => builder,
+        }
+        .pooled(max_conns)
+    }
+
+    /// Connects to the daemon on this machine owning the given BOINC data directory, using the
+    /// password from `gui_rpc_auth.cfg` and the port from `cc_config.xml` if overridden there.
+    ///
+    /// # Errors
+    ///
+    /// Files that cannot be read fail with `Error::ConnectError`, a password file that is not
+    /// text with `Error::InvalidPasswordError`.
+    pub fn from_data_dir<D: AsRef<Path>>(dir: D) -> Result<Self, Error> {
+        let (port, password) = read_data_dir(dir.as_ref())?;
+        Ok(Self::new((Ipv4Addr::LOCALHOST, port), password))
+    }
+
+    pub fn builder<A: Into<Address>>(addr: A) -> TransportBuilder {
+        let addr = addr.into();
+        TransportBuilder::with_connector(
+            addr.to_string(),
+            Box::new(move |options| Box::pin(connect_tcp(addr.clone(), options))),
+            tcp_addrs,
+        )
+    }
+}
+
+#[cfg(unix)]
+impl Transport<UnixStream> {
+    /// Connects to a daemon listening on a unix domain socket.
+    pub fn new_unix<A: AsRef<Path>, P: Into<Secret>>(path: A, password: Option<P>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let endpoint = format!("{}", path.display());
+        let builder = TransportBuilder::with_connector(
+            endpoint,
+            Box::new(move |_| {
+                let path = path.clone();
+                Box::pin(async move {
+                    UnixStream::connect(&path)
+                        .await
+                        .map_err(|e| Error::ConnectError(format!("{}: {}", path.display(), e)))
+                })
+            }),
+            |_| None,
+        );
+        match password {
+            Some(password) => builder.password(password),
+            None => builder,
+        }
+        .build()
+    }
+}
+
+#[cfg(feature = "tls")]
+impl Transport<TlsStream<TcpStream>> {
+    /// Connects over TLS, for daemons behind a TLS terminating proxy such as stunnel.
+    ///
+    /// `connector` decides which certificates are trusted. `server_name` is the name the
+    /// certificate is checked against.
+    pub fn new_tls<A, P>(
+        addr: A,
+        server_name: &str,
+        password: Option<P>,
+        connector: TlsConnector,
+    ) -> Self
+    where
+        A: Into<Address>,
+        P: Into<Secret>,
+    {
+        let addr = addr.into();
+        let endpoint = addr.to_string();
+        let server_name = server_name.to_string();
+        let connector = Box::new(move |options| -> IoFuture<TlsStream<TcpStream>> {
+            let addr = addr.clone();
+            let server_name = server_name.clone();
+            let connector = connector.clone();
+            Box::pin(async move {
+                let name = DNSNameRef::try_from_ascii_str(&server_name).map_err(|_| {
+                    Error::ConnectError(format!("Invalid server name: {server_name}"))
+                })?;
+                let stream = connect_tcp(addr, options).await?;
+                connector.connect(name, stream).await.map_err(|e| {
+                    Error::ConnectError(format!("TLS handshake with {server_name} failed: {e}"))
+                })
+            })
+        });
+        let builder = TransportBuilder::with_connector(endpoint, connector, |stream| {
+            tcp_addrs(stream.get_ref().0)
+        });
+        match password {
+            Some(password) => builder.password(password),
+            None => builder,
+        }
+        .build()
+    }
+}
+
+impl<Io: AsyncRead + AsyncWrite + Unpin + Send + 'static> Transport<Io> {
+    /// Runs the daemon protocol over a stream opened by the caller, for example a tunnel or an
+    /// in-memory pipe.
+    ///
+    /// The stream cannot be reopened, so once it fails all further requests fail with
+    /// `Error::ConnectionClosed`.
+    pub fn from_io<P: Into<Secret>>(io: Io, password: Option<P>) -> Self {
+        let io = std::sync::Mutex::new(Some(io));
+        let builder = TransportBuilder::with_connector(
+            "stream".into(),
+            Box::new(move |_| {
+                let io = io.lock().ok().and_then(|mut io| io.take());
+                Box::pin(async move { io.ok_or(Error::ConnectionClosed) })
+            }),
+            |_| None,
+        );
+        match password {
+            Some(password) => builder.password(password),
+            None => builder,
+        }
+        .build()
+    }
+}
+
+impl<Io> Transport<Io>
+where
+    Io: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    /// Connects and authenticates now rather than with the first request, so that a wrong
+    /// address or password is reported right away. Does nothing when already connected.
+    ///
+    /// # Errors
+    ///
+    /// Fails with the error of the last connection attempt, as the reconnect policy has it, and
+    /// with `Error::ConnectionClosed` once closed.
+    pub async fn connect(&self) -> Result<(), Error> {
+        let mut state = self.state.lock().await;
+        let conn = match state.take() {
+            Some(ConnState::Ready(conn)) => conn,
+            Some(ConnState::Connecting(future)) => self.config.connected(future.await)?,
+            Some(ConnState::Failed(_)) | None => {
+                self.config.connected(reconnect(&self.config).await)?
+            }
+            Some(ConnState::Closed) => {
+                *state = Some(ConnState::Closed);
+                return Err(Error::ConnectionClosed);
+            }
+        };
+        *state = Some(ConnState::Ready(conn));
+        Ok(())
+    }
+
+    /// Closes the connection cleanly, flushing pending data and shutting down the stream. The
+    /// transport, and all its clones, then fail every request with `Error::ConnectionClosed`
+    /// rather than reconnecting. Closing again does nothing.
+    ///
+    /// # Errors
+    ///
+    /// Fails with the error of shutting down the stream. The transport is closed all the same.
+    pub async fn close(&self) -> Result<(), Error> {
+        let mut state = self.state.lock().await;
+        let prev = state.replace(ConnState::Closed);
+        self.config.disconnected(ConnectionState::Closed);
+        match prev {
+            Some(ConnState::Ready(mut conn)) => conn.close().await,
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<Io> Transport<Io> {
+    #[must_use]
+    pub const fn request_timeout(&self) -> Option<Duration> {
+        self.request_timeout
+    }
+
+    /// Changes the request timeout for subsequent calls. See `TransportBuilder::request_timeout`.
+    pub fn set_request_timeout(&mut self, timeout: Option<Duration>) {
+        self.request_timeout = timeout;
+    }
+
+    /// Daemon address as it was given: the address for TCP, the path for unix sockets, and
+    /// `"stream"` for `from_io`.
+    #[must_use]
+    pub fn endpoint(&self) -> &str {
+        &self.config.endpoint
+    }
+
+    /// Address of the daemon while connected over TCP.
+    #[must_use]
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.config.session().addrs.map(|(_, peer)| peer)
+    }
+
+    /// Local address of the connection while connected over TCP.
+    #[must_use]
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.config.session().addrs.map(|(local, _)| local)
+    }
+
+    /// Daemon version reported by the version handshake while connected. See
+    /// `TransportBuilder::version_handshake`.
+    #[must_use]
+    pub fn server_version(&self) -> Option<VersionInfo> {
+        self.config.session().version
+    }
+
+    /// Follows the connection state, for example to show whether the daemon is reachable.
+    ///
+    /// Like any `watch` channel, the receiver only sees the latest state, so short-lived states
+    /// may be skipped.
+    #[must_use]
+    pub fn state_watcher(&self) -> watch::Receiver<ConnectionState> {
+        self.config.state_rx.clone()
+    }
+}
+
+impl<Io> Config<Io> {
+    fn session(&self) -> Session {
+        self.session
+            .lock()
+            .map(|session| session.clone())
+            .unwrap_or_default()
+    }
+
+    fn set_session(&self, session: Session) {
+        if let Ok(mut v) = self.session.lock() {
+            *v = session;
+        }
+    }
+
+    fn set_state(&self, state: ConnectionState) {
+        // Cannot fail, as `state_rx` is kept around
+        let _ = self.state_tx.broadcast(state);
+    }
+
+    /// Records the outcome of a connection attempt.
+    fn connected(
+        &self,
+        res: Result<Connected<Io>, Error>,
+    ) -> Result<DaemonStream<TokioIo<Io>>, Error> {
+        match res {
+            Ok(connected) => {
+                if let Ok(mut reconnects) = self.reconnects.lock() {
+                    reconnects.failures = 0;
+                }
+                self.connections.fetch_add(1, Ordering::Relaxed);
+                self.set_session(Session {
+                    addrs: (self.addrs_of)(connected.conn.get_ref().get_ref()),
+                    version: connected.version,
+                });
+                self.set_state(ConnectionState::Ready);
+                Ok(connected.conn)
+            }
+            Err(e) => {
+                if let Some(state) = self.connect_failed(&e) {
+                    self.set_state(state);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Counts a failed connection attempt, returning the state to report if there is news.
+    fn connect_failed(&self, e: &Error) -> Option<ConnectionState> {
+        let gave_up = {
+            let mut reconnects = self.reconnects.lock().ok()?;
+            if reconnects.gave_up.is_some() {
+                return None;
+            }
+            reconnects.failures = reconnects.failures.saturating_add(1);
+            let exhausted = self.reconnect.exhausted(reconnects.failures);
+            if exhausted {
+                reconnects.gave_up = Some(e.clone());
+            }
+            exhausted
+        };
+        Some(if gave_up {
+            ConnectionState::GaveUp(e.clone())
+        } else {
+            ConnectionState::Failed(e.clone())
+        })
+    }
+
+    fn gave_up(&self) -> Option<Error> {
+        self.reconnects.lock().ok()?.gave_up.clone()
+    }
+
+    fn disconnected(&self, state: ConnectionState) {
+        self.set_session(Session::default());
+        self.set_state(state);
+    }
+
+    /// Waits until the rate limit lets another request through.
+    async fn throttle(&self) {
+        let delay = match (&self.rate_limit, self.rate_schedule.lock()) {
+            (Some(limit), Ok(mut schedule)) => {
+                limit.delay(&mut schedule, tokio::time::Instant::now())
+            }
+            _ => return,
+        };
+        if delay > Duration::from_secs(0) {
+            debug!("Rate limited, waiting {:?}", delay);
+            tokio::time::delay_for(delay).await;
+        }
+    }
+}
+
+impl<Io> ConnectionSource for Config<Io> {
+    fn connection_info(&self) -> ConnectionInfo {
+        ConnectionInfo {
+            number: self.connections.load(Ordering::Relaxed),
+            version: self.session().version,
+        }
+    }
+}
+
+impl<Io> ConnectionSource for Transport<Io> {
+    fn connection_info(&self) -> ConnectionInfo {
+        self.config.connection_info()
+    }
+}
+
+pub struct TransportBuilder<Io = TcpStream> {
+    config: Config<Io>,
+}
+
+impl<Io> TransportBuilder<Io> {
+    fn with_connector(
+        endpoint: String,
+        connector: Connector<Io>,
+        addrs_of: fn(&Io) -> Option<Addrs>,
+    ) -> Self {
+        let (state_tx, state_rx) = watch::channel(ConnectionState::Connecting);
+        Self {
+            config: Config {
+                endpoint,
+                connector,
+                addrs_of,
+                version_handshake: false,
+                codec: BoincCodec::new(CodecMode::Client),
+                session: std::sync::Mutex::new(Session::default()),
+                connections: AtomicU64::new(0),
+                state_tx,
+                state_rx,
+                password: None,
+                connect_timeout: None,
+                request_timeout: None,
+                retry: None,
+                reconnect: ReconnectPolicy::default(),
+                reconnects: std::sync::Mutex::new(Reconnects::default()),
+                rate_limit: None,
+                rate_schedule: std::sync::Mutex::new(None),
+                keepalive: None,
+                tcp: TcpOptions::default(),
+                observer: Observer::default(),
+            },
+        }
+    }
+
+    #[must_use]
+    pub fn password<P: Into<Secret>>(mut self, password: P) -> Self {
+        self.config.password = Some(password.into());
+        self
+    }
+
+    /// Limits how long connecting and authenticating may take before failing with
+    /// `Error::Timeout`. Unlimited by default.
+    #[must_use]
+    pub const fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.config.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Limits how long the daemon may take to reply to a request before failing with
+    /// `Error::Timeout`. The connection is then dropped, as a late reply would be mistaken for
+    /// the reply to the next request, and the next request reconnects. Unlimited by default.
+    #[must_use]
+    pub const fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.config.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Retries requests failing with transient errors. Off by default.
+    #[must_use]
+    pub const fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.config.retry = Some(policy);
+        self
+    }
+
+    /// Controls reconnecting after the connection is lost or fails to be established. By default
+    /// the transport keeps trying forever, see `ReconnectPolicy::default`.
+    #[must_use]
+    pub const fn reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.config.reconnect = policy;
+        self
+    }
+
+    /// Spaces out requests so as not to take the daemon away from its actual work, for example
+    /// when a dashboard polls `get_state` in a tight loop. Requests over the limit wait rather
+    /// than fail. Off by default.
+    #[must_use]
+    pub const fn rate_limit(mut self, limit: RateLimit) -> Self {
+        self.config.rate_limit = Some(limit);
+        self
+    }
+
+    /// Pings the daemon after the connection has been idle for `interval`, so that a connection
+    /// that silently died, for example with an expired NAT entry, is noticed and replaced before
+    /// the next request rather than stalling it. A ping not answered within the request timeout,
+    /// or `interval` if there is none, drops the connection.
+    ///
+    /// Only the background task started by `spawn` sends pings. Off by default.
+    #[must_use]
+    pub const fn keepalive(mut self, interval: Duration) -> Self {
+        self.config.keepalive = Some(interval);
+        self
+    }
+
+    /// Sets `TCP_NODELAY` on TCP connections, so that small requests go out without waiting for
+    /// more data. On by default.
+    #[must_use]
+    pub const fn tcp_nodelay(mut self, nodelay: bool) -> Self {
+        self.config.tcp.nodelay = nodelay;
+        self
+    }
+
+    /// Enables OS-level keepalive on TCP connections, probing the daemon after the connection
+    /// has been idle for the given time, so that dead peers are eventually detected without any
+    /// requests being made. Off by default. See also `keepalive`, which works at the protocol
+    /// level.
+    #[must_use]
+    pub const fn tcp_keepalive(mut self, keepalive: Option<Duration>) -> Self {
+        self.config.tcp.keepalive = keepalive;
+        self
+    }
+
+    /// Asks the daemon for its version right after authenticating, as BOINC recommends, and
+    /// keeps the answer for `Transport::server_version`. Repeated on every reconnection, since
+    /// the daemon may have been upgraded in between. Off by default.
+    #[must_use]
+    pub const fn version_handshake(mut self, enabled: bool) -> Self {
+        self.config.version_handshake = enabled;
+        self
+    }
+
+    /// Character encoding of requests and replies. UTF-8 by default, see `Charset`.
+    #[must_use]
+    pub fn charset(mut self, charset: Charset) -> Self {
+        self.config.codec = self.config.codec.charset(charset);
+        self
+    }
+
+    /// Longest reply accepted, in bytes. See `BoincCodec::max_frame_length`.
+    #[must_use]
+    pub fn max_frame_length(mut self, len: usize) -> Self {
+        self.config.codec = self.config.codec.max_frame_length(len);
+        self
+    }
+
+    /// Deepest nesting accepted in replies. See `BoincCodec::max_depth`.
+    #[must_use]
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.config.codec = self.config.codec.max_depth(depth);
+        self
+    }
+
+    /// Most elements accepted in a reply. See `BoincCodec::max_elements`.
+    #[must_use]
+    pub fn max_elements(mut self, count: usize) -> Self {
+        self.config.codec = self.config.codec.max_elements(count);
+        self
+    }
+
+    /// Calls `inspector` with the XML of every request and reply, credentials redacted. See
+    /// `BoincCodec::wire_inspector`.
+    #[must_use]
+    pub fn wire_inspector<F>(mut self, inspector: F) -> Self
+    where
+        F: Fn(Direction, &str) + Send + Sync + 'static,
+    {
+        self.config.codec = self.config.codec.wire_inspector(inspector);
+        self
+    }
+
+    /// Tells `observer` about every RPC made, see `RpcObserver`.
+    #[must_use]
+    pub fn observer<O: RpcObserver + 'static>(mut self, observer: O) -> Self {
+        self.config.observer = Observer(Some(Arc::new(observer)));
+        self
+    }
+
+    /// Like `build`, but hands the connection to a background task instead. See `spawn`.
+    #[must_use]
+    pub fn spawn(self) -> (Handle, JoinHandle<()>)
+    where
+        Io: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel::<Command>(32);
+        let config = Arc::new(self.config);
+        let task = tokio::spawn(run(config.clone(), rx));
+        (Handle { tx, config }, task)
+    }
+
+    #[must_use]
+    pub fn build(self) -> Transport<Io>
+    where
+        Io: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let state = Some(ConnState::Connecting(connect(&self.config)));
+        Transport {
+            request_timeout: self.config.request_timeout,
+            config: Arc::new(self.config),
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Like `build`, but keeps up to `max_conns` connections, sending each request on an idle
+    /// one. See `Pool`.
+    #[must_use]
+    pub fn pooled(self, max_conns: usize) -> Pool<Io> {
+        Pool {
+            config: Arc::new(self.config),
+            idle: Arc::new(std::sync::Mutex::new(Idle {
+                conns: Vec::new(),
+                reaping: false,
+            })),
+            permits: Arc::new(Semaphore::new(max_conns.max(1))),
+            idle_timeout: Duration::from_secs(60),
+        }
+    }
+
+    /// Like `build`, but connects anew for every request and hangs up after the reply, like
+    /// `boinccmd` does. See `OneShotTransport`.
+    #[must_use]
+    pub fn one_shot(self) -> OneShotTransport<Io> {
+        OneShotTransport {
+            config: Arc::new(self.config),
+        }
+    }
+}
+
+/// Port the daemon listens on for GUI RPC unless configured otherwise.
+pub const DEFAULT_PORT: u16 = 31416;
+
+/// Daemon address, as taken by `Transport::new` and the other TCP constructors.
+///
+/// Converts from strings such as `"myhost"`, `"myhost:31416"` or `"[::1]"`, from host and port
+/// pairs such as `("::1", 31416)`, and from socket addresses. Strings are split with
+/// `parse_addr` when connecting, so that the port defaults to `DEFAULT_PORT`, and one that does
+/// not parse fails the connection attempt with the address as given.
+///
+/// Shows as `host:port`, IPv6 hosts in brackets, which is what `Transport::endpoint` returns.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Address {
+    /// As given, with or without a port.
+    Text(String),
+    /// Host name or IP address, and port.
+    Host(String, u16),
+    /// Socket addresses, tried in turn.
+    Socket(Vec<SocketAddr>),
+}
+
+impl Address {
+    /// Resolves host names, blocking meanwhile.
+    fn resolve(&self) -> Result<Vec<SocketAddr>, Error> {
+        let (host, port) = match self {
+            Self::Text(text) => parse_addr(text)?,
+            Self::Host(host, port) => (host.clone(), *port),
+            Self::Socket(addrs) => return Ok(addrs.clone()),
+        };
+        (host.as_str(), port)
+            .to_socket_addrs()
+            .map(Iterator::collect)
+            .map_err(|e| Error::ConnectError(format!("Address resolution failed: {e}")))
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text(text) => f.write_str(text),
+            Self::Host(host, port) if host.contains(':') => write!(f, "[{host}]:{port}"),
+            Self::Host(host, port) => write!(f, "{host}:{port}"),
+            Self::Socket(addrs) => {
+                let addrs: Vec<_> = addrs.iter().map(ToString::to_string).collect();
+                f.write_str(&addrs.join(", "))
+            }
+        }
+    }
+}
+
+impl From<&str> for Address {
+    fn from(addr: &str) -> Self {
+        Self::Text(addr.into())
+    }
+}
+
+impl From<&String> for Address {
+    fn from(addr: &String) -> Self {
+        Self::Text(addr.clone())
+    }
+}
+
+impl From<String> for Address {
+    fn from(addr: String) -> Self {
+        Self::Text(addr)
+    }
+}
+
+impl From<Cow<'_, str>> for Address {
+    fn from(addr: Cow<'_, str>) -> Self {
+        Self::Text(addr.into_owned())
+    }
+}
+
+impl From<(&str, u16)> for Address {
+    fn from((host, port): (&str, u16)) -> Self {
+        Self::Host(host.into(), port)
+    }
+}
+
+impl From<(String, u16)> for Address {
+    fn from((host, port): (String, u16)) -> Self {
+        Self::Host(host, port)
+    }
+}
+
+impl From<(IpAddr, u16)> for Address {
+    fn from(addr: (IpAddr, u16)) -> Self {
+        SocketAddr::from(addr).into()
+    }
+}
+
+impl From<(Ipv4Addr, u16)> for Address {
+    fn from(addr: (Ipv4Addr, u16)) -> Self {
+        SocketAddr::from(addr).into()
+    }
+}
+
+impl From<(Ipv6Addr, u16)> for Address {
+    fn from(addr: (Ipv6Addr, u16)) -> Self {
+        SocketAddr::from(addr).into()
+    }
+}
+
+impl From<SocketAddr> for Address {
+    fn from(addr: SocketAddr) -> Self {
+        Self::Socket(vec![addr])
+    }
+}
+
+impl From<SocketAddrV4> for Address {
+    fn from(addr: SocketAddrV4) -> Self {
+        SocketAddr::from(addr).into()
+    }
+}
+
+impl From<SocketAddrV6> for Address {
+    fn from(addr: SocketAddrV6) -> Self {
+        SocketAddr::from(addr).into()
+    }
+}
+
+impl From<Vec<SocketAddr>> for Address {
+    fn from(addrs: Vec<SocketAddr>) -> Self {
+        Self::Socket(addrs)
+    }
+}
+
+impl From<&[SocketAddr]> for Address {
+    fn from(addrs: &[SocketAddr]) -> Self {
+        Self::Socket(addrs.to_vec())
+    }
+}
+
+/// Reads the GUI RPC port and password from a BOINC data directory.
+fn read_data_dir(dir: &Path) -> Result<(u16, Option<Secret>), Error> {
+    let path = dir.join("gui_rpc_auth.cfg");
+    let password = std::fs::read_to_string(&path)
+        .map(Secret::from)
+        .map_err(|e| {
+            let msg = format!("{}: {}", path.display(), e);
+            if e.kind() == std::io::ErrorKind::InvalidData {
+                Error::InvalidPasswordError(msg)
+            } else {
+                Error::ConnectError(msg)
+            }
+        })?;
+    // The file usually ends with a newline that is not part of the password
+    let password = Some(password.expose().trim())
+        .filter(|password| !password.is_empty())
+        .map(Secret::from);
+
+    let path = dir.join("cc_config.xml");
+    let port = match std::fs::read_to_string(&path) {
+        Ok(data) => util::parse_node(&data)
+            .map_err(|e| Error::DataParseError(format!("{}: {:?}", path.display(), e)))?
+            .children
+            .iter()
+            .filter(|node| node.name == "options")
+            .flat_map(|node| &node.children)
+            .find(|node| node.name == "gui_rpc_port")
+            .map(util::parse_node_contents)
+            .transpose()?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            return Err(Error::ConnectError(format!("{}: {}", path.display(), e)));
+        }
+    };
+
+    Ok((port.unwrap_or(DEFAULT_PORT), password))
+}
+
+/// Requests that must not be repeated blindly, as the first attempt may have taken effect
+/// before the connection failed.
+const NON_IDEMPOTENT_REQUESTS: &[&str] = &[
+    "abort_file_transfer",
+    "abort_result",
+    "acct_mgr_rpc",
+    "create_account",
+    "project_attach",
+    "project_detach",
+    "project_reset",
+    "quit",
+    "retry_file_transfer",
+    "run_benchmarks",
+];
+
+/// Whether repeating `req` is harmless.
+#[must_use]
+pub fn is_idempotent(req: &[treexml::Element]) -> bool {
+    req.iter()
+        .all(|node| !NON_IDEMPOTENT_REQUESTS.contains(&&*node.name))
+}
+
+/// Exponential backoff for retrying transient failures, see `Error::is_transient`.
+///
+/// Requests that are not idempotent (see `is_idempotent`) are only retried after opting in
+/// with `retry_non_idempotent`, unless they failed before being sent, such as while connecting
+/// or authenticating.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+    retry_non_idempotent: bool,
+}
+
+impl RetryPolicy {
+    /// Makes up to `max_attempts` attempts in total, waiting 100ms before the first retry and
+    /// doubling the delay up to 10s for each further one.
+    #[must_use]
+    pub const fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            jitter: true,
+            retry_non_idempotent: false,
+        }
+    }
+
+    #[must_use]
+    pub const fn base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+
+    #[must_use]
+    pub const fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Randomly shortens each delay by up to half, so that clients failing together do not
+    /// retry in lockstep. On by default.
+    #[must_use]
+    pub const fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    #[must_use]
+    pub const fn retry_non_idempotent(mut self, retry: bool) -> Self {
+        self.retry_non_idempotent = retry;
+        self
+    }
+
+    /// Delay before the given retry, counting from 1.
+    #[must_use]
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let delay = exponential_backoff(self.base_delay, self.max_delay, retry);
+        if self.jitter {
+            // In thousandths of half the delay, which cannot overflow however long it is
+            delay / 2 + delay / 2000 * (random_u64() % 1000) as u32
+        } else {
+            delay
+        }
+    }
+
+    /// Whether to retry after attempt number `attempt` failed with `e`, `sent` telling if the
+    /// request had gone out by then.
+    fn should_retry(&self, attempt: u32, req: &[treexml::Element], e: &Error, sent: bool) -> bool {
+        attempt < self.max_attempts
+            && e.is_transient()
+            && (!sent || self.retry_non_idempotent || is_idempotent(req))
+    }
+}
+
+/// Number for jitter only, from the keys std seeds every `RandomState` with, which differ
+/// between calls. Good enough to keep clients from retrying in lockstep, and for nothing else.
+fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// How the transport reconnects after the connection is lost or fails to be established.
+///
+/// Reconnecting happens on demand, with the next request. The first attempt after losing a
+/// working connection is made right away; further consecutive attempts back off exponentially.
+#[derive(Clone, Debug)]
+pub struct ReconnectPolicy {
+    max_attempts: Option<u32>,
+    base_delay: Duration,
+    max_delay: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    /// Keeps trying forever, waiting 100ms after the first failed attempt and doubling the delay
+    /// up to 30s for each further one.
+    fn default() -> Self {
+        Self {
+            max_attempts: None,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Gives up after `attempts` consecutive failed connection attempts, the initial connection
+    /// included. Requests then fail with the error of the last attempt, as reported by
+    /// `ConnectionState::GaveUp`.
+    #[must_use]
+    pub const fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = Some(attempts);
+        self
+    }
+
+    #[must_use]
+    pub const fn base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+
+    #[must_use]
+    pub const fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Delay before the next connection attempt, after `failures` consecutive failed ones.
+    #[must_use]
+    pub fn backoff(&self, failures: u32) -> Duration {
+        if failures == 0 {
+            Duration::from_secs(0)
+        } else {
+            exponential_backoff(self.base_delay, self.max_delay, failures)
+        }
+    }
+
+    const fn exhausted(&self, failures: u32) -> bool {
+        matches!(self.max_attempts, Some(max) if failures >= max)
+    }
+}
+
+/// Minimum interval between requests, with an allowance for short bursts.
+///
+/// Every attempt counts, retries included. Time spent in a retry's backoff counts towards the
+/// interval, so a retry backing off for at least the interval is not held up any further.
+///
+/// With a `Pool`, the limit applies to all connections together.
+#[derive(Clone, Debug)]
+pub struct RateLimit {
+    min_interval: Duration,
+    burst: u32,
+}
+
+impl RateLimit {
+    /// Sends at most one request per `min_interval`, without bursts.
+    #[must_use]
+    pub const fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            burst: 1,
+        }
+    }
+
+    /// Lets up to `requests` requests through back to back, as long as the average rate stays
+    /// within the limit. A burst of 0 is treated as 1.
+    #[must_use]
+    pub fn burst(mut self, requests: u32) -> Self {
+        self.burst = requests.max(1);
+        self
+    }
+
+    /// Books a request at `now`, returning how long it has to wait.
+    ///
+    /// `schedule` is when the requests booked so far would all have been sent had each waited
+    /// for the full interval; a request may go ahead of it by up to the burst allowance.
+    fn delay(
+        &self,
+        schedule: &mut Option<tokio::time::Instant>,
+        now: tokio::time::Instant,
+    ) -> Duration {
+        let due = schedule.map_or(now, |due| due.max(now));
+        *schedule = Some(due + self.min_interval);
+        let allowance = self.min_interval * (self.burst - 1);
+        due.duration_since(now)
+            .checked_sub(allowance)
+            .unwrap_or_default()
+    }
+}
+
+/// `base` doubled for each step after the first, up to `max`.
+fn exponential_backoff(base: Duration, max: Duration, step: u32) -> Duration {
+    let factor = 1_u32
+        .checked_shl(step.saturating_sub(1))
+        .unwrap_or(u32::MAX);
+    base.checked_mul(factor).map_or(max, |d| d.min(max))
+}
+
+fn tcp_addrs(stream: &TcpStream) -> Option<Addrs> {
+    Some((stream.local_addr().ok()?, stream.peer_addr().ok()?))
+}
+
+/// Splits a daemon address into host and port, the port defaulting to `DEFAULT_PORT`.
+///
+/// Accepts `host`, `host:port`, IP addresses, and IPv6 addresses in brackets, with or without
+/// a port, such as `[::1]:31416`. The host is not resolved.
+///
+/// # Errors
+///
+/// Fails with `Error::ConnectError` for a malformed address, such as a bad port or an IPv6 address
+/// with a port but no brackets.
+pub fn parse_addr(addr: &str) -> Result<(String, u16), Error> {
+    let invalid = |reason: &str| Error::ConnectError(format!("Invalid address {addr:?}: {reason}"));
+    if let Ok(ip) = addr.parse::<IpAddr>() {
+        return Ok((ip.to_string(), DEFAULT_PORT));
+    }
+    if let Ok(socket) = addr.parse::<SocketAddr>() {
+        return Ok((socket.ip().to_string(), socket.port()));
+    }
+    let (host, port) = if let Some(rest) = addr.strip_prefix('[') {
+        let end = rest
+            .find(']')
+            .ok_or_else(|| invalid("missing closing bracket"))?;
+        let host = &rest[..end];
+        if host.parse::<Ipv6Addr>().is_err() {
+            return Err(invalid("not an IPv6 address in brackets"));
+        }
+        let port = match &rest[end + 1..] {
+            "" => None,
+            rest => Some(
+                rest.strip_prefix(':')
+                    .ok_or_else(|| invalid("expected a port after the brackets"))?,
+            ),
+        };
+        (host, port)
+    } else {
+        match addr.find(':') {
+            Some(i) if addr[i + 1..].contains(':') => {
+                return Err(invalid("IPv6 addresses with a port need brackets"))
+            }
+            Some(i) => (&addr[..i], Some(&addr[i + 1..])),
+            None => (addr, None),
+        }
+    };
+    if host.is_empty() {
+        return Err(invalid("missing host"));
+    }
+    let port = match port {
+        Some(port) => port.parse().map_err(|_| invalid("invalid port"))?,
+        None => DEFAULT_PORT,
+    };
+    Ok((host.into(), port))
+}
+
+/// Resolves `addr` and connects to the first address accepting the connection.
+async fn connect_tcp(addr: Address, options: TcpOptions) -> Result<TcpStream, Error> {
+    // Name resolution blocks
+    let addrs = tokio::task::spawn_blocking(move || addr.resolve())
+        .await
+        .map_err(|e| Error::ConnectError(format!("Address resolution failed: {e}")))??;
+
+    let mut errors = Vec::new();
+    for addr in addrs {
+        let stream = match TcpStream::connect(addr).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                errors.push(format!("{addr}: {e}"));
+                continue;
+            }
+        };
+        stream.set_nodelay(options.nodelay)?;
+        stream.set_keepalive(options.keepalive)?;
+        return Ok(stream);
+    }
+    Err(Error::ConnectError(if errors.is_empty() {
+        "Address resolved to nothing".into()
+    } else {
+        format!("Could not connect to any address: {}", errors.join("; "))
+    }))
+}
+
+/// Starts connecting anew after the previous connection failed or was lost.
+fn reconnect<Io>(config: &Config<Io>) -> DaemonStreamFuture<Io>
+where
+    Io: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    if let Some(e) = config.gave_up() {
+        return Box::pin(async move { Err(e) });
+    }
+    let failures = config
+        .reconnects
+        .lock()
+        .map(|reconnects| reconnects.failures)
+        .unwrap_or_default();
+    let attempt = failures.saturating_add(1);
+    debug!("Connecting to {}, attempt {}", config.endpoint, attempt);
+    config.set_state(ConnectionState::Reconnecting { attempt });
+    let future = connect(config);
+    let delay = config.reconnect.backoff(failures);
+    if delay == Duration::from_secs(0) {
+        return future;
+    }
+    Box::pin(async move {
+        tokio::time::delay_for(delay).await;
+        future.await
+    })
+}
+
+fn connect<Io>(config: &Config<Io>) -> DaemonStreamFuture<Io>
+where
+    Io: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let io = (config.connector)(config.tcp);
+    let codec = config.codec.clone();
+    let password = config.password.clone();
+    let version_handshake = config.version_handshake;
+    let observer = config.observer.clone();
+    let future = async move {
+        let io = io.await?;
+        let started = tokio::time::Instant::now();
+        let res = DaemonStream::with_codec(io.compat(), codec, password).await;
+        observer.observe("auth", started, res.as_ref().map(drop).map_err(Error::kind));
+        let mut conn = res?;
+        let version = if version_handshake {
+            let started = tokio::time::Instant::now();
+            let reply = conn
+                .query(Request::ExchangeVersions(VersionInfo::current()).into())
+                .await;
+            let res = reply.as_ref().map_err(Clone::clone).and_then(|reply| {
+                crate::parse_object("exchange_versions", reply, "server_version")
+            });
+            let outcome = res.as_ref().map(drop).map_err(Error::kind);
+            observer.observe("exchange_versions", started, outcome);
+            reply?;
+            match res {
+                Ok(version) => Some(version),
+                // Not worth failing the connection for
+                Err(e) => {
+                    debug!("Version handshake failed: {:?}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        Ok(Connected { conn, version })
+    };
+    match config.connect_timeout {
+        Some(timeout) => Box::pin(async move {
+            tokio::time::timeout(timeout, future)
+                .await
+                .map_err(|_| Error::Timeout)?
+        }),
+        None => Box::pin(future),
+    }
+}
+
+impl<Io> tower::Service<Vec<treexml::Element>> for Transport<Io>
+where
+    Io: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    type Response = Vec<treexml::Element>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let mut g = match self.state.try_lock() {
+            Ok(g) => g,
+            // A clone is busy with a request, `call` waits for it and takes care of the connection
+            Err(_) => return Poll::Ready(Ok(())),
+        };
+
+        let mut future = match g.take() {
+            Some(ConnState::Ready(conn)) => {
+                *g = Some(ConnState::Ready(conn));
+                return Poll::Ready(Ok(()));
+            }
+            Some(ConnState::Connecting(future)) => future,
+            Some(ConnState::Failed(_)) | None => reconnect(&self.config),
+            Some(ConnState::Closed) => {
+                *g = Some(ConnState::Closed);
+                return Poll::Ready(Err(Error::ConnectionClosed));
+            }
+        };
+
+        match future.as_mut().poll(cx) {
+            Poll::Pending => {
+                *g = Some(ConnState::Connecting(future));
+                Poll::Pending
+            }
+            Poll::Ready(res) => match self.config.connected(res) {
+                Ok(conn) => {
+                    *g = Some(ConnState::Ready(conn));
+                    Poll::Ready(Ok(()))
+                }
+                // Leave it to `call` to retry
+                Err(e) if self.config.retry.is_some() => {
+                    *g = Some(ConnState::Failed(e));
+                    Poll::Ready(Ok(()))
+                }
+                Err(e) => Poll::Ready(Err(e)),
+            },
+        }
+    }
+
+    fn call(&mut self, req: Vec<treexml::Element>) -> Self::Future {
+        let span = request_span(&self.config.endpoint);
+        let state = self.state.clone();
+        let config = self.config.clone();
+        let request_timeout = self.request_timeout;
+        Box::pin(
+            async move {
+                let mut state = state.lock().await;
+                execute(&mut state, &config, request_timeout, req).await
+            }
+            .instrument(span),
+        )
+    }
+}
+
+/// Span that the transports do their part of a request in, with the daemon's address as `host`.
+/// Within `Client` requests, it is a child of their `rpc` span.
+fn request_span(endpoint: &str) -> tracing::Span {
+    tracing::info_span!("transport", host = %endpoint)
+}
+
+/// Sends the request, retrying according to the configured policy.
+async fn execute<Io>(
+    state: &mut Option<ConnState<Io>>,
+    config: &Config<Io>,
+    request_timeout: Option<Duration>,
+    req: Vec<treexml::Element>,
+) -> Result<Vec<treexml::Element>, Error>
+where
+    Io: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let mut attempt = 1;
+    loop {
+        let (res, sent) = match connection(state, config).await {
+            Ok(conn) => (
+                query(state, config, conn, request_timeout, req.clone()).await,
+                true,
+            ),
+            Err(e) => (Err(e), false),
+        };
+        let closed = matches!(state, Some(ConnState::Closed)) || config.gave_up().is_some();
+        match (&res, &config.retry) {
+            (Err(e), Some(policy)) if !closed && policy.should_retry(attempt, &req, e, sent) => {
+                let delay = policy.backoff(attempt);
+                let name = req.first().map(|n| &*n.name).unwrap_or_default();
+                debug!("Retrying {} in {:?} after error: {:?}", name, delay, e);
+                tokio::time::delay_for(delay).await;
+                attempt += 1;
+            }
+            _ => return res,
+        }
+    }
+}
+
+async fn call_once<Io>(
+    state: &mut Option<ConnState<Io>>,
+    config: &Config<Io>,
+    request_timeout: Option<Duration>,
+    req: Vec<treexml::Element>,
+) -> Result<Vec<treexml::Element>, Error>
+where
+    Io: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let conn = connection(state, config).await?;
+    query(state, config, conn, request_timeout, req).await
+}
+
+/// Waits out the rate limit, then takes the connection for a request, connecting if need be.
+/// Nothing of the request has been sent should this fail.
+async fn connection<Io>(
+    state: &mut Option<ConnState<Io>>,
+    config: &Config<Io>,
+) -> Result<DaemonStream<TokioIo<Io>>, Error>
+where
+    Io: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    config.throttle().await;
+
+    // Normally `poll_ready` has connected already, but do not rely on it
+    match state.take() {
+        Some(ConnState::Ready(conn)) => Ok(conn),
+        Some(ConnState::Connecting(future)) => config.connected(future.await),
+        Some(ConnState::Failed(e)) => Err(e),
+        None => config.connected(reconnect(config).await),
+        Some(ConnState::Closed) => {
+            *state = Some(ConnState::Closed);
+            Err(Error::ConnectionClosed)
+        }
+    }
+}
+
+/// Sends the request over `conn` and reads the reply, putting the connection back into `state`
+/// unless it failed.
+async fn query<Io>(
+    state: &mut Option<ConnState<Io>>,
+    config: &Config<Io>,
+    mut conn: DaemonStream<TokioIo<Io>>,
+    request_timeout: Option<Duration>,
+    req: Vec<treexml::Element>,
+) -> Result<Vec<treexml::Element>, Error>
+where
+    Io: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    // Should the caller drop us mid-request, the stream may hold half a request or an unread
+    // reply, so leave the state empty for the next call to reconnect
+    let mut in_flight = InFlight {
+        config,
+        done: false,
+    };
+    let rpc = req
+        .first()
+        .map(|node| node.name.clone())
+        .unwrap_or_default();
+    let started = tokio::time::Instant::now();
+    let res = match request_timeout {
+        Some(timeout) => tokio::time::timeout(timeout, conn.query(req))
+            .await
+            .unwrap_or(Err(Error::Timeout)),
+        None => conn.query(req).await,
+    };
+    in_flight.done = true;
+    config.observer.observe(&rpc, started, reply_outcome(&res));
+    match &res {
+        Ok(_) => *state = Some(ConnState::Ready(conn)),
+        // A reply that failed to parse was still read in full
+        Err(e) if e.keeps_connection() => *state = Some(ConnState::Ready(conn)),
+        Err(e) => config.disconnected(ConnectionState::Failed(e.clone())),
+    }
+    res
+}
+
+/// Marks the connection as lost unless the request ran to completion.
+struct InFlight<'a, Io> {
+    config: &'a Config<Io>,
+    done: bool,
+}
+
+impl<Io> Drop for InFlight<'_, Io> {
+    fn drop(&mut self) {
+        if !self.done {
+            debug!("Request cancelled, dropping the connection");
+            self.config
+                .disconnected(ConnectionState::Failed(Error::NetworkError(
+                    "Request cancelled".into(),
+                )));
+        }
+    }
+}
+
+/// Serves requests from handles until all of them are gone.
+async fn run<Io>(config: Arc<Config<Io>>, mut rx: mpsc::Receiver<Command>)
+where
+    Io: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let mut state = None;
+    loop {
+        let next = match (config.keepalive, &state) {
+            (Some(interval), Some(ConnState::Ready(_))) => {
+                if let Ok(next) = tokio::time::timeout(interval, rx.recv()).await {
+                    next
+                } else {
+                    let ping = Request::PING.into();
+                    let timeout = config.request_timeout.unwrap_or(interval);
+                    // On failure the connection is dropped, to be replaced by the next request
+                    if let Err(e) = call_once(&mut state, &config, Some(timeout), ping).await {
+                        debug!("Keepalive ping failed: {:?}", e);
+                    }
+                    continue;
+                }
+            }
+            _ => rx.recv().await,
+        };
+        let (req, reply) = match next {
+            Some(command) => command,
+            None => return,
+        };
+        let res = execute(&mut state, &config, config.request_timeout, req).await;
+        // The caller may have given up waiting, which is fine
+        let _ = reply.send(res);
+    }
+}
+
+type Command = (
+    Vec<treexml::Element>,
+    oneshot::Sender<Result<Vec<treexml::Element>, Error>>,
+);
+
+/// Starts a background task owning the daemon connection.
+///
+/// Requests sent through the returned `Handle` and its clones are queued and executed in order.
+/// A caller abandoning its request does not disturb the connection, as the task still reads the
+/// reply. The task stops once all handles are dropped.
+#[must_use]
+pub fn spawn<A, P>(addr: A, password: Option<P>) -> (Handle, JoinHandle<()>)
+where
+    A: Into<Address>,
+    P: Into<Secret>,
+{
+    let builder = Transport::builder(addr);
+    match password {
+        Some(password) => builder.password(password),
+        None => builder,
+    }
+    .spawn()
+}
+
+/// Sends requests to the connection task started by `spawn`.
+#[derive(Clone)]
+pub struct Handle {
+    tx: mpsc::Sender<Command>,
+    /// Shared with the task, for `connection_info`.
+    config: Arc<dyn ConnectionSource + Send + Sync>,
+}
+
+impl ConnectionSource for Handle {
+    fn connection_info(&self) -> ConnectionInfo {
+        self.config.connection_info()
+    }
+}
+
+impl tower::Service<Vec<treexml::Element>> for Handle {
+    type Response = Vec<treexml::Element>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.tx.poll_ready(cx).map_err(|_| Error::ConnectionClosed)
+    }
+
+    fn call(&mut self, req: Vec<treexml::Element>) -> Self::Future {
+        let (reply, rx) = oneshot::channel();
+        // Without a slot reserved by `poll_ready`, wait for one
+        let queued = match self.tx.try_send((req, reply)) {
+            Ok(()) => None,
+            Err(mpsc::error::TrySendError::Full(cmd)) => Some((self.tx.clone(), cmd)),
+            Err(mpsc::error::TrySendError::Closed(_)) => {
+                return Box::pin(futures::future::err(Error::ConnectionClosed))
+            }
+        };
+        Box::pin(async move {
+            if let Some((mut tx, cmd)) = queued {
+                tx.send(cmd).await.map_err(|_| Error::ConnectionClosed)?;
+            }
+            rx.await.map_err(|_| Error::ConnectionClosed)?
+        })
+    }
+}
+
+/// Transport opening a separate connection for each request.
+///
+/// Slower than `Transport`, as every request pays for connecting and authenticating, but there
+/// is no connection to go stale in between. Suits scripts sending a few requests to many hosts.
+pub struct OneShotTransport<Io = TcpStream> {
+    config: Arc<Config<Io>>,
+}
+
+impl<Io> ConnectionSource for OneShotTransport<Io> {
+    fn connection_info(&self) -> ConnectionInfo {
+        self.config.connection_info()
+    }
+}
+
+impl<Io> Clone for OneShotTransport<Io> {
+    fn clone(&self) -> Self {
+        Self {
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl OneShotTransport {
+    pub fn new<A, P>(addr: A, password: Option<P>) -> Self
+    where
+        A: Into<Address>,
+        P: Into<Secret>,
+    {
+        let builder = Transport::builder(addr);
+        match password {
+            Some(password) => builder.password(password),
+            None => builder,
+        }
+        .one_shot()
+    }
+}
+
+impl<Io> tower::Service<Vec<treexml::Element>> for OneShotTransport<Io>
+where
+    Io: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    type Response = Vec<treexml::Element>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Vec<treexml::Element>) -> Self::Future {
+        let span = request_span(&self.config.endpoint);
+        let config = self.config.clone();
+        Box::pin(
+            async move {
+                let mut state = None;
+                let res = execute(&mut state, &config, config.request_timeout, req).await;
+                if let Some(ConnState::Ready(mut conn)) = state {
+                    if let Err(e) = conn.close().await {
+                        debug!("Failed to close connection: {:?}", e);
+                    }
+                }
+                res
+            }
+            .instrument(span),
+        )
+    }
+}
+
+/// Several connections to one daemon, for sending requests in parallel.
+///
+/// The daemon handles each connection separately, so a slow request such as `get_state` on
+/// one does not hold up requests sent meanwhile on others. Connections are opened as needed, up
+/// to the limit, beyond which requests wait for a connection to become free. A connection that
+/// fails is dropped on its own, leaving the others be. Connections left idle for longer than
+/// the idle timeout are closed as they time out, by a task that runs while there are any.
+///
+/// The options of the `TransportBuilder` the pool was built with apply to every connection.
+pub struct Pool<Io = TcpStream> {
+    config: Arc<Config<Io>>,
+    idle: Arc<std::sync::Mutex<Idle<Io>>>,
+    permits: Arc<Semaphore>,
+    idle_timeout: Duration,
+}
+
+struct Idle<Io> {
+    /// Free connections, with the time each was last used.
+    conns: Vec<(DaemonStream<TokioIo<Io>>, tokio::time::Instant)>,
+    /// Whether a task is running `reap_idle`.
+    reaping: bool,
+}
+
+impl<Io> ConnectionSource for Pool<Io> {
+    fn connection_info(&self) -> ConnectionInfo {
+        self.config.connection_info()
+    }
+}
+
+impl<Io> Clone for Pool<Io> {
+    fn clone(&self) -> Self {
+        Self {
+            config: self.config.clone(),
+            idle: self.idle.clone(),
+            permits: self.permits.clone(),
+            idle_timeout: self.idle_timeout,
+        }
+    }
+}
+
+impl<Io> Pool<Io> {
+    /// Closes connections left idle for longer than `timeout`. One minute by default.
+    #[must_use]
+    pub const fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// Number of open connections not in use.
+    #[must_use]
+    pub fn idle_connections(&self) -> usize {
+        self.idle
+            .lock()
+            .map(|idle| idle.conns.len())
+            .unwrap_or_default()
+    }
+
+    /// Takes the most recently used idle connection.
+    fn checkout(&self) -> Option<DaemonStream<TokioIo<Io>>> {
+        let mut idle = self.idle.lock().ok()?;
+        idle.reap(self.idle_timeout);
+        idle.conns.pop().map(|(conn, _)| conn)
+    }
+}
+
+impl<Io: Send + 'static> Pool<Io> {
+    fn checkin(&self, conn: DaemonStream<TokioIo<Io>>) {
+        if let Ok(mut idle) = self.idle.lock() {
+            idle.reap(self.idle_timeout);
+            idle.conns.push((conn, tokio::time::Instant::now()));
+            if !idle.reaping {
+                idle.reaping = true;
+                tokio::spawn(reap_idle(Arc::downgrade(&self.idle), self.idle_timeout));
+            }
+        }
+    }
+}
+
+impl<Io> Idle<Io> {
+    fn reap(&mut self, timeout: Duration) {
+        self.conns.retain(|(_, used)| used.elapsed() < timeout);
+    }
+}
+
+/// Closes idle connections of a pool as they time out, until there are none left or the pool is
+/// gone.
+async fn reap_idle<Io>(idle: std::sync::Weak<std::sync::Mutex<Idle<Io>>>, timeout: Duration) {
+    loop {
+        let next = match idle.upgrade().as_deref().map(std::sync::Mutex::lock) {
+            Some(Ok(mut idle)) => {
+                idle.reap(timeout);
+                let next = idle.conns.iter().map(|(_, used)| *used + timeout).min();
+                // Checked in with the lock held, so no connection is left without a reaper
+                idle.reaping = next.is_some();
+                next
+            }
+            _ => None,
+        };
+        match next {
+            Some(deadline) => tokio::time::delay_until(deadline).await,
+            None => return,
+        }
+    }
+}
+
+impl<Io> tower::Service<Vec<treexml::Element>> for Pool<Io>
+where
+    Io: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    type Response = Vec<treexml::Element>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Vec<treexml::Element>) -> Self::Future {
+        let span = request_span(&self.config.endpoint);
+        let pool = self.clone();
+        Box::pin(
+            async move {
+                let _permit = pool.permits.acquire().await;
+                let mut state = pool.checkout().map(ConnState::Ready);
+                let res = execute(&mut state, &pool.config, pool.config.request_timeout, req).await;
+                // Failed connections are left out
+                if let Some(ConnState::Ready(conn)) = state {
+                    pool.checkin(conn);
+                }
+                res
+            }
+            .instrument(span),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        errors::AuthFailure,
+        rpc::{compute_nonce_hash, BoincCodec, CodecMode},
+    };
+    use futures::{SinkExt, TryStreamExt};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::net::TcpListener;
+    use tokio_util::codec::{Decoder, Framed};
+    use tower::{Service, ServiceExt};
+
+    /// Accepts connections forever, running `script` with the connection index on each.
+    async fn scripted_daemon<F, Fut>(script: F) -> String
+    where
+        F: Fn(usize, Framed<TcpStream, BoincCodec>) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            for i in 0.. {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(script(i, BoincCodec::new(CodecMode::Server).framed(stream)));
+            }
+        });
+        addr
+    }
+
+    /// Like `scripted_daemon`, but over in-memory streams, so that tests with the clock paused
+    /// do not skip ahead while a reply is still on its way through a socket.
+    fn memory_daemon<F, Fut>(script: F) -> TransportBuilder<tokio::io::DuplexStream>
+    where
+        F: Fn(usize, Framed<tokio::io::DuplexStream, BoincCodec>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let connections = AtomicUsize::new(0);
+        TransportBuilder::with_connector(
+            "memory".into(),
+            Box::new(move |_| {
+                let (client, server) = tokio::io::duplex(4096);
+                let i = connections.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(script(i, BoincCodec::new(CodecMode::Server).framed(server)));
+                Box::pin(async move { Ok(client) })
+            }),
+            |_| None,
+        )
+    }
+
+    /// Authorizes the client, then answers every request with its own tag name.
+    async fn echo(mut conn: Framed<TcpStream, BoincCodec>) {
+        conn.try_next().await.unwrap();
+        conn.send(vec![treexml::Element::new("authorized")])
+            .await
+            .unwrap();
+        while let Ok(Some(req)) = conn.try_next().await {
+            conn.send(req).await.unwrap();
+        }
+    }
+
+    /// Runs the nonce handshake, accepting only `password`.
+    async fn authorize<Io>(conn: &mut Framed<Io, BoincCodec>, password: &str) -> bool
+    where
+        Io: AsyncRead + AsyncWrite + Unpin,
+    {
+        conn.try_next().await.unwrap();
+        let mut nonce = treexml::Element::new("nonce");
+        nonce.text = Some("1700000000.5".into());
+        conn.send(vec![nonce]).await.unwrap();
+        let auth2 = conn.try_next().await.unwrap().unwrap();
+        let authorized =
+            auth2[0].children[0].text == Some(compute_nonce_hash(password, "1700000000.5"));
+        let reply = if authorized {
+            "authorized"
+        } else {
+            "unauthorized"
+        };
+        conn.send(vec![treexml::Element::new(reply)]).await.unwrap();
+        authorized
+    }
+
+    async fn request<S>(transport: &mut S, tag: &str) -> Result<String, Error>
+    where
+        S: Service<Vec<treexml::Element>, Response = Vec<treexml::Element>, Error = Error>,
+    {
+        transport.ready_and().await?;
+        let reply = transport.call(vec![treexml::Element::new(tag)]).await?;
+        Ok(reply[0].name.clone())
+    }
+
+    #[tokio::test]
+    async fn reconnect_after_dropped_connection() {
+        let addr = scripted_daemon(|i, conn| async move {
+            if i > 0 {
+                echo(conn).await;
+            }
+        })
+        .await;
+
+        let mut transport = Transport::new(addr, None::<String>);
+        assert!(request(&mut transport, "get_state").await.is_err());
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Ok("get_state".into())
+        );
+        assert_eq!(
+            request(&mut transport, "get_results").await,
+            Ok("get_results".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn reconnect_after_failed_query() {
+        let addr = scripted_daemon(|i, mut conn| async move {
+            if i > 0 {
+                return echo(conn).await;
+            }
+            conn.try_next().await.unwrap();
+            conn.send(vec![treexml::Element::new("authorized")])
+                .await
+                .unwrap();
+            conn.try_next().await.unwrap();
+        })
+        .await;
+
+        let mut transport = Transport::new(addr, None::<String>);
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Err(Error::ConnectionClosed)
+        );
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Ok("get_state".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn call_without_poll_ready() {
+        let addr = scripted_daemon(|_, conn| echo(conn)).await;
+
+        let mut transport = Transport::new(addr, None::<String>);
+        let reply = transport
+            .call(vec![treexml::Element::new("get_state")])
+            .await
+            .unwrap();
+        assert_eq!(reply[0].name, "get_state");
+    }
+
+    #[tokio::test]
+    async fn reauthenticate_after_daemon_restart() {
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // First daemon life serves one request, then goes away with its connection
+        let first_life = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut conn = BoincCodec::new(CodecMode::Server).framed(stream);
+            assert!(authorize(&mut conn, "secret").await);
+            let req = conn.try_next().await.unwrap().unwrap();
+            conn.send(req).await.unwrap();
+        });
+
+        let mut transport = Transport::new(addr, Some("secret"));
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Ok("get_state".into())
+        );
+        first_life.await.unwrap();
+
+        let mut listener = TcpListener::bind(addr).await.unwrap();
+        let second_life = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut conn = BoincCodec::new(CodecMode::Server).framed(stream);
+            let authorized = authorize(&mut conn, "secret").await;
+            while let Ok(Some(req)) = conn.try_next().await {
+                conn.send(req).await.unwrap();
+            }
+            authorized
+        });
+
+        // This one still goes to the dead connection
+        assert!(request(&mut transport, "get_results").await.is_err());
+        assert_eq!(
+            request(&mut transport, "get_results").await,
+            Ok("get_results".into())
+        );
+        drop(transport);
+        assert!(second_life.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn reconnect_with_wrong_password() {
+        let addr = scripted_daemon(|_, mut conn| async move {
+            authorize(&mut conn, "secret").await;
+        })
+        .await;
+
+        let mut transport = Transport::new(addr, Some("guess"));
+        for _ in 0..2 {
+            assert_eq!(
+                request(&mut transport, "get_state").await,
+                Err(Error::AuthError(AuthFailure::WrongPassword))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn connect_timeout_during_handshake() {
+        let addr = scripted_daemon(|_, mut conn| async move {
+            // Swallow everything without ever answering
+            while let Ok(Some(_)) = conn.try_next().await {}
+        })
+        .await;
+
+        let mut transport = Transport::builder(addr)
+            .password("secret")
+            .connect_timeout(Duration::from_millis(100))
+            .build();
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Err(Error::Timeout)
+        );
+    }
+
+    #[tokio::test]
+    async fn connect_timeout_while_connecting() {
+        tokio::time::pause();
+        // Like a SYN to an unroutable address that goes unanswered
+        let mut transport = TransportBuilder::<tokio::io::DuplexStream>::with_connector(
+            "nowhere".into(),
+            Box::new(|_| Box::pin(futures::future::pending())),
+            |_| None,
+        )
+        .connect_timeout(Duration::from_secs(5))
+        .build();
+        let started = tokio::time::Instant::now();
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Err(Error::Timeout)
+        );
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_secs(5), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(6), "{:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn request_timeout_reconnects() {
+        let addr = scripted_daemon(|i, mut conn| async move {
+            if i > 0 {
+                return echo(conn).await;
+            }
+            conn.try_next().await.unwrap();
+            conn.send(vec![treexml::Element::new("authorized")])
+                .await
+                .unwrap();
+            // Never reply
+            while let Ok(Some(_)) = conn.try_next().await {}
+        })
+        .await;
+
+        let mut transport = Transport::builder(addr)
+            .request_timeout(Duration::from_millis(100))
+            .build();
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Err(Error::Timeout)
+        );
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Ok("get_state".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn request_timeout_override() {
+        let addr = scripted_daemon(|_, mut conn| async move {
+            conn.try_next().await.unwrap();
+            conn.send(vec![treexml::Element::new("authorized")])
+                .await
+                .unwrap();
+            while let Ok(Some(req)) = conn.try_next().await {
+                tokio::time::delay_for(Duration::from_millis(200)).await;
+                conn.send(req).await.unwrap();
+            }
+        })
+        .await;
+
+        let mut transport = Transport::builder(addr)
+            .request_timeout(Duration::from_secs(5))
+            .build();
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Ok("get_state".into())
+        );
+
+        transport.set_request_timeout(Some(Duration::from_millis(50)));
+        assert_eq!(transport.request_timeout(), Some(Duration::from_millis(50)));
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Err(Error::Timeout)
+        );
+    }
+
+    #[tokio::test]
+    async fn cancelled_request() {
+        let addr = scripted_daemon(|i, mut conn| async move {
+            if i > 0 {
+                return echo(conn).await;
+            }
+            authorize(&mut conn, "secret").await;
+            // Reply too late, after the next request went out
+            let req = conn.try_next().await.unwrap().unwrap();
+            tokio::time::delay_for(Duration::from_millis(100)).await;
+            let _ = conn.send(req).await;
+            while let Ok(Some(_)) = conn.try_next().await {}
+        })
+        .await;
+
+        let mut transport = Transport::new(addr, Some("secret"));
+        let watcher = transport.state_watcher();
+        assert!(tokio::time::timeout(
+            Duration::from_millis(20),
+            request(&mut transport, "get_state")
+        )
+        .await
+        .is_err());
+        assert!(matches!(
+            *watcher.borrow(),
+            ConnectionState::Failed(Error::NetworkError(_))
+        ));
+        assert_eq!(transport.peer_addr(), None);
+
+        assert_eq!(
+            request(&mut transport, "get_host_info").await,
+            Ok("get_host_info".into())
+        );
+        assert_eq!(*watcher.borrow(), ConnectionState::Ready);
+    }
+
+    #[tokio::test]
+    async fn reconnect_backoff() {
+        let (addr, connections) = flaky_daemon(3, "secret").await;
+        let policy = ReconnectPolicy::default()
+            .base_delay(Duration::from_millis(40))
+            .max_delay(Duration::from_millis(100));
+        assert_eq!(policy.backoff(0), Duration::from_secs(0));
+        assert_eq!(policy.backoff(2), Duration::from_millis(80));
+        assert_eq!(policy.backoff(3), Duration::from_millis(100));
+
+        let mut transport = Transport::builder(addr)
+            .password("secret")
+            .reconnect(policy)
+            .build();
+        let watcher = transport.state_watcher();
+        let started = std::time::Instant::now();
+        for _ in 0..3 {
+            assert!(request(&mut transport, "get_state").await.is_err());
+            assert!(matches!(*watcher.borrow(), ConnectionState::Failed(_)));
+        }
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Ok("get_state".into())
+        );
+        assert_eq!(*watcher.borrow(), ConnectionState::Ready);
+        assert_eq!(connections.load(Ordering::SeqCst), 4);
+        // 40ms + 80ms + 100ms
+        assert!(started.elapsed() >= Duration::from_millis(220));
+    }
+
+    #[tokio::test]
+    async fn reconnect_gives_up() {
+        let (addr, connections) = flaky_daemon(3, "secret").await;
+        let mut transport = Transport::builder(addr)
+            .password("secret")
+            .reconnect(
+                ReconnectPolicy::default()
+                    .max_attempts(2)
+                    .base_delay(Duration::from_millis(10)),
+            )
+            .retry(RetryPolicy::new(5).base_delay(Duration::from_millis(10)))
+            .build();
+        let mut watcher = transport.state_watcher();
+        let states = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = states.clone();
+        tokio::spawn(async move {
+            while let Some(state) = watcher.recv().await {
+                seen.lock().unwrap().push(state);
+            }
+        });
+
+        let e = request(&mut transport, "get_state").await.unwrap_err();
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+        // No more attempts, even though the daemon would now accept the connection
+        assert_eq!(request(&mut transport, "get_state").await, Err(e.clone()));
+        assert_eq!(transport.connect().await, Err(e.clone()));
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+
+        tokio::time::delay_for(Duration::from_millis(10)).await;
+        let states = states.lock().unwrap().clone();
+        assert_eq!(
+            states.first(),
+            Some(&ConnectionState::Connecting),
+            "{states:?}"
+        );
+        assert!(states.contains(&ConnectionState::Reconnecting { attempt: 2 }));
+        assert_eq!(states.last(), Some(&ConnectionState::GaveUp(e)));
+    }
+
+    /// Counts connections, dropping the first `failures` of them right away.
+    async fn flaky_daemon(failures: usize, password: &'static str) -> (String, Arc<AtomicUsize>) {
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+        let addr = scripted_daemon(move |i, mut conn| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if i >= failures && authorize(&mut conn, password).await {
+                    while let Ok(Some(req)) = conn.try_next().await {
+                        conn.send(req).await.unwrap();
+                    }
+                }
+            }
+        })
+        .await;
+        (addr, connections)
+    }
+
+    #[tokio::test]
+    async fn retry_backoff() {
+        tokio::time::pause();
+        let (addr, connections) = flaky_daemon(2, "secret").await;
+
+        let mut transport = Transport::builder(addr)
+            .password("secret")
+            .retry(
+                RetryPolicy::new(3)
+                    .base_delay(Duration::from_secs(1))
+                    .jitter(false),
+            )
+            .build();
+        let started = tokio::time::Instant::now();
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Ok("get_state".into())
+        );
+        assert_eq!(connections.load(Ordering::SeqCst), 3);
+        // 1s before the first retry, 2s before the second one
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_secs(3), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(4), "{:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn retry_gives_up() {
+        tokio::time::pause();
+        let (addr, connections) = flaky_daemon(usize::MAX, "secret").await;
+
+        let mut transport = Transport::builder(addr).retry(RetryPolicy::new(4)).build();
+        assert!(request(&mut transport, "get_state").await.is_err());
+        assert_eq!(connections.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn retry_skips_auth_errors() {
+        tokio::time::pause();
+        let (addr, connections) = flaky_daemon(0, "secret").await;
+
+        let mut transport = Transport::builder(addr)
+            .password("guess")
+            .retry(RetryPolicy::new(5))
+            .build();
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Err(Error::AuthError(AuthFailure::WrongPassword))
+        );
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    /// Counts connections, the first of which takes a request and drops without replying.
+    async fn drops_first_request() -> (String, Arc<AtomicUsize>) {
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+        let addr = scripted_daemon(move |i, mut conn| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                authorize(&mut conn, "secret").await;
+                while let Ok(Some(req)) = conn.try_next().await {
+                    if i == 0 {
+                        return;
+                    }
+                    conn.send(req).await.unwrap();
+                }
+            }
+        })
+        .await;
+        (addr, connections)
+    }
+
+    #[tokio::test]
+    async fn retry_non_idempotent() {
+        tokio::time::pause();
+        let (addr, connections) = drops_first_request().await;
+        let mut transport = Transport::builder(addr)
+            .password("secret")
+            .retry(RetryPolicy::new(3))
+            .build();
+        assert!(request(&mut transport, "abort_result").await.is_err());
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+        let (addr, connections) = drops_first_request().await;
+        let mut transport = Transport::builder(addr)
+            .password("secret")
+            .retry(RetryPolicy::new(3).retry_non_idempotent(true))
+            .build();
+        assert_eq!(
+            request(&mut transport, "abort_result").await,
+            Ok("abort_result".into())
+        );
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn retry_non_idempotent_before_sending() {
+        tokio::time::pause();
+        let (addr, connections) = flaky_daemon(1, "secret").await;
+        let mut transport = Transport::builder(addr)
+            .password("secret")
+            .retry(RetryPolicy::new(3))
+            .build();
+        // Dropped while authenticating, so the request never went out
+        assert_eq!(
+            request(&mut transport, "abort_result").await,
+            Ok("abort_result".into())
+        );
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn retry_backoff_schedule() {
+        let policy = RetryPolicy::new(10)
+            .base_delay(Duration::from_millis(100))
+            .max_delay(Duration::from_secs(1))
+            .jitter(false);
+        let delays = (1..=6).map(|n| policy.backoff(n)).collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            [100, 200, 400, 800, 1000, 1000]
+                .iter()
+                .map(|&ms| Duration::from_millis(ms))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(policy.backoff(100), Duration::from_secs(1));
+
+        let policy = policy.jitter(true);
+        for _ in 0..100 {
+            let delay = policy.backoff(3);
+            assert!(delay >= Duration::from_millis(200) && delay <= Duration::from_millis(400));
+        }
+
+        // No cap at all
+        let policy = policy
+            .base_delay(Duration::MAX / 2)
+            .max_delay(Duration::MAX);
+        for _ in 0..100 {
+            let delay = policy.backoff(3);
+            assert!(delay >= Duration::MAX / 4, "{:?}", delay);
+        }
+    }
+
+    #[test]
+    fn rate_limit_schedule() {
+        let limit = RateLimit::new(Duration::from_secs(1)).burst(3);
+        let start = tokio::time::Instant::now();
+        let mut schedule = None;
+        let delays = [0, 0, 0, 0, 0, 2500, 5000]
+            .iter()
+            .map(|&ms| limit.delay(&mut schedule, start + Duration::from_millis(ms)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            [0, 0, 0, 1000, 2000, 500, 0]
+                .iter()
+                .map(|&ms| Duration::from_millis(ms))
+                .collect::<Vec<_>>()
+        );
+
+        let limit = RateLimit::new(Duration::from_secs(1)).burst(0);
+        let mut schedule = None;
+        assert_eq!(limit.delay(&mut schedule, start), Duration::from_secs(0));
+        assert_eq!(limit.delay(&mut schedule, start), Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn rate_limit_spacing() {
+        tokio::time::pause();
+        let addr = scripted_daemon(|_, conn| echo(conn)).await;
+        let mut transport = Transport::builder(addr)
+            .rate_limit(RateLimit::new(Duration::from_secs(1)).burst(2))
+            .build();
+
+        let started = tokio::time::Instant::now();
+        let mut sent = Vec::new();
+        for _ in 0..4 {
+            assert_eq!(
+                request(&mut transport, "get_state").await,
+                Ok("get_state".into())
+            );
+            sent.push(started.elapsed().as_secs());
+        }
+        // The burst goes through at once, then one request per second
+        assert_eq!(sent, [0, 0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_with_retry() {
+        tokio::time::pause();
+        let (addr, connections) = flaky_daemon(1, "secret").await;
+        let mut transport = Transport::builder(addr)
+            .password("secret")
+            .retry(
+                RetryPolicy::new(3)
+                    .base_delay(Duration::from_secs(1))
+                    .jitter(false),
+            )
+            .rate_limit(RateLimit::new(Duration::from_secs(1)))
+            .build();
+
+        let started = tokio::time::Instant::now();
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Ok("get_state".into())
+        );
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+        // The retry's backoff covers the interval, rather than adding to it
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_secs(1), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Ok("get_state".into())
+        );
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_secs(2), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(3), "{:?}", elapsed);
+    }
+
+    #[test]
+    fn idempotency() {
+        assert!(is_idempotent(&[treexml::Element::new("get_state")]));
+        assert!(!is_idempotent(&[treexml::Element::new("project_detach")]));
+    }
+
+    #[tokio::test]
+    async fn clones_share_connection() {
+        let (addr, connections) = flaky_daemon(0, "secret").await;
+
+        let transport = Transport::new(addr, Some("secret"));
+        let worker = |mut transport: Transport, name: &'static str| async move {
+            for _ in 0..20 {
+                assert_eq!(request(&mut transport, name).await, Ok(name.to_string()));
+            }
+        };
+        futures::join!(
+            worker(transport.clone(), "get_state"),
+            worker(transport.clone(), "get_results"),
+            worker(transport, "get_messages"),
+        );
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    async fn handle_request(handle: &mut Handle, tag: &str) -> Result<String, Error> {
+        handle.ready_and().await?;
+        let reply = handle.call(vec![treexml::Element::new(tag)]).await?;
+        Ok(reply[0].name.clone())
+    }
+
+    #[tokio::test]
+    async fn actor_queues_in_order() {
+        let requests = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        let addr = scripted_daemon(move |_, mut conn| {
+            let seen = seen.clone();
+            async move {
+                authorize(&mut conn, "secret").await;
+                while let Ok(Some(req)) = conn.try_next().await {
+                    seen.lock().unwrap().push(req[0].name.clone());
+                    conn.send(req).await.unwrap();
+                }
+            }
+        })
+        .await;
+
+        let (handle, task) = spawn(addr, Some("secret"));
+        let calls = (0..10)
+            .map(|i| {
+                let mut handle = handle.clone();
+                let tag = format!("request_{i}");
+                // Build the futures up front so that all requests are queued in this order
+                let reply = handle.call(vec![treexml::Element::new(tag.clone())]);
+                async move { assert_eq!(reply.await.unwrap()[0].name, tag) }
+            })
+            .collect::<Vec<_>>();
+        futures::future::join_all(calls).await;
+
+        assert_eq!(
+            *requests.lock().unwrap(),
+            (0..10).map(|i| format!("request_{i}")).collect::<Vec<_>>()
+        );
+
+        drop(handle);
+        task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn actor_reconnects_after_disconnect() {
+        let addr = scripted_daemon(|i, mut conn| async move {
+            authorize(&mut conn, "secret").await;
+            if i == 0 {
+                // Read one request and hang up mid-exchange
+                conn.try_next().await.unwrap();
+                return;
+            }
+            while let Ok(Some(req)) = conn.try_next().await {
+                conn.send(req).await.unwrap();
+            }
+        })
+        .await;
+
+        let (mut handle, _task) = spawn(addr, Some("secret"));
+        assert!(handle_request(&mut handle, "get_state").await.is_err());
+        assert_eq!(
+            handle_request(&mut handle, "get_state").await,
+            Ok("get_state".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn actor_abandoned_request() {
+        let (addr, connections) = flaky_daemon(0, "secret").await;
+
+        let (mut handle, _task) = spawn(addr, Some("secret"));
+        drop(handle.call(vec![treexml::Element::new("get_state")]));
+        assert_eq!(
+            handle_request(&mut handle, "get_results").await,
+            Ok("get_results".into())
+        );
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn actor_stopped() {
+        let (addr, _) = flaky_daemon(0, "secret").await;
+        let (mut handle, task) = spawn(addr, Some("secret"));
+        let mut other = handle.clone();
+        assert_eq!(
+            handle_request(&mut handle, "get_state").await,
+            Ok("get_state".into())
+        );
+        drop(handle);
+        assert_eq!(
+            handle_request(&mut other, "get_state").await,
+            Ok("get_state".into())
+        );
+        drop(other);
+        task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn from_io_duplex() {
+        let (client, server) = tokio::io::duplex(4096);
+        let daemon = tokio::spawn(async move {
+            let mut conn = BoincCodec::new(CodecMode::Server).framed(server);
+            authorize(&mut conn, "secret").await;
+            let req = conn.try_next().await.unwrap().unwrap();
+            conn.send(req).await.unwrap();
+        });
+
+        let mut transport = Transport::from_io(client, Some("secret"));
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Ok("get_state".into())
+        );
+        daemon.await.unwrap();
+        assert!(request(&mut transport, "get_state").await.is_err());
+        // The stream is gone for good
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Err(Error::ConnectionClosed)
+        );
+    }
+
+    #[tokio::test]
+    async fn from_io_client() {
+        let (client, server) = tokio::io::duplex(4096);
+        tokio::spawn(async move {
+            let mut conn = BoincCodec::new(CodecMode::Server).framed(server);
+            authorize(&mut conn, "secret").await;
+            while let Ok(Some(_)) = conn.try_next().await {
+                conn.send(vec![treexml::Element::new("msgs")])
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let client = crate::Client::new(Transport::from_io(client, Some("secret")));
+        assert!(client.get_messages(0).await.unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn unix_socket() {
+        use tokio::net::UnixListener;
+
+        let path = std::env::temp_dir().join(format!("boinc-rpc-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut listener = UnixListener::bind(&path).unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut conn = BoincCodec::new(CodecMode::Server).framed(stream);
+            authorize(&mut conn, "secret").await;
+            while let Ok(Some(req)) = conn.try_next().await {
+                conn.send(req).await.unwrap();
+            }
+        });
+
+        let mut transport = Transport::new_unix(&path, Some("secret"));
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Ok("get_state".into())
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        let mut transport = Transport::new_unix(&path, None::<String>);
+        match request(&mut transport, "get_state").await {
+            Err(Error::ConnectError(msg)) => assert!(msg.contains(&*path.to_string_lossy())),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    #[tokio::test]
+    async fn tls() {
+        use tokio_rustls::{
+            rustls::{Certificate, ClientConfig, NoClientAuth, PrivateKey, ServerConfig},
+            TlsAcceptor,
+        };
+
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+        let der = Certificate(cert.serialize_der().unwrap());
+        let mut server = ServerConfig::new(NoClientAuth::new());
+        server
+            .set_single_cert(
+                vec![der.clone()],
+                PrivateKey(cert.serialize_private_key_der()),
+            )
+            .unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(server));
+
+        let mut listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    // Clients distrusting the certificate abort the handshake
+                    if let Ok(stream) = acceptor.accept(stream).await {
+                        let mut conn = BoincCodec::new(CodecMode::Server).framed(stream);
+                        authorize(&mut conn, "secret").await;
+                        while let Ok(Some(req)) = conn.try_next().await {
+                            conn.send(req).await.unwrap();
+                        }
+                    }
+                });
+            }
+        });
+
+        let mut client = ClientConfig::new();
+        client.root_store.add(&der).unwrap();
+        let connector = TlsConnector::from(Arc::new(client));
+
+        let mut transport =
+            Transport::new_tls(addr.clone(), "localhost", Some("secret"), connector.clone());
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Ok("get_state".into())
+        );
+
+        let mut transport =
+            Transport::new_tls(addr.clone(), "example.com", Some("secret"), connector);
+        assert_eq!(
+            request(&mut transport, "get_state")
+                .await
+                .unwrap_err()
+                .kind(),
+            ErrorKind::Network
+        );
+
+        let untrusting = TlsConnector::from(Arc::new(ClientConfig::new()));
+        let mut transport = Transport::new_tls(addr, "localhost", Some("secret"), untrusting);
+        assert!(request(&mut transport, "get_state").await.is_err());
+    }
+
+    /// Port and password, the password exposed for comparing.
+    fn exposed(res: Result<(u16, Option<Secret>), Error>) -> Result<(u16, Option<String>), Error> {
+        res.map(|(port, password)| (port, password.map(|p| p.expose().into())))
+    }
+
+    #[test]
+    fn data_dir() {
+        let dir = std::env::temp_dir().join(format!("boinc-rpc-data-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        match read_data_dir(&dir) {
+            Err(Error::ConnectError(msg)) => {
+                assert!(msg.contains(&*dir.join("gui_rpc_auth.cfg").to_string_lossy()));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        std::fs::write(dir.join("gui_rpc_auth.cfg"), b"\xff\xfe").unwrap();
+        assert!(matches!(
+            read_data_dir(&dir),
+            Err(Error::InvalidPasswordError(_))
+        ));
+
+        std::fs::write(dir.join("gui_rpc_auth.cfg"), "secret\n").unwrap();
+        assert_eq!(
+            exposed(read_data_dir(&dir)),
+            Ok((DEFAULT_PORT, Some("secret".into())))
+        );
+
+        std::fs::write(dir.join("gui_rpc_auth.cfg"), "").unwrap();
+        std::fs::write(
+            dir.join("cc_config.xml"),
+            "<cc_config>\n  <options>\n    <gui_rpc_port>31420</gui_rpc_port>\n  </options>\n</cc_config>\n",
+        )
+        .unwrap();
+        assert_eq!(exposed(read_data_dir(&dir)), Ok((31420, None)));
+
+        std::fs::write(dir.join("cc_config.xml"), "<cc_config>").unwrap();
+        assert!(matches!(read_data_dir(&dir), Err(Error::DataParseError(_))));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Address nothing listens on.
+    async fn refusing_addr() -> SocketAddr {
+        TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn socket_options() {
+        let addr = scripted_daemon(|_, conn| echo(conn)).await;
+
+        let stream = connect_tcp(addr.clone().into(), TcpOptions::default())
+            .await
+            .unwrap();
+        assert!(stream.nodelay().unwrap());
+        assert_eq!(stream.keepalive().unwrap(), None);
+
+        let builder = Transport::builder(addr.clone())
+            .tcp_nodelay(false)
+            .tcp_keepalive(Some(Duration::from_secs(30)));
+        let stream = connect_tcp(addr.into(), builder.config.tcp).await.unwrap();
+        assert!(!stream.nodelay().unwrap());
+        assert_eq!(stream.keepalive().unwrap(), Some(Duration::from_secs(30)));
+    }
+
+    #[tokio::test]
+    async fn connect_falls_back() {
+        let refusing = refusing_addr().await;
+        let addr = scripted_daemon(|_, conn| echo(conn)).await.parse().unwrap();
+
+        let mut transport = Transport::new(vec![refusing, addr], None::<String>);
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Ok("get_state".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn connect_reports_all_addresses() {
+        let first = refusing_addr().await;
+        let second = refusing_addr().await;
+
+        let mut transport = Transport::new(vec![first, second], None::<String>);
+        match request(&mut transport, "get_state").await {
+            Err(Error::ConnectError(msg)) => {
+                assert!(msg.contains(&first.to_string()));
+                assert!(msg.contains(&second.to_string()));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let mut transport = Transport::new(Vec::new(), None::<String>);
+        assert!(matches!(
+            request(&mut transport, "get_state").await,
+            Err(Error::ConnectError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn connect_ipv6() {
+        let mut listener = match TcpListener::bind("[::1]:0").await {
+            Ok(listener) => listener,
+            // No IPv6 on this machine
+            Err(_) => return,
+        };
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            echo(BoincCodec::new(CodecMode::Server).framed(stream)).await;
+        });
+
+        let mut transport = Transport::new(("::1", port), None::<String>);
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Ok("get_state".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn keepalive_replaces_dead_connection() {
+        tokio::time::pause();
+        let pings = Arc::new(AtomicUsize::new(0));
+        let counter = pings.clone();
+        let builder = memory_daemon(move |i, mut conn| {
+            let counter = counter.clone();
+            async move {
+                authorize(&mut conn, "secret").await;
+                if i > 0 {
+                    while let Ok(Some(req)) = conn.try_next().await {
+                        conn.send(req).await.unwrap();
+                    }
+                    return;
+                }
+                // Answer one request, then keep the connection open without ever replying
+                let req = conn.try_next().await.unwrap().unwrap();
+                conn.send(req).await.unwrap();
+                while let Ok(Some(req)) = conn.try_next().await {
+                    if req[0].name == "get_message_count" {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                    }
+                }
+            }
+        });
+
+        let (mut handle, _task) = builder
+            .password("secret")
+            .keepalive(Duration::from_secs(1))
+            .spawn();
+        assert_eq!(
+            handle_request(&mut handle, "get_state").await,
+            Ok("get_state".into())
+        );
+        // Pinged after a second, and given up on after another
+        tokio::time::delay_for(Duration::from_secs(3)).await;
+        assert_eq!(pings.load(Ordering::SeqCst), 1);
+
+        // Without the ping this would wait on the dead connection forever
+        let res = tokio::time::timeout(
+            Duration::from_secs(5),
+            handle_request(&mut handle, "get_state"),
+        )
+        .await;
+        assert_eq!(res, Ok(Ok("get_state".into())));
+    }
+
+    #[tokio::test]
+    async fn keepalive_healthy_connection() {
+        tokio::time::pause();
+        let pings = Arc::new(AtomicUsize::new(0));
+        let counter = pings.clone();
+        let builder = memory_daemon(move |i, mut conn| {
+            let counter = counter.clone();
+            async move {
+                authorize(&mut conn, "secret").await;
+                while let Ok(Some(req)) = conn.try_next().await {
+                    if req[0].name == "get_message_count" {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                    }
+                    // Name the connection in the reply, to tell if it was replaced
+                    let mut reply = treexml::Element::new(format!("{}{}", req[0].name, i));
+                    reply.children = req;
+                    conn.send(vec![reply]).await.unwrap();
+                }
+            }
+        });
+
+        let (mut handle, _task) = builder
+            .password("secret")
+            .keepalive(Duration::from_secs(1))
+            .spawn();
+        handle_request(&mut handle, "get_state").await.unwrap();
+        tokio::time::delay_for(Duration::from_millis(3500)).await;
+        assert!(pings.load(Ordering::SeqCst) >= 3);
+        assert_eq!(
+            handle_request(&mut handle, "get_state").await,
+            Ok("get_state0".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn addresses() {
+        let addr = scripted_daemon(|i, mut conn| async move {
+            if i > 0 {
+                return echo(conn).await;
+            }
+            // Answer one request, then hang up
+            authorize(&mut conn, "secret").await;
+            let req = conn.try_next().await.unwrap().unwrap();
+            conn.send(req).await.unwrap();
+        })
+        .await;
+
+        let mut transport = Transport::new(addr.clone(), Some("secret"));
+        assert_eq!(transport.endpoint(), addr);
+        assert_eq!(transport.peer_addr(), None);
+        request(&mut transport, "get_state").await.unwrap();
+        assert_eq!(transport.peer_addr(), Some(addr.parse().unwrap()));
+        let local = transport.local_addr().unwrap();
+        assert!(local.ip().is_loopback());
+        // Clones share the connection
+        assert_eq!(transport.clone().local_addr(), Some(local));
+
+        assert!(request(&mut transport, "get_state").await.is_err());
+        assert_eq!(transport.peer_addr(), None);
+        assert_eq!(transport.local_addr(), None);
+    }
+
+    #[test]
+    fn addr_parsing() {
+        for (addr, host, port) in &[
+            ("myhost", "myhost", DEFAULT_PORT),
+            ("myhost:31420", "myhost", 31420),
+            ("127.0.0.1", "127.0.0.1", DEFAULT_PORT),
+            ("127.0.0.1:31420", "127.0.0.1", 31420),
+            ("::1", "::1", DEFAULT_PORT),
+            ("[::1]", "::1", DEFAULT_PORT),
+            ("[::1]:31420", "::1", 31420),
+            ("[2001:db8::1]:80", "2001:db8::1", 80),
+        ] {
+            assert_eq!(parse_addr(addr), Ok((host.to_string(), *port)), "{addr}");
+        }
+
+        for addr in &[
+            "",
+            ":31416",
+            "myhost:",
+            "myhost:boinc",
+            "myhost:70000",
+            "fe80::1:31416x",
+            "[::1",
+            "[::1]31416",
+            "[myhost]:31416",
+        ] {
+            match parse_addr(addr) {
+                Err(Error::ConnectError(msg)) => {
+                    assert!(msg.contains(&format!("{addr:?}")), "{}", msg);
+                }
+                other => panic!("{:?} parsed as {:?}", addr, other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn addr_default_port() {
+        let addr = scripted_daemon(|_, conn| echo(conn)).await;
+        let socket: SocketAddr = addr.parse().unwrap();
+
+        // Given the port, strings and socket addresses behave alike
+        for transport in &mut [
+            Transport::new(addr.clone(), None::<&str>),
+            Transport::new(("127.0.0.1", socket.port()), None::<&str>),
+            Transport::new(socket, None::<&str>),
+        ] {
+            assert_eq!(
+                request(transport, "get_state").await,
+                Ok("get_state".into())
+            );
+        }
+
+        let mut transport = Transport::new("myhost:boinc", None::<&str>);
+        match request(&mut transport, "get_state").await {
+            Err(Error::ConnectError(msg)) => assert!(msg.contains("\"myhost:boinc\""), "{}", msg),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn address_default_port() {
+        let localhost = SocketAddr::from((Ipv4Addr::LOCALHOST, DEFAULT_PORT));
+        for addr in [
+            Address::from("127.0.0.1"),
+            Address::from(&String::from("127.0.0.1")),
+            Address::from(Cow::from("127.0.0.1")),
+            Address::from(("127.0.0.1", DEFAULT_PORT)),
+            Address::from(localhost),
+        ] {
+            assert_eq!(addr.resolve(), Ok(vec![localhost]), "{addr}");
+        }
+        assert_eq!(
+            Address::from(("127.0.0.1", 31420)).resolve(),
+            Ok(vec![SocketAddr::from((Ipv4Addr::LOCALHOST, 31420))])
+        );
+    }
+
+    #[test]
+    fn endpoints() {
+        assert_eq!(
+            Address::from("localhost:31416").to_string(),
+            "localhost:31416"
+        );
+        assert_eq!(Address::from("myhost").to_string(), "myhost");
+        assert_eq!(Address::from(("::1", 31416)).to_string(), "[::1]:31416");
+        assert_eq!(
+            Address::from((String::from("localhost"), 31416)).to_string(),
+            "localhost:31416"
+        );
+        assert_eq!(
+            Address::from((Ipv6Addr::LOCALHOST, DEFAULT_PORT)).to_string(),
+            "[::1]:31416"
+        );
+        assert_eq!(
+            Address::from(SocketAddr::from((Ipv4Addr::LOCALHOST, 31420))).to_string(),
+            "127.0.0.1:31420"
+        );
+        assert_eq!(
+            Transport::new(Cow::from("myhost"), None::<&str>).endpoint(),
+            "myhost"
+        );
+        assert_eq!(
+            Transport::from_io(tokio::io::duplex(1).0, None::<String>).endpoint(),
+            "stream"
+        );
+    }
+
+    #[tokio::test]
+    async fn eager_connect() {
+        let (addr, connections) = flaky_daemon(0, "secret").await;
+
+        let transport = Transport::new(addr.clone(), Some("guess"));
+        assert_eq!(
+            transport.connect().await,
+            Err(Error::AuthError(AuthFailure::WrongPassword))
+        );
+
+        let mut transport = Transport::new(addr, Some("secret"));
+        assert_eq!(transport.connect().await, Ok(()));
+        assert!(transport.peer_addr().is_some());
+        assert_eq!(transport.connect().await, Ok(()));
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Ok("get_state".into())
+        );
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn version_handshake() {
+        let addr = scripted_daemon(|i, mut conn| async move {
+            authorize(&mut conn, "secret").await;
+            let req = conn.try_next().await.unwrap().unwrap();
+            assert_eq!(req[0].name, "exchange_versions");
+            let mut version = treexml::Element::new("server_version");
+            for (tag, v) in &[("major", 7), ("minor", 16 + i), ("release", 3)] {
+                let mut node = treexml::Element::new(*tag);
+                node.text = Some(v.to_string());
+                version.children.push(node);
+            }
+            conn.send(vec![version]).await.unwrap();
+
+            // The first daemon gets upgraded after one request
+            let limit = if i == 0 { 1 } else { usize::MAX };
+            for _ in 0..limit {
+                match conn.try_next().await {
+                    Ok(Some(_)) => conn
+                        .send(vec![treexml::Element::new("msgs")])
+                        .await
+                        .unwrap(),
+                    _ => return,
+                }
+            }
+        })
+        .await;
+
+        let client = crate::Client::connect(addr, Some("secret")).await.unwrap();
+        assert_eq!(client.server_version(), Some(VersionInfo::new(7, 16, 3)));
+        client.get_messages(0).await.unwrap();
+        assert!(client.get_messages(0).await.is_err());
+        assert_eq!(client.server_version(), None);
+        client.get_messages(0).await.unwrap();
+        assert_eq!(client.server_version(), Some(VersionInfo::new(7, 17, 3)));
+    }
+
+    #[tokio::test]
+    async fn close() {
+        let (done_tx, done_rx) = oneshot::channel();
+        let done_tx = Arc::new(std::sync::Mutex::new(Some(done_tx)));
+        let addr = scripted_daemon(move |_, mut conn| {
+            let done_tx = done_tx.clone();
+            async move {
+                authorize(&mut conn, "secret").await;
+                while let Ok(Some(req)) = conn.try_next().await {
+                    conn.send(req).await.unwrap();
+                }
+                // The client hung up cleanly
+                let tx = done_tx.lock().unwrap().take();
+                if let Some(tx) = tx {
+                    let _ = tx.send(());
+                }
+            }
+        })
+        .await;
+
+        let mut transport = Transport::builder(addr)
+            .password("secret")
+            .retry(RetryPolicy::new(5).base_delay(Duration::from_secs(30)))
+            .build();
+        let other = transport.clone();
+        assert_eq!(
+            request(&mut transport, "get_state").await,
+            Ok("get_state".into())
+        );
+        assert_eq!(other.close().await, Ok(()));
+        done_rx.await.unwrap();
+
+        assert_eq!(transport.peer_addr(), None);
+        // No retries and no reconnection
+        assert_eq!(
+            tokio::time::timeout(Duration::from_secs(5), transport.call(vec![])).await,
+            Ok(Err(Error::ConnectionClosed))
+        );
+        assert_eq!(
+            transport.ready_and().await.err(),
+            Some(Error::ConnectionClosed)
+        );
+        assert_eq!(transport.connect().await, Err(Error::ConnectionClosed));
+        assert_eq!(transport.close().await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn one_shot() {
+        let (addr, connections) = flaky_daemon(0, "secret").await;
+
+        let mut transport = OneShotTransport::new(addr, Some("secret"));
+        for tag in &["get_state", "get_results"] {
+            transport.ready_and().await.unwrap();
+            let reply = transport.call(vec![treexml::Element::new(*tag)]).await;
+            assert_eq!(reply.unwrap()[0].name, *tag);
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn state_watcher() {
+        let addr = scripted_daemon(|i, mut conn| async move {
+            if i > 0 {
+                return echo(conn).await;
+            }
+            // Answer one request, then hang up
+            authorize(&mut conn, "secret").await;
+            let req = conn.try_next().await.unwrap().unwrap();
+            conn.send(req).await.unwrap();
+        })
+        .await;
+
+        let mut transport = Transport::new(addr, Some("secret"));
+        let mut watcher = transport.state_watcher();
+        let states = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = states.clone();
+        tokio::spawn(async move {
+            while let Some(state) = watcher.recv().await {
+                seen.lock().unwrap().push(state);
+            }
+        });
+
+        request(&mut transport, "get_state").await.unwrap();
+        tokio::time::delay_for(Duration::from_millis(10)).await;
+        assert!(request(&mut transport, "get_state").await.is_err());
+        tokio::time::delay_for(Duration::from_millis(10)).await;
+        request(&mut transport, "get_state").await.unwrap();
+        tokio::time::delay_for(Duration::from_millis(10)).await;
+        transport.close().await.unwrap();
+        tokio::time::delay_for(Duration::from_millis(10)).await;
+
+        assert_eq!(
+            *states.lock().unwrap(),
+            vec![
+                ConnectionState::Connecting,
+                ConnectionState::Ready,
+                ConnectionState::Failed(Error::ConnectionClosed),
+                ConnectionState::Reconnecting { attempt: 1 },
+                ConnectionState::Ready,
+                ConnectionState::Closed,
+            ]
+        );
+    }
+
+    /// Answers `get_state` after `delay` and everything else right away.
+    fn slow_state_daemon(
+        delay: Duration,
+    ) -> (TransportBuilder<tokio::io::DuplexStream>, Arc<AtomicUsize>) {
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+        let builder = memory_daemon(move |_, mut conn| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                authorize(&mut conn, "secret").await;
+                while let Ok(Some(req)) = conn.try_next().await {
+                    if req[0].name == "get_state" {
+                        tokio::time::delay_for(delay).await;
+                    }
+                    if conn.send(req).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        (builder.password("secret"), connections)
+    }
+
+    #[tokio::test]
+    async fn pool_parallel_requests() {
+        tokio::time::pause();
+        let (builder, connections) = slow_state_daemon(Duration::from_secs(10));
+        let pool = builder.pooled(2);
+
+        let mut slow = pool.clone();
+        let slow = tokio::spawn(async move { request(&mut slow, "get_state").await });
+        tokio::time::delay_for(Duration::from_secs(1)).await;
+
+        let mut fast = pool.clone();
+        // Timed in a task of its own, which runs as soon as it can
+        let fast = tokio::spawn(async move {
+            let started = tokio::time::Instant::now();
+            let res = request(&mut fast, "get_host_info").await;
+            (res, started.elapsed())
+        });
+        let (res, elapsed) = fast.await.unwrap();
+        assert_eq!(res, Ok("get_host_info".into()));
+        // Not held up by the slow request on the other connection
+        assert!(elapsed < Duration::from_secs(1), "{:?}", elapsed);
+        assert_eq!(slow.await.unwrap(), Ok("get_state".into()));
+
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+        assert_eq!(pool.idle_connections(), 2);
+    }
+
+    #[tokio::test]
+    async fn pool_limit() {
+        tokio::time::pause();
+        let (builder, connections) = slow_state_daemon(Duration::from_secs(1));
+        let pool = builder.pooled(2);
+
+        let requests = (0..4).map(|_| {
+            let mut pool = pool.clone();
+            async move { request(&mut pool, "get_state").await }
+        });
+        for res in futures::future::join_all(requests).await {
+            assert_eq!(res, Ok("get_state".into()));
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn pool_drops_failed_connection() {
+        let addr = scripted_daemon(|i, mut conn| async move {
+            if i > 0 {
+                return echo(conn).await;
+            }
+            // First connection answers once, then hangs up
+            conn.try_next().await.unwrap();
+            conn.send(vec![treexml::Element::new("authorized")])
+                .await
+                .unwrap();
+            let req = conn.try_next().await.unwrap().unwrap();
+            conn.send(req).await.unwrap();
+        })
+        .await;
+        let mut pool = Transport::pooled(addr, None::<&str>, 2);
+
+        assert_eq!(
+            request(&mut pool, "get_state").await,
+            Ok("get_state".into())
+        );
+        assert_eq!(pool.idle_connections(), 1);
+        assert!(request(&mut pool, "get_state").await.is_err());
+        assert_eq!(pool.idle_connections(), 0);
+        assert_eq!(
+            request(&mut pool, "get_state").await,
+            Ok("get_state".into())
+        );
+        assert_eq!(pool.idle_connections(), 1);
+    }
+
+    #[tokio::test]
+    async fn pool_idle_timeout() {
+        tokio::time::pause();
+        let (builder, connections) = slow_state_daemon(Duration::from_secs(0));
+        let mut pool = builder.pooled(2).idle_timeout(Duration::from_secs(60));
+
+        request(&mut pool, "get_host_info").await.unwrap();
+        tokio::time::delay_for(Duration::from_secs(30)).await;
+        request(&mut pool, "get_host_info").await.unwrap();
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+        // Still open a minute after the first request, as the second one reused it
+        tokio::time::delay_for(Duration::from_secs(45)).await;
+        assert_eq!(pool.idle_connections(), 1);
+        // Closed without the pool being used again
+        tokio::time::delay_for(Duration::from_secs(30)).await;
+        assert_eq!(pool.idle_connections(), 0);
+
+        request(&mut pool, "get_host_info").await.unwrap();
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+        assert_eq!(pool.idle_connections(), 1);
+    }
+}
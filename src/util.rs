@@ -1,8 +1,38 @@
-use std::str::FromStr;
+use std::{borrow::Cow, fmt::Display, str::FromStr};
+use tracing::{debug, warn};
 
-use crate::errors::Error;
+use crate::{
+    errors::Error,
+    rpc::{DEFAULT_MAX_DEPTH, DEFAULT_MAX_ELEMENTS},
+};
 
+/// Bounds on the documents accepted for parsing, as the daemon port may be reachable by anyone
+/// on the network.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    pub max_depth: usize,
+    pub max_elements: usize,
+}
+
+impl Limits {
+    pub const DEFAULT: Self = Self {
+        max_depth: DEFAULT_MAX_DEPTH,
+        max_elements: DEFAULT_MAX_ELEMENTS,
+    };
+}
+
+/// Parses the document within `Limits::DEFAULT`.
+#[cfg(any(test, feature = "tokio"))]
 pub fn parse_node(s: &str) -> Result<treexml::Element, Error> {
+    parse_node_limited(s, Limits::DEFAULT)
+}
+
+pub fn parse_node_limited(s: &str, limits: Limits) -> Result<treexml::Element, Error> {
+    check_limits(s, limits)?;
+    parse_unchecked(s)
+}
+
+fn parse_unchecked(s: &str) -> Result<treexml::Element, Error> {
     let doc = treexml::Document::parse(s.as_bytes())?;
 
     Ok(doc
@@ -10,12 +40,184 @@ pub fn parse_node(s: &str) -> Result<treexml::Element, Error> {
         .ok_or_else(|| Error::NullError("Root is empty".into()))?)
 }
 
+/// Like `parse_node_limited`, but repairs the malformed XML that daemons are known to send, such
+/// as unescaped ampersands in project names and control characters in message bodies, rather
+/// than failing. The error is that of the original document if the repairs do not help.
+pub fn parse_node_lenient(s: &str, limits: Limits) -> Result<treexml::Element, Error> {
+    check_limits(s, limits)?;
+    parse_unchecked(s).or_else(|e| {
+        let repaired = repair_xml(s);
+        if repaired == s {
+            return Err(e);
+        }
+        debug!("Repairing malformed XML: {:?}", e);
+        parse_unchecked(&repaired).map_err(|_| e)
+    })
+}
+
+/// Scans the markup of `s` for nesting deeper than `limits.max_depth` or more elements than
+/// `limits.max_elements`, before the parser builds a tree of it. Malformed markup is left for the
+/// parser to reject.
+fn check_limits(s: &str, limits: Limits) -> Result<(), Error> {
+    let mut depth = 0_usize;
+    let mut elements = 0_usize;
+    let mut rest = s;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start..];
+        let skip_to = |terminator: &str| rest.find(terminator).map(|i| i + terminator.len());
+        let end = if rest.starts_with("<!--") {
+            skip_to("-->")
+        } else if rest.starts_with("<![CDATA[") {
+            skip_to("]]>")
+        } else if rest.starts_with("<?") || rest.starts_with("<!") {
+            skip_to(">")
+        } else if rest.starts_with("</") {
+            depth = depth.saturating_sub(1);
+            skip_to(">")
+        } else {
+            let end = tag_end(rest);
+            elements += 1;
+            if elements > limits.max_elements {
+                return Err(Error::DataParseError(format!(
+                    "XML exceeds the maximum of {} elements",
+                    limits.max_elements
+                )));
+            }
+            if depth + 1 > limits.max_depth {
+                return Err(Error::DataParseError(format!(
+                    "XML exceeds the maximum nesting depth of {}",
+                    limits.max_depth
+                )));
+            }
+            if !matches!(end, Some(end) if rest.as_bytes()[end - 2] == b'/') {
+                depth += 1;
+            }
+            end
+        };
+        match end {
+            Some(end) => rest = &rest[end..],
+            None => break,
+        }
+    }
+    Ok(())
+}
+
+/// Length of the tag at the start of `s` up to and including its `>`, which may also appear in
+/// quoted attribute values.
+fn tag_end(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, b) in s.bytes().enumerate() {
+        match (quote, b) {
+            (None, b'"' | b'\'') => quote = Some(b),
+            (Some(q), b) if b == q => quote = None,
+            (None, b'>') => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Elements holding credentials, or what can stand in for them.
+const SECRET_TAGS: &[&str] = &[
+    "nonce_hash",
+    "authenticator",
+    "passwd_hash",
+    "password",
+    "http_user_passwd",
+    "socks5_user_passwd",
+];
+
+/// Replaces the contents of `SECRET_TAGS` elements in the XML text with `***`.
+pub fn redact_secrets(s: &str) -> Cow<'_, str> {
+    let mut text = Cow::Borrowed(s);
+    for tag in SECRET_TAGS {
+        let open = format!("<{tag}>");
+        let close = format!("</{tag}>");
+        let mut from = 0;
+        while let Some(start) = text[from..].find(&open).map(|i| from + i + open.len()) {
+            let end = match text[start..].find(&close) {
+                Some(len) => start + len,
+                None => break,
+            };
+            text.to_mut().replace_range(start..end, "***");
+            from = start + "***".len() + close.len();
+        }
+    }
+    text
+}
+
+/// Escapes ampersands that do not start an entity and drops characters XML does not allow.
+/// CDATA sections are left alone, control characters aside.
+fn repair_xml(s: &str) -> String {
+    const CDATA_START: &str = "<![CDATA[";
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with(CDATA_START) {
+            let end = rest.find("]]>").map_or(rest.len(), |i| i + 3);
+            out.extend(rest[..end].chars().filter(|&c| is_xml_char(c)));
+            rest = &rest[end..];
+            continue;
+        }
+        match c {
+            '&' if !starts_with_entity(rest) => out.push_str("&amp;"),
+            c if !is_xml_char(c) => {}
+            c => out.push(c),
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// Whether `s` starts with one of the predefined entities or a character reference.
+fn starts_with_entity(s: &str) -> bool {
+    let digits = |code: &str, radix| !code.is_empty() && code.chars().all(|c| c.is_digit(radix));
+    let name = match s.find(';') {
+        Some(end) => &s[1..end],
+        None => return false,
+    };
+    match name {
+        "amp" | "lt" | "gt" | "quot" | "apos" => true,
+        name => name
+            .strip_prefix("#x")
+            .map(|hex| digits(hex, 16))
+            .or_else(|| name.strip_prefix('#').map(|dec| digits(dec, 10)))
+            .unwrap_or(false),
+    }
+}
+
+/// Whether XML 1.0 allows `c` in documents, as text or otherwise.
+pub const fn is_xml_char(c: char) -> bool {
+    !matches!(c, '\u{0}'..='\u{8}' | '\u{b}' | '\u{c}' | '\u{e}'..='\u{1f}' | '\u{fffe}' | '\u{ffff}')
+}
+
+/// Parses the node contents, ignoring surrounding whitespace. CDATA contents are accepted too.
 pub fn eval_node_contents<T>(node: &treexml::Element) -> Option<T>
 where
     T: FromStr,
 {
-    match node.text {
-        Some(ref v) => v.parse::<T>().ok(),
+    node.text
+        .as_ref()
+        .or(node.cdata.as_ref())
+        .and_then(|v| v.trim().parse::<T>().ok())
+}
+
+/// Strict variant of `eval_node_contents` for values that must be present and well-formed.
+pub fn parse_node_contents<T>(node: &treexml::Element) -> Result<T, Error>
+where
+    T: FromStr,
+{
+    eval_node_contents(node).ok_or_else(|| Error::InvalidNodeValue {
+        node: node.name.clone(),
+        value: any_text(node).unwrap_or_default(),
+    })
+}
+
+/// Evaluates a boolean node, accepting both `0`/`1` and bare `<flag/>` markers.
+pub fn eval_bool(node: &treexml::Element) -> Option<bool> {
+    match node.text.as_ref().map(|v| v.trim()) {
+        None | Some("" | "1" | "true") => Some(true),
+        Some("0" | "false") => Some(false),
         _ => None,
     }
 }
@@ -30,6 +232,388 @@ pub fn any_text(node: &treexml::Element) -> Option<String> {
     None
 }
 
+/// Trims the text, mapping blank contents to `None`.
 pub fn trimmed_optional(e: &Option<String>) -> Option<String> {
-    e.clone().map(|v| v.trim().into())
+    e.as_ref()
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .map(Into::into)
+}
+
+/// Whether two master URLs name the same project. The daemon settles on a trailing slash,
+/// either scheme leads to the same project and host names are case-insensitive, though paths
+/// are not.
+pub fn same_master_url(a: &str, b: &str) -> bool {
+    let bare = |url: &str| {
+        let url = url.trim();
+        let url = ["https://", "http://"]
+            .iter()
+            .find(|scheme| matches!(url.get(..scheme.len()), Some(s) if s.eq_ignore_ascii_case(scheme)))
+            .map_or(url, |scheme| &url[scheme.len()..]);
+        let url = url.trim_end_matches('/');
+        let (host, path) = url.split_at(url.find('/').unwrap_or(url.len()));
+        (host.to_ascii_lowercase(), path.to_owned())
+    };
+    bare(a) == bare(b)
+}
+
+/// Point in time of `seconds` since the Unix epoch, as the daemon reports times, `None` for 0,
+/// which the daemon reports for times not yet come.
+pub fn system_time(seconds: f64) -> Option<std::time::SystemTime> {
+    if seconds.is_finite() && seconds > 0.0 {
+        std::time::UNIX_EPOCH.checked_add(std::time::Duration::from_secs_f64(seconds))
+    } else {
+        None
+    }
+}
+
+/// Whether `s` matches `pattern`, in which `*` stands for any run of characters.
+pub fn glob_match(pattern: &str, s: &str) -> bool {
+    let parts: Vec<_> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return s == pattern;
+    }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if s.len() < first.len() + last.len() || !s.starts_with(first) || !s.ends_with(last) {
+        return false;
+    }
+    // Taking each part in between as early as it occurs leaves the most room for the rest
+    let mut rest = &s[first.len()..s.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Current time in seconds since the Unix epoch, as the daemon reports times.
+pub fn unix_time() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
+
+/// Appends a child node holding `value`, skipping it entirely when the value is unset.
+pub fn push_node<T: Display>(node: &mut treexml::Element, tag: &str, value: Option<T>) {
+    if let Some(v) = value {
+        let mut child = treexml::Element::new(tag);
+        child.text = Some(format!("{v}"));
+        node.children.push(child);
+    }
+}
+
+/// Reports a child tag that `model` does not map, which usually means the daemon speaks a newer
+/// protocol. Logged at debug level, or as a warning with the `strict-unknown` feature.
+pub fn unknown_tag(model: &str, node: &treexml::Element) {
+    if cfg!(feature = "strict-unknown") {
+        warn!(model, tag = &*node.name, "Unknown tag");
+    } else {
+        debug!(model, tag = &*node.name, "Unknown tag");
+    }
+}
+
+/// Reports a top-level reply node that the request does not expect. Fails with the
+/// `strict-unknown` feature so that test runs against beta clients catch protocol changes.
+pub fn unknown_reply_node(request: &str, node: &treexml::Element) -> Result<(), Error> {
+    if cfg!(feature = "strict-unknown") {
+        return Err(Error::UnexpectedNode {
+            request: request.into(),
+            node: node.name.clone(),
+        });
+    }
+    debug!(request, node = &*node.name, "Unknown reply node");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(xml: &str) -> treexml::Element {
+        parse_node(xml).unwrap()
+    }
+
+    #[test]
+    fn master_urls() {
+        assert!(same_master_url(
+            "https://einsteinathome.org/",
+            "http://EinsteinAtHome.org"
+        ));
+        assert!(same_master_url(
+            " einsteinathome.org ",
+            "https://einsteinathome.org/"
+        ));
+        assert!(!same_master_url(
+            "https://einsteinathome.org/",
+            "https://einsteinathome.org/beta/"
+        ));
+        assert!(same_master_url(
+            "HTTP://MilkyWay.cs.rpi.edu/milkyway//",
+            "https://milkyway.cs.rpi.edu/milkyway"
+        ));
+        assert!(!same_master_url(
+            "https://milkyway.cs.rpi.edu/milkyway/",
+            "https://milkyway.cs.rpi.edu/MilkyWay/"
+        ));
+        assert!(!same_master_url(
+            "https://einsteinathome.org:8080/",
+            "https://einsteinathome.org/"
+        ));
+        assert!(!same_master_url(
+            "ftp://einsteinathome.org/",
+            "einsteinathome.org"
+        ));
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob_match("h1_0001", "h1_0001"));
+        assert!(!glob_match("h1_0001", "h1_0001_1"));
+        assert!(glob_match("h1_*", "h1_0001_1"));
+        assert!(glob_match("h1_*", "h1_"));
+        assert!(!glob_match("h1_*", "l1_0001"));
+        assert!(glob_match("*_1", "h1_0001_1"));
+        assert!(glob_match("h1_*_*_1", "h1_0001_7_1"));
+        assert!(!glob_match("h1_*_*_1", "h1_0001_1"));
+        assert!(glob_match("*a*a*", "banana"));
+        assert!(!glob_match("*aaa*", "banana"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("", "a"));
+    }
+
+    #[test]
+    fn eval_padded_integers() {
+        assert_eq!(eval_node_contents::<i64>(&node("<v> 42 </v>")), Some(42));
+        assert_eq!(eval_node_contents::<u64>(&node("<v>\n7\n</v>")), Some(7));
+        assert_eq!(
+            eval_node_contents::<i32>(&node("<v>\t-136\n</v>")),
+            Some(-136)
+        );
+    }
+
+    #[test]
+    fn eval_padded_floats() {
+        assert_eq!(eval_node_contents::<f64>(&node("<v> 1.5\n</v>")), Some(1.5));
+        assert_eq!(
+            eval_node_contents::<f64>(&node("<v>\n    0.123456\n</v>")),
+            Some(0.123_456)
+        );
+        assert_eq!(
+            eval_node_contents::<f64>(&node("<v> -2.25 </v>")),
+            Some(-2.25)
+        );
+    }
+
+    #[test]
+    fn eval_cdata() {
+        assert_eq!(
+            eval_node_contents::<i64>(&node("<v><![CDATA[ 17 ]]></v>")),
+            Some(17)
+        );
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert_eq!(
+            parse_node_contents::<i32>(&node("<error_num> -204 </error_num>")),
+            Ok(-204)
+        );
+        assert_eq!(
+            parse_node_contents::<i32>(&node("<error_num>n/a</error_num>")),
+            Err(Error::InvalidNodeValue {
+                node: "error_num".into(),
+                value: "n/a".into(),
+            })
+        );
+    }
+
+    /// Replies as received from daemons, broken in ways seen in the wild.
+    const BROKEN_REPLIES: &[&str] = &[
+        // Project names and URLs with raw ampersands
+        "<boinc_gui_rpc_reply>\n<project>\n    <master_url>https://boinc.example.org/?team=1&lang=en</master_url>\n    <project_name>Cosmology & Friends</project_name>\n    <dont_request_more_work/>\n</project>\n</boinc_gui_rpc_reply>",
+        // Terminal escapes and a bell from an application's output in a message body
+        "<boinc_gui_rpc_reply>\n<msgs>\n<msg>\n <project>Einstein@Home</project>\n <pri>1</pri>\n <seqno>42</seqno>\n <body>\nTask finished\u{7} \u{1b}[0mwith exit code 0\n</body>\n <time>1700000000</time>\n</msg>\n</msgs>\n</boinc_gui_rpc_reply>",
+        // HTML entity from a project's news feed
+        "<boinc_gui_rpc_reply>\n<notices>\n<notice>\n <title>Server&nbsp;maintenance &amp; upgrade &#8211; &#x2014;</title>\n</notice>\n</notices>\n</boinc_gui_rpc_reply>",
+    ];
+
+    #[test]
+    fn lenient_parsing() {
+        for reply in BROKEN_REPLIES {
+            assert!(parse_node(reply).is_err(), "{}", reply);
+        }
+
+        let project = &parse_node_lenient(BROKEN_REPLIES[0], Limits::DEFAULT)
+            .unwrap()
+            .children[0];
+        assert_eq!(
+            any_text(&project.children[0]).as_deref(),
+            Some("https://boinc.example.org/?team=1&lang=en")
+        );
+        assert_eq!(
+            any_text(&project.children[1]).as_deref(),
+            Some("Cosmology & Friends")
+        );
+        assert_eq!(project.children[2].name, "dont_request_more_work");
+
+        let msg = &parse_node_lenient(BROKEN_REPLIES[1], Limits::DEFAULT)
+            .unwrap()
+            .children[0]
+            .children[0];
+        assert_eq!(
+            msg.children[3].text.as_deref(),
+            Some("\nTask finished [0mwith exit code 0\n")
+        );
+
+        let notice = &parse_node_lenient(BROKEN_REPLIES[2], Limits::DEFAULT)
+            .unwrap()
+            .children[0]
+            .children[0];
+        assert_eq!(
+            notice.children[0].text.as_deref(),
+            Some("Server&nbsp;maintenance & upgrade \u{2013} \u{2014}")
+        );
+    }
+
+    #[test]
+    fn lenient_parsing_leaves_cdata_alone() {
+        let node =
+            parse_node_lenient("<v>a & b<![CDATA[c & d\u{1}]]></v>", Limits::DEFAULT).unwrap();
+        assert_eq!(node.text.as_deref(), Some("a & b"));
+        assert_eq!(node.cdata.as_deref(), Some("c & d"));
+
+        assert!(parse_node_lenient("<v>unclosed", Limits::DEFAULT).is_err());
+    }
+
+    #[test]
+    fn secrets_redacted() {
+        assert_eq!(
+            redact_secrets(
+                "<account_out><authenticator>0123abcd</authenticator></account_out>\
+                 <acct_mgr_rpc><name>me</name><password>hunter2</password>\
+                 <password></password><password/></acct_mgr_rpc>"
+            ),
+            "<account_out><authenticator>***</authenticator></account_out>\
+             <acct_mgr_rpc><name>me</name><password>***</password>\
+             <password>***</password><password/></acct_mgr_rpc>"
+        );
+        assert!(matches!(
+            redact_secrets("<get_state/>"),
+            Cow::Borrowed("<get_state/>")
+        ));
+    }
+
+    #[test]
+    fn depth_limit() {
+        let limits = Limits {
+            max_depth: 3,
+            max_elements: 100,
+        };
+        let doc = "<a x='>'><b/><b><!-- <c><c><c> --><c><![CDATA[<d><d>]]></c></b><?pi <e>?></a>";
+        assert!(parse_node_limited(doc, limits).is_ok());
+        assert!(parse_node_lenient(doc, limits).is_ok());
+        assert_eq!(
+            parse_node_limited("<a><b><c><d/></c></b></a>", limits),
+            Err(Error::DataParseError(
+                "XML exceeds the maximum nesting depth of 3".into()
+            ))
+        );
+
+        // Fails before the parser gets to recurse into it
+        let deep = "<a>".repeat(10_000) + &"</a>".repeat(10_000);
+        assert_eq!(
+            parse_node(&deep),
+            Err(Error::DataParseError(
+                "XML exceeds the maximum nesting depth of 256".into()
+            ))
+        );
+        assert!(parse_node_lenient(&deep, Limits::DEFAULT).is_err());
+    }
+
+    #[test]
+    fn element_limit() {
+        let limits = Limits {
+            max_depth: 10,
+            max_elements: 4,
+        };
+        assert!(parse_node_limited("<a><b/><b/><b></b></a>", limits).is_ok());
+        assert_eq!(
+            parse_node_limited("<a><b/><b/><b/><b/></a>", limits),
+            Err(Error::DataParseError(
+                "XML exceeds the maximum of 4 elements".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn eval_invalid() {
+        assert_eq!(eval_node_contents::<i64>(&node("<v>1.5</v>")), None);
+        assert_eq!(eval_node_contents::<f64>(&node("<v>abc</v>")), None);
+        assert_eq!(eval_node_contents::<f64>(&node("<v/>")), None);
+    }
+
+    /// Collects `(model, tag)` fields of every event.
+    #[derive(Clone, Default)]
+    struct Recorder(std::sync::Arc<std::sync::Mutex<Vec<(String, String)>>>);
+
+    #[derive(Default)]
+    struct Fields {
+        model: String,
+        tag: String,
+    }
+
+    impl tracing::field::Visit for Fields {
+        fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+            match field.name() {
+                "model" => self.model = value.into(),
+                "tag" => self.tag = value.into(),
+                _ => {}
+            }
+        }
+
+        fn record_debug(&mut self, _: &tracing::field::Field, _: &dyn std::fmt::Debug) {}
+    }
+
+    impl tracing::Subscriber for Recorder {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            tracing::span::Id::from_u64(1)
+        }
+
+        fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) {
+            let mut fields = Fields::default();
+            event.record(&mut fields);
+            self.0.lock().unwrap().push((fields.model, fields.tag));
+        }
+
+        fn enter(&self, _: &tracing::span::Id) {}
+
+        fn exit(&self, _: &tracing::span::Id) {}
+    }
+
+    #[test]
+    fn unknown_tags_reported() {
+        let recorder = Recorder::default();
+        let app = tracing::subscriber::with_default(recorder.clone(), || {
+            crate::models::App::from(&node(
+                "<app><name>einstein</name><fancy_new_field>1</fancy_new_field></app>",
+            ))
+        });
+
+        assert_eq!(app.name, Some("einstein".into()));
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            vec![("App".to_string(), "fancy_new_field".to_string())]
+        );
+    }
 }
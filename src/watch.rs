@@ -0,0 +1,585 @@
+//! Changes over time, as the polling streams of `Client` report them, such as
+//! `Client::watch_tasks`.
+
+use crate::models::{FileTransfer, ProjectStatistics, ResultState, TaskResult};
+use std::collections::{HashMap, HashSet};
+
+/// Smallest change in a task's `fraction_done` reported by default.
+pub const DEFAULT_PROGRESS_EPSILON: f64 = 0.001;
+
+/// Polls without progress after which a file transfer is reported as stalled by default.
+pub const DEFAULT_STALL_POLLS: u32 = 10;
+
+/// Item of a list that the daemon sends whole every time, known by its name.
+trait Named {
+    fn name(&self) -> Option<&str>;
+}
+
+impl Named for TaskResult {
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+impl Named for FileTransfer {
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+}
+
+/// Items of the last snapshot of a list by name, in order, along with what is kept of each.
+#[derive(Clone, Debug)]
+struct Snapshot<S> {
+    items: Vec<(String, S)>,
+}
+
+impl<S> Snapshot<S> {
+    const fn new() -> Self {
+        Self { items: Vec::new() }
+    }
+
+    /// Takes in the next snapshot, reporting the items gone through `removed` first. Then
+    /// `compare` reports on each of the others in order, given what was kept of it, if
+    /// anything, and returns what to keep now.
+    ///
+    /// Of duplicates, the first one counts, while items without a name, which the daemon does
+    /// not send, are ignored.
+    fn update<T: Named, E>(
+        &mut self,
+        items: &[T],
+        removed: impl Fn(String) -> E,
+        mut compare: impl FnMut(&str, &T, Option<S>, &mut Vec<E>) -> S,
+    ) -> Vec<E> {
+        let current: HashSet<&str> = items.iter().filter_map(Named::name).collect();
+        let mut events: Vec<_> = self
+            .items
+            .iter()
+            .filter(|(name, _)| !current.contains(&**name))
+            .map(|(name, _)| removed(name.clone()))
+            .collect();
+
+        let mut previous: HashMap<String, S> = self.items.drain(..).collect();
+        let mut seen = HashSet::new();
+        for item in items {
+            if let Some(name) = item.name().filter(|name| seen.insert(*name)) {
+                let kept = compare(name, item, previous.remove(name), &mut events);
+                self.items.push((name.to_owned(), kept));
+            }
+        }
+        events
+    }
+}
+
+/// Change to the task list.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug)]
+pub enum TaskEvent {
+    Added(TaskResult),
+    Removed(String),
+    StateChanged {
+        name: String,
+        from: Option<ResultState>,
+        to: Option<ResultState>,
+    },
+    ProgressChanged {
+        name: String,
+        fraction_done: f64,
+    },
+}
+
+/// Task as of the last snapshot.
+#[derive(Clone, Debug)]
+struct TaskState {
+    state: Option<ResultState>,
+    /// As last reported, rather than as last seen, so that slow progress still adds up.
+    fraction_done: f64,
+}
+
+/// Compares successive snapshots of the task list, keyed by task name, for what changed.
+///
+/// The first snapshot reports all tasks as added. A renamed task is one removed and another
+/// added, while tasks without a name, which the daemon does not send, are ignored.
+#[derive(Clone, Debug)]
+pub struct TaskDiff {
+    tasks: Snapshot<TaskState>,
+    progress_epsilon: f64,
+}
+
+impl Default for TaskDiff {
+    fn default() -> Self {
+        Self {
+            tasks: Snapshot::new(),
+            progress_epsilon: DEFAULT_PROGRESS_EPSILON,
+        }
+    }
+}
+
+impl TaskDiff {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reports progress only once `fraction_done` moved by more than `epsilon`.
+    /// `DEFAULT_PROGRESS_EPSILON` by default.
+    #[must_use]
+    pub const fn progress_epsilon(mut self, epsilon: f64) -> Self {
+        self.progress_epsilon = epsilon;
+        self
+    }
+
+    /// Takes in the next snapshot, as `Client::get_results` returns it, and returns the
+    /// changes since the last one: removed tasks first, then the others in snapshot order.
+    pub fn update(&mut self, tasks: &[TaskResult]) -> Vec<TaskEvent> {
+        let progress_epsilon = self.progress_epsilon;
+        self.tasks
+            .update(tasks, TaskEvent::Removed, |name, task, last, events| {
+                let state = task.result_state();
+                let fraction_done = task.active_task.as_ref().and_then(|a| a.fraction_done);
+
+                if let Some(mut last) = last {
+                    if last.state != state {
+                        events.push(TaskEvent::StateChanged {
+                            name: name.into(),
+                            from: last.state,
+                            to: state,
+                        });
+                        last.state = state;
+                    }
+                    match fraction_done {
+                        Some(fraction_done)
+                            if (fraction_done - last.fraction_done).abs() > progress_epsilon =>
+                        {
+                            events.push(TaskEvent::ProgressChanged {
+                                name: name.into(),
+                                fraction_done,
+                            });
+                            last.fraction_done = fraction_done;
+                        }
+                        _ => {}
+                    }
+                    last
+                } else {
+                    events.push(TaskEvent::Added(task.clone()));
+                    TaskState {
+                        state,
+                        fraction_done: fraction_done.unwrap_or_default(),
+                    }
+                }
+            })
+    }
+}
+
+/// Predicate for `Client::wait_for_task`: the server acknowledged the task as reported. The
+/// client forgets such tasks soon after, so a poll may well find the task gone instead.
+pub fn task_is_reported() -> impl Fn(&TaskResult) -> bool {
+    |task| task.got_server_ack == Some(true)
+}
+
+/// Predicate for `Client::wait_for_task`: the task is done and waiting to be reported.
+pub fn task_is_ready_to_report() -> impl Fn(&TaskResult) -> bool {
+    |task| task.ready_to_report == Some(true)
+}
+
+/// Predicate for `Client::wait_for_task`: the task is in `state`.
+pub fn task_reached_state(state: ResultState) -> impl Fn(&TaskResult) -> bool {
+    move |task| task.result_state() == Some(state)
+}
+
+/// Change to the file transfers.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug)]
+pub enum TransferEvent {
+    Added(FileTransfer),
+    /// Gone from the list, as when completed.
+    Removed(String),
+    /// The failed attempts, or when the next one is due, changed, as when the transfer failed
+    /// and backed off.
+    BackoffChanged {
+        name: String,
+        num_retries: Option<i64>,
+        next_request_time: Option<f64>,
+    },
+    /// No bytes transferred for the stall threshold of polls. Reported once, until the transfer
+    /// makes progress again.
+    Stalled {
+        name: String,
+        bytes_so_far: f64,
+    },
+}
+
+/// File transfer as of the last snapshot.
+#[derive(Clone, Debug)]
+struct TransferState {
+    num_retries: Option<i64>,
+    next_request_time: Option<f64>,
+    /// The most seen, as a new attempt may start over.
+    bytes_so_far: f64,
+    /// Polls since `bytes_so_far` last grew.
+    idle_polls: u32,
+}
+
+/// Compares successive snapshots of the file transfers, keyed by file name, for what changed.
+///
+/// The first snapshot reports all transfers as added.
+#[derive(Clone, Debug)]
+pub struct TransferDiff {
+    transfers: Snapshot<TransferState>,
+    stall_polls: u32,
+}
+
+impl Default for TransferDiff {
+    fn default() -> Self {
+        Self {
+            transfers: Snapshot::new(),
+            stall_polls: DEFAULT_STALL_POLLS,
+        }
+    }
+}
+
+impl TransferDiff {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reports a transfer as stalled once it made no progress for `polls` snapshots, or never
+    /// if zero. `DEFAULT_STALL_POLLS` by default.
+    #[must_use]
+    pub const fn stall_polls(mut self, polls: u32) -> Self {
+        self.stall_polls = polls;
+        self
+    }
+
+    /// Takes in the next snapshot, as `Client::get_file_transfers` returns it, and returns the
+    /// changes since the last one: removed transfers first, then the others in snapshot order.
+    pub fn update(&mut self, transfers: &[FileTransfer]) -> Vec<TransferEvent> {
+        let stall_polls = self.stall_polls;
+        self.transfers.update(
+            transfers,
+            TransferEvent::Removed,
+            |name, transfer, last, events| {
+                let bytes_so_far = transfer.bytes_so_far().unwrap_or_default();
+
+                if let Some(mut last) = last {
+                    if (last.num_retries, last.next_request_time)
+                        != (transfer.num_retries, transfer.next_request_time)
+                    {
+                        events.push(TransferEvent::BackoffChanged {
+                            name: name.into(),
+                            num_retries: transfer.num_retries,
+                            next_request_time: transfer.next_request_time,
+                        });
+                        last.num_retries = transfer.num_retries;
+                        last.next_request_time = transfer.next_request_time;
+                    }
+                    if bytes_so_far > last.bytes_so_far {
+                        last.bytes_so_far = bytes_so_far;
+                        last.idle_polls = 0;
+                    } else {
+                        last.idle_polls = last.idle_polls.saturating_add(1);
+                        if last.idle_polls == stall_polls {
+                            events.push(TransferEvent::Stalled {
+                                name: name.into(),
+                                bytes_so_far: last.bytes_so_far,
+                            });
+                        }
+                    }
+                    last
+                } else {
+                    events.push(TransferEvent::Added(transfer.clone()));
+                    TransferState {
+                        num_retries: transfer.num_retries,
+                        next_request_time: transfer.next_request_time,
+                        bytes_so_far,
+                        idle_polls: 0,
+                    }
+                }
+            },
+        )
+    }
+}
+
+/// Credit with a project as of a `CreditSample`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProjectCredit {
+    pub master_url: String,
+    pub user_total_credit: f64,
+    pub host_expavg_credit: f64,
+}
+
+/// Credit with each project at some point in time, as `Client::sample_statistics` yields it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreditSample {
+    /// Seconds since the Unix epoch.
+    pub timestamp: f64,
+    pub projects: Vec<ProjectCredit>,
+}
+
+impl CreditSample {
+    /// Takes the latest day of the statistics of each project, as `Client::get_statistics`
+    /// returns them. Projects without any are left out.
+    #[must_use]
+    pub fn new(timestamp: f64, statistics: &[ProjectStatistics]) -> Self {
+        let projects = statistics
+            .iter()
+            .filter_map(|project| {
+                let latest = project.daily_statistics.last()?;
+                Some(ProjectCredit {
+                    master_url: project.master_url.clone()?,
+                    user_total_credit: latest.user_total_credit.unwrap_or_default(),
+                    host_expavg_credit: latest.host_expavg_credit.unwrap_or_default(),
+                })
+            })
+            .collect();
+        Self {
+            timestamp,
+            projects,
+        }
+    }
+
+    /// Credit earned with each project since `earlier`, in the order of this sample.
+    ///
+    /// Only projects in both samples count: one attached in between may bring along credit
+    /// earned long before, while one detached has nothing more to tell.
+    #[must_use]
+    pub fn delta_since(&self, earlier: &Self) -> CreditDelta {
+        let projects = self
+            .projects
+            .iter()
+            .filter_map(|project| {
+                let before = earlier
+                    .projects
+                    .iter()
+                    .find(|before| before.master_url == project.master_url)?;
+                Some(ProjectCreditDelta {
+                    master_url: project.master_url.clone(),
+                    user_credit_earned: project.user_total_credit - before.user_total_credit,
+                    host_expavg_credit_change: project.host_expavg_credit
+                        - before.host_expavg_credit,
+                })
+            })
+            .collect();
+        CreditDelta {
+            window: self.timestamp - earlier.timestamp,
+            projects,
+        }
+    }
+}
+
+/// Credit earned between two `CreditSample`s.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreditDelta {
+    /// Seconds between the samples.
+    pub window: f64,
+    pub projects: Vec<ProjectCreditDelta>,
+}
+
+/// Credit earned with a project between two `CreditSample`s.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProjectCreditDelta {
+    pub master_url: String,
+    pub user_credit_earned: f64,
+    pub host_expavg_credit_change: f64,
+}
+
+/// Item that the daemon numbers in sequence, such as a message.
+pub(crate) trait Sequenced {
+    fn seqno(&self) -> Option<i64>;
+}
+
+impl Sequenced for crate::models::Message {
+    fn seqno(&self) -> Option<i64> {
+        self.msg_number
+    }
+}
+
+impl Sequenced for crate::models::Notice {
+    fn seqno(&self) -> Option<i64> {
+        self.seqno
+    }
+}
+
+/// Latest of a sequence of items seen, such as for `Client::stream_messages`.
+pub(crate) struct Seqno {
+    pub(crate) seqno: i64,
+    /// Whether the daemon renumbers the items from 1 now and then, sending all of them anew.
+    renumbered: bool,
+}
+
+impl Seqno {
+    pub(crate) const fn new(seqno: i64, renumbered: bool) -> Self {
+        Self { seqno, renumbered }
+    }
+
+    /// Returns the items not seen yet in order, as the daemon may repeat older ones.
+    pub(crate) fn receive<T: Sequenced>(&mut self, items: Vec<T>) -> Vec<T> {
+        if self.renumbered
+            && self.seqno > 0
+            && items.iter().filter_map(Sequenced::seqno).min() == Some(1)
+        {
+            tracing::debug!("Items renumbered from 1, starting over");
+            self.seqno = 0;
+        }
+
+        let seqno = self.seqno;
+        let mut items: Vec<_> = items
+            .into_iter()
+            .filter(|item| !matches!(item.seqno(), Some(n) if n <= seqno))
+            .collect();
+        items.sort_by_key(Sequenced::seqno);
+        if let Some(latest) = items.iter().filter_map(Sequenced::seqno).max() {
+            self.seqno = latest;
+        }
+        items
+    }
+}
+
+/// Progress of a stream fed by polling the daemon.
+pub(crate) struct Polling<St, T> {
+    pub(crate) state: St,
+    /// Received, but not yet yielded.
+    pub(crate) pending: std::collections::VecDeque<T>,
+    /// Whether to wait before polling again.
+    pub(crate) polled: bool,
+    /// An error ended the stream.
+    pub(crate) done: bool,
+}
+
+impl<St, T> Polling<St, T> {
+    pub(crate) const fn new(state: St) -> Self {
+        Self {
+            state,
+            pending: std::collections::VecDeque::new(),
+            polled: false,
+            done: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ActiveTask;
+
+    fn transfer(name: &str, bytes_xferred: f64) -> FileTransfer {
+        FileTransfer {
+            name: Some(name.into()),
+            xfer_active: true,
+            bytes_xferred: Some(bytes_xferred),
+            ..FileTransfer::default()
+        }
+    }
+
+    fn task(name: &str, state: i64, fraction_done: Option<f64>) -> TaskResult {
+        TaskResult {
+            name: Some(name.into()),
+            state: Some(state),
+            active_task: fraction_done.map(|fraction_done| ActiveTask {
+                fraction_done: Some(fraction_done),
+                ..ActiveTask::default()
+            }),
+            ..TaskResult::default()
+        }
+    }
+
+    /// Events in a form that compares, as `TaskResult` does not.
+    fn show(events: &[TaskEvent]) -> Vec<String> {
+        events
+            .iter()
+            .map(|event| match event {
+                TaskEvent::Added(task) => format!("+{}", task.name.as_deref().unwrap()),
+                TaskEvent::Removed(name) => format!("-{name}"),
+                TaskEvent::StateChanged { name, from, to } => {
+                    format!("{name} {from:?} -> {to:?}")
+                }
+                TaskEvent::ProgressChanged {
+                    name,
+                    fraction_done,
+                } => format!("{name} {fraction_done}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn progress_epsilon() {
+        let mut diff = TaskDiff::new().progress_epsilon(0.01);
+        diff.update(&[task("a", 2, Some(0.1))]);
+        assert!(diff.update(&[task("a", 2, Some(0.105))]).is_empty());
+        // Measured against the last reported value, so creeping progress is not lost
+        assert_eq!(
+            show(&diff.update(&[task("a", 2, Some(0.115))])),
+            ["a 0.115"]
+        );
+        assert!(diff.update(&[task("a", 2, Some(0.12))]).is_empty());
+        // Nor is progress lost when the task goes inactive for a while
+        assert!(diff.update(&[task("a", 2, None)]).is_empty());
+        assert_eq!(show(&diff.update(&[task("a", 2, Some(0.05))])), ["a 0.05"]);
+    }
+
+    #[test]
+    fn stalled_once() {
+        let mut diff = TransferDiff::new().stall_polls(3);
+        let stalls = |events: Vec<TransferEvent>| {
+            events
+                .iter()
+                .filter(|event| matches!(event, TransferEvent::Stalled { .. }))
+                .count()
+        };
+
+        assert_eq!(stalls(diff.update(&[transfer("a", 0.0)])), 0);
+        assert_eq!(stalls(diff.update(&[transfer("a", 100.0)])), 0);
+        let stalled: usize = (0..10)
+            .map(|_| stalls(diff.update(&[transfer("a", 100.0)])))
+            .sum();
+        assert_eq!(stalled, 1);
+
+        // Progress rearms it
+        diff.update(&[transfer("a", 200.0)]);
+        assert_eq!(stalls(diff.update(&[transfer("a", 200.0)])), 0);
+        assert_eq!(stalls(diff.update(&[transfer("a", 200.0)])), 0);
+        assert_eq!(stalls(diff.update(&[transfer("a", 200.0)])), 1);
+
+        // A new attempt going over the same bytes again is no progress
+        diff.update(&[transfer("a", 300.0)]);
+        diff.update(&[transfer("a", 50.0)]);
+        diff.update(&[transfer("a", 250.0)]);
+        assert_eq!(stalls(diff.update(&[transfer("a", 290.0)])), 1);
+
+        let mut never = TransferDiff::new().stall_polls(0);
+        let stalled: usize = (0..10)
+            .map(|_| stalls(never.update(&[transfer("a", 0.0)])))
+            .sum();
+        assert_eq!(stalled, 0);
+    }
+
+    #[test]
+    fn credit_delta() {
+        let credit = |master_url: &str, user_total_credit, host_expavg_credit| ProjectCredit {
+            master_url: master_url.into(),
+            user_total_credit,
+            host_expavg_credit,
+        };
+        let earlier = CreditSample {
+            timestamp: 1000.0,
+            projects: vec![credit("a", 100.0, 10.0), credit("b", 50.0, 5.0)],
+        };
+        // b detached, and c attached with credit earned elsewhere
+        let later = CreditSample {
+            timestamp: 1060.0,
+            projects: vec![credit("c", 5000.0, 1.0), credit("a", 130.0, 12.0)],
+        };
+
+        assert_eq!(
+            later.delta_since(&earlier),
+            CreditDelta {
+                window: 60.0,
+                projects: vec![ProjectCreditDelta {
+                    master_url: "a".into(),
+                    user_credit_earned: 30.0,
+                    host_expavg_credit_change: 2.0,
+                }],
+            }
+        );
+    }
+}
@@ -0,0 +1,310 @@
+//! Serializing element trees for the wire.
+
+use crate::{errors::Error, util::is_xml_char};
+use std::fmt::{self, Write};
+
+/// Writes `element` to `out`, formatted as `treexml` does, two-space indentation included,
+/// but with empty elements written as `<tag/>`.
+///
+/// Characters above `max_char` are written as character references, which CDATA cannot hold, so
+/// there they fail the whole element. So do characters that XML does not allow anywhere.
+pub fn write_element<W: Write>(
+    out: &mut W,
+    element: &treexml::Element,
+    max_char: char,
+) -> Result<(), Error> {
+    let mut emitter = Emitter::new(out, max_char);
+    emitter.element(element).map_err(|_| {
+        Error::DataParseError(format!(
+            "Cannot encode request: {}",
+            emitter.problem.as_deref().unwrap_or("formatting failed")
+        ))
+    })
+}
+
+/// What was last written at an element level, which decides where line breaks go.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Wrote {
+    Nothing,
+    Markup,
+    Text,
+}
+
+/// Port of the indentation rules of the `xml-rs` emitter that `treexml` uses, so that
+/// requests come out byte for byte as they used to.
+struct Emitter<'a, W> {
+    out: &'a mut W,
+    max_char: char,
+    level: usize,
+    wrote: Vec<Wrote>,
+    /// The last start tag is still open, awaiting either `>` or `/>`.
+    open_tag: bool,
+    /// Why writing failed, other than `out` failing.
+    problem: Option<String>,
+}
+
+impl<'a, W: Write> Emitter<'a, W> {
+    fn new(out: &'a mut W, max_char: char) -> Self {
+        Self {
+            out,
+            max_char,
+            level: 0,
+            wrote: vec![Wrote::Nothing],
+            open_tag: false,
+            problem: None,
+        }
+    }
+
+    fn fail(&mut self, problem: String) -> fmt::Result {
+        self.problem = Some(problem);
+        Err(fmt::Error)
+    }
+
+    fn element(&mut self, element: &treexml::Element) -> fmt::Result {
+        self.start(element)?;
+        if let Some(text) = &element.text {
+            self.text(text)?;
+        }
+        if let Some(cdata) = &element.cdata {
+            self.cdata(cdata)?;
+        }
+        for child in &element.children {
+            self.element(child)?;
+        }
+        self.end(&element.name)
+    }
+
+    fn wrote(&self) -> Wrote {
+        self.wrote.last().copied().unwrap_or(Wrote::Nothing)
+    }
+
+    fn set_wrote(&mut self, wrote: Wrote) {
+        if let Some(last) = self.wrote.last_mut() {
+            *last = wrote;
+        }
+    }
+
+    fn newline(&mut self, level: usize) -> fmt::Result {
+        self.out.write_char('\n')?;
+        for _ in 0..level {
+            self.out.write_str("  ")?;
+        }
+        Ok(())
+    }
+
+    fn close_tag(&mut self) -> fmt::Result {
+        if self.open_tag {
+            self.open_tag = false;
+            self.out.write_char('>')?;
+        }
+        Ok(())
+    }
+
+    fn start(&mut self, element: &treexml::Element) -> fmt::Result {
+        self.close_tag()?;
+        if self.wrote() != Wrote::Text && (self.level > 0 || self.wrote() == Wrote::Markup) {
+            self.newline(self.level)?;
+            if self.level > 0 {
+                self.set_wrote(Wrote::Markup);
+            }
+        }
+        self.wrote.push(Wrote::Markup);
+        self.level += 1;
+
+        write!(self.out, "<{}", element.name)?;
+        for (name, value) in &element.attributes {
+            write!(self.out, " {name}=\"")?;
+            self.escaped(value, true)?;
+            self.out.write_char('"')?;
+        }
+        self.open_tag = true;
+        Ok(())
+    }
+
+    fn text(&mut self, text: &str) -> fmt::Result {
+        self.close_tag()?;
+        self.escaped(text, false)?;
+        self.set_wrote(Wrote::Text);
+        Ok(())
+    }
+
+    fn cdata(&mut self, cdata: &str) -> fmt::Result {
+        if let Some(c) = cdata.chars().find(|&c| !is_xml_char(c)) {
+            return self.fail(format!("U+{:04X} is not allowed in XML", u32::from(c)));
+        }
+        if let Some(c) = cdata.chars().find(|&c| c > self.max_char) {
+            return self.fail(format!(
+                "U+{:04X} cannot be encoded inside CDATA",
+                u32::from(c)
+            ));
+        }
+        self.close_tag()?;
+        self.out.write_str("<![CDATA[")?;
+        // A terminator inside the contents splits them across two sections
+        for (i, part) in cdata.split("]]>").enumerate() {
+            if i > 0 {
+                self.out.write_str("]]]]><![CDATA[>")?;
+            }
+            self.out.write_str(part)?;
+        }
+        self.out.write_str("]]>")?;
+        self.set_wrote(Wrote::Text);
+        Ok(())
+    }
+
+    fn end(&mut self, name: &str) -> fmt::Result {
+        if self.open_tag {
+            self.open_tag = false;
+            self.out.write_str("/>")?;
+        } else {
+            if self.level > 0 && self.wrote() == Wrote::Markup {
+                self.newline(self.level - 1)?;
+            }
+            write!(self.out, "</{name}>")?;
+        }
+        if self.level > 0 {
+            self.level -= 1;
+            self.wrote.pop();
+        }
+        self.set_wrote(Wrote::Markup);
+        Ok(())
+    }
+
+    /// Writes `s` with markup characters replaced by entities, and in attributes also quotes and
+    /// line breaks. Characters above `max_char` become character references.
+    fn escaped(&mut self, s: &str, attribute: bool) -> fmt::Result {
+        let max_char = self.max_char;
+        let mut rest = s;
+        while let Some((i, c)) = rest.char_indices().find(|&(_, c)| match c {
+            '<' | '>' | '&' => true,
+            '"' | '\'' | '\n' | '\r' => attribute,
+            c => c > max_char || !is_xml_char(c),
+        }) {
+            self.out.write_str(&rest[..i])?;
+            match c {
+                '<' => self.out.write_str("&lt;")?,
+                '>' => self.out.write_str("&gt;")?,
+                '&' => self.out.write_str("&amp;")?,
+                '"' => self.out.write_str("&quot;")?,
+                '\'' => self.out.write_str("&apos;")?,
+                '\n' => self.out.write_str("&#xA;")?,
+                '\r' => self.out.write_str("&#xD;")?,
+                c if !is_xml_char(c) => {
+                    return self.fail(format!("U+{:04X} is not allowed in XML", u32::from(c)))
+                }
+                // Decimal, as the daemon only understands a few hexadecimal references
+                c => write!(self.out, "&#{};", u32::from(c))?,
+            }
+            rest = &rest[i + c.len_utf8()..];
+        }
+        self.out.write_str(rest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, text: Option<&str>, children: Vec<treexml::Element>) -> treexml::Element {
+        let mut node = treexml::Element::new(name);
+        node.text = text.map(Into::into);
+        node.children = children;
+        node
+    }
+
+    /// Requests as the client sends them, and then some edge cases.
+    fn corpus() -> Vec<treexml::Element> {
+        let mut attributed = node("project", Some("x"), vec![]);
+        attributed
+            .attributes
+            .insert("url".into(), "https://a.example/?a=1&b=\"2\"\n".into());
+        let mut cdata = node("script", None, vec![]);
+        cdata.cdata = Some("a < b ]]> c".into());
+        let mut cdata_and_text = node("body", Some("text"), vec![node("x", None, vec![])]);
+        cdata_and_text.cdata = Some("data".into());
+
+        vec![
+            node("get_state", None, vec![]),
+            node("auth1", None, vec![]),
+            node(
+                "auth2",
+                None,
+                vec![node(
+                    "nonce_hash",
+                    Some("69f972e9283ef3fa59c69ab48c1e4af9"),
+                    vec![],
+                )],
+            ),
+            node(
+                "get_results",
+                None,
+                vec![node("active_only", Some("1"), vec![])],
+            ),
+            node(
+                "set_run_mode",
+                None,
+                vec![
+                    node("never", None, vec![]),
+                    node("duration", Some("3600"), vec![]),
+                ],
+            ),
+            node(
+                "acct_mgr_rpc",
+                None,
+                vec![
+                    node("url", Some("https://bam.example/?x=1&y=<2>"), vec![]),
+                    node("name", Some("Jürgen \"J\" O'Neil"), vec![]),
+                    node("password", Some(""), vec![]),
+                ],
+            ),
+            node(
+                "set_global_prefs_override",
+                None,
+                vec![node(
+                    "global_preferences",
+                    None,
+                    vec![
+                        node("run_on_batteries", Some("0"), vec![]),
+                        node("cpu_usage_limit", Some("100.000000"), vec![]),
+                        node(
+                            "day_prefs",
+                            None,
+                            vec![
+                                node("day_of_week", Some("1"), vec![]),
+                                node("start_hour", Some("9.5"), vec![]),
+                            ],
+                        ),
+                    ],
+                )],
+            ),
+            node("set_language", Some("日本語 😀 é"), vec![]),
+            node(
+                "mixed",
+                Some("text first"),
+                vec![
+                    node("a", None, vec![]),
+                    node("b", Some("x"), vec![node("c", None, vec![])]),
+                    node("d", None, vec![]),
+                ],
+            ),
+            attributed,
+            cdata,
+            cdata_and_text,
+        ]
+    }
+
+    #[test]
+    fn same_as_treexml() {
+        for request in corpus() {
+            let mut root = treexml::Element::new("boinc_gui_rpc_request");
+            root.children = vec![request];
+            let expected = format!("{root}")
+                .replace("<?xml version='1.0'?>", "")
+                .replace(" />", "/>");
+
+            let mut out = String::new();
+            write_element(&mut out, &root, char::MAX).unwrap();
+            assert_eq!(out, expected);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! `Client::abort_tasks_matching` against scripted replies.
+
+use boinc_rpc::{
+    tasks::AbortReport,
+    test_util::{node, Expect, MockTransport, TaskNode},
+    Client, Error,
+};
+
+fn results() -> Expect {
+    let mut results = treexml::Element::new("results");
+    for name in &["wu_bad_1_0", "wu_good_0", "wu_bad_2_1", "wu_bad_3_0"] {
+        results.children.push(
+            TaskNode::new(name)
+                .project_url("https://a.example/")
+                .state(2)
+                .build(),
+        );
+    }
+    Expect::op("get_results").reply(vec![results])
+}
+
+fn abort(name: &str) -> Expect {
+    let mut node = treexml::Element::new("abort_result");
+    node.children = vec![
+        self::node("project_url", "https://a.example/"),
+        self::node("name", name),
+    ];
+    Expect::request(vec![node])
+}
+
+fn is_bad(task: &boinc_rpc::models::TaskResult) -> bool {
+    matches!(&task.name, Some(name) if name.starts_with("wu_bad_"))
+}
+
+#[tokio::test]
+async fn aborted() {
+    let transport = MockTransport::new()
+        .expect(results())
+        .expect(abort("wu_bad_1_0"))
+        .expect(abort("wu_bad_2_1").reply(vec![node("error", "No such result")]))
+        .expect(abort("wu_bad_3_0"));
+    let client = Client::new(transport.clone());
+
+    let report = client.abort_tasks_matching(is_bad, false).await.unwrap();
+    assert_eq!(
+        report,
+        AbortReport {
+            dry_run: false,
+            aborted: vec!["wu_bad_1_0".into(), "wu_bad_3_0".into()],
+            failed: vec![(
+                "wu_bad_2_1".into(),
+                Error::DaemonError("No such result".into())
+            )],
+        }
+    );
+    assert!(!report.is_complete());
+    assert_eq!(transport.remaining(), 0);
+}
+
+#[tokio::test]
+async fn dry_run() {
+    // Any abort request would be unexpected
+    let transport = MockTransport::new().expect(results());
+    let client = Client::new(transport.clone());
+
+    let report = client.abort_tasks_matching(is_bad, true).await.unwrap();
+    assert_eq!(
+        report,
+        AbortReport {
+            dry_run: true,
+            aborted: vec![
+                "wu_bad_1_0".into(),
+                "wu_bad_2_1".into(),
+                "wu_bad_3_0".into()
+            ],
+            failed: vec![],
+        }
+    );
+    assert_eq!(transport.remaining(), 0);
+}
+
+#[tokio::test]
+async fn listing_failed() {
+    let transport = MockTransport::new().expect(Expect::op("get_results").error(Error::Timeout));
+    let client = Client::new(transport.clone());
+
+    assert_eq!(
+        client.abort_tasks_matching(|_| true, false).await,
+        Err(Error::Timeout)
+    );
+    assert_eq!(transport.remaining(), 0);
+}
@@ -0,0 +1,363 @@
+//! `Client::apply_project_plan` against a `MockDaemon` keeping a list of attached projects.
+
+use boinc_rpc::{
+    account::{
+        AddProjectOptions, Credentials, ExtraProjects, PlanReport, PlannedProject, ProjectChange,
+        ProjectPlan, ShareMismatch,
+    },
+    test_util::{node, parent, MockDaemon},
+    transport::Transport,
+    BoincErrorCode, Client, Error,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+const EINSTEIN: &str = "https://einsteinathome.org/";
+const MILKYWAY: &str = "https://milkyway.cs.rpi.edu/milkyway/";
+const ROSETTA: &str = "https://boinc.bakerlab.org/rosetta/";
+const WCG: &str = "https://www.worldcommunitygrid.org/";
+
+#[derive(Clone, Debug, PartialEq)]
+struct Attached {
+    master_url: String,
+    resource_share: f64,
+    detach_when_done: bool,
+}
+
+fn attached(master_url: &str, detach_when_done: bool) -> Attached {
+    Attached {
+        master_url: master_url.into(),
+        resource_share: 100.0,
+        detach_when_done,
+    }
+}
+
+/// State of the daemon: the projects attached and the requests received, by op.
+#[derive(Default)]
+struct State {
+    projects: Vec<Attached>,
+    attaching: Option<String>,
+    ops: Vec<String>,
+}
+
+fn child_text(req: &treexml::Element, name: &str) -> String {
+    req.find_child(|n| n.name == name)
+        .and_then(|n| n.text.clone())
+        .unwrap_or_default()
+}
+
+/// Serves the project RPCs over `state`. Attaching with the authenticator `bad` fails, as
+/// does looking up accounts with any password but `hunter2`.
+async fn daemon(state: &Arc<Mutex<State>>) -> MockDaemon {
+    fn handle<F>(daemon: &MockDaemon, state: &Arc<Mutex<State>>, op: &str, handler: F)
+    where
+        F: Fn(&mut State, &treexml::Element) -> Vec<treexml::Element> + Send + Sync + 'static,
+    {
+        let state = state.clone();
+        let name = op.to_owned();
+        daemon.handle(op, move |req| {
+            let mut state = state.lock().unwrap();
+            state.ops.push(name.clone());
+            handler(&mut state, req)
+        });
+    }
+    let success = || vec![treexml::Element::new("success")];
+
+    let daemon = MockDaemon::bind("127.0.0.1:0").await.unwrap();
+    handle(&daemon, state, "get_project_status", |state, _| {
+        let projects = state
+            .projects
+            .iter()
+            .map(|project| {
+                parent(
+                    "project",
+                    vec![
+                        node("master_url", &project.master_url),
+                        node("resource_share", &format!("{:.6}", project.resource_share)),
+                        node(
+                            "detach_when_done",
+                            if project.detach_when_done { "1" } else { "0" },
+                        ),
+                    ],
+                )
+            })
+            .collect();
+        vec![parent("projects", projects)]
+    });
+    handle(&daemon, state, "project_attach", move |state, req| {
+        if child_text(req, "authenticator") != "bad" {
+            state.attaching = Some(child_text(req, "project_url"));
+        }
+        success()
+    });
+    handle(&daemon, state, "project_attach_poll", |state, _| {
+        let error_num = match state.attaching.take() {
+            Some(url) => {
+                state.projects.push(attached(&url, false));
+                "0"
+            }
+            None => "-155",
+        };
+        vec![parent(
+            "project_attach_reply",
+            vec![node("error_num", error_num)],
+        )]
+    });
+    handle(&daemon, state, "project_detach", move |state, req| {
+        let url = child_text(req, "project_url");
+        state.projects.retain(|project| project.master_url != url);
+        success()
+    });
+    for (op, detach_when_done) in &[
+        ("project_detach_when_done", true),
+        ("project_dont_detach_when_done", false),
+    ] {
+        let detach_when_done = *detach_when_done;
+        handle(&daemon, state, op, move |state, req| {
+            let url = child_text(req, "project_url");
+            for project in &mut state.projects {
+                if project.master_url == url {
+                    project.detach_when_done = detach_when_done;
+                }
+            }
+            success()
+        });
+    }
+    handle(&daemon, state, "get_project_config", move |_, _| success());
+    handle(&daemon, state, "get_project_config_poll", |_, _| {
+        vec![parent("project_config", vec![node("name", "Rosetta@home")])]
+    });
+    handle(&daemon, state, "lookup_account", move |state, req| {
+        // md5 of hunter2alice@example.com
+        if child_text(req, "passwd_hash") == "5ba93934dc35b0ea1ec313dfb2c9d88b" {
+            state.attaching = Some(ROSETTA.into());
+        }
+        success()
+    });
+    handle(&daemon, state, "lookup_account_poll", |state, _| {
+        let reply = if state.attaching.is_some() {
+            node("authenticator", "4567cdef")
+        } else {
+            node("error_num", "-206")
+        };
+        vec![parent("account_out", vec![reply])]
+    });
+    daemon
+}
+
+fn client(daemon: &MockDaemon) -> Client<Transport> {
+    Client::new(Transport::new(daemon.local_addr(), None::<&str>))
+}
+
+fn by_authenticator(url: &str, authenticator: &str) -> PlannedProject {
+    PlannedProject::new(url, Credentials::Authenticator(authenticator.into()))
+}
+
+fn plan(extras: ExtraProjects) -> ProjectPlan {
+    ProjectPlan::new()
+        .project(by_authenticator(EINSTEIN, "0123abcd").resource_share(100.0))
+        .project(
+            by_authenticator("http://milkyway.cs.rpi.edu/milkyway", "89abcdef")
+                .resource_share(50.0),
+        )
+        .extras(extras)
+        .options(AddProjectOptions::new().poll_interval(Duration::from_millis(1)))
+}
+
+#[tokio::test]
+async fn applied() {
+    let state = Arc::new(Mutex::new(State {
+        projects: vec![attached(MILKYWAY, true), attached(WCG, false)],
+        ..State::default()
+    }));
+    let daemon = daemon(&state).await;
+
+    let report = client(&daemon)
+        .apply_project_plan(&plan(ExtraProjects::Detach), false)
+        .await
+        .unwrap();
+    assert_eq!(
+        report,
+        PlanReport {
+            dry_run: false,
+            changes: vec![
+                ProjectChange::Attach(EINSTEIN.into()),
+                ProjectChange::KeepAttached(MILKYWAY.into()),
+                ProjectChange::Detach(WCG.into()),
+            ],
+            failed: vec![],
+            share_mismatches: vec![ShareMismatch {
+                master_url: MILKYWAY.into(),
+                planned: 50.0,
+                actual: Some(100.0),
+            }],
+        }
+    );
+    assert!(report.is_complete());
+
+    let state = state.lock().unwrap();
+    assert_eq!(
+        state.projects,
+        [attached(MILKYWAY, false), attached(EINSTEIN, false)]
+    );
+    assert_eq!(
+        state.ops,
+        [
+            "get_project_status",
+            "project_attach",
+            "project_attach_poll",
+            "get_project_status",
+            "project_dont_detach_when_done",
+            "project_detach",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn dry_run() {
+    let state = Arc::new(Mutex::new(State {
+        projects: vec![attached(MILKYWAY, true), attached(WCG, false)],
+        ..State::default()
+    }));
+    let daemon = daemon(&state).await;
+
+    let report = client(&daemon)
+        .apply_project_plan(&plan(ExtraProjects::Detach), true)
+        .await
+        .unwrap();
+    assert!(report.dry_run);
+    assert_eq!(
+        report.changes,
+        [
+            ProjectChange::Attach(EINSTEIN.into()),
+            ProjectChange::KeepAttached(MILKYWAY.into()),
+            ProjectChange::Detach(WCG.into()),
+        ]
+    );
+    assert_eq!(report.share_mismatches.len(), 1);
+
+    let state = state.lock().unwrap();
+    assert_eq!(
+        state.projects,
+        [attached(MILKYWAY, true), attached(WCG, false)]
+    );
+    assert_eq!(state.ops, ["get_project_status"]);
+}
+
+#[tokio::test]
+async fn applied_twice() {
+    let state = Arc::new(Mutex::new(State {
+        projects: vec![attached(WCG, false)],
+        ..State::default()
+    }));
+    let daemon = daemon(&state).await;
+    let client = client(&daemon);
+    let plan = plan(ExtraProjects::DetachWhenDone);
+
+    let report = client.apply_project_plan(&plan, false).await.unwrap();
+    assert_eq!(
+        report.changes,
+        [
+            ProjectChange::Attach(EINSTEIN.into()),
+            ProjectChange::Attach("http://milkyway.cs.rpi.edu/milkyway".into()),
+            ProjectChange::DetachWhenDone(WCG.into()),
+        ]
+    );
+
+    // Nothing is left to do, the project to be detached when done included
+    state.lock().unwrap().ops.clear();
+    let report = client.apply_project_plan(&plan, false).await.unwrap();
+    assert_eq!(report.changes, []);
+    assert_eq!(report.share_mismatches.len(), 1);
+    assert_eq!(state.lock().unwrap().ops, ["get_project_status"]);
+    assert_eq!(
+        state.lock().unwrap().projects,
+        [
+            attached(WCG, true),
+            attached(EINSTEIN, false),
+            attached("http://milkyway.cs.rpi.edu/milkyway", false),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn extras_ignored() {
+    let state = Arc::new(Mutex::new(State {
+        projects: vec![attached(EINSTEIN, false), attached(WCG, true)],
+        ..State::default()
+    }));
+    let daemon = daemon(&state).await;
+
+    let plan = ProjectPlan::new().project(by_authenticator(EINSTEIN, "0123abcd"));
+    let report = client(&daemon)
+        .apply_project_plan(&plan, false)
+        .await
+        .unwrap();
+    assert_eq!(report, PlanReport::default());
+    assert_eq!(state.lock().unwrap().ops, ["get_project_status"]);
+}
+
+#[tokio::test]
+async fn failures_carried_past() {
+    let state = Arc::new(Mutex::new(State {
+        projects: vec![attached(WCG, false)],
+        ..State::default()
+    }));
+    let daemon = daemon(&state).await;
+
+    let plan = ProjectPlan::new()
+        .project(by_authenticator(EINSTEIN, "bad"))
+        .project(PlannedProject::new(
+            ROSETTA,
+            Credentials::Account {
+                email_or_user: "alice@example.com".into(),
+                password: "hunter3".into(),
+            },
+        ))
+        .extras(ExtraProjects::Detach)
+        .options(AddProjectOptions::new().poll_interval(Duration::from_millis(1)));
+    let report = client(&daemon)
+        .apply_project_plan(&plan, false)
+        .await
+        .unwrap();
+    assert_eq!(report.changes, [ProjectChange::Detach(WCG.into())]);
+    assert_eq!(
+        report.failed,
+        [
+            (
+                ProjectChange::Attach(EINSTEIN.into()),
+                Error::StatusError(BoincErrorCode::Authenticator)
+            ),
+            (
+                ProjectChange::Attach(ROSETTA.into()),
+                Error::StatusError(BoincErrorCode::BadPasswd)
+            ),
+        ]
+    );
+    assert!(!report.is_complete());
+    assert_eq!(state.lock().unwrap().projects, []);
+}
+
+#[tokio::test]
+async fn attached_by_account() {
+    let state = Arc::new(Mutex::new(State::default()));
+    let daemon = daemon(&state).await;
+
+    let plan = ProjectPlan::new()
+        .project(PlannedProject::new(
+            ROSETTA,
+            Credentials::Account {
+                email_or_user: "Alice@Example.com".into(),
+                password: "hunter2".into(),
+            },
+        ))
+        .options(AddProjectOptions::new().poll_interval(Duration::from_millis(1)));
+    let report = client(&daemon)
+        .apply_project_plan(&plan, false)
+        .await
+        .unwrap();
+    assert_eq!(report.changes, [ProjectChange::Attach(ROSETTA.into())]);
+    assert_eq!(state.lock().unwrap().projects, [attached(ROSETTA, false)]);
+}
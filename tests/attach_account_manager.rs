@@ -0,0 +1,88 @@
+//! `Client::attach_account_manager` against scripted replies.
+
+mod common;
+
+use boinc_rpc::{
+    test_util::{node, Expect, MockTransport},
+    BoincErrorCode, Client, Error,
+};
+use std::time::Duration;
+
+fn acct_mgr_rpc() -> Expect {
+    let mut request = treexml::Element::new("acct_mgr_rpc");
+    request.children = vec![
+        node("url", "https://bam.boincstats.com/"),
+        node("name", "alice"),
+        node("password", "hunter2"),
+    ];
+    Expect::request(vec![request])
+}
+
+fn poll(error_num: i32, messages: &[&str]) -> Expect {
+    common::poll(
+        "acct_mgr_rpc_poll",
+        "acct_mgr_rpc_reply",
+        error_num,
+        messages,
+    )
+}
+
+async fn attach(transport: MockTransport, timeout: Duration) -> Result<(), Error> {
+    Client::new(transport)
+        .attach_account_manager(
+            "https://bam.boincstats.com/",
+            "alice",
+            "hunter2",
+            Duration::from_millis(1),
+            timeout,
+        )
+        .await
+}
+
+#[tokio::test]
+async fn attached() {
+    let transport = MockTransport::new()
+        .expect(acct_mgr_rpc())
+        .expect(poll(-204, &[]))
+        .expect(Expect::op("acct_mgr_rpc_poll").error(Error::ConnectionClosed))
+        .expect(poll(0, &["Attached to BAM!"]));
+
+    attach(transport.clone(), Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert_eq!(transport.remaining(), 0);
+}
+
+#[tokio::test]
+async fn wrong_password() {
+    let transport = MockTransport::new()
+        .expect(acct_mgr_rpc())
+        .expect(poll(-204, &[]))
+        .expect(poll(-206, &["Invalid password", "Try again"]));
+
+    let e = attach(transport, Duration::from_secs(5)).await.unwrap_err();
+    assert_eq!(
+        e,
+        Error::AccountManagerFailed {
+            code: BoincErrorCode::BadPasswd,
+            messages: vec!["Invalid password".into(), "Try again".into()],
+        }
+    );
+    assert_eq!(
+        e.to_string(),
+        "Account manager returned error BadPasswd (-206): Invalid password; Try again"
+    );
+}
+
+#[tokio::test]
+async fn timeout() {
+    let transport = MockTransport::new()
+        .expect(acct_mgr_rpc())
+        .expect(poll(-204, &[]))
+        .expect(poll(-204, &[]).latency(Duration::from_secs(60)));
+
+    assert_eq!(
+        attach(transport, Duration::from_millis(50)).await,
+        Err(Error::Timeout)
+    );
+}
@@ -0,0 +1,148 @@
+//! `Client::attach_project` against scripted replies.
+
+mod common;
+
+use boinc_rpc::{
+    test_util::{node, Expect, MockTransport},
+    BoincErrorCode, Client, Error,
+};
+use std::time::Duration;
+
+const URL: &str = "https://einsteinathome.org";
+
+fn attach() -> Expect {
+    let mut request = treexml::Element::new("project_attach");
+    request.children = vec![
+        node("project_url", URL),
+        node("authenticator", "0123abcd"),
+        node("project_name", "Einstein@Home"),
+    ];
+    Expect::request(vec![request])
+}
+
+fn poll(error_num: i32, messages: &[&str]) -> Expect {
+    common::poll(
+        "project_attach_poll",
+        "project_attach_reply",
+        error_num,
+        messages,
+    )
+}
+
+fn project_status(master_urls: &[&str]) -> Expect {
+    let mut projects = treexml::Element::new("projects");
+    for master_url in master_urls {
+        let mut project = treexml::Element::new("project");
+        project.children = vec![
+            node("master_url", master_url),
+            node("resource_share", "100"),
+        ];
+        projects.children.push(project);
+    }
+    Expect::op("get_project_status").reply(vec![projects])
+}
+
+async fn attach_project(transport: MockTransport, timeout: Duration) -> Result<(), Error> {
+    Client::new(transport)
+        .attach_project(
+            URL,
+            "0123abcd",
+            "Einstein@Home",
+            Duration::from_millis(1),
+            timeout,
+        )
+        .await
+}
+
+#[tokio::test]
+async fn attached() {
+    let transport = MockTransport::new()
+        .expect(attach())
+        .expect(poll(-204, &[]))
+        .expect(Expect::op("project_attach_poll").error(Error::ConnectionClosed))
+        .expect(poll(-204, &[]))
+        .expect(poll(0, &["Welcome to Einstein@Home"]))
+        // As the daemon canonicalizes it
+        .expect(project_status(&[
+            "https://milkyway.cs.rpi.edu/milkyway/",
+            "https://einsteinathome.org/",
+        ]));
+
+    attach_project(transport.clone(), Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert_eq!(transport.remaining(), 0);
+}
+
+#[tokio::test]
+async fn failure_codes() {
+    for (error_num, messages, expected) in [
+        (
+            -155,
+            vec!["Invalid account key"],
+            Error::StatusError(BoincErrorCode::Authenticator),
+        ),
+        (
+            -189,
+            vec!["Invalid URL"],
+            Error::InvalidURLError("Invalid URL".into()),
+        ),
+        (-130, vec![], Error::AlreadyAttachedError(URL.into())),
+        (
+            -183,
+            vec![],
+            Error::StatusError(BoincErrorCode::ProjectDown),
+        ),
+    ] {
+        let transport = MockTransport::new()
+            .expect(attach())
+            .expect(poll(-204, &[]))
+            .expect(poll(error_num, &messages));
+        assert_eq!(
+            attach_project(transport, Duration::from_secs(5)).await,
+            Err(expected)
+        );
+    }
+}
+
+#[tokio::test]
+async fn already_attached() {
+    let mut error = treexml::Element::new("error");
+    error.text = Some("Already attached to project".into());
+    let transport = MockTransport::new().expect(attach().reply(vec![error]));
+
+    assert_eq!(
+        attach_project(transport, Duration::from_secs(5)).await,
+        Err(Error::AlreadyAttachedError(
+            "Already attached to project".into()
+        ))
+    );
+}
+
+#[tokio::test]
+async fn not_listed() {
+    let transport = MockTransport::new()
+        .expect(attach())
+        .expect(poll(0, &[]))
+        .expect(project_status(&["https://milkyway.cs.rpi.edu/milkyway/"]));
+
+    assert_eq!(
+        attach_project(transport, Duration::from_secs(5)).await,
+        Err(Error::DaemonError(
+            "https://einsteinathome.org is not among the projects after attaching".into()
+        ))
+    );
+}
+
+#[tokio::test]
+async fn timeout() {
+    let transport = MockTransport::new()
+        .expect(attach())
+        .expect(poll(-204, &[]))
+        .expect(poll(-204, &[]).latency(Duration::from_secs(60)));
+
+    assert_eq!(
+        attach_project(transport, Duration::from_millis(50)).await,
+        Err(Error::Timeout)
+    );
+}
@@ -0,0 +1,136 @@
+//! `blocking::Client` against `MockDaemon`, which runs on a thread of its own.
+
+mod common;
+
+use boinc_rpc::{
+    blocking::Client, test_util::MockDaemon, transport::Transport, AuthFailure, Error,
+};
+use common::host_info;
+use std::{
+    net::SocketAddr,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use tokio::sync::oneshot;
+
+/// `MockDaemon` served by a runtime on another thread until dropped.
+struct Daemon {
+    addr: SocketAddr,
+    stop: Option<oneshot::Sender<()>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Daemon {
+    fn start<F>(password: Option<&'static str>, setup: F) -> Self
+    where
+        F: FnOnce(&MockDaemon) + Send + 'static,
+    {
+        let (addr_tx, addr_rx) = mpsc::channel();
+        let (stop, stopped) = oneshot::channel::<()>();
+        let thread = thread::spawn(move || {
+            let mut runtime = tokio::runtime::Builder::new()
+                .basic_scheduler()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(async move {
+                let daemon = MockDaemon::bind("127.0.0.1:0").await.unwrap();
+                daemon.set_password(password);
+                daemon.handle("get_host_info", |_| host_info("cruncher"));
+                setup(&daemon);
+                addr_tx.send(daemon.local_addr()).unwrap();
+                let _ = stopped.await;
+            });
+        });
+        Self {
+            addr: addr_rx.recv().unwrap(),
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = self.stop.take().unwrap().send(());
+        let _ = self.thread.take().unwrap().join();
+    }
+}
+
+#[test]
+fn authenticated_requests() {
+    let daemon = Daemon::start(Some("secret"), |_| {});
+    let client = Client::connect(daemon.addr, Some("secret")).unwrap();
+
+    for _ in 0..3 {
+        let info = client.get_host_info().unwrap();
+        assert_eq!(info.domain_name.as_deref(), Some("cruncher"));
+    }
+}
+
+#[test]
+fn wrong_password() {
+    let daemon = Daemon::start(Some("secret"), |_| {});
+    assert_eq!(
+        Client::connect(daemon.addr, Some("guess")).err(),
+        Some(Error::AuthError(AuthFailure::WrongPassword))
+    );
+
+    let client = Client::new(Transport::new(daemon.addr, None::<&str>)).unwrap();
+    assert_eq!(
+        client.get_host_info().unwrap_err(),
+        Error::AuthError(AuthFailure::PasswordRequired)
+    );
+}
+
+#[test]
+fn request_timeout() {
+    let daemon = Daemon::start(None, |daemon| daemon.set_latency(Duration::from_secs(1)));
+    let transport = Transport::builder(daemon.addr)
+        .request_timeout(Duration::from_millis(50))
+        .build();
+    let client = Client::new(transport).unwrap();
+    assert_eq!(client.get_host_info().unwrap_err(), Error::Timeout);
+
+    // So do timeouts taken as arguments
+    let e = client
+        .run_benchmarks_and_wait(Duration::from_millis(1), Duration::from_millis(20))
+        .unwrap_err();
+    assert_eq!(e, Error::Timeout);
+}
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn shared_between_threads() {
+    assert_send_sync::<Client>();
+
+    let daemon = Daemon::start(None, |daemon| {
+        daemon.handle("set_language", |req| {
+            assert_eq!(req.children[0].text.as_deref(), Some("de_DE"));
+            vec![treexml::Element::new("success")]
+        });
+    });
+    // As kept in the state of a GUI
+    let client = Arc::new(Mutex::new(
+        Client::new(Transport::new(daemon.addr, None::<&str>)).unwrap(),
+    ));
+
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let client = client.clone();
+            thread::spawn(move || {
+                let client = client.lock().unwrap();
+                client.set_language("de_DE").unwrap();
+                client.get_host_info().unwrap()
+            })
+        })
+        .collect();
+    for thread in threads {
+        assert_eq!(
+            thread.join().unwrap().domain_name.as_deref(),
+            Some("cruncher")
+        );
+    }
+}
@@ -0,0 +1,56 @@
+//! Code written against `BoincRpc`, run over a scripted `Client` and over `StubRpc`.
+
+use boinc_rpc::{
+    models::ProjectOp,
+    test_util::{node, Expect, MockTransport, StubRpc},
+    BoincRpc, Client, Error,
+};
+
+/// Suspends every attached project, returning how many there were.
+async fn suspend_all(rpc: &dyn BoincRpc) -> Result<usize, Error> {
+    let projects = rpc.get_project_status().await?;
+    for project in &projects {
+        let url = project.master_url.as_deref().unwrap_or_default();
+        rpc.project_op(url, ProjectOp::Suspend).await?;
+    }
+    Ok(projects.len())
+}
+
+/// Same, but generic, for code that would rather not box.
+async fn running<R: BoincRpc>(rpc: &R) -> Result<usize, Error> {
+    let tasks = rpc.get_results(true).await?;
+    Ok(tasks
+        .iter()
+        .filter(|task| task.active_task.is_some())
+        .count())
+}
+
+#[tokio::test]
+async fn client() {
+    let mut project = treexml::Element::new("project");
+    project.children = vec![node("master_url", "https://a.example/")];
+    let mut projects = treexml::Element::new("projects");
+    projects.children = vec![project];
+    let mut suspend = treexml::Element::new("project_suspend");
+    suspend.children = vec![node("project_url", "https://a.example/")];
+    let transport = MockTransport::new()
+        .expect(Expect::op("get_project_status").reply(vec![projects]))
+        .expect(Expect::request(vec![suspend]).reply(vec![treexml::Element::new("success")]))
+        .expect(Expect::op("get_results").reply(vec![treexml::Element::new("results")]));
+    let client = Client::new(transport.clone());
+
+    assert_eq!(suspend_all(&client).await, Ok(1));
+    assert_eq!(running(&client).await, Ok(0));
+    assert_eq!(transport.remaining(), 0);
+}
+
+#[tokio::test]
+async fn stub() {
+    let stub = StubRpc::new(Error::Timeout);
+    assert_eq!(suspend_all(&stub).await, Err(Error::Timeout));
+    assert_eq!(running(&stub).await, Err(Error::Timeout));
+    assert_eq!(stub.summary().await.version.ok(), None, "sections fail too");
+
+    let stub = StubRpc::default();
+    assert_eq!(running(&stub).await, Err(Error::ConnectionClosed));
+}
@@ -0,0 +1,39 @@
+//! Clones of a `Client` used from tasks of their own.
+
+mod common;
+
+use boinc_rpc::{
+    test_util::{Expect, MockTransport},
+    Client,
+};
+use std::time::Duration;
+
+fn slow_host_info(domain_name: &str) -> Expect {
+    Expect::op("get_host_info")
+        .reply(common::host_info(domain_name))
+        .latency(Duration::from_millis(100))
+}
+
+#[tokio::test]
+async fn two_tasks() {
+    tokio::time::pause();
+    let transport = MockTransport::new()
+        .expect(slow_host_info("cruncher"))
+        .expect(slow_host_info("cruncher"))
+        .expect(Expect::op("set_language"));
+    let client = Client::new(transport.clone());
+
+    let tasks: Vec<_> = (0..2)
+        .map(|_| {
+            let client = client.clone();
+            tokio::spawn(async move { client.get_host_info().await })
+        })
+        .collect();
+    for task in tasks {
+        let info = task.await.unwrap().unwrap();
+        assert_eq!(info.domain_name.as_deref(), Some("cruncher"));
+    }
+    // The original carries on as before
+    client.set_language("de_DE").await.unwrap();
+    assert_eq!(transport.remaining(), 0);
+}
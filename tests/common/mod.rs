@@ -0,0 +1,26 @@
+//! Replies shared by the integration tests, on top of the builders in `test_util`.
+
+// Each test uses some of them
+#![allow(dead_code)]
+
+use boinc_rpc::test_util::{node, parent, Expect};
+
+/// `get_results` answered with `tasks`.
+pub fn results(tasks: Vec<treexml::Element>) -> Expect {
+    Expect::op("get_results").reply(vec![parent("results", tasks)])
+}
+
+/// Poll of an operation in progress, `op` answered with a `reply` element holding `error_num`
+/// and `messages`.
+pub fn poll(op: &str, reply: &str, error_num: i32, messages: &[&str]) -> Expect {
+    let mut reply = parent(reply, vec![node("error_num", &error_num.to_string())]);
+    for message in messages {
+        reply.children.push(node("message", message));
+    }
+    Expect::op(op).reply(vec![reply])
+}
+
+/// Host info naming the host `domain_name`.
+pub fn host_info(domain_name: &str) -> Vec<treexml::Element> {
+    vec![parent("host_info", vec![node("domain_name", domain_name)])]
+}
@@ -0,0 +1,194 @@
+//! `Client::drain` against `MockDaemon`, with a task list shrinking from poll to poll.
+
+use boinc_rpc::{
+    tasks::{AbortReport, DrainEvent, DrainOptions},
+    test_util::{node, MockDaemon, TaskNode},
+    transport::Transport,
+    Client,
+};
+use futures::TryStreamExt;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+const A: &str = "https://a.example/";
+const B: &str = "https://b.example/";
+
+const POLL: Duration = Duration::from_secs(10);
+
+fn progress(tasks_remaining: usize, seconds: u64) -> DrainEvent {
+    DrainEvent::Progress {
+        tasks_remaining,
+        cpu_time_remaining: Duration::from_secs(seconds),
+    }
+}
+
+/// Requests acting on projects and tasks, in order, as `op argument`.
+type Log = Arc<Mutex<Vec<String>>>;
+
+/// Daemon attached to projects `A` and `B` that answers the polls of the task list with
+/// `polls` in turn, the last one over and over.
+async fn daemon(polls: Vec<Vec<treexml::Element>>) -> (MockDaemon, Log) {
+    let daemon = MockDaemon::bind("127.0.0.1:0").await.unwrap();
+    let log = Log::default();
+    daemon.handle("get_project_status", |_| {
+        let mut projects = treexml::Element::new("projects");
+        for &url in &[A, B] {
+            let mut project = treexml::Element::new("project");
+            project.children = vec![node("master_url", url)];
+            projects.children.push(project);
+        }
+        vec![projects]
+    });
+
+    let polls = Mutex::new(polls.into_iter().collect::<VecDeque<_>>());
+    daemon.handle("get_results", move |_| {
+        let mut polls = polls.lock().unwrap();
+        let tasks = if polls.len() > 1 {
+            polls.pop_front().unwrap()
+        } else {
+            polls[0].clone()
+        };
+        let mut results = treexml::Element::new("results");
+        results.children = tasks;
+        vec![results]
+    });
+
+    for &(op, argument) in &[
+        ("project_nomorework", "project_url"),
+        ("project_detach", "project_url"),
+        ("abort_result", "name"),
+    ] {
+        let log = log.clone();
+        daemon.handle(op, move |request| {
+            let argument = request.find_child(|n| n.name == argument).unwrap();
+            log.lock()
+                .unwrap()
+                .push(format!("{} {}", op, argument.text.as_deref().unwrap()));
+            vec![treexml::Element::new("success")]
+        });
+    }
+    (daemon, log)
+}
+
+#[tokio::test]
+async fn drains_and_detaches() {
+    tokio::time::pause();
+    let (daemon, log) = daemon(vec![
+        vec![
+            TaskNode::new("a1").project_url(A).remaining(600.0).build(),
+            TaskNode::new("b1").project_url(B).remaining(3000.0).build(),
+            TaskNode::new("a2").project_url(A).remaining(1200.5).build(),
+        ],
+        vec![
+            TaskNode::new("b1").project_url(B).remaining(2400.0).build(),
+            TaskNode::new("a2").project_url(A).ready_to_report().build(),
+        ],
+        vec![TaskNode::new("b1").project_url(B).remaining(1200.0).build()],
+        vec![TaskNode::new("b1").project_url(B).ready_to_report().build()],
+        vec![],
+    ])
+    .await;
+    let client = Client::new(Transport::new(daemon.local_addr(), None::<&str>));
+
+    let events: Vec<_> = client
+        .drain(DrainOptions::new().detach(true), POLL)
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(
+        events,
+        [
+            DrainEvent::Progress {
+                tasks_remaining: 3,
+                cpu_time_remaining: Duration::from_millis(4_800_500),
+            },
+            progress(2, 2400),
+            progress(1, 1200),
+            progress(1, 0),
+            progress(0, 0),
+            DrainEvent::Detached(A.into()),
+            DrainEvent::Detached(B.into()),
+        ]
+    );
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            format!("project_nomorework {A}"),
+            format!("project_nomorework {B}"),
+            format!("project_detach {A}"),
+            format!("project_detach {B}"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn aborts_stragglers() {
+    tokio::time::pause();
+    let running = || {
+        vec![
+            TaskNode::new("a1").project_url(A).remaining(900.0).build(),
+            TaskNode::new("b1").project_url(B).ready_to_report().build(),
+        ]
+    };
+    // Polled at 0, 10, 20 and 30 seconds, then once more for aborting
+    let (daemon, log) = daemon(vec![
+        running(),
+        running(),
+        running(),
+        running(),
+        running(),
+        vec![TaskNode::new("a1").project_url(A).ready_to_report().build()],
+        vec![],
+    ])
+    .await;
+    let client = Client::new(Transport::new(daemon.local_addr(), None::<&str>));
+
+    let options = DrainOptions::new().abort_after(Some(Duration::from_secs(25)));
+    let events: Vec<_> = client
+        .drain(options, POLL)
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(
+        events,
+        [
+            progress(2, 900),
+            progress(2, 900),
+            progress(2, 900),
+            progress(2, 900),
+            DrainEvent::Aborted(AbortReport {
+                aborted: vec!["a1".into()],
+                ..AbortReport::default()
+            }),
+            progress(1, 0),
+            progress(0, 0),
+        ]
+    );
+    assert_eq!(
+        *log.lock().unwrap(),
+        [
+            format!("project_nomorework {A}"),
+            format!("project_nomorework {B}"),
+            "abort_result a1".into(),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn no_more_work_refused() {
+    let (daemon, log) = daemon(vec![vec![]]).await;
+    daemon.handle("project_nomorework", |_| {
+        vec![node("error", "no such project")]
+    });
+    let client = Client::new(Transport::new(daemon.local_addr(), None::<&str>));
+
+    assert!(client.drain(DrainOptions::new(), POLL).await.is_err());
+    assert!(log.lock().unwrap().is_empty());
+}
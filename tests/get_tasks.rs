@@ -0,0 +1,77 @@
+//! `Client::get_tasks` against scripted `get_results` replies.
+
+use boinc_rpc::{
+    models::{ResultState, TaskFilter},
+    rpc::request::Request,
+    test_util::{parent, Expect, MockTransport, TaskNode},
+    Client,
+};
+
+fn results(active_only: bool, tasks: Vec<treexml::Element>) -> Expect {
+    Expect::request(Request::GetResults { active_only }.into())
+        .reply(vec![parent("results", tasks)])
+}
+
+fn names(tasks: &[boinc_rpc::models::TaskResult]) -> Vec<&str> {
+    tasks
+        .iter()
+        .filter_map(|task| task.name.as_deref())
+        .collect()
+}
+
+#[tokio::test]
+async fn filtered() {
+    let transport = MockTransport::new().expect(results(
+        false,
+        vec![
+            TaskNode::new("h1_0001_0")
+                .project_url("https://einsteinathome.org/")
+                .state(2)
+                .active()
+                .build(),
+            TaskNode::new("h1_0002_0")
+                .project_url("https://einsteinathome.org/")
+                .state(5)
+                .active()
+                .build(),
+            TaskNode::new("de_modfit_1_0")
+                .project_url("https://milkyway.cs.rpi.edu/milkyway/")
+                .state(2)
+                .active()
+                .build(),
+        ],
+    ));
+    let client = Client::new(transport.clone());
+
+    let filter = TaskFilter::new().state(ResultState::FilesDownloaded);
+    let tasks = client.get_tasks(&filter).await.unwrap();
+    assert_eq!(names(&tasks), ["h1_0001_0", "de_modfit_1_0"]);
+    assert_eq!(transport.remaining(), 0);
+}
+
+#[tokio::test]
+async fn active_only_asked_for() {
+    let transport = MockTransport::new().expect(results(
+        true,
+        vec![
+            TaskNode::new("h1_0001_0")
+                .project_url("https://einsteinathome.org/")
+                .state(2)
+                .active()
+                .build(),
+            TaskNode::new("de_modfit_1_0")
+                .project_url("https://milkyway.cs.rpi.edu/milkyway/")
+                .state(2)
+                .active()
+                .build(),
+        ],
+    ));
+    let client = Client::new(transport.clone());
+
+    let filter = TaskFilter::new()
+        .active_only()
+        .project("https://milkyway.cs.rpi.edu/milkyway");
+    let tasks = client.get_tasks(&filter).await.unwrap();
+    assert_eq!(names(&tasks), ["de_modfit_1_0"]);
+    assert_eq!(transport.remaining(), 0);
+}
@@ -0,0 +1,264 @@
+//! `Client::update_global_prefs` and `temporary_prefs_override` against scripted replies.
+
+use boinc_rpc::{
+    models::{Component, DayPrefs, GlobalPreferences, RunMode},
+    rpc::request::Request,
+    test_util::{Expect, MockTransport},
+    AuthFailure, Client, Error,
+};
+use std::time::Duration;
+
+fn prefs() -> GlobalPreferences {
+    GlobalPreferences {
+        run_on_batteries: Some(false),
+        run_if_user_active: Some(true),
+        start_hour: Some(22.0),
+        end_hour: Some(7.5),
+        max_ncpus_pct: Some(75.0),
+        cpu_usage_limit: Some(100.0),
+        disk_max_used_gb: Some(20.0),
+        max_bytes_sec_up: Some(25600.0),
+        day_prefs: vec![DayPrefs {
+            day_of_week: Some(3),
+            net_start_hour: Some(1.0),
+            net_end_hour: Some(5.0),
+            ..DayPrefs::default()
+        }],
+        ..GlobalPreferences::default()
+    }
+}
+
+fn reply(prefs: &GlobalPreferences) -> Vec<treexml::Element> {
+    vec![treexml::Element::from(prefs)]
+}
+
+fn success() -> Vec<treexml::Element> {
+    vec![treexml::Element::new("success")]
+}
+
+fn no_override() -> Vec<treexml::Element> {
+    let mut error = treexml::Element::new("error");
+    error.text = Some("no prefs override file".into());
+    vec![error]
+}
+
+/// Expects the override to be written as `prefs` and read, and the working preferences to be
+/// `working` after.
+fn apply(
+    transport: MockTransport,
+    prefs: GlobalPreferences,
+    working: &GlobalPreferences,
+) -> MockTransport {
+    transport
+        .expect(
+            Expect::request(Request::SetGlobalPrefsOverride { prefs: Some(prefs) }.into())
+                .reply(success()),
+        )
+        .expect(Expect::op("read_global_prefs_override").reply(success()))
+        .expect(Expect::op("get_global_prefs_working").reply(reply(working)))
+}
+
+#[tokio::test]
+async fn from_override() {
+    let updated = GlobalPreferences {
+        cpu_usage_limit: Some(50.0),
+        ..prefs()
+    };
+    let working = GlobalPreferences {
+        source_project: Some("https://example.com/".into()),
+        work_buf_min_days: Some(0.1),
+        ..updated.clone()
+    };
+    let transport =
+        MockTransport::new().expect(Expect::op("get_global_prefs_override").reply(reply(&prefs())));
+    let client = Client::new(apply(transport, updated, &working));
+
+    let applied = client
+        .update_global_prefs(|prefs| prefs.cpu_usage_limit = Some(50.0))
+        .await
+        .unwrap();
+    assert_eq!(applied, working);
+    assert_eq!(client.transport().remaining(), 0);
+}
+
+#[tokio::test]
+async fn from_working() {
+    let working = GlobalPreferences {
+        source_project: Some("https://example.com/".into()),
+        mod_time: Some(1_700_000_000.0),
+        ..prefs()
+    };
+    let updated = GlobalPreferences {
+        run_if_user_active: Some(false),
+        max_ncpus_pct: None,
+        ..working.clone()
+    };
+    let transport = MockTransport::new()
+        .expect(Expect::op("get_global_prefs_override").reply(no_override()))
+        .expect(Expect::op("get_global_prefs_working").reply(reply(&working)));
+    let client = Client::new(apply(transport, updated.clone(), &updated));
+
+    let applied = client
+        .update_global_prefs(|prefs| {
+            prefs.run_if_user_active = Some(false);
+            prefs.max_ncpus_pct = None;
+        })
+        .await
+        .unwrap();
+    assert_eq!(applied, updated);
+    assert_eq!(applied.day_prefs, prefs().day_prefs);
+}
+
+#[tokio::test]
+async fn nothing_written_on_error() {
+    let transport = MockTransport::new().expect(
+        Expect::op("get_global_prefs_override").reply(vec![treexml::Element::new("unauthorized")]),
+    );
+    let client = Client::new(transport);
+
+    assert_eq!(
+        client.update_global_prefs(|_| unreachable!()).await,
+        Err(Error::AuthError(AuthFailure::Unauthorized))
+    );
+}
+
+/// Expects the override to be written as `prefs`, or removed for `None`, and read.
+fn write(transport: MockTransport, prefs: Option<GlobalPreferences>) -> MockTransport {
+    transport
+        .expect(Expect::request(Request::SetGlobalPrefsOverride { prefs }.into()).reply(success()))
+        .expect(Expect::op("read_global_prefs_override").reply(success()))
+}
+
+fn throttled(prefs: GlobalPreferences) -> GlobalPreferences {
+    GlobalPreferences {
+        cpu_usage_limit: Some(50.0),
+        ..prefs
+    }
+}
+
+#[tokio::test]
+async fn temporary_over_override() {
+    let transport =
+        MockTransport::new().expect(Expect::op("get_global_prefs_override").reply(reply(&prefs())));
+    let transport = write(transport, Some(throttled(prefs())));
+    let transport = write(transport, Some(prefs()));
+    let client = Client::new(transport);
+
+    let mut guard = client
+        .temporary_prefs_override(|prefs| prefs.cpu_usage_limit = Some(50.0))
+        .await
+        .unwrap();
+    assert_eq!(guard.previous(), Some(&prefs()));
+    assert_eq!(guard.prefs(), &throttled(prefs()));
+    guard.restore().await.unwrap();
+    assert!(guard.is_restored());
+    drop(guard);
+    assert_eq!(client.transport().remaining(), 0);
+}
+
+#[tokio::test]
+async fn temporary_without_override() {
+    let working = GlobalPreferences {
+        source_project: Some("https://example.com/".into()),
+        ..prefs()
+    };
+    let transport = MockTransport::new()
+        .expect(Expect::op("get_global_prefs_override").reply(no_override()))
+        .expect(Expect::op("get_global_prefs_working").reply(reply(&working)));
+    let transport = write(transport, Some(throttled(working)));
+    // Removed again, rather than left in place with the preferences in effect before
+    let transport = write(transport, None);
+    let client = Client::new(transport);
+
+    let mut guard = client
+        .temporary_prefs_override(|prefs| prefs.cpu_usage_limit = Some(50.0))
+        .await
+        .unwrap();
+    assert_eq!(guard.previous(), None);
+    guard.restore().await.unwrap();
+    drop(guard);
+    assert_eq!(client.transport().remaining(), 0);
+}
+
+#[tokio::test]
+async fn temporary_with_timed_mode() {
+    let hours = Duration::from_secs(2 * 3600);
+    let mode = |mode, duration| {
+        Expect::request(
+            Request::set_mode(Component::GPU, mode, duration)
+                .unwrap()
+                .into(),
+        )
+        .reply(success())
+    };
+    let transport =
+        MockTransport::new().expect(Expect::op("get_global_prefs_override").reply(reply(&prefs())));
+    let transport =
+        write(transport, Some(throttled(prefs()))).expect(mode(RunMode::Never, Some(hours)));
+    let transport = write(transport, Some(prefs())).expect(mode(RunMode::Restore, None));
+    let client = Client::new(transport);
+
+    let mut guard = client
+        .temporary_prefs_override(|prefs| prefs.cpu_usage_limit = Some(50.0))
+        .await
+        .unwrap();
+    guard
+        .timed_mode(Component::GPU, RunMode::Never, hours)
+        .await
+        .unwrap();
+    assert_eq!(
+        guard
+            .timed_mode(Component::CPU, RunMode::Restore, hours)
+            .await,
+        Err(Error::InvalidArgument(
+            "restoring the previous mode cannot be timed".into()
+        ))
+    );
+    guard.restore().await.unwrap();
+    drop(guard);
+    assert_eq!(client.transport().remaining(), 0);
+}
+
+#[tokio::test]
+async fn temporary_restored_on_error() {
+    let transport = MockTransport::new()
+        .expect(Expect::op("get_global_prefs_override").reply(no_override()))
+        .expect(Expect::op("get_global_prefs_working").reply(reply(&prefs())))
+        .expect(
+            Expect::request(
+                Request::SetGlobalPrefsOverride {
+                    prefs: Some(throttled(prefs())),
+                }
+                .into(),
+            )
+            .reply(success()),
+        )
+        .expect(
+            Expect::op("read_global_prefs_override")
+                .reply(vec![treexml::Element::new("unauthorized")]),
+        );
+    let client = Client::new(write(transport, None));
+
+    assert_eq!(
+        client
+            .temporary_prefs_override(|prefs| prefs.cpu_usage_limit = Some(50.0))
+            .await
+            .err(),
+        Some(Error::AuthError(AuthFailure::Unauthorized))
+    );
+    assert_eq!(client.transport().remaining(), 0);
+}
+
+#[tokio::test]
+async fn remove_override() {
+    let transport = MockTransport::new()
+        .expect(Expect::op("get_global_prefs_override").reply(no_override()))
+        .expect(
+            Expect::request(Request::SetGlobalPrefsOverride { prefs: None }.into())
+                .reply(success()),
+        );
+    let client = Client::new(transport);
+
+    assert_eq!(client.get_global_prefs_override().await, Ok(None));
+    client.set_global_prefs_override(None).await.unwrap();
+}
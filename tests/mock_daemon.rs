@@ -0,0 +1,604 @@
+//! The client stack against `MockDaemon`, over real TCP connections.
+
+mod common;
+
+use boinc_rpc::{
+    account::{AddProjectOptions, AddProjectStage},
+    models::{RunMode, SuspendReason},
+    test_util::{node, parent, MockDaemon},
+    transport::{RetryPolicy, Transport},
+    AuthFailure, BoincErrorCode, Client, Error,
+};
+use common::host_info;
+use futures::StreamExt;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+async fn daemon(password: Option<&str>) -> MockDaemon {
+    let daemon = MockDaemon::bind("127.0.0.1:0").await.unwrap();
+    daemon.set_password(password);
+    daemon.handle("get_host_info", |_| host_info("cruncher"));
+    daemon
+}
+
+#[tokio::test]
+async fn authenticated_requests() {
+    let daemon = daemon(Some("secret")).await;
+    let client = Client::new(Transport::new(daemon.local_addr(), Some("secret")));
+
+    for _ in 0..3 {
+        let info = client.get_host_info().await.unwrap();
+        assert_eq!(info.domain_name.as_deref(), Some("cruncher"));
+    }
+    assert_eq!(daemon.connections(), 1);
+}
+
+#[tokio::test]
+async fn without_password() {
+    let daemon = daemon(None).await;
+    let client = Client::new(Transport::new(daemon.local_addr(), None::<&str>));
+    assert!(client.get_host_info().await.is_ok());
+}
+
+#[tokio::test]
+async fn wrong_password() {
+    let daemon = daemon(Some("secret")).await;
+
+    let client = Client::new(Transport::new(daemon.local_addr(), Some("guess")));
+    assert_eq!(
+        client.get_host_info().await.unwrap_err(),
+        Error::AuthError(AuthFailure::WrongPassword)
+    );
+
+    let client = Client::new(Transport::new(daemon.local_addr(), None::<&str>));
+    assert_eq!(
+        client.get_host_info().await.unwrap_err(),
+        Error::AuthError(AuthFailure::PasswordRequired)
+    );
+}
+
+#[tokio::test]
+async fn request_arguments() {
+    let daemon = daemon(None).await;
+    daemon.handle("set_language", |req| {
+        assert_eq!(req.children[0].text.as_deref(), Some("de_DE"));
+        vec![treexml::Element::new("success")]
+    });
+    let client = Client::new(Transport::new(daemon.local_addr(), None::<&str>));
+    client.set_language("de_DE").await.unwrap();
+}
+
+#[tokio::test]
+async fn unrecognized_op() {
+    let daemon = daemon(None).await;
+    let client = Client::new(Transport::new(daemon.local_addr(), None::<&str>));
+    assert_eq!(
+        client.get_state().await.unwrap_err(),
+        Error::DaemonError("unrecognized op".into())
+    );
+}
+
+#[tokio::test]
+async fn malformed_reply() {
+    let daemon = daemon(Some("secret")).await;
+    let client = Client::new(Transport::new(daemon.local_addr(), Some("secret")));
+
+    for garbage in &[
+        "<boinc_gui_rpc_reply><client_state>",
+        "<html>Not found</html>",
+    ] {
+        client.get_host_info().await.unwrap();
+        daemon.handle_raw("get_state", move |_| garbage.to_string());
+        assert!(matches!(
+            client.get_state().await.unwrap_err(),
+            Error::DataParseError(_)
+        ));
+        client.get_host_info().await.unwrap();
+    }
+    assert_eq!(daemon.connections(), 1);
+}
+
+/// Serves messages from `log`, as the daemon does from its own.
+fn serve_messages(daemon: &MockDaemon, log: &Arc<Mutex<Vec<&'static str>>>) {
+    daemon.handle("get_message_count", {
+        let log = log.clone();
+        move |_| {
+            let mut seqno = treexml::Element::new("seqno");
+            seqno.text = Some(log.lock().unwrap().len().to_string());
+            vec![seqno]
+        }
+    });
+    daemon.handle("get_messages", {
+        let log = log.clone();
+        move |req| {
+            let after: usize = req.text.as_deref().unwrap().parse().unwrap();
+            let mut msgs = treexml::Element::new("msgs");
+            for (i, body) in log.lock().unwrap().iter().enumerate().skip(after) {
+                let mut msg = treexml::Element::new("msg");
+                let mut seqno = treexml::Element::new("seqno");
+                seqno.text = Some((i + 1).to_string());
+                let mut body_node = treexml::Element::new("body");
+                body_node.cdata = Some((*body).into());
+                msg.children = vec![seqno, body_node];
+                msgs.children.push(msg);
+            }
+            vec![msgs]
+        }
+    });
+}
+
+#[tokio::test]
+async fn message_stream() {
+    let daemon = daemon(None).await;
+    let log = Arc::new(Mutex::new(vec!["Starting BOINC client"]));
+    serve_messages(&daemon, &log);
+    let client = Client::new(Transport::new(daemon.local_addr(), None::<&str>));
+
+    let stream = client
+        .stream_messages(Duration::from_millis(10))
+        .await
+        .unwrap()
+        .map(|message| message.unwrap().body.unwrap());
+    let history = client
+        .stream_messages_since(0, Duration::from_millis(10))
+        .map(|message| message.unwrap().body.unwrap());
+    futures::pin_mut!(stream, history);
+    assert_eq!(history.next().await.unwrap(), "Starting BOINC client");
+
+    for batch in &[
+        &["Fetching scheduler list", "Scheduler request completed"][..],
+        &["Started download of input_1"],
+    ] {
+        log.lock().unwrap().extend(batch.iter());
+        for &expected in *batch {
+            assert_eq!(stream.next().await.unwrap(), expected);
+            assert_eq!(history.next().await.unwrap(), expected);
+        }
+    }
+
+    // Carries on after the daemon restarts
+    daemon.disconnect_all();
+    log.lock().unwrap().push("Finished download of input_1");
+    assert_eq!(stream.next().await.unwrap(), "Finished download of input_1");
+}
+
+/// Serves the notices in `list`, each tagged with its sequence number.
+fn serve_notices(daemon: &MockDaemon, list: &Arc<Mutex<Vec<(i64, &'static str)>>>) {
+    let list = list.clone();
+    daemon.handle("get_notices", move |req| {
+        let after: i64 = req.children[0].text.as_deref().unwrap().parse().unwrap();
+        let list = list.lock().unwrap();
+        // Renumbered notices are all sent anew, as the daemon does after regenerating them
+        let latest = list
+            .iter()
+            .map(|(seqno, _)| *seqno)
+            .max()
+            .unwrap_or_default();
+        let after = if after > latest { 0 } else { after };
+
+        let mut notices = treexml::Element::new("notices");
+        for (seqno, title) in list.iter().filter(|(seqno, _)| *seqno > after) {
+            let mut notice = treexml::Element::new("notice");
+            let mut seqno_node = treexml::Element::new("seqno");
+            seqno_node.text = Some(seqno.to_string());
+            let mut title_node = treexml::Element::new("title");
+            title_node.text = Some((*title).into());
+            notice.children = vec![title_node, seqno_node];
+            notices.children.push(notice);
+        }
+        vec![notices]
+    });
+}
+
+#[tokio::test]
+async fn notice_stream() {
+    let daemon = daemon(None).await;
+    let list = Arc::new(Mutex::new(vec![(1, "Welcome"), (2, "New application")]));
+    serve_notices(&daemon, &list);
+    let client = Client::new(Transport::new(daemon.local_addr(), None::<&str>));
+
+    let stream = client
+        .stream_notices(Duration::from_millis(10))
+        .await
+        .unwrap()
+        .map(|notice| {
+            let notice = notice.unwrap();
+            (notice.seqno.unwrap(), notice.title.unwrap())
+        });
+    futures::pin_mut!(stream);
+
+    list.lock().unwrap().push((3, "Server maintenance"));
+    assert_eq!(
+        stream.next().await.unwrap(),
+        (3, "Server maintenance".into())
+    );
+
+    // Regenerated, the notices are numbered from 1 again and all of them come anew
+    *list.lock().unwrap() = vec![(1, "Welcome"), (2, "Server maintenance")];
+    assert_eq!(stream.next().await.unwrap(), (1, "Welcome".into()));
+    assert_eq!(
+        stream.next().await.unwrap(),
+        (2, "Server maintenance".into())
+    );
+
+    list.lock().unwrap().push((3, "Project ended"));
+    assert_eq!(stream.next().await.unwrap(), (3, "Project ended".into()));
+}
+
+/// Serves the statistics of the projects in `credit`, by master URL, with yesterday's entry at
+/// half today's total credit.
+fn serve_statistics(daemon: &MockDaemon, credit: &Arc<Mutex<Vec<(&'static str, f64)>>>) {
+    fn day(day: f64, total_credit: f64) -> treexml::Element {
+        let mut node = treexml::Element::new("daily_statistics");
+        for (name, value) in &[
+            ("day", day),
+            ("user_total_credit", total_credit),
+            ("user_expavg_credit", total_credit / 10.0),
+            ("host_total_credit", total_credit),
+            ("host_expavg_credit", total_credit / 10.0),
+        ] {
+            let mut child = treexml::Element::new(*name);
+            child.text = Some(format!("{value:.6}"));
+            node.children.push(child);
+        }
+        node
+    }
+
+    let credit = credit.clone();
+    daemon.handle("get_statistics", move |_| {
+        let mut statistics = treexml::Element::new("statistics");
+        for (master_url, total_credit) in credit.lock().unwrap().iter() {
+            let mut project = treexml::Element::new("project_statistics");
+            let mut url = treexml::Element::new("master_url");
+            url.text = Some((*master_url).into());
+            project.children = vec![
+                url,
+                day(1_699_920_000.0, total_credit / 2.0),
+                day(1_700_006_400.0, *total_credit),
+            ];
+            statistics.children.push(project);
+        }
+        vec![statistics]
+    });
+}
+
+#[tokio::test]
+async fn statistics_samples() {
+    let daemon = daemon(None).await;
+    let credit = Arc::new(Mutex::new(vec![("https://a.example/", 100.0)]));
+    serve_statistics(&daemon, &credit);
+    let client = Client::new(Transport::new(daemon.local_addr(), None::<&str>));
+
+    let stream = client
+        .sample_statistics(Duration::from_millis(10))
+        .map(Result::unwrap);
+    futures::pin_mut!(stream);
+
+    let first = stream.next().await.unwrap();
+    assert_eq!(first.projects.len(), 1);
+    assert_eq!(first.projects[0].master_url, "https://a.example/");
+    assert_eq!(first.projects[0].user_total_credit, 100.0);
+    assert_eq!(first.projects[0].host_expavg_credit, 10.0);
+
+    *credit.lock().unwrap() = vec![("https://b.example/", 70.0), ("https://a.example/", 150.0)];
+    let second = stream.next().await.unwrap();
+    let delta = second.delta_since(&first);
+    assert!(delta.window > 0.0);
+    // b has only just appeared
+    assert_eq!(delta.projects.len(), 1);
+    assert_eq!(delta.projects[0].master_url, "https://a.example/");
+    assert_eq!(delta.projects[0].user_credit_earned, 50.0);
+    assert_eq!(delta.projects[0].host_expavg_credit_change, 5.0);
+
+    *credit.lock().unwrap() = vec![("https://b.example/", 90.0)];
+    let delta = stream.next().await.unwrap().delta_since(&second);
+    assert_eq!(delta.projects.len(), 1);
+    assert_eq!(delta.projects[0].master_url, "https://b.example/");
+    assert_eq!(delta.projects[0].user_credit_earned, 20.0);
+}
+
+/// Serves the RPCs of adding Einstein@Home by account, logging the requests to `log`. Each
+/// operation is in progress at the first poll. The account lookup fails with `lookup_error`,
+/// if given.
+fn serve_add_project(
+    daemon: &MockDaemon,
+    log: &Arc<Mutex<Vec<String>>>,
+    lookup_error: Option<i32>,
+) {
+    fn logged<F>(
+        log: &Arc<Mutex<Vec<String>>>,
+        handler: F,
+    ) -> impl Fn(&treexml::Element) -> Vec<treexml::Element>
+    where
+        F: Fn(bool) -> Vec<treexml::Element>,
+    {
+        let log = log.clone();
+        let polled = Mutex::new(false);
+        move |req| {
+            log.lock().unwrap().push(req.to_string());
+            let mut polled = polled.lock().unwrap();
+            let first = !*polled;
+            *polled = true;
+            handler(first)
+        }
+    }
+    let success = || vec![treexml::Element::new("success")];
+    let in_progress = |reply: &str| vec![parent(reply, vec![node("error_num", "-204")])];
+
+    daemon.handle("get_project_config", logged(log, move |_| success()));
+    daemon.handle(
+        "get_project_config_poll",
+        logged(log, move |first| {
+            if first {
+                return in_progress("project_config");
+            }
+            vec![parent(
+                "project_config",
+                vec![
+                    node("name", "Einstein@Home"),
+                    node("min_passwd_length", "6"),
+                ],
+            )]
+        }),
+    );
+    daemon.handle("lookup_account", logged(log, move |_| success()));
+    daemon.handle(
+        "lookup_account_poll",
+        logged(log, move |first| {
+            if first {
+                return in_progress("account_out");
+            }
+            vec![parent(
+                "account_out",
+                vec![match lookup_error {
+                    Some(error_num) => node("error_num", &error_num.to_string()),
+                    None => node("authenticator", "0123abcd"),
+                }],
+            )]
+        }),
+    );
+    daemon.handle("project_attach", logged(log, move |_| success()));
+    daemon.handle(
+        "project_attach_poll",
+        logged(log, move |first| {
+            if first {
+                return in_progress("project_attach_reply");
+            }
+            vec![parent(
+                "project_attach_reply",
+                vec![node("error_num", "0"), node("message", "Welcome")],
+            )]
+        }),
+    );
+    daemon.handle(
+        "get_project_status",
+        logged(log, move |_| {
+            vec![parent(
+                "projects",
+                vec![parent(
+                    "project",
+                    vec![node("master_url", "https://einsteinathome.org/")],
+                )],
+            )]
+        }),
+    );
+}
+
+#[tokio::test]
+async fn add_project_by_account() {
+    let daemon = daemon(None).await;
+    let log = Arc::new(Mutex::new(Vec::new()));
+    serve_add_project(&daemon, &log, None);
+    let client = Client::new(Transport::new(daemon.local_addr(), None::<&str>));
+
+    let options = AddProjectOptions::new().poll_interval(Duration::from_millis(1));
+    client
+        .add_project_by_account(
+            "https://einsteinathome.org/",
+            "Alice@Example.com",
+            "hunter2",
+            &options,
+        )
+        .await
+        .unwrap();
+
+    let log = log.lock().unwrap();
+    let ops: Vec<_> = log
+        .iter()
+        .map(|req| {
+            req.trim_start_matches('<')
+                .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .next()
+                .unwrap()
+        })
+        .collect();
+    assert_eq!(
+        ops,
+        [
+            "get_project_config",
+            "get_project_config_poll",
+            "get_project_config_poll",
+            "lookup_account",
+            "lookup_account_poll",
+            "lookup_account_poll",
+            "project_attach",
+            "project_attach_poll",
+            "project_attach_poll",
+            "get_project_status",
+        ]
+    );
+    // Known by a hash of the password, salted with the lowercase email address
+    assert!(log[3].contains("<email_addr>alice@example.com</email_addr>"));
+    assert!(log[3].contains("<passwd_hash>5ba93934dc35b0ea1ec313dfb2c9d88b</passwd_hash>"));
+    assert!(log[6].contains("<authenticator>0123abcd</authenticator>"));
+    assert!(log[6].contains("<project_name>Einstein@Home</project_name>"));
+    assert!(log.iter().all(|req| !req.contains("hunter2")));
+}
+
+#[tokio::test]
+async fn add_project_wrong_password() {
+    let daemon = daemon(None).await;
+    let log = Arc::new(Mutex::new(Vec::new()));
+    serve_add_project(&daemon, &log, Some(-206));
+    let client = Client::new(Transport::new(daemon.local_addr(), None::<&str>));
+
+    let options = AddProjectOptions::new().poll_interval(Duration::from_millis(1));
+    let e = client
+        .add_project_by_account("https://einsteinathome.org/", "alice", "hunter2", &options)
+        .await
+        .unwrap_err();
+    assert_eq!(e.stage, AddProjectStage::LookupAccount);
+    assert_eq!(e.error, Error::StatusError(BoincErrorCode::BadPasswd));
+    assert!(!format!("{e} {e:?}").contains("hunter2"));
+    assert_eq!(log.lock().unwrap().len(), 6);
+}
+
+/// Serves benchmarks that suspend tasks for `polls` status polls, after which the host info
+/// has the new whetstone figure.
+fn serve_benchmarks(daemon: &MockDaemon, polls: Option<usize>) {
+    let remaining = Arc::new(Mutex::new(None));
+    let started = remaining.clone();
+    daemon.handle("run_benchmarks", move |_| {
+        *started.lock().unwrap() = polls;
+        vec![treexml::Element::new("success")]
+    });
+    let running = remaining.clone();
+    daemon.handle("get_cc_status", move |_| {
+        let mut remaining = running.lock().unwrap();
+        let reason = match &mut *remaining {
+            Some(0) | None => 0,
+            Some(n) => {
+                *n -= 1;
+                16
+            }
+        };
+        vec![parent(
+            "cc_status",
+            vec![
+                node("task_suspend_reason", &reason.to_string()),
+                node("task_mode", "2"),
+            ],
+        )]
+    });
+    daemon.handle("get_host_info", move |_| {
+        let p_fpops = match *remaining.lock().unwrap() {
+            Some(0) => "3500000000.000000",
+            _ => "3000000000.000000",
+        };
+        vec![parent(
+            "host_info",
+            vec![node("p_fpops", p_fpops), node("p_calculated", p_fpops)],
+        )]
+    });
+}
+
+#[tokio::test]
+async fn benchmarks() {
+    let daemon = daemon(None).await;
+    serve_benchmarks(&daemon, Some(3));
+    let client = Client::new(Transport::new(daemon.local_addr(), None::<&str>));
+
+    let host_info = client
+        .run_benchmarks_and_wait(Duration::from_millis(1), Duration::from_secs(10))
+        .await
+        .unwrap();
+    assert_eq!(host_info.p_fpops, Some(3_500_000_000.0));
+    let status = client.get_cc_status().await.unwrap();
+    assert_eq!(
+        status.task_suspend_reason,
+        Some(SuspendReason::NotSuspended)
+    );
+    assert_eq!(status.task_mode, Some(RunMode::Auto));
+}
+
+#[tokio::test]
+async fn benchmarks_timeout() {
+    let daemon = daemon(None).await;
+    serve_benchmarks(&daemon, Some(usize::MAX));
+    let client = Client::new(Transport::new(daemon.local_addr(), None::<&str>));
+
+    let e = client
+        .run_benchmarks_and_wait(Duration::from_millis(1), Duration::from_millis(50))
+        .await
+        .unwrap_err();
+    assert_eq!(e, Error::Timeout);
+    let status = client.get_cc_status().await.unwrap();
+    assert_eq!(status.task_suspend_reason, Some(SuspendReason::Benchmarks));
+}
+
+#[tokio::test]
+async fn benchmarks_refused() {
+    let daemon = daemon(None).await;
+    serve_benchmarks(&daemon, None);
+    daemon.handle("run_benchmarks", |_| {
+        vec![node("error", "benchmarks are disabled")]
+    });
+    let client = Client::new(Transport::new(daemon.local_addr(), None::<&str>));
+
+    let e = client
+        .run_benchmarks_and_wait(Duration::from_millis(1), Duration::from_secs(10))
+        .await
+        .unwrap_err();
+    assert_eq!(e, Error::DaemonError("benchmarks are disabled".into()));
+}
+
+#[tokio::test]
+async fn reconnects_after_restart() {
+    let daemon = daemon(Some("secret")).await;
+    let client = Client::new(Transport::new(daemon.local_addr(), Some("secret")));
+    client.get_host_info().await.unwrap();
+
+    // The request that finds the connection gone fails, the next one connects anew
+    daemon.disconnect_all();
+    assert!(client.get_host_info().await.is_err());
+    client.get_host_info().await.unwrap();
+    assert_eq!(daemon.connections(), 2);
+}
+
+#[tokio::test]
+async fn retries_after_restart() {
+    let daemon = daemon(Some("secret")).await;
+    let transport = Transport::builder(daemon.local_addr())
+        .password("secret")
+        .retry(RetryPolicy::new(2).base_delay(Duration::from_millis(1)))
+        .build();
+    let client = Client::new(transport);
+    client.get_host_info().await.unwrap();
+
+    daemon.disconnect_all();
+    client.get_host_info().await.unwrap();
+    assert_eq!(daemon.connections(), 2);
+}
+
+#[tokio::test]
+async fn request_timeout() {
+    let daemon = daemon(None).await;
+    let transport = Transport::builder(daemon.local_addr())
+        .request_timeout(Duration::from_millis(50))
+        .build();
+    let client = Client::new(transport);
+    client.get_host_info().await.unwrap();
+
+    daemon.set_latency(Duration::from_secs(1));
+    assert_eq!(client.get_host_info().await.unwrap_err(), Error::Timeout);
+
+    // The late reply is not mistaken for that of the next request
+    daemon.set_latency(Duration::from_secs(0));
+    daemon.handle("get_host_info", |_| host_info("other"));
+    let info = client.get_host_info().await.unwrap();
+    assert_eq!(info.domain_name.as_deref(), Some("other"));
+    assert_eq!(daemon.connections(), 2);
+}
+
+#[tokio::test]
+async fn dropped_daemon() {
+    let daemon = daemon(None).await;
+    let addr = daemon.local_addr();
+    let client = Client::new(Transport::new(addr, None::<&str>));
+    client.get_host_info().await.unwrap();
+
+    drop(daemon);
+    assert!(client.get_host_info().await.is_err());
+}
@@ -0,0 +1,120 @@
+//! `RpcObserver` told about requests to `MockDaemon`, timed on a paused clock.
+
+use boinc_rpc::{
+    test_util::MockDaemon,
+    transport::{RpcObserver, Transport},
+    Client, ErrorKind,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Request, milliseconds taken and outcome.
+type Record = (String, u128, Result<(), ErrorKind>);
+
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<Record>>>);
+
+impl Recorder {
+    fn take(&self) -> Vec<Record> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+impl RpcObserver for Recorder {
+    fn on_complete(&self, rpc: &str, duration: Duration, outcome: Result<(), ErrorKind>) {
+        // The timer only keeps to whole milliseconds, which may cost the first delay a fraction
+        self.0
+            .lock()
+            .unwrap()
+            .push((rpc.into(), duration.as_millis(), outcome));
+    }
+}
+
+fn record(rpc: &str, millis: u128, outcome: Result<(), ErrorKind>) -> Record {
+    (rpc.into(), millis, outcome)
+}
+
+async fn daemon() -> MockDaemon {
+    let daemon = MockDaemon::bind("127.0.0.1:0").await.unwrap();
+    daemon.set_password(Some("secret"));
+    daemon.set_latency(Duration::from_millis(100));
+    daemon.handle("get_host_info", |_| {
+        vec![treexml::Element::new("host_info")]
+    });
+    daemon
+}
+
+#[tokio::test]
+async fn names_and_durations() {
+    tokio::time::pause();
+    let daemon = daemon().await;
+    let recorder = Recorder::default();
+    let transport = Transport::builder(daemon.local_addr())
+        .password("secret")
+        .version_handshake(true)
+        .observer(recorder.clone())
+        .build();
+    let client = Client::new(transport);
+
+    client.get_host_info().await.unwrap();
+    client.get_state().await.unwrap_err();
+    assert_eq!(
+        recorder.take(),
+        [
+            // Two round trips
+            record("auth", 200, Ok(())),
+            // Unknown to `MockDaemon`
+            record("exchange_versions", 100, Err(ErrorKind::Daemon)),
+            record("get_host_info", 100, Ok(())),
+            record("get_state", 100, Err(ErrorKind::Daemon)),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn reconnect() {
+    tokio::time::pause();
+    let daemon = daemon().await;
+    let recorder = Recorder::default();
+    let transport = Transport::builder(daemon.local_addr())
+        .password("secret")
+        .observer(recorder.clone())
+        .build();
+    let client = Client::new(transport);
+
+    client.get_host_info().await.unwrap();
+    daemon.disconnect_all();
+    client.get_host_info().await.unwrap_err();
+    client.get_host_info().await.unwrap();
+    assert_eq!(
+        recorder.take(),
+        [
+            record("auth", 200, Ok(())),
+            record("get_host_info", 100, Ok(())),
+            record("get_host_info", 0, Err(ErrorKind::Network)),
+            record("auth", 200, Ok(())),
+            record("get_host_info", 100, Ok(())),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn closure() {
+    tokio::time::pause();
+    let daemon = daemon().await;
+    let failed = Arc::new(Mutex::new(Vec::new()));
+    let observed = failed.clone();
+    let transport = Transport::builder(daemon.local_addr())
+        .password("guess")
+        .observer(move |rpc: &str, _, outcome: Result<(), ErrorKind>| {
+            if let Err(kind) = outcome {
+                observed.lock().unwrap().push(format!("{rpc} {kind:?}"));
+            }
+        })
+        .build();
+
+    Client::new(transport).get_host_info().await.unwrap_err();
+    assert_eq!(*failed.lock().unwrap(), ["auth Auth"]);
+}
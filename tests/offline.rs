@@ -0,0 +1,83 @@
+//! `offline::read_client_state` over the `client_state.xml` in `tests/fixtures`.
+
+use boinc_rpc::{models::Process, offline, Error};
+
+const CLIENT_STATE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/client_state.xml"
+);
+
+#[test]
+fn client_state() {
+    let state = offline::read_client_state(CLIENT_STATE).unwrap();
+    assert_eq!(state.platform_name.as_deref(), Some("x86_64-pc-linux-gnu"));
+    assert_eq!(state.core_client_version.minor, Some(18));
+    assert_eq!(
+        state.host_info.as_ref().unwrap().domain_name.as_deref(),
+        Some("worker02")
+    );
+    assert_eq!(state.net_stats.as_ref().unwrap().bwdown, Some(9034.75));
+    assert_eq!(
+        state
+            .apps
+            .iter()
+            .map(|app| app.name.as_deref())
+            .collect::<Vec<_>>(),
+        [Some("hsgamma_FGRPB1"), Some("mcm1")]
+    );
+    assert_eq!(state.app_versions.len(), 2);
+}
+
+#[test]
+fn tasks() {
+    let state = offline::read_client_state(CLIENT_STATE).unwrap();
+    let tasks: Vec<_> = state
+        .results
+        .iter()
+        .map(|task| (task.name.as_deref().unwrap(), task.project_url.as_deref()))
+        .collect();
+    assert_eq!(
+        tasks,
+        [
+            (
+                "LATeah1089F_88.0_1034_-1.5e-10_0",
+                Some("https://einsteinathome.org/")
+            ),
+            (
+                "LATeah1089F_88.0_1066_-1.5e-10_1",
+                Some("https://einsteinathome.org/")
+            ),
+            (
+                "MCM1_0201234_5678_1",
+                Some("https://www.worldcommunitygrid.org/")
+            ),
+        ]
+    );
+
+    let running = state.results[0].active_task.as_ref().unwrap();
+    assert_eq!(running.process_state(), Some(Process::Executing));
+    assert_eq!(running.slot, Some(2));
+    assert_eq!(running.fraction_done, Some(0.633_12));
+    assert!(state.results[1].active_task.is_none());
+    assert_eq!(state.results[2].ready_to_report, Some(true));
+}
+
+#[test]
+fn same_as_reader() {
+    let from_reader =
+        offline::parse_client_state(&include_bytes!("fixtures/client_state.xml")[..]).unwrap();
+    assert_eq!(
+        offline::read_client_state(CLIENT_STATE).unwrap(),
+        from_reader
+    );
+}
+
+#[test]
+fn missing_file() {
+    let e = offline::read_client_state("/nonexistent/client_state.xml").unwrap_err();
+    assert!(
+        matches!(&e, Error::DataParseError(message) if message.contains("/nonexistent")),
+        "{:?}",
+        e
+    );
+}
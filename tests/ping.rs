@@ -0,0 +1,40 @@
+//! `Client::ping` against scripted replies.
+
+use boinc_rpc::{
+    test_util::{Expect, MockTransport},
+    AuthFailure, Client, Error, ErrorKind,
+};
+use std::time::Duration;
+
+fn seqno() -> Vec<treexml::Element> {
+    let mut seqno = treexml::Element::new("seqno");
+    seqno.text = Some("42".into());
+    vec![seqno]
+}
+
+#[tokio::test]
+async fn latency() {
+    let transport = MockTransport::new().expect(
+        Expect::op("get_message_count")
+            .reply(seqno())
+            .latency(Duration::from_millis(20)),
+    );
+    let client = Client::new(transport.clone());
+
+    assert!(client.ping().await.unwrap() >= Duration::from_millis(20));
+    assert_eq!(transport.remaining(), 0);
+}
+
+#[tokio::test]
+async fn failures() {
+    let transport = MockTransport::new()
+        .expect(Expect::op("get_message_count").reply(vec![treexml::Element::new("unauthorized")]))
+        .expect(Expect::op("get_message_count").error(Error::ConnectionClosed));
+    let client = Client::new(transport);
+
+    assert_eq!(
+        client.ping().await,
+        Err(Error::AuthError(AuthFailure::Unauthorized))
+    );
+    assert_eq!(client.ping().await.unwrap_err().kind(), ErrorKind::Network);
+}
@@ -0,0 +1,181 @@
+//! `parse_reply` and `parse_reply_vec` over the replies captured in `tests/fixtures`.
+
+use boinc_rpc::{
+    models::{
+        CcStatus, ClientState, FileTransfer, HostInfo, Message, Project, ProjectStatistics,
+        RunMode, SuspendReason, TaskResult,
+    },
+    parse_reply, parse_reply_vec, AuthFailure, Error,
+};
+
+#[test]
+fn host_info() {
+    let host: HostInfo =
+        parse_reply(include_str!("fixtures/get_host_info.xml"), "host_info").unwrap();
+    assert_eq!(host.domain_name.as_deref(), Some("worker01"));
+    assert_eq!(host.tz_shift, Some(3600));
+    assert_eq!(host.p_ncpus, Some(8));
+    assert_eq!(host.p_vm_extensions_disabled, Some(false));
+    assert_eq!(host.m_nbytes, Some(33_565_356_032.0));
+    assert_eq!(host.virtualbox_version.as_deref(), Some("7.0.10"));
+}
+
+#[test]
+fn cc_status() {
+    let status: CcStatus =
+        parse_reply(include_str!("fixtures/get_cc_status.xml"), "cc_status").unwrap();
+    assert_eq!(status.task_suspend_reason, Some(SuspendReason::UserActive));
+    assert_eq!(status.task_mode, Some(RunMode::Auto));
+    assert_eq!(status.gpu_mode, Some(RunMode::Never));
+    assert_eq!(status.gpu_mode_delay, Some(5_321.438_822));
+    assert_eq!(status.max_event_log_lines, Some(2000));
+}
+
+#[test]
+fn projects() {
+    let projects: Vec<Project> = parse_reply_vec(
+        include_str!("fixtures/get_project_status.xml"),
+        "projects",
+        "project",
+    )
+    .unwrap();
+    assert_eq!(projects.len(), 2);
+    assert_eq!(projects[0].project_name.as_deref(), Some("Einstein@Home"));
+    assert_eq!(projects[0].hostid, Some(13579));
+    assert_eq!(projects[0].suspended_via_gui, None);
+    assert_eq!(projects[1].team_name, None);
+    assert_eq!(projects[1].suspended_via_gui, Some(true));
+    assert_eq!(projects[1].dont_request_more_work, Some(true));
+}
+
+#[test]
+fn results() {
+    let results: Vec<TaskResult> = parse_reply_vec(
+        include_str!("fixtures/get_results.xml"),
+        "results",
+        "result",
+    )
+    .unwrap();
+    assert_eq!(results.len(), 3);
+
+    let running = results[0].active_task.as_ref().unwrap();
+    assert_eq!(running.pid, Some(41234));
+    assert_eq!(running.fraction_done, Some(0.412_003));
+    assert_eq!(results[1].ready_to_report, Some(true));
+    assert_eq!(results[1].completed_time, Some(1_700_003_400.0));
+    assert!(results[1].active_task.is_none());
+    assert_eq!(results[2].suspended_via_gui, Some(true));
+}
+
+#[test]
+fn messages() {
+    let messages: Vec<Message> =
+        parse_reply_vec(include_str!("fixtures/get_messages.xml"), "msgs", "msg").unwrap();
+    assert_eq!(
+        messages
+            .iter()
+            .map(|msg| (msg.msg_number, msg.project_name.as_deref()))
+            .collect::<Vec<_>>(),
+        [
+            (Some(1), None),
+            (Some(2), Some("Einstein@Home")),
+            (Some(3), Some("World Community Grid")),
+        ]
+    );
+    assert_eq!(
+        messages[2].body.as_deref(),
+        Some("Scheduler request failed: Couldn't resolve host name")
+    );
+}
+
+#[test]
+fn file_transfers() {
+    let transfers: Vec<FileTransfer> = parse_reply_vec(
+        include_str!("fixtures/get_file_transfers.xml"),
+        "file_transfers",
+        "file_transfer",
+    )
+    .unwrap();
+    assert_eq!(transfers.len(), 2);
+    assert!(transfers[0].xfer_active);
+    assert_eq!(transfers[0].xfer_speed, Some(523_011.003_912));
+    assert!(!transfers[1].xfer_active);
+    assert_eq!(transfers[1].num_retries, Some(3));
+    assert_eq!(transfers[1].project_backoff, Some(1190.0));
+}
+
+#[test]
+fn statistics() {
+    let statistics: Vec<ProjectStatistics> = parse_reply_vec(
+        include_str!("fixtures/get_statistics.xml"),
+        "statistics",
+        "project_statistics",
+    )
+    .unwrap();
+    assert_eq!(statistics[0].daily_statistics.len(), 2);
+    assert_eq!(
+        statistics[0].daily_statistics[1].user_total_credit,
+        Some(2_318_475.0)
+    );
+    assert!(statistics[1].daily_statistics.is_empty());
+}
+
+#[test]
+fn state() {
+    let state: ClientState =
+        parse_reply(include_str!("fixtures/get_state.xml"), "client_state").unwrap();
+    assert_eq!(state.platform_name.as_deref(), Some("x86_64-pc-linux-gnu"));
+    assert_eq!(state.core_client_version.minor, Some(20));
+    assert_eq!(state.executing_as_daemon, Some(true));
+    assert_eq!(
+        state.host_info.unwrap().domain_name.as_deref(),
+        Some("worker01")
+    );
+    assert_eq!(state.apps.len(), 1);
+    assert_eq!(state.app_versions[0].coprocs[0].count, Some(1.0));
+    assert_eq!(state.results.len(), 1);
+}
+
+#[test]
+fn daemon_errors() {
+    assert_eq!(
+        parse_reply::<HostInfo>(include_str!("fixtures/unauthorized.xml"), "host_info"),
+        Err(Error::AuthError(AuthFailure::Unauthorized))
+    );
+    assert_eq!(
+        parse_reply_vec::<TaskResult>(
+            "<boinc_gui_rpc_reply><error>no such project</error></boinc_gui_rpc_reply>",
+            "results",
+            "result"
+        ),
+        Err(Error::DaemonError("no such project".into()))
+    );
+}
+
+#[test]
+fn malformed() {
+    let e = parse_reply_vec::<TaskResult>(
+        include_str!("fixtures/get_cc_status.xml"),
+        "results",
+        "result",
+    )
+    .unwrap_err();
+    assert!(
+        matches!(&e, Error::NodeNotFound { node, .. } if node == "results"),
+        "{:?}",
+        e
+    );
+    let e = parse_reply::<HostInfo>(
+        "<boinc_gui_rpc_request><host_info/></boinc_gui_rpc_request>",
+        "host_info",
+    )
+    .unwrap_err();
+    assert!(matches!(e, Error::DataParseError(_)), "{:?}", e);
+}
+
+#[test]
+fn terminator() {
+    let reply = format!("{}\u{3}", include_str!("fixtures/get_host_info.xml"));
+    let host: HostInfo = parse_reply(&reply, "host_info").unwrap();
+    assert_eq!(host.p_ncpus, Some(8));
+}
@@ -0,0 +1,135 @@
+//! `Client::set_mode_for`, `snooze` and `unsnooze` against scripted replies.
+
+use boinc_rpc::{
+    models::{Component, RunMode},
+    test_util::{Expect, MockTransport},
+    Client, Error, ErrorKind,
+};
+use std::time::Duration;
+
+/// Request setting the mode of `component` to `mode` for `duration` seconds.
+fn set_mode(component: &str, mode: &str, duration: &str) -> Expect {
+    let mut duration_node = treexml::Element::new("duration");
+    duration_node.text = Some(duration.into());
+    let mut node = treexml::Element::new(format!("set_{component}_mode"));
+    node.children = vec![duration_node, treexml::Element::new(mode)];
+    Expect::request(vec![node])
+}
+
+#[tokio::test]
+async fn durations() {
+    let transport = MockTransport::new()
+        .expect(set_mode("run", "never", "0"))
+        .expect(set_mode("gpu", "auto", "1.5"))
+        .expect(set_mode("network", "always", "86400"))
+        .expect(set_mode("run", "never", "30"));
+    let client = Client::new(transport.clone());
+
+    client
+        .set_mode_for(Component::CPU, RunMode::Never, None)
+        .await
+        .unwrap();
+    client
+        .set_mode_for(
+            Component::GPU,
+            RunMode::Auto,
+            Some(Duration::from_millis(1500)),
+        )
+        .await
+        .unwrap();
+    client
+        .set_mode_for(
+            Component::Network,
+            RunMode::Always,
+            Some(Duration::from_secs(86400)),
+        )
+        .await
+        .unwrap();
+    #[allow(deprecated)]
+    client
+        .set_mode(Component::CPU, RunMode::Never, 30.0)
+        .await
+        .unwrap();
+    assert_eq!(transport.remaining(), 0);
+}
+
+#[tokio::test]
+async fn invalid_arguments_not_sent() {
+    // Any request would fail the test
+    let client = Client::new(MockTransport::new());
+
+    let hour = Some(Duration::from_secs(3600));
+    let zero = Some(Duration::from_secs(0));
+    for (mode, duration) in &[(RunMode::Restore, hour), (RunMode::Never, zero)] {
+        let e = client
+            .set_mode_for(Component::CPU, *mode, *duration)
+            .await
+            .unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidInput, "{e:?}");
+    }
+    #[allow(deprecated)]
+    for duration in &[-60.0, f64::NAN, f64::INFINITY] {
+        let e = client
+            .set_mode(Component::GPU, RunMode::Never, *duration)
+            .await
+            .unwrap_err();
+        assert!(matches!(e, Error::InvalidArgument(_)), "{:?}", e);
+    }
+}
+
+#[tokio::test]
+async fn snooze_and_unsnooze() {
+    let transport = MockTransport::new()
+        .expect(set_mode("run", "never", "3600"))
+        .expect(set_mode("gpu", "never", "3600"))
+        .expect(set_mode("run", "restore", "0"))
+        .expect(set_mode("gpu", "restore", "0"));
+    let client = Client::new(transport.clone());
+
+    client.snooze(Duration::from_secs(3600)).await.unwrap();
+    client.unsnooze().await.unwrap();
+    assert_eq!(transport.remaining(), 0);
+}
+
+#[tokio::test]
+async fn rolled_back() {
+    let transport = MockTransport::new()
+        .expect(set_mode("run", "never", "90.5"))
+        .expect(set_mode("gpu", "never", "90.5").error(Error::Timeout))
+        .expect(set_mode("run", "restore", "0"));
+    let client = Client::new(transport.clone());
+
+    assert_eq!(
+        client.snooze(Duration::from_millis(90_500)).await,
+        Err(Error::Timeout)
+    );
+    assert_eq!(transport.remaining(), 0);
+}
+
+#[tokio::test]
+async fn rollback_failed() {
+    let transport = MockTransport::new()
+        .expect(set_mode("run", "never", "60"))
+        .expect(set_mode("gpu", "never", "60").error(Error::Timeout))
+        .expect(set_mode("run", "restore", "0").error(Error::ConnectionClosed));
+    let client = Client::new(transport);
+
+    let e = client.snooze(Duration::from_secs(60)).await.unwrap_err();
+    assert_eq!(
+        e.to_string(),
+        "Daemon error: Snoozing GPUs failed (Timed out), and CPUs are left snoozed as resuming \
+         them failed (Connection closed)"
+    );
+}
+
+#[tokio::test]
+async fn cpu_failure_stops_snooze() {
+    let transport =
+        MockTransport::new().expect(set_mode("run", "never", "60").error(Error::Timeout));
+    let client = Client::new(transport);
+
+    assert_eq!(
+        client.snooze(Duration::from_secs(60)).await,
+        Err(Error::Timeout)
+    );
+}
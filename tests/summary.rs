@@ -0,0 +1,315 @@
+//! `Client::summary` against scripted replies.
+
+use boinc_rpc::{
+    models::{
+        ActiveTaskSummary, HostSummary, ResultState, RunMode, StatusSummary, SummarySection,
+        SuspendReason, TransferSummary,
+    },
+    test_util::{node, parent, Expect, MockDaemon, MockTransport},
+    Client, Error, Transport,
+};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+/// The version handshake, sending the version this crate speaks.
+fn version() -> Expect {
+    let ours = parent(
+        "exchange_versions",
+        vec![
+            node("major", "7"),
+            node("minor", "16"),
+            node("release", "0"),
+        ],
+    );
+    Expect::request(vec![ours]).reply(vec![parent(
+        "server_version",
+        vec![
+            node("major", "7"),
+            node("minor", "16"),
+            node("release", "11"),
+        ],
+    )])
+}
+
+fn host_info() -> Expect {
+    Expect::op("get_host_info").reply(vec![parent(
+        "host_info",
+        vec![node("domain_name", "cruncher"), node("p_ncpus", "8")],
+    )])
+}
+
+fn cc_status() -> Expect {
+    Expect::op("get_cc_status").reply(vec![parent(
+        "cc_status",
+        vec![
+            node("task_suspend_reason", "4"),
+            node("task_mode", "3"),
+            node("gpu_suspend_reason", "4"),
+            node("gpu_mode", "3"),
+            node("network_suspend_reason", "0"),
+            node("network_mode", "2"),
+        ],
+    )])
+}
+
+fn results() -> Expect {
+    let result = |name: &str, state: &str, active: Option<&str>| {
+        let mut result = parent("result", vec![node("name", name), node("state", state)]);
+        if let Some(fraction_done) = active {
+            result.children.push(parent(
+                "active_task",
+                vec![
+                    node("active_task_state", "1"),
+                    node("scheduler_state", "2"),
+                    node("fraction_done", fraction_done),
+                ],
+            ));
+        }
+        result
+    };
+    Expect::op("get_results").reply(vec![parent(
+        "results",
+        vec![
+            result("a", "2", Some("0.250000")),
+            result("b", "2", None),
+            result("c", "1", None),
+            result("d", "5", None),
+        ],
+    )])
+}
+
+fn transfers() -> Expect {
+    let transfer = |name: &str, nbytes: &str, is_upload: &str| {
+        parent(
+            "file_transfer",
+            vec![
+                node("name", name),
+                node("nbytes", nbytes),
+                parent(
+                    "persistent_file_xfer",
+                    vec![
+                        node("last_bytes_xferred", "1000.000000"),
+                        node("is_upload", is_upload),
+                    ],
+                ),
+            ],
+        )
+    };
+    Expect::op("get_file_transfers").reply(vec![parent(
+        "file_transfers",
+        vec![
+            transfer("c_in", "4000.000000", "0"),
+            transfer("d_out", "3000.000000", "1"),
+        ],
+    )])
+}
+
+fn projects() -> Expect {
+    Expect::op("get_project_status").reply(vec![parent(
+        "projects",
+        vec![
+            parent(
+                "project",
+                vec![
+                    node("master_url", "https://einsteinathome.org/"),
+                    node("project_name", "Einstein@Home"),
+                ],
+            ),
+            parent(
+                "project",
+                vec![node("master_url", "https://milkyway.cs.rpi.edu/milkyway/")],
+            ),
+        ],
+    )])
+}
+
+#[tokio::test]
+async fn summary() {
+    let transport = MockTransport::new()
+        .expect(cc_status())
+        .expect(results())
+        .expect(transfers())
+        .expect(projects())
+        .expect(version())
+        .expect(host_info())
+        // Host info and version are known by now
+        .expect(cc_status())
+        .expect(results())
+        .expect(transfers())
+        .expect(projects());
+    let client = Client::new(transport.clone());
+
+    let summary = client.summary().await;
+    assert_eq!(summary.version, SummarySection::Ok("7.16.11".into()));
+    assert_eq!(
+        summary.host,
+        SummarySection::Ok(HostSummary {
+            domain_name: Some("cruncher".into()),
+            p_ncpus: Some(8),
+            ..HostSummary::default()
+        })
+    );
+    assert_eq!(
+        summary.status,
+        SummarySection::Ok(StatusSummary {
+            task_mode: Some(RunMode::Never),
+            task_suspend_reason: Some(SuspendReason::UserRequest),
+            gpu_mode: Some(RunMode::Never),
+            gpu_suspend_reason: Some(SuspendReason::UserRequest),
+            network_mode: Some(RunMode::Auto),
+            network_suspend_reason: Some(SuspendReason::NotSuspended),
+        })
+    );
+    let tasks = summary.tasks.ok().unwrap();
+    assert_eq!(
+        tasks.by_state.iter().collect::<Vec<_>>(),
+        [
+            (&ResultState::FilesDownloading, &1),
+            (&ResultState::FilesDownloaded, &2),
+            (&ResultState::FilesUploaded, &1),
+        ]
+    );
+    assert_eq!(
+        tasks.active,
+        [ActiveTaskSummary {
+            name: Some("a".into()),
+            project_url: None,
+            status: "Suspended - user request".into(),
+            fraction_done: Some(0.25),
+            elapsed_time: None,
+        }]
+    );
+    assert_eq!(
+        summary.transfers,
+        SummarySection::Ok(TransferSummary {
+            uploads: 1,
+            downloads: 1,
+            bytes_remaining: 5000.0,
+        })
+    );
+    assert_eq!(
+        summary.projects,
+        SummarySection::Ok(vec![
+            "Einstein@Home".into(),
+            "https://milkyway.cs.rpi.edu/milkyway/".into()
+        ])
+    );
+
+    assert_eq!(client.summary().await, summary);
+    assert_eq!(transport.remaining(), 0);
+}
+
+#[tokio::test]
+async fn partial_failure() {
+    let transport = MockTransport::new()
+        .expect(cc_status())
+        .expect(Expect::op("get_results").error(Error::DataParseError("garbage".into())))
+        .expect(transfers())
+        .expect(projects())
+        .expect(version())
+        .expect(Expect::op("get_host_info").error(Error::ConnectionClosed))
+        .expect(cc_status())
+        .expect(results())
+        .expect(transfers())
+        .expect(projects())
+        // Failures are not kept
+        .expect(host_info());
+    let client = Client::new(transport.clone());
+
+    let summary = client.summary().await;
+    assert_eq!(summary.version, SummarySection::Ok("7.16.11".into()));
+    assert_eq!(
+        summary.host,
+        SummarySection::Error("Connection closed".into())
+    );
+    assert!(summary.status.ok().is_some());
+    assert_eq!(
+        summary.tasks,
+        SummarySection::Error("Invalid data: garbage".into())
+    );
+    assert!(summary.transfers.ok().is_some());
+    assert!(summary.projects.ok().is_some());
+
+    let summary = client.summary().await;
+    assert!(summary.host.ok().is_some());
+    assert!(summary.tasks.ok().is_some());
+    assert_eq!(transport.remaining(), 0);
+}
+
+/// Daemon answering the requests of a summary, counting those for the version and host info.
+async fn daemon() -> (MockDaemon, Arc<AtomicUsize>, Arc<AtomicUsize>) {
+    let daemon = MockDaemon::bind("127.0.0.1:0").await.unwrap();
+    for (op, reply) in &[
+        ("get_cc_status", "cc_status"),
+        ("get_results", "results"),
+        ("get_file_transfers", "file_transfers"),
+        ("get_project_status", "projects"),
+    ] {
+        daemon.handle(op, move |_| vec![treexml::Element::new(*reply)]);
+    }
+    let versions = Arc::new(AtomicUsize::new(0));
+    let counter = versions.clone();
+    daemon.handle("exchange_versions", move |_| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        vec![parent(
+            "server_version",
+            vec![
+                node("major", "7"),
+                node("minor", "16"),
+                node("release", "11"),
+            ],
+        )]
+    });
+    let host_infos = Arc::new(AtomicUsize::new(0));
+    let counter = host_infos.clone();
+    daemon.handle("get_host_info", move |_| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        vec![parent("host_info", vec![node("domain_name", "cruncher")])]
+    });
+    (daemon, versions, host_infos)
+}
+
+#[tokio::test]
+async fn daemon_restart() {
+    let (daemon, versions, host_infos) = daemon().await;
+    let client = Client::new(Transport::new(daemon.local_addr(), None::<&str>));
+
+    let summary = client.summary().await;
+    assert_eq!(summary.version, SummarySection::Ok("7.16.11".into()));
+    assert!(summary.host.ok().is_some());
+    client.summary().await;
+    assert_eq!(versions.load(Ordering::SeqCst), 1);
+    assert_eq!(host_infos.load(Ordering::SeqCst), 1);
+
+    // The first request finds the connection gone, the next one reconnects
+    daemon.disconnect_all();
+    let summary = client.summary().await;
+    assert!(summary.status.ok().is_none());
+    assert_eq!(summary.version, SummarySection::Ok("7.16.11".into()));
+    assert!(summary.host.ok().is_some());
+    assert_eq!(daemon.connections(), 2);
+    assert_eq!(versions.load(Ordering::SeqCst), 2);
+    assert_eq!(host_infos.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn version_handshake() {
+    let (daemon, versions, host_infos) = daemon().await;
+    let transport = Transport::builder(daemon.local_addr())
+        .version_handshake(true)
+        .build();
+    let client = Client::new(transport);
+
+    let summary = client.summary().await;
+    assert_eq!(summary.version, SummarySection::Ok("7.16.11".into()));
+    // Made when connecting, and not again for the summary
+    assert_eq!(versions.load(Ordering::SeqCst), 1);
+
+    daemon.disconnect_all();
+    client.summary().await;
+    client.summary().await;
+    assert_eq!(versions.load(Ordering::SeqCst), 2);
+    assert_eq!(host_infos.load(Ordering::SeqCst), 2);
+}
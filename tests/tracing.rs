@@ -0,0 +1,162 @@
+//! Spans and events of requests to `MockDaemon`, as seen by a subscriber.
+
+use boinc_rpc::{test_util::MockDaemon, Client, Transport};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+type Span = (
+    &'static tracing::Metadata<'static>,
+    BTreeMap<String, String>,
+    Option<u64>,
+);
+
+/// Spans with their fields and parent, and the messages of events. Spans are numbered from 1 in
+/// the order created, and entered ones kept on a stack, as there is but one thread.
+#[derive(Clone, Default)]
+struct Recorder {
+    spans: Arc<Mutex<Vec<Span>>>,
+    entered: Arc<Mutex<Vec<u64>>>,
+    events: Arc<Mutex<Vec<String>>>,
+}
+
+#[derive(Default)]
+struct Fields(BTreeMap<String, String>);
+
+impl tracing::field::Visit for Fields {
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().into(), format!("{value:?}"));
+    }
+}
+
+impl tracing::Subscriber for Recorder {
+    fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+        let mut fields = Fields::default();
+        span.record(&mut fields);
+        let parent = match span.parent() {
+            Some(parent) => Some(parent.into_u64()),
+            None if span.is_contextual() => self.entered.lock().unwrap().last().copied(),
+            None => None,
+        };
+        let mut spans = self.spans.lock().unwrap();
+        spans.push((span.metadata(), fields.0, parent));
+        tracing::span::Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, id: &tracing::span::Id, values: &tracing::span::Record<'_>) {
+        let mut fields = Fields::default();
+        values.record(&mut fields);
+        let mut spans = self.spans.lock().unwrap();
+        spans[id.into_u64() as usize - 1].1.extend(fields.0);
+    }
+
+    fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+    fn event(&self, event: &tracing::Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let message = fields.0.remove("message").unwrap_or_default();
+        self.events.lock().unwrap().push(message);
+    }
+
+    fn enter(&self, id: &tracing::span::Id) {
+        self.entered.lock().unwrap().push(id.into_u64());
+    }
+
+    fn exit(&self, _: &tracing::span::Id) {
+        self.entered.lock().unwrap().pop();
+    }
+
+    fn current_span(&self) -> tracing_core::span::Current {
+        match self.entered.lock().unwrap().last() {
+            Some(&id) => tracing_core::span::Current::new(
+                tracing::span::Id::from_u64(id),
+                self.spans.lock().unwrap()[id as usize - 1].0,
+            ),
+            None => tracing_core::span::Current::none(),
+        }
+    }
+}
+
+#[tokio::test]
+async fn rpc_spans() {
+    let recorder = Recorder::default();
+    let _guard = tracing::subscriber::set_default(recorder.clone());
+
+    let daemon = MockDaemon::bind("127.0.0.1:0").await.unwrap();
+    daemon.set_password(Some("secret"));
+    daemon.handle("get_host_info", |_| {
+        vec![treexml::Element::new("host_info")]
+    });
+    let client = Client::new(Transport::new(daemon.local_addr(), Some("secret")));
+    client.get_host_info().await.unwrap();
+    client.get_state().await.unwrap_err();
+
+    let recorded = recorder.spans.lock().unwrap();
+    let spans: Vec<_> = recorded
+        .iter()
+        .filter(|(meta, ..)| meta.name() == "rpc")
+        .map(|(_, fields, _)| {
+            let mut fields = fields.clone();
+            let elapsed: f64 = fields.remove("elapsed_ms").unwrap().parse().unwrap();
+            assert!(elapsed >= 0.0);
+            fields
+        })
+        .collect();
+    let host = daemon.local_addr().to_string();
+    let fields = |pairs: &[(&str, &str)]| {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<BTreeMap<_, _>>()
+    };
+    assert_eq!(
+        spans,
+        [
+            fields(&[("rpc", "get_host_info"), ("outcome", "ok")]),
+            fields(&[
+                ("rpc", "get_state"),
+                ("outcome", "error"),
+                ("error_kind", "Daemon"),
+            ]),
+        ]
+    );
+
+    // The transport's own, one within each of the above
+    let transport: Vec<_> = recorded
+        .iter()
+        .filter(|(meta, ..)| meta.name() == "transport")
+        .map(|(_, fields, parent)| {
+            let parent = recorded[parent.expect("transport span without parent") as usize - 1].0;
+            (parent.name(), fields.clone())
+        })
+        .collect();
+    assert_eq!(
+        transport,
+        [
+            ("rpc", fields(&[("host", &host)])),
+            ("rpc", fields(&[("host", &host)])),
+        ]
+    );
+
+    let events = recorder.events.lock().unwrap();
+    assert!(events.iter().any(|event| event.contains("get_host_info")));
+    // Neither side traces the authentication handshake
+    for event in events.iter() {
+        assert!(
+            !event.contains("auth") && !event.contains("nonce"),
+            "{}",
+            event
+        );
+    }
+}
@@ -0,0 +1,115 @@
+//! `Client::wait_for_project_update` against scripted `get_project_status` replies.
+
+use boinc_rpc::{
+    test_util::{node, Expect, MockTransport},
+    Client, Error, ErrorKind,
+};
+use std::time::Duration;
+
+const URL: &str = "https://project.example/";
+
+/// The project as of a scheduler RPC at `last_rpc_time`, with another one pending if `pending`.
+fn status(last_rpc_time: f64, pending: bool, min_rpc_time: f64) -> Expect {
+    let mut project = treexml::Element::new("project");
+    project.children = vec![
+        node("master_url", URL),
+        node("last_rpc_time", &last_rpc_time.to_string()),
+        node("sched_rpc_pending", if pending { "5" } else { "0" }),
+        node("min_rpc_time", &min_rpc_time.to_string()),
+    ];
+    let mut projects = treexml::Element::new("projects");
+    projects.children = vec![project];
+    Expect::op("get_project_status").reply(vec![projects])
+}
+
+fn update() -> Expect {
+    let mut request = treexml::Element::new("project_update");
+    request.children = vec![node("project_url", URL)];
+    Expect::request(vec![request]).reply(vec![treexml::Element::new("success")])
+}
+
+const POLL: Duration = Duration::from_millis(1);
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[tokio::test]
+async fn updated() {
+    let transport = MockTransport::new()
+        .expect(status(1000.0, false, 0.0))
+        .expect(update())
+        .expect(status(1000.0, true, 0.0))
+        .expect(Expect::op("get_project_status").error(Error::ConnectionClosed))
+        .expect(status(1000.0, true, 0.0))
+        .expect(status(2000.0, false, 0.0));
+    let client = Client::new(transport.clone());
+
+    let project = client
+        .wait_for_project_update("https://PROJECT.example", POLL, TIMEOUT)
+        .await
+        .unwrap();
+    assert_eq!(project.last_rpc_time, Some(2000.0));
+    assert_eq!(transport.remaining(), 0);
+}
+
+#[tokio::test]
+async fn deferred() {
+    // Backed off for a day, far beyond the timeout
+    let min_rpc_time = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs_f64()
+        + 86400.0;
+    let transport = MockTransport::new()
+        .expect(status(1000.0, false, min_rpc_time))
+        .expect(update())
+        .expect(status(1000.0, true, min_rpc_time));
+    let client = Client::new(transport.clone());
+
+    let e = client
+        .wait_for_project_update(URL, POLL, TIMEOUT)
+        .await
+        .unwrap_err();
+    assert_eq!(e.kind(), ErrorKind::Daemon);
+    assert!(!e.is_transient());
+    match e {
+        Error::SchedulerRpcDeferred {
+            master_url,
+            min_rpc_time: until,
+        } => {
+            assert_eq!(master_url, URL);
+            assert!((until - min_rpc_time).abs() < 1.0);
+        }
+        e => panic!("unexpected error: {:?}", e),
+    }
+    assert_eq!(transport.remaining(), 0);
+}
+
+#[tokio::test]
+async fn timeout() {
+    let mut transport = MockTransport::new()
+        .expect(status(1000.0, false, 0.0))
+        .expect(update());
+    // The second poll is cut short
+    for _ in 0..2 {
+        transport = transport.expect(status(1000.0, true, 0.0).latency(Duration::from_millis(40)));
+    }
+    let client = Client::new(transport);
+
+    let e = client
+        .wait_for_project_update(URL, POLL, Duration::from_millis(50))
+        .await
+        .unwrap_err();
+    assert!(matches!(e, Error::Timeout), "{:?}", e);
+}
+
+#[tokio::test]
+async fn not_attached() {
+    let transport = MockTransport::new()
+        .expect(Expect::op("get_project_status").reply(vec![treexml::Element::new("projects")]));
+    let client = Client::new(transport);
+
+    let e = client
+        .wait_for_project_update(URL, POLL, TIMEOUT)
+        .await
+        .unwrap_err();
+    assert!(matches!(e, Error::DaemonError(_)), "{:?}", e);
+}
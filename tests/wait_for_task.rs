@@ -0,0 +1,130 @@
+//! `Client::wait_for_task` against scripted `get_results` replies.
+
+mod common;
+
+use boinc_rpc::{
+    models::ResultState,
+    test_util::{Expect, MockTransport, TaskNode},
+    watch, Client, Error,
+};
+use common::results;
+use std::time::Duration;
+
+const POLL: Duration = Duration::from_millis(1);
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[tokio::test]
+async fn reaches_state() {
+    let transport = MockTransport::new()
+        .expect(results(vec![
+            TaskNode::new("a").state(2).build(),
+            TaskNode::new("b").state(2).build(),
+        ]))
+        .expect(results(vec![
+            TaskNode::new("a").state(2).build(),
+            TaskNode::new("b").state(4).build(),
+        ]))
+        .expect(Expect::op("get_results").error(Error::ConnectionClosed))
+        .expect(results(vec![
+            TaskNode::new("b").state(5).build(),
+            TaskNode::new("a").state(4).build(),
+        ]));
+    let client = Client::new(transport.clone());
+
+    let task = client
+        .wait_for_task(
+            "a",
+            watch::task_reached_state(ResultState::FilesUploading),
+            POLL,
+            TIMEOUT,
+        )
+        .await
+        .unwrap();
+    assert_eq!(task.name.as_deref(), Some("a"));
+    assert_eq!(task.result_state(), Some(ResultState::FilesUploading));
+    assert_eq!(transport.remaining(), 0);
+}
+
+#[tokio::test]
+async fn reported() {
+    let transport = MockTransport::new()
+        .expect(results(vec![TaskNode::new("a").state(5).build()]))
+        .expect(results(vec![TaskNode::new("a")
+            .state(5)
+            .reported()
+            .build()]));
+    let client = Client::new(transport);
+
+    let task = client
+        .wait_for_task("a", watch::task_is_reported(), POLL, TIMEOUT)
+        .await
+        .unwrap();
+    assert_eq!(task.got_server_ack, Some(true));
+}
+
+#[tokio::test]
+async fn gone() {
+    let transport = MockTransport::new()
+        .expect(results(vec![
+            TaskNode::new("a").state(2).build(),
+            TaskNode::new("b").state(2).build(),
+        ]))
+        .expect(results(vec![TaskNode::new("b").state(2).build()]));
+    let client = Client::new(transport);
+
+    assert_eq!(
+        client
+            .wait_for_task("a", watch::task_is_ready_to_report(), POLL, TIMEOUT)
+            .await
+            .unwrap_err(),
+        Error::TaskGone("a".into())
+    );
+
+    // Never there to begin with
+    let client = Client::new(MockTransport::new().expect(results(vec![])));
+    assert_eq!(
+        client
+            .wait_for_task("a", |_| true, POLL, TIMEOUT)
+            .await
+            .unwrap_err(),
+        Error::TaskGone("a".into())
+    );
+}
+
+#[tokio::test]
+async fn timeout() {
+    let transport = MockTransport::new()
+        .expect(results(vec![TaskNode::new("a").state(2).build()]))
+        .expect(
+            results(vec![TaskNode::new("a").state(2).build()]).latency(Duration::from_secs(60)),
+        );
+    let client = Client::new(transport);
+
+    assert_eq!(
+        client
+            .wait_for_task(
+                "a",
+                watch::task_is_reported(),
+                POLL,
+                Duration::from_millis(50)
+            )
+            .await
+            .unwrap_err(),
+        Error::Timeout
+    );
+}
+
+#[tokio::test]
+async fn fatal_error() {
+    let transport = MockTransport::new()
+        .expect(Expect::op("get_results").error(Error::DataParseError("garbage".into())));
+    let client = Client::new(transport);
+
+    assert_eq!(
+        client
+            .wait_for_task("a", watch::task_is_reported(), POLL, TIMEOUT)
+            .await
+            .unwrap_err(),
+        Error::DataParseError("garbage".into())
+    );
+}
@@ -0,0 +1,143 @@
+//! `Client::wait_until_idle` against a scripted active set draining over several polls.
+
+mod common;
+
+use boinc_rpc::{
+    models::{Component, RunMode},
+    rpc::request::Request,
+    tasks::IdleOptions,
+    test_util::{Expect, MockTransport, TaskNode},
+    Client, Error,
+};
+use common::results;
+use std::time::Duration;
+
+const EXECUTING: i64 = 1;
+const SUSPENDED: i64 = 9;
+const QUIT_PENDING: i64 = 8;
+
+const POLL: Duration = Duration::from_secs(10);
+const TIMEOUT: Duration = Duration::from_secs(300);
+
+fn names(tasks: &[boinc_rpc::models::TaskResult]) -> Vec<&str> {
+    tasks
+        .iter()
+        .filter_map(|task| task.name.as_deref())
+        .collect()
+}
+
+#[tokio::test]
+async fn drains() {
+    tokio::time::pause();
+    let transport = MockTransport::new()
+        .expect(results(vec![
+            TaskNode::new("a").active_task_state(EXECUTING).build(),
+            TaskNode::new("b").active_task_state(EXECUTING).build(),
+        ]))
+        .expect(results(vec![
+            TaskNode::new("a").active_task_state(QUIT_PENDING).build(),
+            TaskNode::new("b").active_task_state(EXECUTING).build(),
+        ]))
+        .expect(Expect::op("get_results").error(Error::ConnectionClosed))
+        .expect(results(vec![TaskNode::new("b")
+            .active_task_state(QUIT_PENDING)
+            .build()]))
+        .expect(results(vec![TaskNode::new("b")
+            .active_task_state(SUSPENDED)
+            .build()]));
+    let client = Client::new(transport.clone());
+
+    let report = client.wait_until_idle(POLL, TIMEOUT).await.unwrap();
+    assert!(report.is_complete());
+    assert_eq!(names(&report.tasks), ["b"]);
+    assert_eq!(transport.remaining(), 0);
+}
+
+#[tokio::test]
+async fn suspends_first() {
+    tokio::time::pause();
+    let transport = MockTransport::new()
+        .expect(
+            Expect::request(
+                Request::SetMode {
+                    component: Component::CPU,
+                    mode: RunMode::Never,
+                    duration: None,
+                }
+                .into(),
+            )
+            .reply(vec![treexml::Element::new("success")]),
+        )
+        .expect(results(vec![TaskNode::new("a")
+            .active_task_state(EXECUTING)
+            .build()]))
+        .expect(results(vec![]));
+    let client = Client::new(transport.clone());
+
+    let report = client
+        .wait_until_idle_with(IdleOptions::new().suspend(true), POLL, TIMEOUT)
+        .await
+        .unwrap();
+    assert!(report.tasks.is_empty());
+    assert_eq!(transport.remaining(), 0);
+}
+
+#[tokio::test]
+async fn stuck_quitting() {
+    tokio::time::pause();
+    let mut transport = MockTransport::new().expect(results(vec![
+        TaskNode::new("a").active_task_state(EXECUTING).build(),
+        TaskNode::new("b").active_task_state(QUIT_PENDING).build(),
+    ]));
+    // Quitting for the grace period of 30 seconds, polled every 10
+    for _ in 0..3 {
+        transport = transport.expect(results(vec![
+            TaskNode::new("a").active_task_state(SUSPENDED).build(),
+            TaskNode::new("b").active_task_state(QUIT_PENDING).build(),
+        ]));
+    }
+    let client = Client::new(transport.clone());
+
+    let report = client
+        .wait_until_idle_with(
+            IdleOptions::new().grace_period(Duration::from_secs(30)),
+            POLL,
+            TIMEOUT,
+        )
+        .await
+        .unwrap();
+    assert!(!report.is_complete());
+    assert_eq!(report.stuck, ["b"]);
+    assert_eq!(names(&report.tasks), ["a", "b"]);
+    assert_eq!(transport.remaining(), 0);
+}
+
+#[tokio::test]
+async fn timeout() {
+    tokio::time::pause();
+    let mut transport = MockTransport::new();
+    for _ in 0..3 {
+        transport = transport.expect(results(vec![TaskNode::new("a")
+            .active_task_state(EXECUTING)
+            .build()]));
+    }
+    let client = Client::new(transport.clone());
+
+    assert_eq!(
+        client
+            .wait_until_idle(POLL, Duration::from_secs(25))
+            .await
+            .unwrap_err(),
+        Error::Timeout
+    );
+    assert_eq!(transport.remaining(), 0);
+}
+
+#[tokio::test]
+async fn fatal_error() {
+    let transport = MockTransport::new()
+        .expect(Expect::op("get_results").reply(vec![treexml::Element::new("unauthorized")]));
+    let client = Client::new(transport);
+
+    assert!(client.wait_until_idle(POLL, TIMEOUT).await.is_err());
+}
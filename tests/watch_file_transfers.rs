@@ -0,0 +1,128 @@
+//! `Client::watch_file_transfers` against scripted `get_file_transfers` replies.
+
+use boinc_rpc::{
+    test_util::{node, parent, Expect, MockTransport},
+    watch::{TransferDiff, TransferEvent},
+    Client, Error,
+};
+use futures::StreamExt;
+use std::time::Duration;
+
+/// Upload of `name` after `num_retries` failed attempts, under way with `bytes_xferred` if
+/// given, or else backed off with `last_bytes_xferred` bytes done.
+fn upload(name: &str, num_retries: i64, bytes_xferred: Option<f64>) -> treexml::Element {
+    let last_bytes_xferred = if bytes_xferred.is_some() { 0.0 } else { 1024.0 };
+    let mut transfer = parent(
+        "file_transfer",
+        vec![
+            node("project_url", "https://einsteinathome.org/"),
+            node("name", name),
+            node("nbytes", "4096.000000"),
+            node("status", "0"),
+            parent(
+                "persistent_file_xfer",
+                vec![
+                    node("num_retries", &num_retries.to_string()),
+                    node("first_request_time", "1700000000.000000"),
+                    node(
+                        "next_request_time",
+                        &(1_700_000_000 + 60 * num_retries).to_string(),
+                    ),
+                    node("time_so_far", "12.000000"),
+                    node("last_bytes_xferred", &last_bytes_xferred.to_string()),
+                    node("is_upload", "1"),
+                ],
+            ),
+        ],
+    );
+    if let Some(bytes_xferred) = bytes_xferred {
+        transfer.children.push(parent(
+            "file_xfer",
+            vec![
+                node("bytes_xferred", &bytes_xferred.to_string()),
+                node("file_offset", "0.000000"),
+                node("xfer_speed", "100.000000"),
+                node("url", "https://upload.einsteinathome.org/"),
+            ],
+        ));
+    }
+    transfer
+}
+
+fn transfers(transfers: Vec<treexml::Element>) -> Expect {
+    Expect::op("get_file_transfers").reply(vec![parent("file_transfers", transfers)])
+}
+
+fn show(event: &TransferEvent) -> String {
+    match event {
+        TransferEvent::Added(transfer) => format!("+{}", transfer.name.as_deref().unwrap()),
+        TransferEvent::Removed(name) => format!("-{name}"),
+        TransferEvent::BackoffChanged {
+            name, num_retries, ..
+        } => format!("{name} retries {num_retries:?}"),
+        TransferEvent::Stalled { name, bytes_so_far } => {
+            format!("{name} stalled at {bytes_so_far}")
+        }
+    }
+}
+
+async fn events(transport: MockTransport, diff: TransferDiff) -> Vec<String> {
+    let client = Client::new(transport);
+    let stream = client.watch_file_transfers_with(diff, Duration::from_millis(1));
+    stream
+        .map(|event| match event {
+            Ok(event) => show(&event),
+            Err(e) => format!("error: {e}"),
+        })
+        .collect()
+        .await
+}
+
+#[tokio::test]
+async fn stalled_upload() {
+    let transport = MockTransport::new()
+        .expect(transfers(vec![
+            upload("a", 0, Some(0.0)),
+            upload("b", 0, Some(0.0)),
+        ]))
+        .expect(transfers(vec![
+            upload("a", 0, Some(512.0)),
+            upload("b", 0, Some(4096.0)),
+        ]))
+        // b completed, while a goes nowhere
+        .expect(transfers(vec![upload("a", 0, Some(1024.0))]))
+        .expect(transfers(vec![upload("a", 0, Some(1024.0))]))
+        .expect(Expect::op("get_file_transfers").error(Error::ConnectionClosed))
+        .expect(transfers(vec![upload("a", 1, None)]))
+        .expect(transfers(vec![upload("a", 1, None)]))
+        .expect(transfers(vec![upload("a", 1, None)]))
+        .expect(transfers(vec![upload("a", 1, None)]))
+        .expect(Expect::op("get_file_transfers").error(Error::DataParseError("garbage".into())));
+
+    assert_eq!(
+        events(transport, TransferDiff::new().stall_polls(2)).await,
+        [
+            "+a",
+            "+b",
+            "-b",
+            "a retries Some(1)",
+            // Backing off is no progress either, and the stall is reported once
+            "a stalled at 1024",
+            "error: Invalid data: garbage",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn no_transfers() {
+    let transport = MockTransport::new()
+        .expect(transfers(vec![]))
+        .expect(Expect::op("get_file_transfers").reply(vec![]))
+        .expect(transfers(vec![upload("a", 0, None)]))
+        .expect(Expect::op("get_file_transfers").error(Error::DataParseError("garbage".into())));
+
+    assert_eq!(
+        events(transport, TransferDiff::new()).await,
+        ["+a", "error: Invalid data: garbage"]
+    );
+}
@@ -0,0 +1,156 @@
+//! `Client::watch_tasks` against scripted `get_results` replies.
+
+mod common;
+
+use boinc_rpc::{
+    test_util::{Expect, MockTransport, TaskNode},
+    watch::{TaskDiff, TaskEvent},
+    AuthFailure, Client, Error,
+};
+use common::results;
+use futures::StreamExt;
+use std::time::Duration;
+
+fn show(event: &TaskEvent) -> String {
+    match event {
+        TaskEvent::Added(task) => format!("+{}", task.name.as_deref().unwrap()),
+        TaskEvent::Removed(name) => format!("-{name}"),
+        TaskEvent::StateChanged { name, from, to } => format!("{name} {from:?} -> {to:?}"),
+        TaskEvent::ProgressChanged {
+            name,
+            fraction_done,
+        } => format!("{name} {fraction_done}"),
+    }
+}
+
+async fn events(transport: MockTransport, diff: TaskDiff) -> Vec<String> {
+    let client = Client::new(transport);
+    let stream = client.watch_tasks_with(diff, Duration::from_millis(1));
+    stream
+        .map(|event| match event {
+            Ok(event) => show(&event),
+            Err(e) => format!("error: {e}"),
+        })
+        .collect()
+        .await
+}
+
+#[tokio::test]
+async fn added_and_removed() {
+    let transport = MockTransport::new()
+        .expect(results(vec![
+            TaskNode::new("a").state(2).fraction_done(0.1).build(),
+            TaskNode::new("b").state(2).build(),
+        ]))
+        .expect(results(vec![
+            TaskNode::new("b").state(2).build(),
+            TaskNode::new("c").state(1).build(),
+        ]))
+        .expect(results(vec![]))
+        .expect(results(vec![TaskNode::new("a").state(2).build()]))
+        .expect(Expect::op("get_results").error(Error::AuthError(AuthFailure::Unauthorized)));
+
+    assert_eq!(
+        events(transport, TaskDiff::new()).await,
+        [
+            "+a",
+            "+b",
+            "-a",
+            "+c",
+            "-b",
+            "-c",
+            // A task coming back is as good as new
+            "+a",
+            "error: Authentication failed: unauthorized",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn renamed() {
+    let transport = MockTransport::new()
+        .expect(results(vec![
+            TaskNode::new("a").state(2).fraction_done(0.5).build(),
+            TaskNode::new("b").state(2).build(),
+        ]))
+        .expect(results(vec![
+            TaskNode::new("a_1").state(2).fraction_done(0.5).build(),
+            TaskNode::new("b").state(2).build(),
+        ]))
+        // Swapped names are changes to both tasks
+        .expect(results(vec![
+            TaskNode::new("b").state(2).fraction_done(0.5).build(),
+            TaskNode::new("a_1").state(2).build(),
+        ]))
+        .expect(Expect::op("get_results").error(Error::DataParseError("garbage".into())));
+
+    assert_eq!(
+        events(transport, TaskDiff::new()).await,
+        [
+            "+a",
+            "+b",
+            "-a",
+            "+a_1",
+            "b 0.5",
+            "error: Invalid data: garbage",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn duplicate_and_unnamed() {
+    let transport = MockTransport::new()
+        .expect(results(vec![
+            TaskNode::unnamed().state(2).build(),
+            TaskNode::new("a").state(2).build(),
+            TaskNode::new("a").state(4).fraction_done(0.9).build(),
+        ]))
+        .expect(results(vec![TaskNode::new("a").state(4).build()]))
+        .expect(Expect::op("get_results").error(Error::DataParseError("garbage".into())));
+
+    assert_eq!(
+        events(transport, TaskDiff::new()).await,
+        [
+            "+a",
+            "a Some(FilesDownloaded) -> Some(FilesUploading)",
+            "error: Invalid data: garbage",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn state_and_progress() {
+    let transport = MockTransport::new()
+        .expect(results(vec![TaskNode::new("a").state(1).build()]))
+        .expect(results(vec![TaskNode::new("a")
+            .state(2)
+            .fraction_done(0.0)
+            .build()]))
+        .expect(results(vec![TaskNode::new("a")
+            .state(2)
+            .fraction_done(0.004)
+            .build()]))
+        .expect(Expect::op("get_results").error(Error::ConnectionClosed))
+        .expect(results(vec![TaskNode::new("a")
+            .state(2)
+            .fraction_done(0.02)
+            .build()]))
+        .expect(results(vec![TaskNode::new("a")
+            .state(4)
+            .fraction_done(1.0)
+            .build()]))
+        .expect(Expect::op("get_results").error(Error::DataParseError("garbage".into())));
+
+    assert_eq!(
+        events(transport, TaskDiff::new().progress_epsilon(0.01)).await,
+        [
+            "+a",
+            "a Some(FilesDownloading) -> Some(FilesDownloaded)",
+            // Transient errors are retried without a word
+            "a 0.02",
+            "a Some(FilesDownloaded) -> Some(FilesUploading)",
+            "a 1",
+            "error: Invalid data: garbage",
+        ]
+    );
+}